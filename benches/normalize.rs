@@ -0,0 +1,36 @@
+//! 正規化関数のベンチマーク。`cargo bench --bench normalize` で実行する。
+//!
+//! 正規化済みの入力に対しては `_cow` 版がアロケーションを行わない分速いことを確認する。
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use dq1_password::*;
+
+const CANONICAL: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+const SPACED: &str = "ざぼちず どぢぎき つたうず せれえむ るのぢえ";
+
+const INPUTS: [(&str, &str); 2] = [("canonical", CANONICAL), ("spaced", SPACED)];
+
+fn bench_normalize_password(c: &mut Criterion) {
+    let mut group = c.benchmark_group("normalize_password");
+    for (name, input) in INPUTS {
+        group.bench_with_input(BenchmarkId::new("owned", name), input, |b, input| {
+            b.iter(|| normalize_password(black_box(input)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("cow", name), input, |b, input| {
+            b.iter(|| normalize_password_cow(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode_canonical(c: &mut Criterion) {
+    c.bench_function("decode_canonical", |b| {
+        b.iter(|| decode(black_box(CANONICAL)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_normalize_password, bench_decode_canonical);
+criterion_main!(benches);
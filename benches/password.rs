@@ -0,0 +1,224 @@
+//! デコード/エンコード/生成/数え上げのベンチマーク。`cargo bench --bench password` で実行する。
+//!
+//! 入力は全て固定なので、異なるマシンやコミット間で結果を比較できる。
+//! 生成系のベンチマークでは、計測前に 1 回あたりのアロケーション回数、総バイト数、ピーク時の使用バイト数を
+//! 標準エラー出力に表示する。
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use dq1_password::*;
+
+struct CountingAlloc;
+
+static ALLOC_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_BYTES: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_LIVE: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        let live = ALLOC_LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        ALLOC_PEAK.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOC_LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// `f` を 1 回実行したときのアロケーション回数、総バイト数、ピーク時の(実行前からの)使用バイト数を表示する。
+fn report_allocations<T>(name: &str, f: impl FnOnce() -> T) {
+    let count = ALLOC_COUNT.load(Ordering::Relaxed);
+    let bytes = ALLOC_BYTES.load(Ordering::Relaxed);
+    let live = ALLOC_LIVE.load(Ordering::Relaxed);
+    ALLOC_PEAK.store(live, Ordering::Relaxed);
+    black_box(f());
+    eprintln!(
+        "{:<40} {:>10} allocs {:>14} bytes {:>14} peak",
+        name,
+        ALLOC_COUNT.load(Ordering::Relaxed) - count,
+        ALLOC_BYTES.load(Ordering::Relaxed) - bytes,
+        ALLOC_PEAK.load(Ordering::Relaxed) - live,
+    );
+}
+
+const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+/// 末尾 `n_wildcard` 文字を '?' にしたパターン。
+fn pattern_with_wildcards(n_wildcard: usize) -> String {
+    let fixed: String = PASSWORD.chars().take(20 - n_wildcard).collect();
+    fixed + &"?".repeat(n_wildcard)
+}
+
+fn bench_decode(c: &mut Criterion) {
+    c.bench_function("decode", |b| {
+        b.iter(|| decode(black_box(PASSWORD)).unwrap())
+    });
+
+    // バッチ用の入力は "ゆうてい" で始まる復活の呪文を生成順に 10000 個。
+    const N_BATCH: usize = 10_000;
+    let passwords = generate(prefix_pattern("ゆうてい").unwrap(), N_BATCH).unwrap();
+    assert_eq!(passwords.len(), N_BATCH);
+
+    let mut group = c.benchmark_group("decode_batch");
+    group.throughput(Throughput::Elements(N_BATCH as u64));
+    group.sample_size(20);
+    group.bench_function("10k", |b| {
+        b.iter(|| {
+            for password in &passwords {
+                black_box(decode(black_box(password)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let state = decode(PASSWORD).unwrap();
+
+    c.bench_function("encode", |b| b.iter(|| encode(black_box(&state)).unwrap()));
+}
+
+fn bench_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+
+    for n_wildcard in [0, 2, 18] {
+        let pattern = pattern_with_wildcards(n_wildcard);
+        report_allocations(&format!("generate/wildcards/{}", n_wildcard), || {
+            generate(&pattern, 10).unwrap()
+        });
+        group.bench_with_input(
+            BenchmarkId::new("wildcards", n_wildcard),
+            &pattern,
+            |b, pattern| b.iter(|| generate(black_box(pattern), 10).unwrap()),
+        );
+    }
+
+    // 先頭 2 文字を '?' にしたパターン。先頭 2 文字の組(4096 通り)ごとに DP が行われる。
+    // 合致するものは n_max 個に満たないので、全ての組について DP が行われる。
+    let pattern: String = "??".chars().chain(PASSWORD.chars().skip(2)).collect();
+    report_allocations("generate/head_wildcards/2", || {
+        generate(&pattern, 100).unwrap()
+    });
+    group.bench_with_input(
+        BenchmarkId::new("head_wildcards", 2),
+        &pattern,
+        |b, pattern| b.iter(|| generate(black_box(pattern), 100).unwrap()),
+    );
+
+    // 最小コストの表(Fill::Defaults)と到達可能性の表(first_match)を用いる DP。
+    let pattern = pattern_with_wildcards(2);
+    let options = GenerateOptions {
+        fill: Fill::Defaults,
+        ..Default::default()
+    };
+    report_allocations("generate/fill_defaults/2", || {
+        generate_with_options(&pattern, 10, &options).unwrap()
+    });
+    group.bench_with_input(
+        BenchmarkId::new("fill_defaults", 2),
+        &pattern,
+        |b, pattern| b.iter(|| generate_with_options(black_box(pattern), 10, &options).unwrap()),
+    );
+    report_allocations("generate/first_match/2", || first_match(&pattern).unwrap());
+    group.bench_with_input(
+        BenchmarkId::new("first_match", 2),
+        &pattern,
+        |b, pattern| b.iter(|| first_match(black_box(pattern)).unwrap()),
+    );
+
+    // 大きな n_max で、DP テーブルの復元と結果の格納にかかるコストを見る。
+    let pattern = pattern_with_wildcards(18);
+    for n_max in [10_000, 100_000] {
+        report_allocations(&format!("generate/n_max/{}", n_max), || {
+            generate(&pattern, n_max).unwrap()
+        });
+        group.throughput(Throughput::Elements(n_max as u64));
+        group.bench_with_input(BenchmarkId::new("n_max", n_max), &n_max, |b, &n_max| {
+            // 結果の解放は計測に含めない。
+            b.iter_with_large_drop(|| generate(black_box(&pattern), n_max).unwrap())
+        });
+    }
+
+    // 同じ個数を、結果ごとの String を作らずに呼び出し側のバッファで受け取る。
+    // アロケーション回数は結果の個数によらない(DP テーブルと先頭 2 文字の組ごとの復元分のみ)。
+    let n_max = 100_000;
+    let parsed = Pattern::parse(&pattern).unwrap();
+    let each = |pattern: &Pattern| {
+        let mut n = 0;
+        generate_each(pattern, n_max, &GenerateOptions::default(), |cums| {
+            black_box(cums);
+            n += 1;
+            true
+        })
+        .unwrap();
+        n
+    };
+    report_allocations(&format!("generate/each/{}", n_max), || each(&parsed));
+    group.bench_with_input(BenchmarkId::new("each", n_max), &parsed, |b, pattern| {
+        b.iter(|| each(black_box(pattern)))
+    });
+
+    let iter_into = |pattern: &Pattern| {
+        let mut iter = GenerateIter::new(pattern);
+        let mut buf = String::new();
+        let mut n = 0;
+        while n < n_max && iter.next_into_string(&mut buf) {
+            black_box(&buf);
+            n += 1;
+        }
+        n
+    };
+    report_allocations(&format!("generate/iter_into_string/{}", n_max), || {
+        iter_into(&parsed)
+    });
+    group.bench_with_input(
+        BenchmarkId::new("iter_into_string", n_max),
+        &parsed,
+        |b, pattern| b.iter(|| iter_into(black_box(pattern))),
+    );
+
+    group.finish();
+}
+
+fn bench_count(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count");
+    group.sample_size(10);
+
+    group.bench_function("total_valid_passwords", |b| b.iter(total_valid_passwords));
+
+    for n_wildcard in [2, 18] {
+        let pattern = pattern_with_wildcards(n_wildcard);
+        report_allocations(&format!("count/char_statistics/{}", n_wildcard), || {
+            char_statistics(&pattern).unwrap()
+        });
+        group.bench_with_input(
+            BenchmarkId::new("char_statistics", n_wildcard),
+            &pattern,
+            |b, pattern| b.iter(|| char_statistics(black_box(pattern)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_decode,
+    bench_encode,
+    bench_generate,
+    bench_count
+);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+from typing import List
+
+class GameState:
+    hero_name: str
+    hero_xp: int
+    purse: int
+    hero_weapon: int
+    hero_armor: int
+    hero_shield: int
+    herb_count: int
+    key_count: int
+    inventory: List[int]
+    flag_equip_dragon_scale: bool
+    flag_equip_warrior_ring: bool
+    flag_got_death_necklace: bool
+    flag_beated_golem: bool
+    flag_beated_dragon: bool
+    salt: int
+
+    def __init__(
+        self,
+        hero_name: str = "",
+        hero_xp: int = 0,
+        purse: int = 0,
+        hero_weapon: int = 0,
+        hero_armor: int = 0,
+        hero_shield: int = 0,
+        herb_count: int = 0,
+        key_count: int = 0,
+        inventory: List[int] = [0, 0, 0, 0, 0, 0, 0, 0],
+        flag_equip_dragon_scale: bool = False,
+        flag_equip_warrior_ring: bool = False,
+        flag_got_death_necklace: bool = False,
+        flag_beated_golem: bool = False,
+        flag_beated_dragon: bool = False,
+        salt: int = 0,
+    ) -> None: ...
+    def validate(self) -> None: ...
+
+def decode(password: str) -> GameState: ...
+def encode(state: GameState) -> str: ...
+def generate(pattern: str, n_max: int) -> List[str]: ...
+
+class Dq1PasswordError(Exception): ...
+class InvalidGameStateError(Dq1PasswordError): ...
+class InvalidPasswordError(Dq1PasswordError): ...
+class CrcMismatchError(Dq1PasswordError): ...
+class InvalidPatternError(Dq1PasswordError): ...
+class InvalidConstraintError(Dq1PasswordError): ...
+class ResourceLimitError(Dq1PasswordError): ...
+class InvalidFormatError(Dq1PasswordError): ...
@@ -1,19 +1,62 @@
+use std::io::BufRead as _;
+use std::path::PathBuf;
+
 use structopt::StructOpt;
 
 use dq1_password::*;
 
 #[derive(Debug, StructOpt)]
 struct Opt {
-    password: String,
+    /// 復活の呪文たちを 1 行に 1 つずつ記したファイル。指定した場合、全てデコードして JSON Lines で出力する。
+    #[structopt(long, parse(from_os_str), conflicts_with = "password")]
+    batch: Option<PathBuf>,
+
+    #[structopt(required_unless = "batch")]
+    password: Option<String>,
 }
 
 fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
 
-    let state = decode(&opt.password)?;
+    if let Some(path) = opt.batch {
+        return decode_batch(path);
+    }
+
+    let (state, warnings) = decode_with_warnings(opt.password.unwrap())?;
     let json = serde_json::to_string_pretty(&state)?;
 
     println!("{}", json);
+    for warning in warnings {
+        eprintln!("warning: {:?}", warning);
+    }
+
+    Ok(())
+}
+
+/// 空行は無視する。失敗したものは行番号と復活の呪文を付けて標準エラー出力に出力する。
+fn decode_batch(path: PathBuf) -> anyhow::Result<()> {
+    let rdr = std::io::BufReader::new(std::fs::File::open(path)?);
+
+    let mut n_error = 0;
+    for (i, line) in rdr.lines().enumerate() {
+        let line = line?;
+        let password = line.trim();
+        if password.is_empty() {
+            continue;
+        }
+
+        match decode(password).map_err(|e| e.with_input(password).with_line(i + 1)) {
+            Ok(state) => println!("{}", serde_json::to_string(&state)?),
+            Err(e) => {
+                eprintln!("{}", e);
+                n_error += 1;
+            }
+        }
+    }
+
+    if n_error > 0 {
+        anyhow::bail!("{} 個の復活の呪文のデコードに失敗した", n_error);
+    }
 
     Ok(())
 }
@@ -14,7 +14,7 @@ fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
 
     let json = std::fs::read_to_string(opt.path_json)?;
-    let state: GameState = serde_json::from_str(&json)?;
+    let state = GameState::from_json(json)?;
 
     let password = encode(&state)?;
 
@@ -4,6 +4,36 @@ use dq1_password::*;
 
 #[derive(Debug, StructOpt)]
 struct Opt {
+    #[structopt(long, conflicts_with = "suffix")]
+    prefix: bool,
+
+    #[structopt(long)]
+    suffix: bool,
+
+    /// 制約 (例: "hero_name=ゆうてい", "xp=1000..=2000", "purse=6")。複数指定可。
+    #[structopt(short, long = "constraint", number_of_values = 1)]
+    constraints: Vec<String>,
+
+    /// ソルトのみ異なる復活の呪文は最初の 1 つだけを出力する。
+    #[structopt(long)]
+    dedup_by_state: bool,
+
+    /// デコード結果の主人公の名前がゲーム内で入力可能なものだけを出力する。
+    #[structopt(long)]
+    strict_names: bool,
+
+    /// デコード結果が実際のプレイで生じうるものだけを出力する。
+    #[structopt(long)]
+    plausible_only: bool,
+
+    /// 入力コストの小さい順に出力する。
+    #[structopt(long)]
+    sort_by_input_cost: bool,
+
+    /// メモリ使用量の上限(バイト単位)。見積もりがこれを超える場合はエラーとする。
+    #[structopt(long)]
+    max_memory_bytes: Option<usize>,
+
     pattern: String,
 
     #[structopt(default_value = "10")]
@@ -13,7 +43,31 @@ struct Opt {
 fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
 
-    let passwords = generate(&opt.pattern, opt.n_max)?;
+    let pattern = if opt.prefix {
+        prefix_pattern(&opt.pattern)?
+    } else if opt.suffix {
+        suffix_pattern(&opt.pattern)?
+    } else {
+        opt.pattern.clone()
+    };
+
+    let mut options = GenerateOptions {
+        dedup_by_state: opt.dedup_by_state,
+        strict_names: opt.strict_names,
+        plausible_only: opt.plausible_only,
+        sort_by: if opt.sort_by_input_cost {
+            SortBy::InputCost
+        } else {
+            SortBy::Generation
+        },
+        max_memory_bytes: opt.max_memory_bytes,
+        ..Default::default()
+    };
+    for constraint in &opt.constraints {
+        options.add_constraint(constraint)?;
+    }
+
+    let passwords = generate_with_options(&pattern, opt.n_max, &options)?;
     assert!(passwords.len() <= opt.n_max);
     assert!(passwords.iter().all(|p| decode(p).is_ok()));
 
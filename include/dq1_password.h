@@ -0,0 +1,69 @@
+#ifndef DQ1_PASSWORD_H
+#define DQ1_PASSWORD_H
+
+/* This file is generated by cbindgen. Do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define DQ1_HERO_NAME_BUF_LEN 16
+
+#define DQ1_PASSWORD_BUF_LEN 64
+
+typedef enum Dq1ErrorCode {
+  DQ1_ERROR_CODE_OK = 0,
+  DQ1_ERROR_CODE_NULL_POINTER,
+  DQ1_ERROR_CODE_INVALID_UTF8,
+  DQ1_ERROR_CODE_BUFFER_TOO_SMALL,
+  DQ1_ERROR_CODE_INVALID_GAME_STATE,
+  DQ1_ERROR_CODE_INVALID_PASSWORD,
+  DQ1_ERROR_CODE_CRC_MISMATCH,
+  DQ1_ERROR_CODE_INVALID_PATTERN,
+  DQ1_ERROR_CODE_INVALID_CONSTRAINT,
+  DQ1_ERROR_CODE_RESOURCE_LIMIT,
+  DQ1_ERROR_CODE_PANIC,
+  DQ1_ERROR_CODE_INVALID_FORMAT,
+} Dq1ErrorCode;
+
+typedef struct Dq1GameState {
+  char hero_name[DQ1_HERO_NAME_BUF_LEN];
+  uint16_t hero_xp;
+  uint16_t purse;
+  uint8_t hero_weapon;
+  uint8_t hero_armor;
+  uint8_t hero_shield;
+  uint8_t herb_count;
+  uint8_t key_count;
+  uint8_t inventory[8];
+  bool flag_equip_dragon_scale;
+  bool flag_equip_warrior_ring;
+  bool flag_got_death_necklace;
+  bool flag_beated_golem;
+  bool flag_beated_dragon;
+  uint8_t salt;
+} Dq1GameState;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *dq1_last_error_message(void);
+
+enum Dq1ErrorCode dq1_decode(const char *password, struct Dq1GameState *out);
+
+enum Dq1ErrorCode dq1_encode(const struct Dq1GameState *state, char *buf, size_t len);
+
+enum Dq1ErrorCode dq1_generate(const char *pattern,
+                               size_t n_max,
+                               char *buf,
+                               size_t len,
+                               size_t *n_out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DQ1_PASSWORD_H */
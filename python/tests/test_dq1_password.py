@@ -0,0 +1,80 @@
+# Rust 側のテスト(src/decode.rs, src/encode.rs, src/generate.rs)と同じ値を使う。
+
+import pytest
+
+import dq1_password
+from dq1_password import GameState
+
+# 復活の呪文 A
+A = "ざぼちずどぢぎきつたうずせれえむるのぢえ"
+
+STATE_A = GameState(
+    hero_name="しと゛-",
+    hero_xp=1234,
+    purse=5678,
+    hero_weapon=5,
+    hero_armor=5,
+    hero_shield=2,
+    herb_count=6,
+    key_count=6,
+    inventory=[1, 2, 3, 4, 5, 6, 7, 8],
+    flag_equip_dragon_scale=True,
+    flag_equip_warrior_ring=True,
+    flag_got_death_necklace=True,
+    flag_beated_golem=True,
+    flag_beated_dragon=True,
+    salt=5,
+)
+
+
+def test_decode():
+    assert dq1_password.decode("つにこへむゆるわげげだどべうきさそさには") == GameState(hero_name="    ")
+
+    state = dq1_password.decode(A)
+    assert state == STATE_A
+    assert state.hero_xp == 1234
+    assert state.inventory == [1, 2, 3, 4, 5, 6, 7, 8]
+    assert state.flag_beated_dragon is True
+
+    with pytest.raises(dq1_password.CrcMismatchError):
+        dq1_password.decode("ざぼちずどぢぎきつたうずせれえむるのぢお")
+    with pytest.raises(dq1_password.InvalidPasswordError):
+        dq1_password.decode("ざぼちず")
+    with pytest.raises(dq1_password.InvalidGameStateError):
+        dq1_password.decode("どくのばうぼぞそこけばがきもびはめつごび")
+
+
+def test_encode():
+    assert dq1_password.encode(STATE_A) == A
+
+    state = GameState(hero_name="しと゛-")
+    state.hero_name = "あ漢"
+    with pytest.raises(dq1_password.InvalidGameStateError):
+        dq1_password.encode(state)
+
+
+def test_generate():
+    passwords = dq1_password.generate("ざぼちずどぢぎきつたうずせれえむるのぢ?", 10)
+    assert A in passwords
+    assert all(dq1_password.decode(pw) is not None for pw in passwords)
+
+    assert len(dq1_password.generate("ざぼちずどぢぎきつたうずせれえむる???", 3)) == 3
+
+    with pytest.raises(dq1_password.InvalidPatternError):
+        dq1_password.generate("ああああ", 10)
+
+
+def test_exception_hierarchy():
+    for name in [
+        "InvalidGameStateError",
+        "InvalidPasswordError",
+        "CrcMismatchError",
+        "InvalidPatternError",
+        "InvalidConstraintError",
+        "ResourceLimitError",
+        "InvalidFormatError",
+    ]:
+        assert issubclass(getattr(dq1_password, name), dq1_password.Dq1PasswordError)
+
+    with pytest.raises(dq1_password.Dq1PasswordError, match="CRC"):
+        dq1_password.decode("ざぼちずどぢぎきつたうずせれえむるのぢお")
@@ -0,0 +1,162 @@
+use core::convert::TryFrom;
+
+/// 復活の呪文に使われる文字たち。
+///
+/// 並び順はゲーム内の内部値(0..=63)の順で、五十音表の順でもある。
+/// 復活の呪文の入力画面に並ぶ文字と同じ。
+#[rustfmt::skip]
+pub const PASSWORD_CHARS: [char; 64] = [
+    'あ', 'い', 'う', 'え', 'お',
+    'か', 'き', 'く', 'け', 'こ',
+    'さ', 'し', 'す', 'せ', 'そ',
+    'た', 'ち', 'つ', 'て', 'と',
+    'な', 'に', 'ぬ', 'ね', 'の',
+    'は', 'ひ', 'ふ', 'へ', 'ほ',
+    'ま', 'み', 'む', 'め', 'も',
+    'や', 'ゆ', 'よ',
+    'ら', 'り', 'る', 'れ', 'ろ',
+    'わ',
+    'が', 'ぎ', 'ぐ', 'げ', 'ご',
+    'ざ', 'じ', 'ず', 'ぜ', 'ぞ',
+    'だ', 'ぢ', 'づ', 'で', 'ど',
+    'ば', 'び', 'ぶ', 'べ', 'ぼ',
+];
+
+/// 主人公の名前に使われる文字たち(正規化後の形)。
+///
+/// 並び順はゲーム内の内部値(0..=63)の順。濁音/半濁音は '゛'/'゜' と分けて表す。
+/// 数字はゲーム内で入力できないが、復活の呪文には含めうる([`ENTERABLE_HERO_NAME_CHARS`](crate::ENTERABLE_HERO_NAME_CHARS) を参照)。
+#[rustfmt::skip]
+pub const HERO_NAME_CHARS: [char; 64] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+    'あ', 'い', 'う', 'え', 'お',
+    'か', 'き', 'く', 'け', 'こ',
+    'さ', 'し', 'す', 'せ', 'そ',
+    'た', 'ち', 'つ', 'て', 'と',
+    'な', 'に', 'ぬ', 'ね', 'の',
+    'は', 'ひ', 'ふ', 'へ', 'ほ',
+    'ま', 'み', 'む', 'め', 'も',
+    'や', 'ゆ', 'よ',
+    'ら', 'り', 'る', 'れ', 'ろ',
+    'わ', 'を', 'ん',
+    'っ', 'ゃ', 'ゅ', 'ょ',
+    '゛', '゜', '-', ' ',
+];
+
+/// 復活の呪文の文字の [`PASSWORD_CHARS`] におけるインデックスを返す。該当しなければ None を返す。
+///
+/// 正規化後の文字のみを受け付ける(カタカナなどは None となる)。
+pub fn password_char_index(c: char) -> Option<u8> {
+    const TABLE: CharIndexTable = CharIndexTable::new(&PASSWORD_CHARS);
+
+    TABLE.get(c)
+}
+
+/// 主人公の名前の文字の [`HERO_NAME_CHARS`] におけるインデックスを返す。該当しなければ None を返す。
+///
+/// 正規化後の文字のみを受け付ける(濁音などは None となる)。
+pub fn hero_name_char_index(c: char) -> Option<u8> {
+    const TABLE: CharIndexTable = CharIndexTable::new(&HERO_NAME_CHARS);
+
+    TABLE.get(c)
+}
+
+/// 64 文字の文字表の逆引き表。
+///
+/// 文字表は ASCII (U+0000..U+0080) とひらがなブロック (U+3040..U+30A0) の文字のみからなるものとし、
+/// それぞれの範囲を添字とする配列で引く。
+struct CharIndexTable {
+    ascii: [u8; 0x80],
+    hiragana: [u8; 0x60],
+}
+
+impl CharIndexTable {
+    /// 表にない文字を表す値。
+    const NONE: u8 = 0xFF;
+
+    const HIRAGANA_FIRST: u32 = 0x3040;
+
+    /// 文字表から逆引き表を作る。文字表が上記の範囲外の文字を含む場合、コンパイル時にエラーとなる。
+    const fn new(chars: &[char; 64]) -> Self {
+        let mut ascii = [Self::NONE; 0x80];
+        let mut hiragana = [Self::NONE; 0x60];
+
+        let mut i = 0;
+        while i < chars.len() {
+            let code = chars[i] as u32;
+            if code < 0x80 {
+                ascii[code as usize] = i as u8;
+            } else if Self::HIRAGANA_FIRST <= code && code < Self::HIRAGANA_FIRST + 0x60 {
+                hiragana[(code - Self::HIRAGANA_FIRST) as usize] = i as u8;
+            } else {
+                panic!("文字表に ASCII とひらがなブロック以外の文字が含まれている");
+            }
+            i += 1;
+        }
+
+        Self { ascii, hiragana }
+    }
+
+    fn get(&self, c: char) -> Option<u8> {
+        let code = u32::from(c);
+        let idx = match code {
+            0..=0x7F => self.ascii[usize::try_from(code).unwrap()],
+            _ if (Self::HIRAGANA_FIRST..Self::HIRAGANA_FIRST + 0x60).contains(&code) => {
+                self.hiragana[usize::try_from(code - Self::HIRAGANA_FIRST).unwrap()]
+            }
+            _ => Self::NONE,
+        };
+
+        (idx != Self::NONE).then_some(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use itertools::Itertools as _;
+
+    #[test]
+    fn test_password_chars() {
+        assert!(PASSWORD_CHARS.iter().all_unique());
+        for (i, &c) in PASSWORD_CHARS.iter().enumerate() {
+            assert_eq!(password_char_index(c), Some(i as u8));
+        }
+        for c in ['を', 'ん', 'ぱ', 'ア', '0', ' ', '?'] {
+            assert_eq!(password_char_index(c), None);
+        }
+        assert_eq!(PASSWORD_CHARS[0], 'あ');
+        assert_eq!(PASSWORD_CHARS[63], 'ぼ');
+    }
+
+    #[test]
+    fn test_hero_name_chars() {
+        assert!(HERO_NAME_CHARS.iter().all_unique());
+        for (i, &c) in HERO_NAME_CHARS.iter().enumerate() {
+            assert_eq!(hero_name_char_index(c), Some(i as u8));
+        }
+        for c in ['が', 'ぱ', 'ア', 'ぁ', '　', 'ー'] {
+            assert_eq!(hero_name_char_index(c), None);
+        }
+        // 入力可能な文字は全て含まれる。
+        assert!(crate::ENTERABLE_HERO_NAME_CHARS
+            .iter()
+            .all(|&c| hero_name_char_index(c).is_some()));
+    }
+
+    #[test]
+    fn test_char_index_exhaustive() {
+        // 全ての文字について、逆引きの結果が文字表を線形探索したものと一致する。
+        for c in (0..=u32::from(char::MAX)).filter_map(char::from_u32) {
+            let position = |chars: &[char; 64]| chars.iter().position(|&x| x == c).map(|i| i as u8);
+            assert_eq!(password_char_index(c), position(&PASSWORD_CHARS), "{:?}", c);
+            assert_eq!(
+                hero_name_char_index(c),
+                position(&HERO_NAME_CHARS),
+                "{:?}",
+                c
+            );
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use core::convert::TryInto;
+
+use crate::char_set::CharSet;
+use crate::error::Dq1PasswordResult;
+use crate::generate::{
+    crc8_table_head, crc8_table_tail, dp_transition, six_transition, Constraints, GenerateOptions,
+};
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// パターンの各位置について、パターンに合致するデコード可能な復活の呪文のいずれかに現れうる文字の集合を返す。
+///
+/// 固定された位置の集合は、合致するものが存在する限り要素数 1 となる。
+/// 合致するものが存在しない場合、全ての集合が空となる。
+///
+/// 全探索ではなく、前向き/後ろ向きの DP で各状態を通る完成形の個数を数えることで求める。
+///
+/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn feasible_chars(pattern: impl AsRef<str>) -> Dq1PasswordResult<[CharSet; 20]> {
+    Ok(feasible_chars_from_counts(&count_by_position(
+        &Pattern::parse(pattern)?,
+    )))
+}
+
+/// [`count_by_position`] の結果から、各位置に現れうる文字の集合を求める。
+pub(crate) fn feasible_chars_from_counts(counts: &[[u128; 0x40]; 20]) -> [CharSet; 20] {
+    let mut sets = [CharSet::new(); 20];
+    for (set, counts) in core::iter::zip(&mut sets, counts) {
+        for (cum, &count) in (0..).zip(counts) {
+            if count > 0 {
+                set.insert_cum(cum);
+            }
+        }
+    }
+
+    sets
+}
+
+/// パターンに合致するデコード可能な復活の呪文全体について、各位置における各文字の出現数を返す。
+///
+/// 戻り値の `[p][x]` は、位置 p に辞書順(五十音表の順)で x 番目の文字が現れるものの個数。
+/// 各位置の出現数の総和は、パターンに合致するデコード可能な復活の呪文の総数に等しい。
+///
+/// サンプリングではなく、DP による数え上げで厳密に求める。
+///
+/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn char_statistics(pattern: impl AsRef<str>) -> Dq1PasswordResult<[[u128; 0x40]; 20]> {
+    Ok(count_by_position(&Pattern::parse(pattern)?))
+}
+
+/// デコード可能な復活の呪文の総数を返す。
+///
+/// これは 64^20 (約 1.33e36) 通りの文字列のうち、以下を全て満たすものの個数で、
+/// その値は 593042530551281003947622400000000 (約 5.93e32) である:
+///
+/// * CRC 下位バイトがバイト列の残りから計算したものと一致する
+/// * やくそう所持数、かぎ所持数がそれぞれ 6 以下
+/// * インベントリ内の道具IDが全て 14 以下
+///
+/// 全ての文字が任意の場合、各文字の累積値は 6bit 値と一対一に対応するので、
+/// 6bit 値配列についての数え上げ DP で高速に求まる。
+pub fn total_valid_passwords() -> u128 {
+    count_by_sixs_prefix(&[])
+}
+
+/// 6bit 値配列の先頭 `sixs_prefix.len()` 要素を固定したとき、
+/// 有効なゲーム状態に対応する 6bit 値配列の個数を返す。
+fn count_by_sixs_prefix(sixs_prefix: &[u8]) -> u128 {
+    const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+    const CRC_TABLE: [[u8; 0x40]; 18] = crc8_table_tail();
+
+    let options = GenerateOptions::default();
+    let constraints = Constraints::new(&options).unwrap();
+    let n_state = constraints.item_state_count();
+
+    let six_range = |idx: usize| sixs_prefix.get(idx).map_or(0..=0x3F, |&six| six..=six);
+
+    // dp[k][l]: tail 部を決めたところまでで、CRC 累積 XOR=k, 状態 l となる個数
+    let mut dp = [[0_u128; 4]; 0x100];
+    dp[0][0] = 1;
+    for (i, crc_table) in CRC_TABLE.iter().enumerate() {
+        let mut dp_nxt = [[0; 4]; 0x100];
+        for (k, l) in itertools::iproduct!(0..=0xFF, 0..n_state) {
+            let count = dp[usize::from(k)][usize::from(l)];
+            if count == 0 {
+                continue;
+            }
+            for six in six_range(i + 2) {
+                if let Some(l_nxt) = six_transition(i, l, six, &constraints) {
+                    let crc = k ^ crc_table[usize::from(six)];
+                    dp_nxt[usize::from(crc)][usize::from(l_nxt)] += count;
+                }
+            }
+        }
+        dp = dp_nxt;
+    }
+
+    itertools::iproduct!(six_range(0), six_range(1))
+        .map(|(six0, six1)| {
+            let crc_ini = CRC_TABLE_HEAD[usize::from(six1 >> 2)];
+            let crc_expect = six0 | (six1 << 6);
+            dp[usize::from(crc_ini ^ crc_expect)].iter().sum::<u128>()
+        })
+        .sum()
+}
+
+/// パターンに合致するデコード可能な復活の呪文たちについて、各位置の各文字(累積値)の出現数を数える。
+///
+/// タイがある場合、タイを解消したパターンごとに数えて足し合わせる。
+pub(crate) fn count_by_position(pattern: &Pattern) -> [[u128; 0x40]; 20] {
+    let mut counts = [[0; 0x40]; 20];
+
+    for char_sets in pattern.untied_char_sets() {
+        let counts_untied = count_by_position_untied(&char_sets);
+        for (counts, counts_untied) in core::iter::zip(&mut counts, &counts_untied) {
+            for (count, count_untied) in core::iter::zip(counts, counts_untied) {
+                *count += count_untied;
+            }
+        }
+    }
+
+    counts
+}
+
+/// 各位置で許される文字の集合に合致するデコード可能な復活の呪文たちについて、
+/// 各位置の各文字(累積値)の出現数を数える。
+///
+/// CRC は XOR について線形なので、tail 部の CRC を 0 から XOR 累積した値として扱えば、
+/// tail 部の DP は先頭 2 文字のうち 2 文字目の累積値のみに依存する。
+/// よって 2 文字目の累積値ごとに前向き/後ろ向きの数え上げ DP を行い、
+/// 各状態を通る個数を (前向きの個数) * (後ろ向きの個数) として求める。
+fn count_by_position_untied(char_sets: &[CharSet; 20]) -> [[u128; 0x40]; 20] {
+    const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+    const CRC_TABLE: [[u8; 0x40]; 18] = crc8_table_tail();
+
+    let options = GenerateOptions::default();
+    let constraints = Constraints::new(&options).unwrap();
+    let n_state = constraints.item_state_count();
+
+    let (cums_head, cums_tail) = char_sets.split_at(2);
+    let cums_tail: &[_; 18] = cums_tail.try_into().unwrap();
+
+    // fwd[index(i, j, k, l)]: tail 部 i 個目までで cum=j, CRC 累積 XOR=k, 状態 l となる個数
+    // bwd[index(i, j, k, l)]: tail 部 i 個目までで cum=j, CRC 累積 XOR=k, 状態 l であるとき、
+    //                         残りと先頭 2 文字の選び方の個数
+    //
+    // 2 文字目の累積値の間で使い回す。bwd は fwd が 0 でないセルについてのみ求める
+    // (それ以外のセルの値は出現数に寄与しない)。
+    let index = |i: usize, j: u8, k: u8, l: u8| {
+        ((i * 0x40 + usize::from(j)) * 0x100 + usize::from(k)) * usize::from(n_state)
+            + usize::from(l)
+    };
+    let mut fwd = vec![0_u128; 19 * 0x40 * 0x100 * usize::from(n_state)];
+    let mut bwd = fwd.clone();
+
+    let mut counts = [[0; 0x40]; 20];
+
+    for cum1 in cums_head[1].cums() {
+        // 2 文字目の累積値が cum1 である先頭 2 文字たち。
+        // 要素は (1 文字目の累積値, tail 部の CRC 累積 XOR の目標値)。
+        let heads: Vec<_> = cums_head[0]
+            .cums()
+            .filter_map(|cum0| {
+                let six0 = cum0.wrapping_sub(4) & 0x3F;
+                let six1 = cum1.wrapping_sub(cum0 + 4) & 0x3F;
+                if !constraints.allows_six(0, six0) || !constraints.allows_six(1, six1) {
+                    return None;
+                }
+                let crc_ini = CRC_TABLE_HEAD[usize::from(six1 >> 2)];
+                let crc_expect = six0 | (six1 << 6);
+                Some((cum0, crc_ini ^ crc_expect))
+            })
+            .collect();
+        if heads.is_empty() {
+            continue;
+        }
+
+        fwd.fill(0);
+        bwd.fill(0);
+
+        fwd[index(0, cum1, 0, 0)] = 1;
+        for i in 0..18 {
+            for (j, k, l) in itertools::iproduct!(0..=0x3F, 0..=0xFF, 0..n_state) {
+                let count = fwd[index(i, j, k, l)];
+                if count == 0 {
+                    continue;
+                }
+                for cum in cums_tail[i].cums() {
+                    let (six, l_nxt) = match dp_transition(i, j, l, cum, &constraints) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    let crc = k ^ CRC_TABLE[i][usize::from(six)];
+                    fwd[index(i + 1, cum, crc, l_nxt)] += count;
+                }
+            }
+        }
+
+        for &(cum0, crc) in &heads {
+            let mut count = 0;
+            for (cum, l) in itertools::iproduct!(cums_tail[17].cums(), 0..n_state) {
+                let count_fwd = fwd[index(18, cum, crc, l)];
+                if count_fwd == 0 {
+                    continue;
+                }
+                count += count_fwd;
+                bwd[index(18, cum, crc, l)] += 1;
+            }
+            counts[0][usize::from(cum0)] += count;
+            counts[1][usize::from(cum1)] += count;
+        }
+        for i in (1..18).rev() {
+            for (j, k, l) in itertools::iproduct!(0..=0x3F, 0..=0xFF, 0..n_state) {
+                if fwd[index(i, j, k, l)] == 0 {
+                    continue;
+                }
+                let mut count = 0;
+                for cum in cums_tail[i].cums() {
+                    let (six, l_nxt) = match dp_transition(i, j, l, cum, &constraints) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    let crc = k ^ CRC_TABLE[i][usize::from(six)];
+                    count += bwd[index(i + 1, cum, crc, l_nxt)];
+                }
+                bwd[index(i, j, k, l)] = count;
+            }
+        }
+
+        // tail 部 i 個目までの状態は、復活の呪文の位置 i+1 の文字を決める。
+        for i in 1..=18 {
+            for (j, k, l) in itertools::iproduct!(0..=0x3F, 0..=0xFF, 0..n_state) {
+                let count_fwd = fwd[index(i, j, k, l)];
+                if count_fwd == 0 {
+                    continue;
+                }
+                counts[i + 1][usize::from(j)] += count_fwd * bwd[index(i, j, k, l)];
+            }
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::convert::TryFrom;
+
+    use itertools::Itertools as _;
+
+    use crate::decode::{decode, password_char_to_cum};
+    use crate::encode::cum_to_password_char;
+
+    /// パターンの '?' の部分を総当たりし、デコード可能なものたちを返す。
+    fn brute_force(pattern: &str) -> Vec<String> {
+        let n_wild = pattern.chars().filter(|&c| c == '?').count();
+
+        (0..n_wild)
+            .map(|_| 0..0x40)
+            .multi_cartesian_product()
+            .map(|cums| {
+                let mut cums = cums.into_iter();
+                pattern
+                    .chars()
+                    .map(|c| match c {
+                        '?' => cum_to_password_char(cums.next().unwrap()),
+                        c => c,
+                    })
+                    .collect::<String>()
+            })
+            .filter(|password| decode(password).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_feasible_chars() {
+        // 総当たりで集めた文字集合と一致する。
+        for pattern in [
+            "ざぼちず?ぢぎきつたうずせれ?えるのぢえ",
+            "ざぼちずど?ぎきつたうずせれえむる?ぢえ",
+            "?ぼちずどぢぎきつたうずせれえむるの?え",
+        ] {
+            let all = brute_force(pattern);
+            assert!(!all.is_empty());
+
+            let mut expect = [CharSet::new(); 20];
+            for password in &all {
+                for (set, c) in core::iter::zip(&mut expect, password.chars()) {
+                    set.insert(c);
+                }
+            }
+            assert_eq!(feasible_chars(pattern), Ok(expect));
+        }
+
+        let sets = feasible_chars("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+        for (set, c) in core::iter::zip(&sets, "ざぼちずどぢぎきつたうずせれえむるのぢえ".chars())
+        {
+            assert_eq!(set.iter().collect::<Vec<_>>(), [c]);
+        }
+
+        let sets = feasible_chars("ざぼちずどぢぎきつたうずせれえむるのぢお").unwrap();
+        assert!(sets.iter().all(|set| set.is_empty()));
+
+        assert!(feasible_chars("ざぼちず").is_err());
+    }
+
+    #[test]
+    fn test_total_valid_passwords() {
+        assert_eq!(total_valid_passwords(), 593042530551281003947622400000000);
+
+        // 先頭 18 文字を固定した場合、総当たりで数えた結果と一致する。
+        const PREFIX: &str = "ざぼちずどぢぎきつたうずせれえむるの";
+        let sixs_prefix: Vec<_> = core::iter::once(0)
+            .chain(PREFIX.chars().map(|c| password_char_to_cum(c).unwrap()))
+            .tuple_windows()
+            .map(|(cum_pre, cum)| cum.wrapping_sub(cum_pre + 4) & 0x3F)
+            .collect();
+        assert_eq!(
+            count_by_sixs_prefix(&sixs_prefix),
+            u128::try_from(brute_force(&format!("{}??", PREFIX)).len()).unwrap()
+        );
+
+        // 先頭 1 文字を固定した個数の総和は総数に等しい。
+        assert_eq!(
+            (0..0x40)
+                .map(|six| count_by_sixs_prefix(&[six]))
+                .sum::<u128>(),
+            total_valid_passwords()
+        );
+    }
+
+    #[test]
+    fn test_char_statistics() {
+        // 総当たりで数えた結果と一致する。
+        for pattern in [
+            "ざぼちず?ぢぎきつたうずせれ?えるのぢえ",
+            "?ぼちずどぢぎきつたうずせれえむるの?え",
+        ] {
+            let all = brute_force(pattern);
+
+            let mut expect = [[0; 0x40]; 20];
+            for password in &all {
+                for (counts, c) in core::iter::zip(&mut expect, password.chars()) {
+                    counts[usize::from(password_char_to_cum(c).unwrap())] += 1;
+                }
+            }
+
+            let stats = char_statistics(pattern).unwrap();
+            assert_eq!(stats, expect);
+            for counts in &stats {
+                assert_eq!(
+                    counts.iter().sum::<u128>(),
+                    u128::try_from(all.len()).unwrap()
+                );
+            }
+        }
+
+        // 各位置の出現数の総和は等しい。
+        let stats = char_statistics("ざぼちずどぢぎきつたうずせれえむ????").unwrap();
+        let total: u128 = stats[0].iter().sum();
+        assert!(total > 0);
+        assert!(stats
+            .iter()
+            .all(|counts| counts.iter().sum::<u128>() == total));
+
+        assert_eq!(
+            char_statistics("ざぼちずどぢぎきつたうずせれえむるのぢお"),
+            Ok([[0; 0x40]; 20])
+        );
+
+        // 文字クラスの位置には、クラス内の文字だけが現れる。
+        let stats = char_statistics("ざぼちずどぢぎきつたうずせれえむ?[のぬ]?[えおあ]").unwrap();
+        let expect = char_statistics("ざぼちずどぢぎきつたうずせれえむ????").unwrap();
+        for (p, cs) in [(17, "のぬ"), (19, "えおあ")] {
+            for cum in 0..0x40 {
+                let c = cum_to_password_char(cum);
+                if !cs.contains(c) {
+                    assert_eq!(stats[p][usize::from(cum)], 0);
+                }
+            }
+        }
+        let total: u128 = stats[0].iter().sum();
+        assert!(total > 0);
+        assert!(total < expect[0].iter().sum::<u128>());
+
+        // 否定文字クラスの個数は、全体から文字クラスの個数を引いたものに等しい。
+        let stats_neg =
+            char_statistics("ざぼちずどぢぎきつたうずせれえむ?[^のぬ]?[^えおあ]").unwrap();
+        let stats_pos =
+            char_statistics("ざぼちずどぢぎきつたうずせれえむ?[のぬ]?[^えおあ]").unwrap();
+        let stats_all = char_statistics("ざぼちずどぢぎきつたうずせれえむ???[^えおあ]").unwrap();
+        for c in "のぬ".chars() {
+            assert_eq!(
+                stats_neg[17][usize::from(password_char_to_cum(c).unwrap())],
+                0
+            );
+        }
+        for c in "えおあ".chars() {
+            assert_eq!(
+                stats_neg[19][usize::from(password_char_to_cum(c).unwrap())],
+                0
+            );
+        }
+        let total = |stats: &[[u128; 0x40]; 20]| stats[0].iter().sum::<u128>();
+        assert!(total(&stats_neg) > 0);
+
+        // タイのある位置では、タイの相手と出現数が等しい。
+        let stats_tied =
+            char_statistics("ざぼちずどぢぎきつたうずせれえむ[あ-こ]1?[あ-こ]1?").unwrap();
+        let stats_untied =
+            char_statistics("ざぼちずどぢぎきつたうずせれえむ[あ-こ]?[あ-こ]?").unwrap();
+        assert_eq!(stats_tied[16], stats_tied[18]);
+        assert!(total(&stats_tied) > 0);
+        assert!(total(&stats_tied) * 5 < total(&stats_untied));
+        assert_eq!(total(&stats_neg), total(&stats_all) - total(&stats_pos));
+    }
+}
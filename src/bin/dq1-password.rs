@@ -0,0 +1,455 @@
+//! 復活の呪文のデコード/エンコード/生成を行う CLI (`cli` feature)。
+//!
+//! ```sh
+//! cargo install dq1-password --features cli
+//! dq1-password decode ざぼちずどぢぎきつたうずせれえむるのぢえ
+//! echo ざぼちずどぢぎきつたうずせれえむるのぢえ | dq1-password decode --pretty
+//! dq1-password encode '{"hero_name":"ゆうてい", ...}'
+//! dq1-password generate --prefix --n-max 5 ゆうてい
+//! dq1-password interactive
+//! ```
+//!
+//! 入力を引数で与えなかった場合は標準入力から読む。
+//! `--lang en` を指定すると、道具などの名前やエラーメッセージを英語で表示する(JSON のキーは変わらない)。
+//! 失敗した入力があっても残りの処理は続け、最初に失敗したエラーの種類に応じた終了コードを返す:
+//!
+//! | 終了コード | 意味                             |
+//! | ---------- | -------------------------------- |
+//! | 0          | 成功                             |
+//! | 1          | コマンドライン引数が無効         |
+//! | 2          | 入出力エラー、または JSON が無効 |
+//! | 3          | ゲーム状態が無効                 |
+//! | 4          | 復活の呪文の形式が無効           |
+//! | 5          | CRC 下位バイトが一致しない       |
+//! | 6          | パターンが無効                   |
+//! | 7          | 制約が無効                       |
+//! | 8          | リソース上限を超える             |
+
+use std::io::{BufRead as _, Read as _};
+use std::process::ExitCode;
+
+use serde::Serialize;
+use structopt::StructOpt;
+
+use dq1_password::*;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "dq1-password")]
+struct Opt {
+    #[structopt(flatten)]
+    output: OutputOpt,
+
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Debug, StructOpt)]
+struct OutputOpt {
+    /// 結果を 1 行に 1 つずつ JSON で出力する。
+    #[structopt(long, global = true)]
+    json: bool,
+
+    /// 結果を整形された JSON で出力する(`--json` を含意する)。
+    #[structopt(long, global = true)]
+    pretty: bool,
+
+    /// 表示に使う言語 (ja, en)。道具などの名前、エラーメッセージ、対話モードの表示に影響する。
+    /// JSON のキーは言語によらない。
+    #[structopt(long, global = true, default_value = "ja", parse(try_from_str = parse_lang))]
+    lang: Lang,
+}
+
+fn parse_lang(s: &str) -> Result<Lang, String> {
+    match s {
+        "ja" => Ok(Lang::Ja),
+        "en" => Ok(Lang::En),
+        _ => Err(format!("未知の言語: {} (ja または en)", s)),
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// 復活の呪文たちをデコードする。省略時は標準入力から 1 行に 1 つずつ読む。
+    Decode { passwords: Vec<String> },
+
+    /// JSON で表したゲーム状態たちをエンコードする。省略時は標準入力から読む。
+    Encode { states: Vec<String> },
+
+    /// パターンに合致する復活の呪文たちを生成する。省略時は標準入力から 1 行に 1 つずつ読む。
+    Generate {
+        /// 生成する最大個数(パターンごと)。
+        #[structopt(long, default_value = "10")]
+        n_max: usize,
+
+        /// パターンの代わりにプレフィックスを指定する。
+        #[structopt(long, conflicts_with = "suffix")]
+        prefix: bool,
+
+        /// パターンの代わりにサフィックスを指定する。
+        #[structopt(long)]
+        suffix: bool,
+
+        /// 制約 (例: "hero_name=ゆうてい", "xp=1000..=2000", "purse=6")。複数指定可。
+        #[structopt(short, long = "constraint", number_of_values = 1)]
+        constraints: Vec<String>,
+
+        patterns: Vec<String>,
+    },
+
+    /// 復活の呪文を対話的に入力する。
+    ///
+    /// 標準入力から 1 行ずつ読み、入力済みの文字列が補完できるかどうかと次に入力できる文字を表示する。
+    /// 行中の '<' は 1 文字の取り消しとなる。20 文字に達したらデコード結果を、
+    /// デコードできなければ修正候補を出力して終了する。
+    Interactive,
+}
+
+/// CLI のエラー。
+#[derive(Debug)]
+enum CliError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Password(ContextError),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Io(_) | Self::Json(_) => 2,
+            Self::Password(e) => match e.source.kind() {
+                ErrorKind::InvalidGameState(_) => 3,
+                ErrorKind::InvalidPassword(_) => 4,
+                ErrorKind::CrcMismatch { .. } => 5,
+                ErrorKind::InvalidPattern(_) => 6,
+                ErrorKind::InvalidConstraint => 7,
+                ErrorKind::ResourceLimit => 8,
+                ErrorKind::InvalidFormat => 2,
+            },
+        }
+    }
+
+    /// 指定した言語のメッセージを返す。
+    fn to_message(&self, lang: Lang) -> String {
+        match self {
+            Self::Io(e) => e.to_string(),
+            Self::Json(e) => match lang {
+                Lang::Ja => format!("JSON が無効: {}", e),
+                Lang::En => format!("invalid JSON: {}", e),
+            },
+            Self::Password(e) => e.to_message(lang),
+        }
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// 入力とその行番号(標準入力から読んだ場合のみ)。
+type Input = (String, Option<usize>);
+
+fn main() -> ExitCode {
+    let opt = Opt::from_args();
+
+    let mut first_error = None;
+    let mut report = |e: CliError| {
+        eprintln!("error: {}", e.to_message(opt.output.lang));
+        first_error.get_or_insert(e.exit_code());
+    };
+
+    match run(&opt, &mut report) {
+        Ok(()) => {}
+        Err(e) => report(e),
+    }
+
+    ExitCode::from(first_error.unwrap_or(0))
+}
+
+/// 入力ごとのエラーは `report` に渡して処理を続ける。入出力エラーなど続行できないものは `Err` を返す。
+fn run(opt: &Opt, report: &mut impl FnMut(CliError)) -> Result<(), CliError> {
+    match &opt.cmd {
+        Command::Decode { passwords } => {
+            for (password, line) in inputs_or_stdin_lines(passwords)? {
+                match decode(&password) {
+                    Ok(state) => print_state(&opt.output, &state)?,
+                    Err(e) => report(context_error(e, password, line)),
+                }
+            }
+        }
+
+        Command::Encode { states } => {
+            for (json, line) in inputs_or_stdin_json(states)? {
+                let state: GameState = match serde_json::from_str(&json) {
+                    Ok(state) => state,
+                    Err(e) => {
+                        report(e.into());
+                        continue;
+                    }
+                };
+                match encode(&state) {
+                    Ok(password) => print_value(&opt.output, &password, &password)?,
+                    Err(e) => report(context_error(e, json, line)),
+                }
+            }
+        }
+
+        Command::Generate {
+            n_max,
+            prefix,
+            suffix,
+            constraints,
+            patterns,
+        } => {
+            let mut options = GenerateOptions::default();
+            for constraint in constraints {
+                options
+                    .add_constraint(constraint)
+                    .map_err(|e| context_error(e, constraint.clone(), None))?;
+            }
+
+            for (pattern, line) in inputs_or_stdin_lines(patterns)? {
+                let passwords = if *prefix {
+                    prefix_pattern(&pattern)
+                } else if *suffix {
+                    suffix_pattern(&pattern)
+                } else {
+                    Ok(pattern.clone())
+                }
+                .and_then(|pattern| generate_with_options(pattern, *n_max, &options));
+
+                match passwords {
+                    Ok(passwords) => {
+                        for password in passwords {
+                            print_value(&opt.output, &password, &password)?;
+                        }
+                    }
+                    Err(e) => report(context_error(e, pattern, line)),
+                }
+            }
+        }
+
+        Command::Interactive => run_interactive(&opt.output, report)?,
+    }
+
+    Ok(())
+}
+
+/// 対話モードの修正候補の最大個数。
+const INTERACTIVE_SUGGESTION_COUNT: usize = 5;
+
+fn run_interactive(output: &OutputOpt, report: &mut impl FnMut(CliError)) -> Result<(), CliError> {
+    let lang = output.lang;
+    let mut decoder = PartialDecoder::new();
+    println!(
+        "{}",
+        text(
+            lang,
+            "復活の呪文を入力する('<' で 1 文字取り消す)",
+            "Enter a password ('<' deletes one character)"
+        )
+    );
+
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        for (j, chunk) in line.split('<').enumerate() {
+            if j > 0 {
+                decoder.pop();
+            }
+            if let Err(e) = decoder.push_str(chunk) {
+                println!(
+                    "{}: {}",
+                    text(lang, "入力を無視した", "input ignored"),
+                    e.to_message(lang)
+                );
+            }
+        }
+
+        println!("{} ({}/20)", decoder.prefix(), decoder.len());
+        if decoder.len() == 20 {
+            return finish_interactive(output, decoder.prefix(), i + 1, report);
+        }
+        if !decoder.is_feasible() {
+            println!(
+                "{}",
+                text(
+                    lang,
+                    "この入力からはデコード可能な復活の呪文にならない('<' で取り消す)",
+                    "this input cannot lead to a valid password ('<' to delete)"
+                )
+            );
+        } else {
+            let next = decoder.valid_next_chars();
+            let next = if next == CharSet::full() {
+                text(lang, "任意", "any").to_owned()
+            } else {
+                next.iter().collect()
+            };
+            println!("{}: {}", text(lang, "次の文字", "next"), next);
+        }
+    }
+
+    // 20 文字に満たないまま入力が終わった。
+    let password = decoder.prefix();
+    if let Err(e) = decode(&password) {
+        report(context_error(e, password, None));
+    }
+
+    Ok(())
+}
+
+/// 対話モードで 20 文字入力し終えたら、デコード結果または修正候補を出力する。
+fn finish_interactive(
+    output: &OutputOpt,
+    password: String,
+    line: usize,
+    report: &mut impl FnMut(CliError),
+) -> Result<(), CliError> {
+    match decode(&password) {
+        Ok(state) => print_state(output, &state)?,
+        Err(e) => {
+            // 距離 1 で見つからなければ距離 2 で探す。
+            let mut suggestions = vec![];
+            for d in 1..=2 {
+                suggestions = neighbors(&password, d, INTERACTIVE_SUGGESTION_COUNT)
+                    .expect("password should be well-formed");
+                if !suggestions.is_empty() {
+                    break;
+                }
+            }
+            if !(output.json || output.pretty) {
+                println!("{}:", text(output.lang, "修正候補", "suggestions"));
+            }
+            for suggestion in &suggestions {
+                print_value(output, suggestion, suggestion)?;
+            }
+            report(context_error(e, password, Some(line)));
+        }
+    }
+
+    Ok(())
+}
+
+/// 言語に応じて `ja` または `en` を返す。
+fn text(lang: Lang, ja: &'static str, en: &'static str) -> &'static str {
+    match lang {
+        Lang::Ja => ja,
+        Lang::En => en,
+    }
+}
+
+fn context_error(e: Dq1PasswordError, input: String, line: Option<usize>) -> CliError {
+    let e = e.with_input(input);
+    CliError::Password(match line {
+        Some(line) => e.with_line(line),
+        None => e,
+    })
+}
+
+/// `args` が空でなければそれを、空ならば標準入力の空でない行たちを返す。
+fn inputs_or_stdin_lines(args: &[String]) -> Result<Vec<Input>, CliError> {
+    if !args.is_empty() {
+        return Ok(args.iter().map(|arg| (arg.clone(), None)).collect());
+    }
+
+    let mut inputs = Vec::new();
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        let line_trimmed = line.trim();
+        if !line_trimmed.is_empty() {
+            inputs.push((line_trimmed.to_owned(), Some(i + 1)));
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// `args` が空でなければそれを、空ならば標準入力に並んだ JSON 値たちを返す(JSON Lines も可)。
+fn inputs_or_stdin_json(args: &[String]) -> Result<Vec<Input>, CliError> {
+    if !args.is_empty() {
+        return Ok(args.iter().map(|arg| (arg.clone(), None)).collect());
+    }
+
+    let mut buf = String::new();
+    std::io::stdin().read_to_string(&mut buf)?;
+
+    let mut inputs = Vec::new();
+    let mut values = serde_json::Deserializer::from_str(&buf).into_iter::<serde_json::Value>();
+    loop {
+        let offset = values.byte_offset();
+        match values.next() {
+            Some(value) => {
+                let line = buf[..offset].matches('\n').count() + 1;
+                inputs.push((value?.to_string(), Some(line)));
+            }
+            None => break,
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn print_state(output: &OutputOpt, state: &GameState) -> Result<(), CliError> {
+    if output.json || output.pretty {
+        return print_value(output, state, "");
+    }
+
+    println!("hero_name: {}", state.hero_name);
+    println!("hero_xp: {}", state.hero_xp);
+    println!("purse: {}", state.purse);
+    let lang = output.lang;
+    let with_name = |id: u8, name: Option<&str>| match name {
+        Some(name) => format!("{} ({})", id, name),
+        None => id.to_string(),
+    };
+    println!(
+        "hero_weapon: {}",
+        with_name(state.hero_weapon, weapon_name(state.hero_weapon, lang))
+    );
+    println!(
+        "hero_armor: {}",
+        with_name(state.hero_armor, armor_name(state.hero_armor, lang))
+    );
+    println!(
+        "hero_shield: {}",
+        with_name(state.hero_shield, shield_name(state.hero_shield, lang))
+    );
+    println!("herb_count: {}", state.herb_count);
+    println!("key_count: {}", state.key_count);
+    println!("inventory: {:?}", state.inventory);
+    let tool_names: Vec<_> = state
+        .inventory
+        .iter()
+        .filter_map(|&tool| tool_name(tool, lang))
+        .collect();
+    println!("inventory_names: [{}]", tool_names.join(", "));
+    println!("flag_equip_dragon_scale: {}", state.flag_equip_dragon_scale);
+    println!("flag_equip_warrior_ring: {}", state.flag_equip_warrior_ring);
+    println!("flag_got_death_necklace: {}", state.flag_got_death_necklace);
+    println!("flag_beated_golem: {}", state.flag_beated_golem);
+    println!("flag_beated_dragon: {}", state.flag_beated_dragon);
+    println!("salt: {}", state.salt);
+    println!();
+
+    Ok(())
+}
+
+/// JSON 出力が指定されていれば `value` を JSON で、さもなくば `text` をそのまま出力する。
+fn print_value(output: &OutputOpt, value: &impl Serialize, text: &str) -> Result<(), CliError> {
+    if output.pretty {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else if output.json {
+        println!("{}", serde_json::to_string(value)?);
+    } else {
+        println!("{}", text);
+    }
+
+    Ok(())
+}
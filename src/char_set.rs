@@ -0,0 +1,104 @@
+use core::convert::TryFrom;
+
+use crate::decode::password_char_to_cum;
+use crate::encode::cum_to_password_char;
+
+/// 復活の呪文に使われる文字の集合。
+///
+/// 各文字の累積値を bit 位置とする 64bit 値で表す。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CharSet(u64);
+
+impl CharSet {
+    /// 空集合を返す。
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// 全ての文字からなる集合を返す。
+    pub fn full() -> Self {
+        Self(u64::MAX)
+    }
+
+    /// 64bit 値から集合を作る。bit x は累積値 x の文字に対応する。
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// 集合を表す 64bit 値を返す。bit x は累積値 x の文字に対応する。
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// 文字 `c` を含むかどうかを返す。復活の呪文に使えない文字に対しては false を返す。
+    pub fn contains(self, c: char) -> bool {
+        password_char_to_cum(c).is_some_and(|cum| self.contains_cum(cum))
+    }
+
+    /// 文字 `c` を追加する。`c` が復活の呪文に使えない文字の場合、何もせず false を返す。
+    pub fn insert(&mut self, c: char) -> bool {
+        match password_char_to_cum(c) {
+            Some(cum) => {
+                self.insert_cum(cum);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 要素数を返す。
+    pub fn len(self) -> usize {
+        usize::try_from(self.0.count_ones()).unwrap()
+    }
+
+    /// 空集合かどうかを返す。
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 要素を辞書順(五十音表の順)に列挙する。
+    pub fn iter(self) -> impl Iterator<Item = char> {
+        (0..0x40)
+            .filter(move |&cum| self.contains_cum(cum))
+            .map(cum_to_password_char)
+    }
+
+    /// 要素の累積値を昇順に列挙する。
+    pub(crate) fn cums(self) -> impl Iterator<Item = u8> + Clone {
+        (0..0x40).filter(move |&cum| self.contains_cum(cum))
+    }
+
+    pub(crate) fn contains_cum(self, cum: u8) -> bool {
+        (self.0 & (1 << cum)) != 0
+    }
+
+    pub(crate) fn insert_cum(&mut self, cum: u8) {
+        self.0 |= 1 << cum;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_char_set() {
+        let mut set = CharSet::new();
+        assert!(set.is_empty());
+
+        assert!(set.insert('ぼ'));
+        assert!(set.insert('あ'));
+        assert!(set.insert('あ'));
+        assert!(!set.insert('ん'));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains('あ'));
+        assert!(!set.contains('い'));
+        assert!(!set.contains('ん'));
+        assert_eq!(set.iter().collect::<String>(), "あぼ");
+        assert_eq!(set.bits(), (1 << 0) | (1 << 0x3F));
+        assert_eq!(CharSet::from_bits(set.bits()), set);
+
+        assert_eq!(CharSet::full().len(), 64);
+    }
+}
@@ -0,0 +1,500 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+
+use itertools::Itertools as _;
+
+use crate::alphabet::{hero_name_char_index, password_char_index, HERO_NAME_CHARS, PASSWORD_CHARS};
+use crate::crc::crc_update_poly;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, GameStateErrorKind};
+use crate::normalize::{fold_compat, normalize_password_cow};
+use crate::prelude::*;
+
+/// 復活の呪文の符号化のパラメータ(文字表と CRC)。
+///
+/// 改造版や他言語版など、文字表や CRC が本家と異なる場合に [`decode_with_codec`](crate::decode_with_codec),
+/// [`encode_with_codec`](crate::encode_with_codec), [`generate_with_codec`](crate::generate_with_codec) に渡す。
+/// ゲーム状態のビット配置や、文字の決め方(累積値による)は本家と同じとする。
+///
+/// 文字表はいずれも内部値(0..=63)の順に並べた 64 文字で、それぞれ [`PASSWORD_CHARS`], [`HERO_NAME_CHARS`] に対応する。
+/// 主人公の名前の文字表のインデックス 63 の文字は、名前が 4 文字に満たない場合のパディングに用いられる。
+///
+/// 入力の正規化(カタカナの変換など)は、本家の文字表を用いる場合に限り行われる。
+/// それ以外の場合、入力は文字表の文字そのもの(空白文字は無視される)でなければならない。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Codec {
+    password_chars: [char; 64],
+    hero_name_chars: [char; 64],
+    crc_poly: u16,
+    crc_init: u16,
+}
+
+impl Codec {
+    /// 本家(FC 版)のパラメータ。既定の API はこれを用いる。
+    pub const fn dq1_fc() -> Self {
+        Self {
+            password_chars: PASSWORD_CHARS,
+            hero_name_chars: HERO_NAME_CHARS,
+            crc_poly: 0x1021,
+            crc_init: 0,
+        }
+    }
+
+    /// 文字表と CRC の生成多項式、初期値を指定して作る。
+    ///
+    /// 文字表に重複がある場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+    /// 復活の呪文の文字表が空白文字、互換文字([`fold_compat`] で変換されるもの)、
+    /// パターンの構文に使われる文字('?', '*', '[', ']', '^', '-', '/', 数字 1-9)を含む場合も同様。
+    pub fn new(
+        password_chars: [char; 64],
+        hero_name_chars: [char; 64],
+        crc_poly: u16,
+        crc_init: u16,
+    ) -> Dq1PasswordResult<Self> {
+        for (name, chars) in [
+            ("復活の呪文", &password_chars),
+            ("主人公の名前", &hero_name_chars),
+        ] {
+            // 重複する文字たち(2 回目に現れた順)。
+            let mut seen = BTreeSet::new();
+            let mut dups = vec![];
+            for &c in chars.iter() {
+                if !seen.insert(c) && !dups.contains(&c) {
+                    dups.push(c);
+                }
+            }
+            if !dups.is_empty() {
+                return Err(Dq1PasswordError::invalid_format(format!(
+                    "{}の文字表に重複がある: {}",
+                    name,
+                    dups.into_iter().map(|c| format!("'{}'", c)).join(", ")
+                )));
+            }
+        }
+
+        let reserved: Vec<_> = password_chars
+            .iter()
+            .filter(|&&c| c.is_whitespace() || fold_compat(c) != c || is_pattern_meta(c))
+            .collect();
+        if !reserved.is_empty() {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "復活の呪文の文字表に使えない文字が含まれている: {}",
+                reserved.into_iter().map(|c| format!("'{}'", c)).join(", ")
+            )));
+        }
+
+        Ok(Self {
+            password_chars,
+            hero_name_chars,
+            crc_poly,
+            crc_init,
+        })
+    }
+
+    /// 復活の呪文の文字表。
+    pub fn password_chars(&self) -> &[char; 64] {
+        &self.password_chars
+    }
+
+    /// 主人公の名前の文字表。
+    pub fn hero_name_chars(&self) -> &[char; 64] {
+        &self.hero_name_chars
+    }
+
+    /// CRC の生成多項式。
+    pub fn crc_poly(&self) -> u16 {
+        self.crc_poly
+    }
+
+    /// CRC の初期値。
+    pub fn crc_init(&self) -> u16 {
+        self.crc_init
+    }
+
+    /// バイト列の CRC を計算する。本家のパラメータならば [`crc16`](crate::crc::crc16) と等しい。
+    pub fn crc16(&self, bytes: &[u8]) -> u16 {
+        bytes.iter().fold(self.crc_init, |crc, &b| {
+            crc_update_poly(crc, b, 8, self.crc_poly)
+        })
+    }
+
+    /// 復活の呪文を、本家の文字表で表した正規化済みのものに変換する。
+    ///
+    /// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+    pub(crate) fn to_dq1_password<'a>(&self, password: &'a str) -> Dq1PasswordResult<Cow<'a, str>> {
+        if self.password_chars == PASSWORD_CHARS {
+            return normalize_password_cow(password);
+        }
+
+        let cs: Vec<_> = password.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if cs.len() != 20 {
+            return Err(Dq1PasswordError::invalid_password_length(
+                cs.len(),
+                "復活の呪文はちょうど 20 文字でなければならない(ただし空白文字は無視される)",
+            ));
+        }
+
+        let cs_invalid: Vec<_> = cs
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, c)| self.password_char_index(c).is_none())
+            .collect();
+
+        if !cs_invalid.is_empty() {
+            let msg = format!(
+                "復活の呪文に無効な文字が含まれている: {}",
+                cs_invalid
+                    .iter()
+                    .map(|(_, c)| format!("'{}'", c))
+                    .join(", ")
+            );
+            return Err(Dq1PasswordError::invalid_password_chars(cs_invalid, msg));
+        }
+
+        Ok(Cow::Owned(
+            cs.into_iter()
+                .map(|c| PASSWORD_CHARS[usize::from(self.password_char_index(c).unwrap())])
+                .collect(),
+        ))
+    }
+
+    /// 本家の文字表で表した復活の呪文を、この文字表で表したものに変換する。
+    pub(crate) fn password_from_dq1(&self, password: &str) -> String {
+        password
+            .chars()
+            .map(|c| self.password_chars[usize::from(password_char_index(c).unwrap())])
+            .collect()
+    }
+
+    /// 主人公の名前を、本家の文字表で表したものに変換する。本家の文字表ならばそのまま返す。
+    ///
+    /// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub(crate) fn to_dq1_hero_name<'a>(
+        &self,
+        hero_name: &'a str,
+    ) -> Dq1PasswordResult<Cow<'a, str>> {
+        if self.hero_name_chars == HERO_NAME_CHARS {
+            return Ok(Cow::Borrowed(hero_name));
+        }
+
+        let cs: Vec<_> = hero_name.chars().collect();
+
+        if cs.len() > 4 {
+            return Err(Dq1PasswordError::invalid_game_state(
+                GameStateErrorKind::HeroNameTooLong,
+                "主人公の名前は 4 文字以内でなければならない",
+            ));
+        }
+
+        let cs_invalid: Vec<_> = cs
+            .iter()
+            .copied()
+            .filter(|&c| self.hero_name_char_index(c).is_none())
+            .collect();
+
+        if !cs_invalid.is_empty() {
+            let msg = format!(
+                "主人公の名前に無効な文字が含まれている: {}",
+                cs_invalid.iter().map(|c| format!("'{}'", c)).join(", ")
+            );
+            return Err(Dq1PasswordError::invalid_game_state(
+                GameStateErrorKind::HeroNameInvalidChars(cs_invalid),
+                msg,
+            ));
+        }
+
+        Ok(Cow::Owned(
+            cs.into_iter()
+                .map(|c| HERO_NAME_CHARS[usize::from(self.hero_name_char_index(c).unwrap())])
+                .chain(core::iter::repeat(HERO_NAME_CHARS[63]))
+                .take(4)
+                .collect(),
+        ))
+    }
+
+    /// 本家の文字表で表した正規化済みの主人公の名前を、この文字表で表したものに変換する。
+    pub(crate) fn hero_name_from_dq1(&self, hero_name: &str) -> String {
+        hero_name
+            .chars()
+            .map(|c| self.hero_name_chars[usize::from(hero_name_char_index(c).unwrap())])
+            .collect()
+    }
+
+    /// パターンの文字を本家の文字表のものに変換する。構文に使われる文字はそのまま残す。
+    ///
+    /// `pattern` が文字表にも構文にもない文字を含む場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+    pub(crate) fn to_dq1_pattern<'a>(&self, pattern: &'a str) -> Dq1PasswordResult<Cow<'a, str>> {
+        if self.password_chars == PASSWORD_CHARS {
+            return Ok(Cow::Borrowed(pattern));
+        }
+
+        pattern
+            .chars()
+            .map(|c| match self.password_char_index(c) {
+                Some(idx) => Ok(PASSWORD_CHARS[usize::from(idx)]),
+                None if c.is_whitespace() || is_pattern_meta(fold_compat(c)) => Ok(c),
+                None => Err(Dq1PasswordError::invalid_pattern(format!(
+                    "パターンに無効な文字が含まれている: '{}'",
+                    c
+                ))),
+            })
+            .collect::<Dq1PasswordResult<String>>()
+            .map(Cow::Owned)
+    }
+
+    fn password_char_index(&self, c: char) -> Option<u8> {
+        self.password_chars
+            .iter()
+            .position(|&x| x == c)
+            .map(|i| i as u8)
+    }
+
+    fn hero_name_char_index(&self, c: char) -> Option<u8> {
+        self.hero_name_chars
+            .iter()
+            .position(|&x| x == c)
+            .map(|i| i as u8)
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::dq1_fc()
+    }
+}
+
+/// パターンの構文に使われる文字かどうかを返す。
+fn is_pattern_meta(c: char) -> bool {
+    matches!(c, '?' | '*' | '[' | ']' | '^' | '-' | '/' | '1'..='9')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::convert::TryInto;
+
+    use crate::error::ErrorKind;
+    use crate::game_state::GameState;
+    use crate::{
+        decode, decode_with_codec, encode, encode_with_codec, generate, generate_with_codec,
+    };
+
+    const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+    /// 英大文字、英小文字、ギリシャ文字からなるおもちゃの文字表。
+    const TOY_PASSWORD_CHARS: &str =
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyzαβγδεζηθικλμ";
+
+    /// 数字、英大文字、英小文字、'-', ' ' からなるおもちゃの文字表。
+    const TOY_HERO_NAME_CHARS: &str =
+        "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz- ";
+
+    fn chars64(s: &str) -> [char; 64] {
+        let cs: Vec<_> = s.chars().collect();
+        cs.try_into().unwrap()
+    }
+
+    /// 文字表のみ本家と異なるもの。
+    fn toy_alphabet() -> Codec {
+        Codec::new(
+            chars64(TOY_PASSWORD_CHARS),
+            chars64(TOY_HERO_NAME_CHARS),
+            0x1021,
+            0,
+        )
+        .unwrap()
+    }
+
+    /// 文字表と CRC が本家と異なるもの。
+    fn toy() -> Codec {
+        Codec::new(
+            chars64(TOY_PASSWORD_CHARS),
+            chars64(TOY_HERO_NAME_CHARS),
+            0x8005,
+            0xFFFF,
+        )
+        .unwrap()
+    }
+
+    fn toy_state() -> GameState {
+        GameState {
+            hero_name: "Loto".to_owned(),
+            hero_xp: 1234,
+            purse: 5678,
+            hero_weapon: 5,
+            hero_armor: 5,
+            hero_shield: 2,
+            herb_count: 6,
+            key_count: 6,
+            inventory: [1, 2, 3, 4, 5, 6, 7, 8],
+            salt: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dq1_fc() {
+        let codec = Codec::dq1_fc();
+        assert_eq!(codec, Codec::default());
+
+        let state = decode(A).unwrap();
+        assert_eq!(decode_with_codec(A, &codec), Ok(state.clone()));
+        assert_eq!(
+            decode_with_codec("ザボチズ ドヂギキ ツタウズ セレエム ルノヂエ", &codec),
+            Ok(state.clone())
+        );
+        assert_eq!(encode_with_codec(&state, &codec), encode(&state));
+        assert_eq!(
+            generate_with_codec("ざぼちずどぢぎきつたうずせれえむる???", 5, &codec),
+            generate("ざぼちずどぢぎきつたうずせれえむる???", 5)
+        );
+
+        assert_eq!(codec.crc16(&[0xFF, 0xFF]), crate::crc::crc16(&[0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for codec in [toy_alphabet(), toy()] {
+            let state = toy_state();
+            let password = encode_with_codec(&state, &codec).unwrap();
+            assert_eq!(password.chars().count(), 20);
+            assert!(password.chars().all(|c| TOY_PASSWORD_CHARS.contains(c)));
+            assert_eq!(decode_with_codec(&password, &codec), Ok(state));
+
+            // 短い名前はパディングされる。
+            let state = GameState {
+                hero_name: "Al".to_owned(),
+                ..toy_state()
+            };
+            let password = encode_with_codec(&state, &codec).unwrap();
+            assert_eq!(
+                decode_with_codec(&password, &codec).unwrap().hero_name,
+                "Al  "
+            );
+        }
+    }
+
+    #[test]
+    fn test_alphabet_only() {
+        // 文字表のみ異なる場合、本家の復活の呪文の文字を置き換えたものとなる。
+        let codec = toy_alphabet();
+        let translate = |password: &str| {
+            password
+                .chars()
+                .map(|c| codec.password_chars()[usize::from(password_char_index(c).unwrap())])
+                .collect::<String>()
+        };
+
+        let state = decode(A).unwrap();
+        let password = translate(A);
+        let decoded = decode_with_codec(&password, &codec).unwrap();
+        assert_eq!(
+            GameState {
+                hero_name: state.hero_name.clone(),
+                ..decoded
+            },
+            state
+        );
+
+        assert_eq!(
+            generate_with_codec(
+                translate("ざぼちずどぢぎきつたうずせれえむる") + "???",
+                10,
+                &codec
+            )
+            .unwrap(),
+            generate("ざぼちずどぢぎきつたうずせれえむる???", 10)
+                .unwrap()
+                .iter()
+                .map(|password| translate(password))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_crc() {
+        let state = toy_state();
+        let password = encode_with_codec(&state, &toy()).unwrap();
+
+        // CRC のパラメータが異なると一致しない。
+        assert!(matches!(
+            decode_with_codec(&password, &toy_alphabet()),
+            Err(Dq1PasswordError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_generate() {
+        let codec = toy();
+
+        let passwords = generate_with_codec("Loto????????????????", 50, &codec).unwrap();
+        assert_eq!(passwords.len(), 50);
+        for password in &passwords {
+            assert!(password.starts_with("Loto"));
+            assert!(decode_with_codec(password, &codec).is_ok());
+        }
+
+        // 文字クラスの範囲は文字表の順。グループ番号も使える。
+        let passwords = generate_with_codec("[A-C]?1*?1", 30, &codec).unwrap();
+        assert_eq!(passwords.len(), 30);
+        for password in &passwords {
+            let cs: Vec<_> = password.chars().collect();
+            assert!(matches!(cs[0], 'A'..='C'));
+            assert_eq!(cs[1], cs[19]);
+            assert!(decode_with_codec(password, &codec).is_ok());
+        }
+
+        // エンコード結果を含む。
+        let password = encode_with_codec(&toy_state(), &codec).unwrap();
+        let pattern: String = password.chars().take(18).chain("??".chars()).collect();
+        let passwords = generate_with_codec(&pattern, 100, &codec).unwrap();
+        assert!(passwords.contains(&password));
+    }
+
+    #[test]
+    fn test_errors() {
+        let codec = toy();
+
+        let e = decode_with_codec(A, &codec).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidPassword(_)));
+        let e = decode_with_codec("ABCD", &codec).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidPassword(_)));
+
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            ..Default::default()
+        };
+        let e = encode_with_codec(&state, &codec).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidGameState(_)));
+        let state = GameState {
+            hero_name: "Alefgard".to_owned(),
+            ..Default::default()
+        };
+        let e = encode_with_codec(&state, &codec).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidGameState(_)));
+
+        let e = generate_with_codec("あ???????????????????", 1, &codec).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidPattern(_)));
+    }
+
+    #[test]
+    fn test_new_error() {
+        let mut dup = chars64(TOY_PASSWORD_CHARS);
+        dup[1] = 'A';
+        let e = Codec::new(dup, chars64(TOY_HERO_NAME_CHARS), 0x1021, 0).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert_eq!(
+            e.to_string(),
+            "データ形式が無効: 復活の呪文の文字表に重複がある: 'A'"
+        );
+
+        for c in ['?', '1', ' ', 'Ａ'] {
+            let mut reserved = chars64(TOY_PASSWORD_CHARS);
+            reserved[63] = c;
+            let e = Codec::new(reserved, chars64(TOY_HERO_NAME_CHARS), 0x1021, 0).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::Dq1PasswordResult;
+use crate::game_state::GameState;
+
+/// 武器ID に対応する攻撃力の加算値。本家(FC 版)の値。
+pub(crate) const WEAPON_ATTACK_BONUS: [u8; 8] = [0, 2, 4, 10, 15, 20, 28, 40];
+
+/// 鎧ID に対応する守備力の加算値。本家(FC 版)の値。
+pub(crate) const ARMOR_DEFENSE_BONUS: [u8; 8] = [0, 2, 4, 10, 16, 24, 24, 28];
+
+/// 盾ID に対応する守備力の加算値。本家(FC 版)の値。
+pub(crate) const SHIELD_DEFENSE_BONUS: [u8; 4] = [0, 4, 10, 20];
+
+/// りゅうのうろこを装備している場合の守備力の加算値。
+pub(crate) const DRAGON_SCALE_DEFENSE_BONUS: u8 = 2;
+
+/// つよさ画面に表示される攻撃力と守備力。[`combat_stats`] を参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CombatStats {
+    /// こうげきりょく。
+    pub attack: u8,
+
+    /// しゅびりょく。
+    pub defense: u8,
+}
+
+/// ゲーム状態から攻撃力と守備力を求める。
+///
+/// 能力値は [`GameState::stats`] で求め、以下のように計算する:
+///
+/// * 攻撃力 = ちから + 武器の加算値
+/// * 守備力 = すばやさ / 2 (切り捨て) + 鎧の加算値 + 盾の加算値 (+ りゅうのうろこ装備フラグが立っていれば 2)
+///
+/// せんしのゆびわ装備フラグは影響しない(本家では効果がない)。
+///
+/// `state` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn combat_stats(state: &GameState) -> Dq1PasswordResult<CombatStats> {
+    state.validate()?;
+    let stats = state.stats()?;
+
+    let attack = stats.strength + WEAPON_ATTACK_BONUS[usize::from(state.hero_weapon)];
+
+    let mut defense = stats.agility / 2
+        + ARMOR_DEFENSE_BONUS[usize::from(state.hero_armor)]
+        + SHIELD_DEFENSE_BONUS[usize::from(state.hero_shield)];
+    if state.flag_equip_dragon_scale {
+        defense += DRAGON_SCALE_DEFENSE_BONUS;
+    }
+
+    Ok(CombatStats { attack, defense })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn test_combat_stats() {
+        // 初期状態: "ゆうてい" レベル 1 (ちから 4, すばやさ 3)。
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            combat_stats(&state),
+            Ok(CombatStats {
+                attack: 4,
+                defense: 1,
+            })
+        );
+
+        // "ゆうてい" レベル 30 (ちから 140, すばやさ 117)、ロト装備 + みかがみのたて。
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            hero_xp: u16::MAX,
+            hero_weapon: 7,
+            hero_armor: 7,
+            hero_shield: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            combat_stats(&state),
+            Ok(CombatStats {
+                attack: 140 + 40,
+                defense: 58 + 28 + 20,
+            })
+        );
+
+        // りゅうのうろこはフラグが立っている場合のみ加算される(所持しているだけでは加算されない)。
+        let state_scale = GameState {
+            inventory: [4, 0, 0, 0, 0, 0, 0, 0],
+            ..state.clone()
+        };
+        assert_eq!(combat_stats(&state_scale), combat_stats(&state));
+        let state_scale = GameState {
+            flag_equip_dragon_scale: true,
+            ..state_scale
+        };
+        assert_eq!(
+            combat_stats(&state_scale),
+            Ok(CombatStats {
+                attack: 180,
+                defense: 106 + 2,
+            })
+        );
+
+        // せんしのゆびわは影響しない。
+        let state_ring = GameState {
+            flag_equip_warrior_ring: true,
+            ..state.clone()
+        };
+        assert_eq!(combat_stats(&state_ring), combat_stats(&state));
+
+        let invalid = GameState {
+            hero_weapon: 8,
+            ..state
+        };
+        assert!(combat_stats(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_combat_stats_tables() {
+        assert!(WEAPON_ATTACK_BONUS.windows(2).all(|w| w[0] < w[1]));
+        assert!(ARMOR_DEFENSE_BONUS.windows(2).all(|w| w[0] <= w[1]));
+        assert!(SHIELD_DEFENSE_BONUS.windows(2).all(|w| w[0] < w[1]));
+    }
+}
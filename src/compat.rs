@@ -0,0 +1,259 @@
+//! Web 上の JavaScript 製の復活の呪文ツールが出力する JSON 形式との相互変換。
+//!
+//! 対応する形式は以下の通り(キーは全て必須。これ以外のキーがあるとエラーとなる):
+//!
+//! ```json
+//! {
+//!   "name": "ゆうてい",
+//!   "exp": 1234,
+//!   "gold": 5678,
+//!   "weapon": "はがねのつるぎ",
+//!   "armor": null,
+//!   "shield": "てつのたて",
+//!   "herbs": 6,
+//!   "keys": 6,
+//!   "items": ["たいまつ", null, "キメラのつばさ"],
+//!   "flags": {
+//!     "dragonScale": true,
+//!     "warriorRing": false,
+//!     "deathNecklace": false,
+//!     "golem": true,
+//!     "dragon": false
+//!   },
+//!   "salt": 0
+//! }
+//! ```
+//!
+//! * `weapon`, `armor`, `shield` は装備名。装備していない場合は `null`。
+//! * `items` は最大 8 個の道具名の配列で、インベントリの先頭から順に対応する。
+//!   空きは `null` で、末尾の空きは省略できる(出力時は省略する)。
+//! * `flags` はそれぞれりゅうのうろこ装備、せんしのゆびわ装備、しのくびかざり取得済、
+//!   ゴーレム撃破済、ドラゴン撃破済を表す。
+//! * 名前は [`GameState`] のドキュメントにある表記と完全に一致しなければならない。
+//!
+//! 変換は損失なしで、[`GameState`] → [`WebToolState`] → [`GameState`] で元に戻る。
+
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::{GameState, ARMOR_NAMES, ITEM_NAMES, SHIELD_NAMES, WEAPON_NAMES};
+use crate::prelude::*;
+
+/// Web ツールの JSON 形式のゲーム状態。形式の詳細は[モジュールのドキュメント](self)を参照。
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebToolState {
+    pub name: String,
+    pub exp: u16,
+    pub gold: u16,
+    pub weapon: Option<String>,
+    pub armor: Option<String>,
+    pub shield: Option<String>,
+    pub herbs: u8,
+    pub keys: u8,
+    pub items: Vec<Option<String>>,
+    pub flags: WebToolFlags,
+    pub salt: u8,
+}
+
+/// [`WebToolState`] のフラグたち。
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct WebToolFlags {
+    pub dragon_scale: bool,
+    pub warrior_ring: bool,
+    pub death_necklace: bool,
+    pub golem: bool,
+    pub dragon: bool,
+}
+
+impl TryFrom<&GameState> for WebToolState {
+    type Error = Dq1PasswordError;
+
+    /// `state` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    fn try_from(state: &GameState) -> Dq1PasswordResult<Self> {
+        state.validate()?;
+
+        let name = |names: &[&str], id: u8| (id != 0).then(|| names[usize::from(id)].to_owned());
+
+        let mut items: Vec<_> = state
+            .inventory
+            .iter()
+            .map(|&id| name(&ITEM_NAMES, id))
+            .collect();
+        while let Some(None) = items.last() {
+            items.pop();
+        }
+
+        Ok(Self {
+            name: state.hero_name.clone(),
+            exp: state.hero_xp,
+            gold: state.purse,
+            weapon: name(&WEAPON_NAMES, state.hero_weapon),
+            armor: name(&ARMOR_NAMES, state.hero_armor),
+            shield: name(&SHIELD_NAMES, state.hero_shield),
+            herbs: state.herb_count,
+            keys: state.key_count,
+            items,
+            flags: WebToolFlags {
+                dragon_scale: state.flag_equip_dragon_scale,
+                warrior_ring: state.flag_equip_warrior_ring,
+                death_necklace: state.flag_got_death_necklace,
+                golem: state.flag_beated_golem,
+                dragon: state.flag_beated_dragon,
+            },
+            salt: state.salt,
+        })
+    }
+}
+
+impl TryFrom<&WebToolState> for GameState {
+    type Error = Dq1PasswordError;
+
+    /// 未知の装備名や道具名を含む場合や、`items` が 8 個を超える場合、
+    /// `Err(Dq1PasswordError::InvalidFormat)` を返す。
+    ///
+    /// 変換結果が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    fn try_from(web: &WebToolState) -> Dq1PasswordResult<Self> {
+        fn id(names: &[&str], name: Option<&str>, key: &str) -> Dq1PasswordResult<u8> {
+            let name = match name {
+                Some(name) => name,
+                None => return Ok(0),
+            };
+            names[1..]
+                .iter()
+                .position(|&s| s == name)
+                .map(|i| u8::try_from(i + 1).unwrap())
+                .ok_or_else(|| {
+                    Dq1PasswordError::invalid_format(format!("{} の名前 {:?} が無効", key, name))
+                })
+        }
+
+        if web.items.len() > 8 {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "items の要素数 {} が 8 を超える",
+                web.items.len()
+            )));
+        }
+        let mut inventory = [0; 8];
+        for (i, (dst, item)) in inventory.iter_mut().zip(&web.items).enumerate() {
+            *dst = id(&ITEM_NAMES, item.as_deref(), &format!("items[{}]", i))?;
+        }
+
+        let state = Self {
+            hero_name: web.name.clone(),
+            hero_xp: web.exp,
+            purse: web.gold,
+            hero_weapon: id(&WEAPON_NAMES, web.weapon.as_deref(), "weapon")?,
+            hero_armor: id(&ARMOR_NAMES, web.armor.as_deref(), "armor")?,
+            hero_shield: id(&SHIELD_NAMES, web.shield.as_deref(), "shield")?,
+            herb_count: web.herbs,
+            key_count: web.keys,
+            inventory,
+            flag_equip_dragon_scale: web.flags.dragon_scale,
+            flag_equip_warrior_ring: web.flags.warrior_ring,
+            flag_got_death_necklace: web.flags.death_necklace,
+            flag_beated_golem: web.flags.golem,
+            flag_beated_dragon: web.flags.dragon,
+            salt: web.salt,
+        };
+        state.validate()?;
+
+        Ok(state)
+    }
+}
+
+/// Web ツールの JSON 形式の文字列からゲーム状態を読み込む。
+///
+/// JSON として解釈できない場合や未知のキーを含む場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+/// その他のエラーは `GameState::try_from(&WebToolState)` と同様。
+pub fn from_web_tool_json(json: impl AsRef<str>) -> Dq1PasswordResult<GameState> {
+    let web: WebToolState = serde_json::from_str(json.as_ref()).map_err(|e| {
+        Dq1PasswordError::invalid_format(format!("Web ツールの JSON として解釈できない: {}", e))
+    })?;
+
+    GameState::try_from(&web)
+}
+
+/// ゲーム状態を Web ツールの JSON 形式の整形された文字列に変換する。
+///
+/// `state` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn to_web_tool_json(state: &GameState) -> Dq1PasswordResult<String> {
+    let web = WebToolState::try_from(state)?;
+
+    Ok(serde_json::to_string_pretty(&web).expect("WebToolState should be serializable"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::ErrorKind;
+
+    /// Web ツールが実際に出力するのと同じ形の JSON。
+    const FIXTURE: &str = include_str!("../tests/fixtures/web_tool.json");
+
+    #[test]
+    fn test_from_web_tool_json() {
+        let state = from_web_tool_json(FIXTURE).unwrap();
+        assert_eq!(
+            state,
+            GameState {
+                hero_name: "ゆうてい".to_owned(),
+                hero_xp: 7000,
+                purse: 30000,
+                hero_weapon: 6,
+                hero_armor: 6,
+                hero_shield: 3,
+                herb_count: 4,
+                key_count: 2,
+                inventory: [8, 4, 0, 13, 0, 0, 0, 0],
+                flag_equip_dragon_scale: true,
+                flag_equip_warrior_ring: false,
+                flag_got_death_necklace: false,
+                flag_beated_golem: true,
+                flag_beated_dragon: true,
+                salt: 3,
+            }
+        );
+        assert!(crate::encode(&state).is_ok());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = from_web_tool_json(FIXTURE).unwrap();
+        let json = to_web_tool_json(&state).unwrap();
+
+        // 末尾の改行を除き、元の形に戻る。
+        assert_eq!(json, FIXTURE.trim_end());
+        assert_eq!(from_web_tool_json(&json).unwrap(), state);
+
+        // GameState 側からも損失なく往復する。
+        let state = crate::decode("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+        let web = WebToolState::try_from(&state).unwrap();
+        assert_eq!(web.items.len(), 8);
+        assert_eq!(GameState::try_from(&web).unwrap(), state);
+    }
+
+    #[test]
+    fn test_from_web_tool_json_error() {
+        let e = from_web_tool_json(FIXTURE.replace("\"salt\"", "\"seed\"")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e.to_string().contains("unknown field `seed`"));
+
+        let e = from_web_tool_json(FIXTURE.replace("\"golem\"", "\"golemDefeated\"")).unwrap_err();
+        assert!(e.to_string().contains("unknown field `golemDefeated`"));
+
+        let e =
+            from_web_tool_json(FIXTURE.replace("あまぐものつえ", "あまぐものつえ+1")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "データ形式が無効: items[3] の名前 \"あまぐものつえ+1\" が無効"
+        );
+
+        let e = from_web_tool_json(FIXTURE.replace("\"herbs\": 4", "\"herbs\": 7")).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::InvalidGameState(_)));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+/// 復活の呪文の文字と見た目が似ているが、そのままでは意図した文字にならない文字の検出結果。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ConfusableFinding {
+    /// 入力中の位置(文字単位、0 始まり)。
+    pub position: usize,
+
+    /// 入力中の疑わしい文字。
+    pub found: char,
+
+    /// 意図したと思われる文字。
+    pub intended: char,
+
+    /// 種類。
+    pub kind: ConfusableKind,
+}
+
+/// 紛らわしい文字の種類。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConfusableKind {
+    /// カタカナと似た漢字(例: '力' → 'か', '口' → 'ろ')。
+    Kanji,
+
+    /// 長音 'ー' と似た文字(例: 漢字の '一')。
+    ///
+    /// 長音は復活の呪文には使えないが、主人公の名前には使える('-' とみなされる)。
+    LongVowel,
+
+    /// 小書き文字(例: 'ぁ' → 'あ')。復活の呪文には小書き文字はない。
+    SmallKana,
+
+    /// 半濁音(例: 'ぱ' → 'ば')。復活の呪文には半濁音はなく、濁音の見間違いであることが多い。
+    Handakuten,
+
+    /// 変換すると無効な文字になるカタカナのうち、他の文字と似たもの(例: 'ン' → 'そ')。
+    Katakana,
+
+    /// ひらがなと似た記号(例: '<' → 'く')。半角/全角どちらも対象。
+    Symbol,
+}
+
+/// 入力中の、復活の呪文の文字と見た目が似ている紛らわしい文字たちを検出する。
+///
+/// OCR や IME による誤入力の診断用。入力の置換は行わない(厳密な正規化とも併用できる)。
+/// 正規化で正しく変換される文字(カタカナの 'ヘ' や半角カタカナなど)は報告しない。
+///
+/// 結果は入力中の位置の順に並ぶ。
+pub fn detect_confusables(input: impl AsRef<str>) -> Vec<ConfusableFinding> {
+    input
+        .as_ref()
+        .chars()
+        .enumerate()
+        .filter_map(|(position, found)| {
+            confusable_of(found).map(|(intended, kind)| ConfusableFinding {
+                position,
+                found,
+                intended,
+                kind,
+            })
+        })
+        .collect()
+}
+
+#[rustfmt::skip]
+fn confusable_of(c: char) -> Option<(char, ConfusableKind)> {
+    use ConfusableKind::*;
+
+    let confusable = match c {
+        // カタカナと似た漢字
+        '力' => ('か', Kanji),
+        '口' => ('ろ', Kanji),
+        '夕' => ('た', Kanji),
+        '卜' => ('と', Kanji),
+        '八' => ('は', Kanji),
+        '二' => ('に', Kanji),
+        '千' => ('ち', Kanji),
+        '工' => ('え', Kanji),
+        '才' => ('お', Kanji),
+        // 長音と似た文字
+        '一' => ('ー', LongVowel),
+        '─' => ('ー', LongVowel), // 罫線
+        '━' => ('ー', LongVowel), // 罫線(太)
+        '〜' => ('ー', LongVowel),
+        '～' => ('ー', LongVowel),
+        // 小書き文字
+        'ぁ' => ('あ', SmallKana), 'ぃ' => ('い', SmallKana), 'ぅ' => ('う', SmallKana),
+        'ぇ' => ('え', SmallKana), 'ぉ' => ('お', SmallKana),
+        'っ' => ('つ', SmallKana),
+        'ゃ' => ('や', SmallKana), 'ゅ' => ('ゆ', SmallKana), 'ょ' => ('よ', SmallKana),
+        'ゎ' => ('わ', SmallKana),
+        'ァ' => ('あ', SmallKana), 'ィ' => ('い', SmallKana), 'ゥ' => ('う', SmallKana),
+        'ェ' => ('え', SmallKana), 'ォ' => ('お', SmallKana),
+        'ッ' => ('つ', SmallKana),
+        'ャ' => ('や', SmallKana), 'ュ' => ('ゆ', SmallKana), 'ョ' => ('よ', SmallKana),
+        'ヮ' => ('わ', SmallKana), 'ヵ' => ('か', SmallKana), 'ヶ' => ('け', SmallKana),
+        'ｧ' => ('あ', SmallKana), 'ｨ' => ('い', SmallKana), 'ｩ' => ('う', SmallKana),
+        'ｪ' => ('え', SmallKana), 'ｫ' => ('お', SmallKana),
+        'ｯ' => ('つ', SmallKana),
+        'ｬ' => ('や', SmallKana), 'ｭ' => ('ゆ', SmallKana), 'ｮ' => ('よ', SmallKana),
+        // 半濁音
+        'ぱ' => ('ば', Handakuten), 'ぴ' => ('び', Handakuten), 'ぷ' => ('ぶ', Handakuten),
+        'ぺ' => ('べ', Handakuten), 'ぽ' => ('ぼ', Handakuten),
+        'パ' => ('ば', Handakuten), 'ピ' => ('び', Handakuten), 'プ' => ('ぶ', Handakuten),
+        'ペ' => ('べ', Handakuten), 'ポ' => ('ぼ', Handakuten),
+        '゜' => ('゛', Handakuten),
+        'ﾟ' => ('゛', Handakuten),
+        // 変換すると無効になるカタカナ
+        'ン' => ('そ', Katakana),
+        'ﾝ' => ('そ', Katakana),
+        'ヲ' => ('ち', Katakana),
+        'ｦ' => ('ち', Katakana),
+        // ひらがなと似た記号
+        '<' => ('く', Symbol),
+        '＜' => ('く', Symbol),
+        '〈' => ('く', Symbol),
+        '^' => ('へ', Symbol),
+        '＾' => ('へ', Symbol),
+        'L' => ('し', Symbol),
+        'Ｌ' => ('し', Symbol),
+        _ => return None,
+    };
+
+    Some(confusable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::normalize::normalize_password;
+
+    #[test]
+    fn test_detect_confusables() {
+        use ConfusableKind::*;
+
+        let finding = |position, found, intended, kind| ConfusableFinding {
+            position,
+            found,
+            intended,
+            kind,
+        };
+
+        assert_eq!(
+            detect_confusables("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            vec![]
+        );
+        // 正規化で正しく変換されるものは報告しない。
+        assert_eq!(detect_confusables("ヘリカｻﾞ へりか"), vec![]);
+
+        for (input, intended, kind) in [
+            ("力", 'か', Kanji),
+            ("口", 'ろ', Kanji),
+            ("夕", 'た', Kanji),
+            ("一", 'ー', LongVowel),
+            ("〜", 'ー', LongVowel),
+            ("ぁ", 'あ', SmallKana),
+            ("ッ", 'つ', SmallKana),
+            ("ｮ", 'よ', SmallKana),
+            ("ぱ", 'ば', Handakuten),
+            ("ポ", 'ぼ', Handakuten),
+            ("ﾟ", '゛', Handakuten),
+            ("ン", 'そ', Katakana),
+            ("ｦ", 'ち', Katakana),
+            ("<", 'く', Symbol),
+            ("＜", 'く', Symbol),
+            ("^", 'へ', Symbol),
+        ] {
+            let found = input.chars().next().unwrap();
+            assert_eq!(
+                detect_confusables(input),
+                vec![finding(0, found, intended, kind)]
+            );
+        }
+
+        // 位置は空白を含む入力中の文字単位。
+        let input = "ざぼちず 力ぢぎきつたうず せれえむるのぢ一";
+        assert_eq!(
+            detect_confusables(input),
+            vec![
+                finding(5, '力', 'か', Kanji),
+                finding(21, '一', 'ー', LongVowel),
+            ]
+        );
+        // 検出された文字は、寛容な正規化でも無効。
+        assert!(normalize_password(input).is_err());
+    }
+}
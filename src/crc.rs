@@ -1,5 +1,76 @@
+//! 復活の呪文で使われる CRC-16 (生成多項式 0x1021、初期値 0、反転なし)。
+//!
+//! ゲーム状態を表す 15 バイトのうち、`bytes[1..15]` の CRC の下位バイトが `bytes[0]` に格納される。
+//!
+//! 復活の呪文の各文字は 6bit 値に対応し、20 個の 6bit 値 `sixs` がバイト列の 120bit を下位側から順に埋める
+//! (`bytes[0] == sixs[0] | sixs[1] << 6` など)。
+//! CRC は線形なので、`bytes[1..15]` の CRC は各 6bit 値の寄与の XOR に分解できる。
+//! [`CRC8_TABLE_HEAD`], [`CRC8_TABLE_TAIL`], [`CRC16_TABLE_TAIL`] はその寄与を事前計算したもので、
+//! ソルバなどで CRC の制約を 6bit 値単位で表すのに使える:
+//!
+//! ```
+//! # use dq1_password::crc::*;
+//! # let sixs = [0u8; 20];
+//! let crc = (0..18).fold(CRC8_TABLE_HEAD[usize::from(sixs[1] >> 2)], |crc, i| {
+//!     crc ^ CRC8_TABLE_TAIL[i][usize::from(sixs[2 + i])]
+//! });
+//! // 有効な復活の呪文ならば、格納された CRC の下位バイトと一致する。
+//! assert_eq!(crc, sixs[0] | (sixs[1] << 6));
+//! ```
+
+use crate::decode::password_to_bytes;
+use crate::error::Dq1PasswordResult;
+use crate::generate::CrcTables;
+use crate::normalize::normalize_password_cow;
+
+/// `sixs[1]` の上位 4bit (`bytes[1]` の下位 4bit)の値ごとの、CRC の下位バイトへの寄与。
+///
+/// 添字は `sixs[1] >> 2`。`sixs[1]` の下位 2bit は `bytes[0]` (CRC 部)に属するので寄与しない。
+pub const CRC8_TABLE_HEAD: [u8; 0x10] = CrcTables::DQ1_FC.head;
+
+/// `sixs[2..20]` の各値の、CRC の下位バイトへの寄与。
+///
+/// `CRC8_TABLE_TAIL[i][six]` は `sixs[2 + i] == six` の寄与で、[`CRC16_TABLE_TAIL`] の下位バイトと等しい。
+pub const CRC8_TABLE_TAIL: [[u8; 0x40]; 18] = CrcTables::DQ1_FC.tail;
+
+/// `sixs[2..20]` (ゲーム状態バイト列の後半 108bit)の各値の、CRC への寄与。
+///
+/// `CRC16_TABLE_TAIL[i][six]` は `sixs[2 + i] == six` で他が全て 0 のときの `crc16(&bytes[1..])`。
+///
+/// エンコード時は 8bit 単位で [`crc_update`] が行われるため、6bit 値から見ると bit 順が変わることに注意。
+/// 24bit (6bit 値 4 個、バイト 3 個)単位で考えると、各バイトの MSB 側から見て以下のように並び替わる:
+///
+/// ```text
+/// 8bit: | abcdefgh | ijklmnop | qrstuvwx |
+/// 6bit: | cdefgh | mnopab | wxijkl | qrstuv |
+/// ```
+///
+/// すなわち 1 個目の 6bit 値は 1 バイト目の下位 6bit、2 個目は 2 バイト目の下位 4bit と 1 バイト目の上位 2bit、
+/// 3 個目は 3 バイト目の下位 2bit と 2 バイト目の上位 4bit、4 個目は 3 バイト目の上位 6bit となる。
+pub const CRC16_TABLE_TAIL: [[u16; 0x40]; 18] = crc16_table_tail(0x1021);
+
+/// バイト列の CRC を計算する。
+///
+/// 初期値 0 から各バイトを 8bit ずつ [`crc_update`] したもの。
+/// エンコーダは `bytes[1..15]` に対してこれを計算し、下位バイトを `bytes[0]` に格納する。
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &b| crc_update(crc, b, 8))
+}
+
+/// CRC `crc_pre` に `data` の下位 `n_bits` ビットを MSB 側から順に与えて更新したものを返す。
+///
+/// `data` の上位ビットは無視される(`n_bits == 8` ならばバイト全体)。
+/// 例えば `crc_update(crc_update(crc, 0b10, 2), 0b110101, 6)` は `crc_update(crc, 0b10110101, 8)` と等しい。
+///
 /// `n_bits` の範囲は `1..=8` でなければならない。
 pub const fn crc_update(crc_pre: u16, data: u8, n_bits: u8) -> u16 {
+    crc_update_poly(crc_pre, data, n_bits, 0x1021)
+}
+
+/// [`crc_update`] の生成多項式を `poly` に変えたもの。
+///
+/// 改造版などで CRC のパラメータが異なる場合に用いる([`Codec`](crate::Codec) を参照)。
+pub const fn crc_update_poly(crc_pre: u16, data: u8, n_bits: u8, poly: u16) -> u16 {
     let mut crc = crc_pre ^ ((data as u16) << (16 - n_bits));
 
     let mut i = 0;
@@ -7,7 +78,7 @@ pub const fn crc_update(crc_pre: u16, data: u8, n_bits: u8) -> u16 {
         let carry = (crc & (1 << 15)) != 0;
         crc <<= 1;
         if carry {
-            crc ^= 0x1021;
+            crc ^= poly;
         }
         i += 1;
     }
@@ -15,10 +86,69 @@ pub const fn crc_update(crc_pre: u16, data: u8, n_bits: u8) -> u16 {
     crc
 }
 
+/// 復活の呪文に格納された CRC の下位バイトと、ゲーム状態部分から再計算した CRC を返す。
+///
+/// 両者の下位バイトが一致しなければ、[`decode`](crate::decode()) は `Err(Dq1PasswordError::CrcMismatch)` を返す。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn password_crc(password: impl AsRef<str>) -> Dq1PasswordResult<(u8, u16)> {
+    let password = normalize_password_cow(password.as_ref())?;
+
+    Ok(bytes_crc(&password_to_bytes(&*password)))
+}
+
+/// ゲーム状態を表すバイト列に格納された CRC の下位バイトと、再計算した CRC を返す。
+pub(crate) fn bytes_crc(bytes: &[u8; 15]) -> (u8, u16) {
+    (bytes[0], crc16(&bytes[1..]))
+}
+
+/// ゲーム状態バイト列の後半 108bit についての 6bit 単位の CRC テーブルを返す。生成多項式は `poly`。
+///
+/// エンコード時は 8bit 単位で [`crc_update`] が行われるため、bit 順が変わることに注意([`CRC16_TABLE_TAIL`] を参照)。
+pub(crate) const fn crc16_table_tail(poly: u16) -> [[u16; 0x40]; 18] {
+    const fn upd(crc: u16, data: u8, n_bits: u8, poly: u16) -> u16 {
+        crc_update_poly(crc, data, n_bits, poly)
+    }
+
+    let mut table = [[0; 0x40]; 18];
+
+    let mut j = 0;
+    while j < 0x40 {
+        table[17][j as usize] = upd(0, j << 2, 8, poly);
+        table[16][j as usize] = upd(0, j >> 4, 2, poly) ^ upd(upd(0, j << 4, 8, poly), 0, 8, poly);
+        table[15][j as usize] = upd(upd(0, j >> 2, 4, poly), 0, 8, poly)
+            ^ upd(upd(upd(0, j << 6, 8, poly), 0, 8, poly), 0, 8, poly);
+        table[14][j as usize] = upd(upd(upd(0, j, 6, poly), 0, 8, poly), 0, 8, poly);
+        j += 1;
+    }
+
+    let mut i = 13;
+    loop {
+        let mut j = 0;
+        while j < 0x40 {
+            table[i][j] = upd(
+                upd(upd(table[i + 4][j], 0, 8, poly), 0, 8, poly),
+                0,
+                8,
+                poly,
+            );
+            j += 1;
+        }
+        if i == 0 {
+            break;
+        }
+        i -= 1;
+    }
+
+    table
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::error::Dq1PasswordError;
+
     #[test]
     fn test_crc_update() {
         assert_eq!(crc_update(0, 0, 1), 0);
@@ -28,5 +158,99 @@ mod tests {
         assert_eq!(crc_update(0, 3, 2), 0x3063);
         assert_eq!(crc_update(crc_update(0, 1, 1), 1, 1), 0x3063);
         assert_eq!(crc_update(crc_update(0, 0xFF, 8), 0xFF, 8), 0x1D0F);
+        assert_eq!(
+            crc_update(crc_update(0, 0b10, 2), 0b110101, 6),
+            crc_update(0, 0b10110101, 8)
+        );
+        assert_eq!(crc_update(0, 0xF0 | 0b10, 2), crc_update(0, 0b10, 2));
+    }
+
+    #[test]
+    fn test_crc_update_poly() {
+        assert_eq!(crc_update_poly(0, 1, 1, 0x1021), crc_update(0, 1, 1));
+        assert_eq!(crc_update_poly(0, 1, 1, 0x8005), 0x8005);
+        assert_eq!(crc_update_poly(0x8000, 0, 1, 0x8005), 0x8005);
+    }
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(&[]), 0);
+        assert_eq!(crc16(&[0]), 0);
+        assert_eq!(crc16(&[0xFF, 0xFF]), 0x1D0F);
+    }
+
+    #[test]
+    fn test_password_crc() {
+        // 復活の呪文 A
+        assert_eq!(
+            password_crc("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok((0xAD, 0xFAAD))
+        );
+
+        // 復活の呪文 A の最後の文字を変えたもの
+        assert_eq!(
+            password_crc("ざぼちずどぢぎきつたうずせれえむるのぢお"),
+            Ok((0xAD, 0xBA29))
+        );
+
+        assert!(matches!(
+            password_crc("ざぼちず"),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+    }
+
+    #[test]
+    fn test_crc16_table_tail() {
+        const TABLE: [[u16; 0x40]; 18] = CRC16_TABLE_TAIL;
+        const BYTES: [u8; 3] = [0b01000101, 0b01100111, 0b10001001];
+
+        let sixs = [
+            BYTES[0] & 0x3F,
+            (BYTES[0] >> 6) | ((BYTES[1] & 0xF) << 2),
+            (BYTES[1] >> 4) | ((BYTES[2] & 0x3) << 4),
+            BYTES[2] >> 2,
+        ];
+
+        let crc_actual = sixs
+            .iter()
+            .enumerate()
+            .fold(0, |crc, (i, &s)| crc ^ TABLE[14 + i][usize::from(s)]);
+        let crc_expect = BYTES.iter().fold(0, |crc, &b| crc_update(crc, b, 8));
+
+        assert_eq!(crc_actual, crc_expect);
+    }
+
+    #[test]
+    fn test_crc_tables() {
+        for (row8, row16) in CRC8_TABLE_TAIL.iter().zip(&CRC16_TABLE_TAIL) {
+            for (&crc8, &crc16) in row8.iter().zip(row16) {
+                assert_eq!(crc8, (crc16 & 0xFF) as u8);
+            }
+        }
+
+        // 復活の呪文 A の CRC をテーブルのみから求める。
+        let mut pre = 0;
+        let sixs: alloc::vec::Vec<_> = "ざぼちずどぢぎきつたうずせれえむるのぢえ"
+            .chars()
+            .map(|c| {
+                let cum = crate::PASSWORD_CHARS.iter().position(|&x| x == c).unwrap() as u8;
+                let six = cum.wrapping_sub(pre + 4) & 0x3F;
+                pre = cum;
+                six
+            })
+            .collect();
+
+        let crc_tail = (0..18).fold(0, |crc, i| {
+            crc ^ CRC16_TABLE_TAIL[i][usize::from(sixs[2 + i])]
+        });
+        let crc = CRC8_TABLE_HEAD[usize::from(sixs[1] >> 2)] ^ (crc_tail & 0xFF) as u8;
+        assert_eq!(crc, sixs[0] | (sixs[1] << 6));
+        assert_eq!(crc, 0xAD);
+
+        // 上位バイトは `sixs[1]` の上位 4bit を除いた分と一致する。
+        let mut bytes =
+            crate::decode::password_to_bytes("ざぼちずどぢぎきつたうずせれえむるのぢえ");
+        bytes[1] &= 0xF0;
+        assert_eq!(crc_tail, crc16(&bytes[1..]));
     }
 }
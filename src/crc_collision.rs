@@ -0,0 +1,113 @@
+use crate::char_set::CharSet;
+use crate::decode::decode;
+use crate::encode::state_to_bytes;
+use crate::error::Dq1PasswordResult;
+use crate::game_state::GameState;
+use crate::generate::{generate_with_pattern, GenerateOptions};
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// `state` と復活の呪文に格納される CRC のバイト(CRC の下位 8bit)が等しい、別の有効なゲーム状態たちを生成する。
+///
+/// 8bit のチェックがどれだけ弱いかを調べるための研究用の機能。
+///
+/// CRC のバイトはゲーム状態バイト列の先頭で、6bit 値配列の先頭の要素全体と 2 番目の要素の下位 2bit にあたる。
+/// これらを固定したパターン(先頭の文字は 1 通り、2 文字目は 16 通り)で [`generate_with_pattern`] を行い、
+/// 結果をデコードしたもののうち `state` 自身を除いたものを返す。
+/// 先頭の文字は CRC のバイトの下位 6bit のみで決まるので、結果の復活の呪文の先頭の文字は常に `state` のものと等しい。
+///
+/// 結果は [`generate_with_pattern`] の生成順に並ぶ(2 文字目の五十音表の順で、各 2 文字目については DP の復元順)。
+/// 同じ入力に対しては常に同じ結果となる。
+/// 異なるゲーム状態は異なるバイト列に対応するので、結果に重複はない(ソルトのみ異なるものは別のものとして扱う)。
+///
+/// `n_max` は生成上限数。
+///
+/// `state` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn crc_collisions(state: &GameState, n_max: usize) -> Dq1PasswordResult<Vec<GameState>> {
+    state.validate()?;
+    let state = state.normalize()?;
+
+    let crc = state_to_bytes(&state)[0];
+
+    let cum0 = ((crc & 0x3F) + 4) & 0x3F;
+    let cums1 = (0..0x40)
+        .filter(|six| six & 3 == crc >> 6)
+        .fold(0, |bits, six| bits | (1 << ((cum0 + six + 4) & 0x3F)));
+
+    let mut char_sets = [CharSet::full(); 20];
+    char_sets[0] = CharSet::from_bits(1 << cum0);
+    char_sets[1] = CharSet::from_bits(cums1);
+
+    // `state` 自身が含まれうるので 1 個余分に生成する。
+    let passwords = generate_with_pattern(
+        &Pattern::from_char_sets(&char_sets),
+        n_max.saturating_add(1),
+        &GenerateOptions::default(),
+    )?;
+
+    Ok(passwords
+        .iter()
+        .map(|password| decode(password).unwrap())
+        .filter(|other| *other != state)
+        .take(n_max)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::collections::BTreeSet;
+
+    use crate::encode::encode;
+
+    #[test]
+    fn test_crc_collisions() {
+        let states = [
+            GameState::default(),
+            GameState {
+                hero_name: "ゆうてい".to_owned(),
+                hero_xp: 1234,
+                purse: 567,
+                herb_count: 3,
+                inventory: [1, 2, 0, 0, 0, 0, 0, 0],
+                salt: 5,
+                ..Default::default()
+            },
+        ];
+        for state in &states {
+            let bytes = state_to_bytes(&state.normalize().unwrap());
+            let password = encode(state).unwrap();
+
+            let collisions = crc_collisions(state, 50).unwrap();
+            assert_eq!(collisions.len(), 50);
+            assert_eq!(
+                collisions.iter().collect::<BTreeSet<_>>().len(),
+                collisions.len()
+            );
+
+            for other in &collisions {
+                assert_ne!(*other, state.normalize().unwrap());
+                other.validate().unwrap();
+                assert_eq!(state_to_bytes(other)[0], bytes[0], "{:?}", other);
+                let other_password = encode(other).unwrap();
+                assert_eq!(other_password.chars().next(), password.chars().next());
+                assert_eq!(decode(&other_password).as_ref(), Ok(other));
+            }
+
+            // 結果は決定的で、生成上限数を減らしても順序は変わらない。
+            assert_eq!(crc_collisions(state, 10).unwrap(), collisions[..10]);
+        }
+
+        assert!(crc_collisions(&GameState::default(), 0).unwrap().is_empty());
+
+        assert!(crc_collisions(
+            &GameState {
+                hero_weapon: 8,
+                ..Default::default()
+            },
+            10
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use itertools::Itertools as _;
+
+use crate::decode::password_char_to_cum;
+use crate::encode::cum_to_password_char;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::normalize::normalize_password_cow;
+use crate::prelude::*;
+
+/// 復活の呪文を累積値の配列に変換する。
+///
+/// 累積値は各文字の [`PASSWORD_CHARS`](crate::PASSWORD_CHARS) 中のインデックス(`0..64`)。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn password_to_cums(password: impl AsRef<str>) -> Dq1PasswordResult<[u8; 20]> {
+    let password = normalize_password_cow(password.as_ref())?;
+
+    Ok(normalized_password_to_cums(&password))
+}
+
+/// 累積値の配列を復活の呪文に変換する。[`password_to_cums`] の逆変換。
+///
+/// 64 以上の値が含まれる場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+pub fn cums_to_password(cums: &[u8; 20]) -> Dq1PasswordResult<String> {
+    let mut password = String::with_capacity(3 * 20);
+    cums_to_password_into(cums, &mut password)?;
+
+    Ok(password)
+}
+
+/// [`cums_to_password`] と同様に変換し、結果を `buf` に書き込む(元の内容は消去する)。
+///
+/// `buf` の容量が足りていればアロケーションを行わないので、同じ `buf` を使い回せる。
+///
+/// 64 以上の値が含まれる場合、`buf` は変更せずに `Err(Dq1PasswordError::InvalidFormat)` を返す。
+pub fn cums_to_password_into(cums: &[u8; 20], buf: &mut String) -> Dq1PasswordResult<()> {
+    if cums.iter().any(|&cum| cum >= 0x40) {
+        return Err(cums_out_of_range(cums));
+    }
+
+    buf.clear();
+    buf.extend(cums.iter().map(|&cum| cum_to_password_char(cum)));
+
+    Ok(())
+}
+
+/// 累積値の配列を 6bit 値配列に変換する。
+///
+/// 各累積値は、直前の累積値(先頭では 0)に 6bit 値と 4 を加えたもの(64 を法とする)。
+/// 入力の上位 2bit は無視される。
+pub fn cums_to_sixs(cums: &[u8; 20]) -> [u8; 20] {
+    let mut sixs = [0; 20];
+
+    let mut pre = 0;
+    for (e, &cum) in core::iter::zip(&mut sixs, cums) {
+        *e = cum.wrapping_sub(pre).wrapping_sub(4) & 0x3F;
+        pre = cum;
+    }
+
+    sixs
+}
+
+/// 6bit 値配列を累積値の配列に変換する。[`cums_to_sixs`] の逆変換。
+///
+/// 入力の上位 2bit は無視される。
+pub fn sixs_to_cums(sixs: &[u8; 20]) -> [u8; 20] {
+    let mut cums = [0; 20];
+
+    let mut cum: u8 = 0;
+    for (e, &six) in core::iter::zip(&mut cums, sixs) {
+        cum = cum.wrapping_add(six).wrapping_add(4) & 0x3F;
+        *e = cum;
+    }
+
+    cums
+}
+
+/// 64 以上の累積値が含まれることを表すエラーを返す。
+fn cums_out_of_range(cums: &[u8; 20]) -> Dq1PasswordError {
+    Dq1PasswordError::invalid_format(format!(
+        "累積値は 64 未満でなければならない: {}",
+        cums.iter()
+            .enumerate()
+            .filter(|&(_, &cum)| cum >= 0x40)
+            .map(|(i, cum)| format!("位置 {} の {}", i, cum))
+            .join(", ")
+    ))
+}
+
+/// 正規化された復活の呪文を累積値の配列に変換する。
+pub(crate) fn normalized_password_to_cums(password: &str) -> [u8; 20] {
+    let mut cums = [0; 20];
+
+    for (e, c) in core::iter::zip(&mut cums, password.chars()) {
+        *e = password_char_to_cum(c).unwrap();
+    }
+
+    cums
+}
+
+/// 6bit 値配列をゲーム状態バイト列に変換する。
+pub(crate) fn sixs_to_bytes(sixs: &[u8; 20]) -> [u8; 15] {
+    let mut bytes = [0; 15];
+
+    for (bs, ss) in itertools::izip!(bytes.chunks_mut(3), sixs.chunks(4)) {
+        bs[0] = ss[0] | (ss[1] << 6);
+        bs[1] = (ss[1] >> 2) | (ss[2] << 4);
+        bs[2] = (ss[2] >> 4) | (ss[3] << 2);
+    }
+
+    bytes
+}
+
+/// ゲーム状態バイト列を 6bit 値配列に変換する。`sixs_to_bytes` の逆変換。
+pub(crate) fn bytes_to_sixs(bytes: &[u8; 15]) -> [u8; 20] {
+    let mut sixs = [0; 20];
+
+    for (ss, bs) in itertools::izip!(sixs.chunks_mut(4), bytes.chunks(3)) {
+        ss[0] = bs[0] & 0x3F;
+        ss[1] = (bs[0] >> 6) | ((bs[1] & 0xF) << 2);
+        ss[2] = (bs[1] >> 4) | ((bs[2] & 0x3) << 4);
+        ss[3] = bs[2] >> 2;
+    }
+
+    sixs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::password_to_bytes;
+    use crate::encode::bytes_to_password;
+    use crate::error::ErrorKind;
+    use crate::test_vector::test_vectors;
+
+    const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+    #[test]
+    fn test_password_to_cums() {
+        let cums = password_to_cums(A).unwrap();
+        assert_eq!(&cums[..4], [49, 63, 16, 51]);
+        assert_eq!(cums_to_password(&cums).unwrap(), A);
+
+        // 正規化される。
+        assert_eq!(
+            password_to_cums("ザボチズ ドヂギキツタ ウズセレエ ムルノヂエ").unwrap(),
+            cums
+        );
+        assert!(matches!(
+            password_to_cums("ざぼちず"),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+
+        let mut invalid = cums;
+        invalid[3] = 0x40;
+        invalid[19] = 0xFF;
+        let e = cums_to_password(&invalid).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert_eq!(
+            e.to_string(),
+            "データ形式が無効: 累積値は 64 未満でなければならない: 位置 3 の 64, 位置 19 の 255"
+        );
+
+        // バッファに書き込む版。容量が足りていれば再確保しない。
+        let mut buf = String::from("ゆうてい");
+        cums_to_password_into(&cums, &mut buf).unwrap();
+        assert_eq!(buf, A);
+        let ptr = buf.as_ptr();
+        cums_to_password_into(
+            &password_to_cums("ゆうていみやおうきむこうほりいゆうじとり").unwrap(),
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(buf, "ゆうていみやおうきむこうほりいゆうじとり");
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(cums_to_password_into(&invalid, &mut buf), Err(e));
+        assert_eq!(buf, "ゆうていみやおうきむこうほりいゆうじとり");
+    }
+
+    #[test]
+    fn test_cums_to_sixs() {
+        let cums = password_to_cums(A).unwrap();
+        let sixs = cums_to_sixs(&cums);
+        assert_eq!(sixs[0], (49 - 4) & 0x3F);
+        assert_eq!(sixs[1], (63 - 49 - 4) & 0x3F);
+        assert_eq!(sixs_to_cums(&sixs), cums);
+
+        // 全ての 6bit 値の組について可逆。
+        for (a, b) in itertools::iproduct!(0..0x40, 0..0x40) {
+            let mut sixs = [0; 20];
+            sixs[7] = a;
+            sixs[8] = b;
+            assert_eq!(cums_to_sixs(&sixs_to_cums(&sixs)), sixs);
+        }
+
+        // 上位 2bit は無視される。
+        assert_eq!(sixs_to_cums(&[0xC0; 20]), sixs_to_cums(&[0; 20]));
+    }
+
+    #[test]
+    fn test_bytes_to_sixs() {
+        let bytes: [u8; 15] = core::array::from_fn(|i| (i as u8).wrapping_mul(0x9D));
+        assert_eq!(sixs_to_bytes(&bytes_to_sixs(&bytes)), bytes);
+    }
+
+    #[test]
+    fn test_layers_roundtrip() {
+        for vector in test_vectors() {
+            let password = crate::normalize_password(vector.password).unwrap();
+
+            // 復活の呪文 -> 累積値 -> 6bit 値 -> バイト列
+            let cums = password_to_cums(&password).unwrap();
+            let sixs = cums_to_sixs(&cums);
+            let bytes = sixs_to_bytes(&sixs);
+            assert_eq!(bytes, password_to_bytes(&password));
+
+            // バイト列 -> 6bit 値 -> 累積値 -> 復活の呪文
+            assert_eq!(bytes_to_sixs(&bytes), sixs);
+            assert_eq!(sixs_to_cums(&sixs), cums);
+            assert_eq!(cums_to_password(&cums).unwrap(), password);
+            assert_eq!(bytes_to_password(&bytes), password);
+        }
+    }
+}
@@ -1,10 +1,14 @@
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
-use crate::crc::crc_update;
-use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::alphabet::{password_char_index, HERO_NAME_CHARS};
+use crate::codec::Codec;
+use crate::cums::{cums_to_sixs, normalized_password_to_cums, sixs_to_bytes};
+use crate::error::{ContextError, Dq1PasswordError, Dq1PasswordResult};
 use crate::game_state::GameState;
-use crate::normalize::normalize_password;
-use crate::validate::{validate_herb_count, validate_inventory, validate_key_count};
+use crate::normalize::{normalize_password_cow, normalize_password_strict};
+use crate::plausibility::Implausibility;
+use crate::prelude::*;
+use crate::validate::ValidationPolicy;
 
 /// 復活の呪文を正規化されたゲーム状態にデコードして返す。
 ///
@@ -14,84 +18,138 @@ use crate::validate::{validate_herb_count, validate_inventory, validate_key_coun
 ///
 /// デコード結果の CRC が一致しない場合、`Err(Dq1PasswordError::CrcMismatch)` を返す。
 pub fn decode(password: impl AsRef<str>) -> Dq1PasswordResult<GameState> {
-    let password = normalize_password(password)?;
+    decode_with_policy(password, &ValidationPolicy::DQ1_FC)
+}
 
-    let bytes = password_to_bytes(password);
-    check_bytes_crc(&bytes)?;
+/// ゲーム状態の検査の基準を指定して、復活の呪文をゲーム状態にデコードする。
+///
+/// 改造版などで、本家の基準では無効なゲーム状態(やくそう所持数が 7 以上など)を受け付ける場合に用いる。
+/// `ValidationPolicy::DQ1_FC` を指定した場合、[`decode`] と同じ結果となる。
+///
+/// エラーは [`decode`] と同様(ゲーム状態が無効かどうかは `policy` により判定する)。
+pub fn decode_with_policy(
+    password: impl AsRef<str>,
+    policy: &ValidationPolicy,
+) -> Dq1PasswordResult<GameState> {
+    decode_normalized(
+        &normalize_password_cow(password.as_ref())?,
+        &Codec::dq1_fc(),
+        policy,
+    )
+}
 
-    let state = bytes_to_state(&bytes);
-    validate_herb_count(state.herb_count)?;
-    validate_key_count(state.key_count)?;
-    validate_inventory(&state.inventory)?;
+/// 復活の呪文が正規形であること([`normalize_password_strict`])を要求してデコードする。
+///
+/// `password` が正規形でない場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+/// それ以外のエラーは [`decode`] と同様。
+pub fn decode_strict(password: impl AsRef<str>) -> Dq1PasswordResult<GameState> {
+    decode_normalized(
+        &normalize_password_strict(password)?,
+        &Codec::dq1_fc(),
+        &ValidationPolicy::DQ1_FC,
+    )
+}
+
+/// 文字表と CRC のパラメータを指定して、復活の呪文をゲーム状態にデコードする。
+///
+/// `password` と戻り値の主人公の名前は `codec` の文字表で表される。
+/// `Codec::dq1_fc()` を指定した場合、[`decode`] と同じ結果となる。
+///
+/// エラーは [`decode`] と同様。
+pub fn decode_with_codec(password: impl AsRef<str>, codec: &Codec) -> Dq1PasswordResult<GameState> {
+    let mut state = decode_normalized(
+        &codec.to_dq1_password(password.as_ref())?,
+        codec,
+        &ValidationPolicy::DQ1_FC,
+    )?;
+    state.hero_name = codec.hero_name_from_dq1(&state.hero_name);
 
     Ok(state)
 }
 
+/// 正規化済みの(本家の文字表で表した)復活の呪文を、`codec` の CRC のパラメータと `policy` の基準でデコードする。
+fn decode_normalized(
+    password: &str,
+    codec: &Codec,
+    policy: &ValidationPolicy,
+) -> Dq1PasswordResult<GameState> {
+    decode_bytes(&password_to_bytes(password), codec, policy)
+}
+
+/// ゲーム状態を表すバイト列を、`codec` の CRC のパラメータと `policy` の基準で検査してゲーム状態に変換する。
+///
+/// エラーは [`decode`] と同様(`Err(Dq1PasswordError::InvalidPassword)` は返さない)。
+pub(crate) fn decode_bytes(
+    bytes: &[u8; 15],
+    codec: &Codec,
+    policy: &ValidationPolicy,
+) -> Dq1PasswordResult<GameState> {
+    check_bytes_crc(bytes, codec)?;
+
+    let state = bytes_to_state(bytes);
+    policy.validate_herb_count(state.herb_count)?;
+    policy.validate_key_count(state.key_count)?;
+    policy.validate_inventory(state.inventory)?;
+
+    Ok(state)
+}
+
+/// 復活の呪文をデコードし、ゲーム状態とその不自然な点([`GameState::plausibility`])を返す。
+///
+/// エラーは [`decode`] と同様。
+pub fn decode_with_warnings(
+    password: impl AsRef<str>,
+) -> Dq1PasswordResult<(GameState, Vec<Implausibility>)> {
+    let state = decode(password)?;
+    let warnings = state.plausibility();
+
+    Ok((state, warnings))
+}
+
+/// 複数の復活の呪文をまとめてデコードする。
+///
+/// 結果は入力と同じ順に並ぶ。失敗したものには、入力の文字列と行番号(何番目の入力か、1 始まり)が付加される。
+pub fn decode_many<I>(passwords: I) -> Vec<Result<GameState, ContextError>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    passwords
+        .into_iter()
+        .enumerate()
+        .map(|(i, password)| {
+            let password = password.as_ref();
+            decode(password).map_err(|e| e.with_input(password).with_line(i + 1))
+        })
+        .collect()
+}
+
 /// 復活の呪文をゲーム状態を表すバイト列に変換する。
 ///
 /// `password` は正規化済みでなければならない。
-fn password_to_bytes(password: impl AsRef<str>) -> [u8; 15] {
-    let cs: Vec<_> = password.as_ref().chars().collect();
-
-    let mut bytes = [0; 15];
-
-    let mut pre = 0;
-    let mut get_bits = move |c: char| -> u8 {
-        let cur = password_char_to_cum(c).unwrap();
-        let bits = (cur.wrapping_sub(pre + 4)) & 0x3F;
-        pre = cur;
-        bits
-    };
-
-    for (chunk, cs) in itertools::izip!(bytes.chunks_mut(3), cs.chunks(4)) {
-        let bits = get_bits(cs[0]);
-        chunk[0] = bits;
-        let bits = get_bits(cs[1]);
-        chunk[0] |= bits << 6;
-        chunk[1] = bits >> 2;
-        let bits = get_bits(cs[2]);
-        chunk[1] |= bits << 4;
-        chunk[2] = bits >> 4;
-        let bits = get_bits(cs[3]);
-        chunk[2] |= bits << 2;
-    }
-
-    bytes
+pub(crate) fn password_to_bytes(password: impl AsRef<str>) -> [u8; 15] {
+    sixs_to_bytes(&cums_to_sixs(&normalized_password_to_cums(
+        password.as_ref(),
+    )))
 }
 
 pub(crate) fn password_char_to_cum(c: char) -> Option<u8> {
-    const MAP: phf::Map<char, u8> = phf::phf_map! {
-        'あ' => 0x00, 'い' => 0x01, 'う' => 0x02, 'え' => 0x03, 'お' => 0x04,
-        'か' => 0x05, 'き' => 0x06, 'く' => 0x07, 'け' => 0x08, 'こ' => 0x09,
-        'さ' => 0x0A, 'し' => 0x0B, 'す' => 0x0C, 'せ' => 0x0D, 'そ' => 0x0E,
-        'た' => 0x0F, 'ち' => 0x10, 'つ' => 0x11, 'て' => 0x12, 'と' => 0x13,
-        'な' => 0x14, 'に' => 0x15, 'ぬ' => 0x16, 'ね' => 0x17, 'の' => 0x18,
-        'は' => 0x19, 'ひ' => 0x1A, 'ふ' => 0x1B, 'へ' => 0x1C, 'ほ' => 0x1D,
-        'ま' => 0x1E, 'み' => 0x1F, 'む' => 0x20, 'め' => 0x21, 'も' => 0x22,
-        'や' => 0x23, 'ゆ' => 0x24, 'よ' => 0x25,
-        'ら' => 0x26, 'り' => 0x27, 'る' => 0x28, 'れ' => 0x29, 'ろ' => 0x2A,
-        'わ' => 0x2B,
-        'が' => 0x2C, 'ぎ' => 0x2D, 'ぐ' => 0x2E, 'げ' => 0x2F, 'ご' => 0x30,
-        'ざ' => 0x31, 'じ' => 0x32, 'ず' => 0x33, 'ぜ' => 0x34, 'ぞ' => 0x35,
-        'だ' => 0x36, 'ぢ' => 0x37, 'づ' => 0x38, 'で' => 0x39, 'ど' => 0x3A,
-        'ば' => 0x3B, 'び' => 0x3C, 'ぶ' => 0x3D, 'べ' => 0x3E, 'ぼ' => 0x3F,
-    };
-
-    MAP.get(&c).copied()
+    password_char_index(c)
 }
 
-fn check_bytes_crc(bytes: &[u8; 15]) -> Dq1PasswordResult<()> {
-    let crc_actual = bytes[1..].iter().fold(0, |crc, &b| crc_update(crc, b, 8));
+fn check_bytes_crc(bytes: &[u8; 15], codec: &Codec) -> Dq1PasswordResult<()> {
+    let crc_expect = bytes[0];
+    let crc_actual = codec.crc16(&bytes[1..]);
 
-    if u8::try_from(crc_actual & 0xFF).unwrap() != bytes[0] {
-        return Err(Dq1PasswordError::crc_mismatch(bytes[0], crc_actual));
+    if u8::try_from(crc_actual & 0xFF).unwrap() != crc_expect {
+        return Err(Dq1PasswordError::crc_mismatch(crc_expect, crc_actual));
     }
 
     Ok(())
 }
 
 /// バイト列をゲーム状態に変換する。
-fn bytes_to_state(bytes: &[u8; 15]) -> GameState {
+pub(crate) fn bytes_to_state(bytes: &[u8; 15]) -> GameState {
     fn bit_test(x: u8, idx: u8) -> bool {
         (x & (1 << idx)) != 0
     }
@@ -150,32 +208,15 @@ fn bytes_to_state(bytes: &[u8; 15]) -> GameState {
 }
 
 /// 6bit 値の配列を主人公の名前に unpack する。
-fn unpack_hero_name(packed: [u8; 4]) -> String {
+pub(crate) fn unpack_hero_name(packed: [u8; 4]) -> String {
     IntoIterator::into_iter(packed)
         .map(unpack_hero_name_char)
         .collect()
 }
 
 /// 6bit 値を主人公の名前の文字に unpack する。
-fn unpack_hero_name_char(b: u8) -> char {
-    #[rustfmt::skip]
-    const CHARS: [char; 0x40] = [
-        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
-        'あ', 'い', 'う', 'え', 'お',
-        'か', 'き', 'く', 'け', 'こ',
-        'さ', 'し', 'す', 'せ', 'そ',
-        'た', 'ち', 'つ', 'て', 'と',
-        'な', 'に', 'ぬ', 'ね', 'の',
-        'は', 'ひ', 'ふ', 'へ', 'ほ',
-        'ま', 'み', 'む', 'め', 'も',
-        'や', 'ゆ', 'よ',
-        'ら', 'り', 'る', 'れ', 'ろ',
-        'わ', 'を', 'ん',
-        'っ', 'ゃ', 'ゅ', 'ょ',
-        '゛', '゜', '-', ' ',
-    ];
-
-    CHARS[usize::from(b)]
+pub(crate) fn unpack_hero_name_char(b: u8) -> char {
+    HERO_NAME_CHARS[usize::from(b)]
 }
 
 #[cfg(test)]
@@ -216,6 +257,12 @@ mod tests {
             }),
         );
 
+        // 復活の呪文 A は自然なゲーム状態。
+        assert!(matches!(
+            decode_with_warnings("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok((_, warnings)) if warnings.is_empty()
+        ));
+
         // 復活の呪文 A に対応する GameState の inventory[7] を 15 にしたもの
         assert!(matches!(
             decode("どくのばうぼぞそこけばがきもびはめつごび"),
@@ -228,4 +275,59 @@ mod tests {
             Err(Dq1PasswordError::CrcMismatch { .. })
         ));
     }
+
+    #[test]
+    fn test_decode_strict() {
+        const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        assert_eq!(decode_strict(A), decode(A));
+        assert!(decode_strict(A).is_ok());
+
+        const A_SPACED: &str = "ざぼちず どぢぎき つたうず せれえむ るのぢえ";
+        assert_eq!(decode(A_SPACED), decode(A));
+        assert!(matches!(
+            decode_strict(A_SPACED).unwrap_err().kind(),
+            crate::ErrorKind::InvalidPassword(crate::PasswordErrorKind::NotCanonical(changes))
+                if changes.len() == 4
+        ));
+
+        // 正規形であっても CRC が一致しなければエラー。
+        assert!(matches!(
+            decode_strict("ざぼちずどぢぎきつたうずせれえむるのぢお"),
+            Err(Dq1PasswordError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_many() {
+        const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+        const BAD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢお";
+
+        let results = decode_many([A, BAD, A]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Ok(decode(A).unwrap()));
+        assert_eq!(results[2], Ok(decode(A).unwrap()));
+
+        let e = results[1].clone().unwrap_err();
+        assert_eq!(e.input, BAD);
+        assert_eq!(e.line, Some(2));
+        assert_eq!(e.source, decode(BAD).unwrap_err());
+        assert_eq!(
+            e.to_string(),
+            format!("line 2: {}: {}", BAD, decode(BAD).unwrap_err())
+        );
+        assert!(core::error::Error::source(&e).is_some());
+
+        // 単体の decode のエラーには入力は付加されない。
+        assert!(matches!(
+            decode(BAD),
+            Err(Dq1PasswordError::CrcMismatch { .. })
+        ));
+        assert_eq!(
+            decode(BAD).unwrap_err().with_input(BAD).to_string(),
+            format!("{}: {}", BAD, decode(BAD).unwrap_err())
+        );
+
+        assert!(decode_many(Vec::<String>::new()).is_empty());
+    }
 }
@@ -1,27 +1,82 @@
+use itertools::Itertools as _;
 use thiserror::Error;
 
+use crate::game_state::GameStateField;
+use crate::prelude::*;
+
 #[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
 pub enum Dq1PasswordError {
     #[error("ゲーム状態が無効: {0}")]
-    InvalidGameState(String),
+    InvalidGameState(GameStateError),
 
     #[error("復活の呪文の形式が無効: {0}")]
-    InvalidPassword(String),
+    InvalidPassword(PasswordError),
 
     #[error("CRC 下位バイトが一致しない: expect=0x??{expect:02X}, actual={actual:#04X}")]
     CrcMismatch { expect: u8, actual: u16 },
 
     #[error("パターンが無効: {0}")]
-    InvalidPattern(String),
+    InvalidPattern(PatternError),
+
+    #[error("制約が無効: {0}")]
+    InvalidConstraint(String),
+
+    #[error("リソース上限を超える: {0}")]
+    ResourceLimit(String),
+
+    #[error("データ形式が無効: {0}")]
+    InvalidFormat(String),
 }
 
 impl Dq1PasswordError {
-    pub(crate) fn invalid_game_state(msg: impl Into<String>) -> Self {
-        Self::InvalidGameState(msg.into())
+    pub(crate) fn invalid_game_state(kind: GameStateErrorKind, msg: impl Into<String>) -> Self {
+        Self::InvalidGameState(GameStateError::new(kind, msg))
+    }
+
+    pub(crate) fn out_of_range(
+        field: GameStateField,
+        value: u32,
+        max: u32,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::invalid_game_state(GameStateErrorKind::OutOfRange { field, value, max }, msg)
+    }
+
+    pub(crate) fn invalid_password_length(len: usize, msg: impl Into<String>) -> Self {
+        Self::InvalidPassword(PasswordError::new(
+            PasswordErrorKind::WrongLength { len },
+            msg,
+        ))
+    }
+
+    pub(crate) fn invalid_password_chars(
+        offenders: Vec<(usize, char)>,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::InvalidPassword(PasswordError::new(
+            PasswordErrorKind::InvalidChars(offenders),
+            msg,
+        ))
+    }
+
+    pub(crate) fn invalid_password_tiles(
+        offenders: Vec<(usize, u8)>,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::InvalidPassword(PasswordError::new(
+            PasswordErrorKind::InvalidTiles(offenders),
+            msg,
+        ))
     }
 
-    pub(crate) fn invalid_password(msg: impl Into<String>) -> Self {
-        Self::InvalidPassword(msg.into())
+    pub(crate) fn password_not_canonical(
+        changes: Vec<(usize, char, Option<char>)>,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::InvalidPassword(PasswordError::new(
+            PasswordErrorKind::NotCanonical(changes),
+            msg,
+        ))
     }
 
     pub(crate) fn crc_mismatch(expect: u8, actual: u16) -> Self {
@@ -29,8 +84,743 @@ impl Dq1PasswordError {
     }
 
     pub(crate) fn invalid_pattern(msg: impl Into<String>) -> Self {
-        Self::InvalidPattern(msg.into())
+        Self::invalid_pattern_syntax(PatternErrorKind::Other, msg)
+    }
+
+    pub(crate) fn invalid_pattern_syntax(kind: PatternErrorKind, msg: impl Into<String>) -> Self {
+        Self::InvalidPattern(PatternError::new(kind, msg))
+    }
+
+    pub(crate) fn invalid_pattern_length(len: usize, msg: impl Into<String>) -> Self {
+        Self::InvalidPattern(PatternError::new(
+            PatternErrorKind::WrongLength { len },
+            msg,
+        ))
+    }
+
+    pub(crate) fn invalid_pattern_chars(
+        offenders: Vec<(usize, char)>,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::InvalidPattern(PatternError::new(
+            PatternErrorKind::InvalidChars(offenders),
+            msg,
+        ))
+    }
+
+    pub(crate) fn invalid_constraint(msg: impl Into<String>) -> Self {
+        Self::InvalidConstraint(msg.into())
+    }
+
+    pub(crate) fn resource_limit(msg: impl Into<String>) -> Self {
+        Self::ResourceLimit(msg.into())
+    }
+
+    pub(crate) fn invalid_format(msg: impl Into<String>) -> Self {
+        Self::InvalidFormat(msg.into())
+    }
+
+    /// 入力を付加したエラーを返す。
+    pub fn with_input(self, input: impl Into<String>) -> ContextError {
+        ContextError {
+            input: input.into(),
+            line: None,
+            source: self,
+        }
+    }
+
+    /// 原因の種類を返す。
+    ///
+    /// メッセージ(`Display` の出力)は人間向けのものなので、プログラムで原因を判別する場合はこちらを使う。
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::InvalidGameState(e) => ErrorKind::InvalidGameState(e.kind().clone()),
+            Self::InvalidPassword(e) => ErrorKind::InvalidPassword(e.kind().clone()),
+            Self::CrcMismatch { expect, actual } => ErrorKind::CrcMismatch {
+                expect: *expect,
+                actual: *actual,
+            },
+            Self::InvalidPattern(e) => ErrorKind::InvalidPattern(e.kind().clone()),
+            Self::InvalidConstraint(_) => ErrorKind::InvalidConstraint,
+            Self::ResourceLimit(_) => ErrorKind::ResourceLimit,
+            Self::InvalidFormat(_) => ErrorKind::InvalidFormat,
+        }
+    }
+
+    /// 指定した言語のメッセージを返す。
+    ///
+    /// `Lang::Ja` の場合は `Display` の出力と同じ。
+    /// それ以外の言語では構造化された原因([`kind`](Self::kind))からメッセージを組み立てる。
+    /// ただし、原因が構造化されていないもの(`InvalidConstraint`, `ResourceLimit`, `InvalidFormat`,
+    /// `PatternErrorKind::Other`)は日本語の詳細をそのまま含む。
+    pub fn to_message(&self, lang: Lang) -> String {
+        match lang {
+            Lang::Ja => self.to_string(),
+            Lang::En => match self {
+                Self::InvalidGameState(e) => {
+                    format!("invalid game state: {}", game_state_message_en(e.kind()))
+                }
+                Self::InvalidPassword(e) => {
+                    format!("invalid password: {}", password_message_en(e.kind()))
+                }
+                Self::CrcMismatch { expect, actual } => format!(
+                    "CRC low byte mismatch: expect=0x??{:02X}, actual={:#04X}",
+                    expect, actual
+                ),
+                Self::InvalidPattern(e) => format!(
+                    "invalid pattern: {}",
+                    pattern_message_en(e.kind()).unwrap_or_else(|| e.message().to_owned())
+                ),
+                Self::InvalidConstraint(msg) => format!("invalid constraint: {}", msg),
+                Self::ResourceLimit(msg) => format!("resource limit exceeded: {}", msg),
+                Self::InvalidFormat(msg) => format!("invalid format: {}", msg),
+            },
+        }
+    }
+}
+
+/// 入力(と、その行番号)を付加したエラー。一括処理で、どの入力が失敗したかを示すのに用いる。
+///
+/// [`Dq1PasswordError::with_input`] で作る。
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub struct ContextError {
+    /// 失敗した入力。
+    pub input: String,
+
+    /// 入力の行番号(1 始まり)。不明な場合は `None`。
+    pub line: Option<usize>,
+
+    /// 元のエラー。
+    pub source: Dq1PasswordError,
+}
+
+impl ContextError {
+    /// 行番号(1 始まり)を付加したエラーを返す。
+    pub fn with_line(self, line: usize) -> Self {
+        Self {
+            line: Some(line),
+            ..self
+        }
+    }
+}
+
+impl ContextError {
+    /// 指定した言語のメッセージを返す。元のエラーの部分は [`Dq1PasswordError::to_message`] による。
+    ///
+    /// `Lang::Ja` の場合は `Display` の出力と同じ。
+    pub fn to_message(&self, lang: Lang) -> String {
+        let line = self
+            .line
+            .map_or_else(String::new, |line| format!("line {}: ", line));
+
+        format!("{}{}: {}", line, self.input, self.source.to_message(lang))
+    }
+}
+
+impl core::fmt::Display for ContextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{}: {}", self.input, self.source)
+    }
+}
+
+/// エラーメッセージの言語。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Lang {
+    /// 日本語。
+    #[default]
+    Ja,
+
+    /// 英語。
+    En,
+}
+
+fn game_state_message_en(kind: &GameStateErrorKind) -> String {
+    match kind {
+        GameStateErrorKind::OutOfRange { field, value, max } => {
+            let name = match field {
+                GameStateField::HeroName => "hero name".to_owned(),
+                GameStateField::HeroWeapon => "hero weapon ID".to_owned(),
+                GameStateField::HeroArmor => "hero armor ID".to_owned(),
+                GameStateField::HeroShield => "hero shield ID".to_owned(),
+                GameStateField::HerbCount => "herb count".to_owned(),
+                GameStateField::KeyCount => "key count".to_owned(),
+                GameStateField::Inventory(i) => format!("inventory[{}]: tool ID", i),
+                GameStateField::Tool => "tool ID".to_owned(),
+                GameStateField::Salt => "salt".to_owned(),
+                GameStateField::Level => {
+                    return format!("level must be between 1 and {}: {}", max, value)
+                }
+            };
+            format!("{} must be at most {}: {}", name, max, value)
+        }
+        GameStateErrorKind::HeroNameTooLong => {
+            "hero name must be at most 4 characters (dakuten/handakuten count as 1 character)"
+                .to_owned()
+        }
+        GameStateErrorKind::HeroNameInvalidChars(cs) => format!(
+            "hero name contains invalid characters: {}",
+            cs.iter().map(|c| format!("'{}'", c)).join(", ")
+        ),
+        GameStateErrorKind::HeroNameNotEnterable(c) => {
+            format!(
+                "hero name contains a character that cannot be entered: '{}'",
+                c
+            )
+        }
+        GameStateErrorKind::HeroNameMisplacedMark { pos, mark } => format!(
+            "'{}' at character {} of hero name cannot follow the preceding character",
+            mark,
+            pos + 1
+        ),
+        GameStateErrorKind::HeroNameLeadingSpace => "hero name starts with a space".to_owned(),
+        GameStateErrorKind::HeroNameInvalidRomaji { pos, syllable } => format!(
+            "cannot convert \"{}\" at character {} of romaji hero name to kana",
+            syllable,
+            pos + 1
+        ),
+    }
+}
+
+fn password_message_en(kind: &PasswordErrorKind) -> String {
+    match kind {
+        PasswordErrorKind::WrongLength { len } => format!(
+            "password must be exactly 20 characters, ignoring whitespace (got {})",
+            len
+        ),
+        PasswordErrorKind::InvalidChars(offenders) => format!(
+            "password contains invalid characters: {}",
+            offenders_en(offenders)
+        ),
+        PasswordErrorKind::NotCanonical(changes) => format!(
+            "password is not in canonical form: {}",
+            changes
+                .iter()
+                .map(|&(i, c, to)| match to {
+                    Some(to) => format!("'{}' at position {} would become '{}'", c, i, to),
+                    None => format!("'{}' at position {} would be removed", c, i),
+                })
+                .join(", ")
+        ),
+        PasswordErrorKind::InvalidTiles(offenders) => format!(
+            "tiles do not represent password characters: {}",
+            offenders
+                .iter()
+                .map(|&(i, tile)| format!("{:#04X} at position {}", tile, i))
+                .join(", ")
+        ),
     }
 }
 
+/// `PatternErrorKind::Other` の場合は None を返す。
+fn pattern_message_en(kind: &PatternErrorKind) -> Option<String> {
+    let msg = match kind {
+        PatternErrorKind::WrongLength { len } => format!(
+            "pattern must be exactly 20 characters, ignoring whitespace (got {})",
+            len
+        ),
+        PatternErrorKind::InvalidChars(offenders) => format!(
+            "pattern contains invalid characters: {}",
+            offenders_en(offenders)
+        ),
+        PatternErrorKind::MultipleStars => "pattern cannot contain more than one '*'".to_owned(),
+        PatternErrorKind::UnmatchedClose { pos } => {
+            format!("']' at position {} has no matching '['", pos)
+        }
+        PatternErrorKind::Unclosed { pos } => format!("'[' at position {} is not closed", pos),
+        PatternErrorKind::NestedClass { pos } => {
+            format!("character class at position {} is nested", pos)
+        }
+        PatternErrorKind::EmptyClass { pos } => {
+            format!("character class at position {} is empty", pos)
+        }
+        PatternErrorKind::ClassExcludesAll { pos } => format!(
+            "negated character class at position {} excludes every character",
+            pos
+        ),
+        PatternErrorKind::RangeWithoutEnd { pos, start } => format!(
+            "range in character class at position {} has no end: '{}-'",
+            pos, start
+        ),
+        PatternErrorKind::RangeWithoutStart { pos } => {
+            format!("range in character class at position {} has no start", pos)
+        }
+        PatternErrorKind::InvalidRange { pos, start, end } => format!(
+            "range in character class at position {} is invalid: '{}-{}'",
+            pos, start, end
+        ),
+        PatternErrorKind::Other => return None,
+    };
+
+    Some(msg)
+}
+
+fn offenders_en(offenders: &[(usize, char)]) -> String {
+    offenders
+        .iter()
+        .map(|&(i, c)| format!("'{}' at position {}", c, i))
+        .join(", ")
+}
+
+/// [`Dq1PasswordError`] の原因の種類。[`Dq1PasswordError::kind`] で得られる。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorKind {
+    /// ゲーム状態が無効。
+    InvalidGameState(GameStateErrorKind),
+
+    /// 復活の呪文の形式が無効。
+    InvalidPassword(PasswordErrorKind),
+
+    /// CRC 下位バイトが一致しない。`expect` は復活の呪文に含まれる値、`actual` は計算した CRC。
+    CrcMismatch { expect: u8, actual: u16 },
+
+    /// パターンが無効。
+    InvalidPattern(PatternErrorKind),
+
+    /// 制約が無効。
+    InvalidConstraint,
+
+    /// リソース上限を超える。
+    ResourceLimit,
+
+    /// JSON や TOML などのデータ形式が無効。
+    InvalidFormat,
+}
+
 pub type Dq1PasswordResult<T> = Result<T, Dq1PasswordError>;
+
+/// ゲーム状態が無効である理由の詳細。
+///
+/// [`kind`](Self::kind) により、メッセージ文字列に頼らずに原因を判別できる。
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("{message}")]
+pub struct GameStateError {
+    kind: GameStateErrorKind,
+    message: String,
+}
+
+impl GameStateError {
+    fn new(kind: GameStateErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// 原因の種類を返す。
+    pub fn kind(&self) -> &GameStateErrorKind {
+        &self.kind
+    }
+
+    /// 人間向けのメッセージを返す。
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// 問題のあるフィールドを返す。
+    pub fn field(&self) -> GameStateField {
+        match self.kind {
+            GameStateErrorKind::OutOfRange { field, .. } => field,
+            GameStateErrorKind::HeroNameTooLong
+            | GameStateErrorKind::HeroNameInvalidChars(_)
+            | GameStateErrorKind::HeroNameNotEnterable(_)
+            | GameStateErrorKind::HeroNameMisplacedMark { .. }
+            | GameStateErrorKind::HeroNameLeadingSpace
+            | GameStateErrorKind::HeroNameInvalidRomaji { .. } => GameStateField::HeroName,
+        }
+    }
+}
+
+/// ゲーム状態が無効である原因の種類。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum GameStateErrorKind {
+    /// 数値のフィールドが上限を超えている。`value` は実際の値、`max` は上限。
+    OutOfRange {
+        field: GameStateField,
+        value: u32,
+        max: u32,
+    },
+
+    /// 主人公の名前が長すぎる。
+    HeroNameTooLong,
+
+    /// 主人公の名前に使えない文字が含まれている。各要素は正規化後の文字。
+    HeroNameInvalidChars(Vec<char>),
+
+    /// 主人公の名前にゲーム内で入力できない文字が含まれている(厳密な validation のみ)。
+    HeroNameNotEnterable(char),
+
+    /// 主人公の名前の位置 `pos` の濁点/半濁点 `mark` が直前の文字に付けられない(厳密な validation のみ)。
+    HeroNameMisplacedMark { pos: usize, mark: char },
+
+    /// 主人公の名前が空白で始まっている(厳密な validation のみ)。
+    HeroNameLeadingSpace,
+
+    /// ローマ字の主人公の名前の位置 `pos` (入力中の文字単位、0 始まり)から始まる音節 `syllable` をかなに変換できない。
+    HeroNameInvalidRomaji { pos: usize, syllable: String },
+}
+
+/// 復活の呪文の形式が無効である理由の詳細。
+///
+/// [`kind`](Self::kind) により、メッセージ文字列に頼らずに原因を判別できる。
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("{message}")]
+pub struct PasswordError {
+    kind: PasswordErrorKind,
+    message: String,
+}
+
+impl PasswordError {
+    fn new(kind: PasswordErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// 原因の種類を返す。
+    pub fn kind(&self) -> &PasswordErrorKind {
+        &self.kind
+    }
+
+    /// 人間向けのメッセージを返す。
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// 復活の呪文の形式が無効である原因の種類。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PasswordErrorKind {
+    /// 長さ(空白文字を除き、濁点/半濁点を合成した後のもの)が正しくない。`len` は実際の長さ。
+    WrongLength { len: usize },
+
+    /// 復活の呪文に使えない文字が含まれている。各要素は (位置, 文字)。
+    ///
+    /// 文字はカタカナなどの変換後のもの。メッセージには変換前の元の文字列が示される。
+    InvalidChars(Vec<(usize, char)>),
+
+    /// 正規形でない(厳密な正規化のみ)。各要素は (入力中の位置(文字単位), 元の文字, 正規化後の文字)。
+    ///
+    /// 正規化後の文字は、除去される場合 `None`。分離した濁点/半濁点は、直前の文字とともに合成後の文字となる。
+    NotCanonical(Vec<(usize, char, Option<char>)>),
+
+    /// タイル列に復活の呪文の文字を表さないタイルが含まれている。各要素は (位置, タイル番号)。
+    ///
+    /// 位置は入力のタイル列中のもの([`password_from_nametable`](crate::password_from_nametable) では名前テーブルのダンプ中のオフセット)。
+    InvalidTiles(Vec<(usize, u8)>),
+}
+
+/// パターンが無効である理由の詳細。
+///
+/// [`kind`](Self::kind) により、メッセージ文字列に頼らずに原因を判別できる。
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("{message}")]
+pub struct PatternError {
+    kind: PatternErrorKind,
+    message: String,
+}
+
+impl PatternError {
+    fn new(kind: PatternErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// 原因の種類を返す。
+    pub fn kind(&self) -> &PatternErrorKind {
+        &self.kind
+    }
+
+    /// 人間向けのメッセージを返す。
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// パターンが無効である原因の種類。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PatternErrorKind {
+    /// 長さ(文字クラスは 1 文字と数える)が正しくない。`len` は実際の長さ。
+    WrongLength { len: usize },
+
+    /// 復活の呪文に使えない文字が含まれている。各要素は (位置, 文字)。
+    ///
+    /// 文字はカタカナなどの変換後のもの。メッセージには変換前の元の文字列が示される。
+    InvalidChars(Vec<(usize, char)>),
+
+    /// '*' が複数含まれている。
+    MultipleStars,
+
+    /// 位置 `pos` の ']' に対応する '[' がない。
+    UnmatchedClose { pos: usize },
+
+    /// 位置 `pos` の '[' が閉じられていない。
+    Unclosed { pos: usize },
+
+    /// 位置 `pos` の文字クラスが入れ子になっている。
+    NestedClass { pos: usize },
+
+    /// 位置 `pos` の文字クラスが空。
+    EmptyClass { pos: usize },
+
+    /// 位置 `pos` の否定文字クラスが全ての文字を除外している。
+    ClassExcludesAll { pos: usize },
+
+    /// 位置 `pos` の文字クラスの範囲に終点がない。`start` は始点。
+    RangeWithoutEnd { pos: usize, start: char },
+
+    /// 位置 `pos` の文字クラスの範囲に始点がない。
+    RangeWithoutStart { pos: usize },
+
+    /// 位置 `pos` の文字クラスの範囲が無効(文字が無効、または始点が終点より後ろ)。
+    InvalidRange { pos: usize, start: char, end: char },
+
+    /// その他(組の位置がパターンの範囲外など)。
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        decode, decode_strict, generate_with_options, hero_name_from_romaji,
+        validate_hero_name_strict, validate_inventory, validate_pattern, validate_tool, GameState,
+        GenerateOptions, PartialDecoder,
+    };
+
+    #[test]
+    fn test_kind() {
+        fn kind<T: core::fmt::Debug>(res: Dq1PasswordResult<T>) -> ErrorKind {
+            res.unwrap_err().kind()
+        }
+
+        assert_eq!(
+            kind(decode("ざぼちずどぢぎきつたうずせれえむるのぢ")),
+            ErrorKind::InvalidPassword(PasswordErrorKind::WrongLength { len: 19 })
+        );
+        assert_eq!(
+            kind(decode("ざぼちず漢ぢぎきつたうずせれえむるのぢパ")),
+            ErrorKind::InvalidPassword(PasswordErrorKind::InvalidChars(vec![
+                (4, '漢'),
+                (19, 'ぱ')
+            ]))
+        );
+        // 復活の呪文 A に対応する GameState の inventory[7] を 15 にしたもの
+        assert_eq!(
+            kind(decode("どくのばうぼぞそこけばがきもびはめつごび")),
+            ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange {
+                field: GameStateField::Inventory(7),
+                value: 15,
+                max: 14,
+            })
+        );
+        // 復活の呪文 A の最後の文字を変えたもの
+        assert!(matches!(
+            kind(decode("ざぼちずどぢぎきつたうずせれえむるのぢお")),
+            ErrorKind::CrcMismatch { .. }
+        ));
+
+        let mut decoder = PartialDecoder::new();
+        assert_eq!(
+            kind(decoder.push('漢')),
+            ErrorKind::InvalidPassword(PasswordErrorKind::InvalidChars(vec![(0, '漢')]))
+        );
+        for c in "ざぼちずどぢぎきつたうずせれえむるのぢえ".chars() {
+            decoder.push(c).unwrap();
+        }
+        assert_eq!(
+            kind(decoder.push('あ')),
+            ErrorKind::InvalidPassword(PasswordErrorKind::WrongLength { len: 21 })
+        );
+
+        assert_eq!(
+            kind(validate_pattern("ああああ")),
+            ErrorKind::InvalidPattern(PatternErrorKind::WrongLength { len: 4 })
+        );
+
+        let mut options = GenerateOptions::default();
+        assert_eq!(
+            kind(options.add_constraint("foo=1")),
+            ErrorKind::InvalidConstraint
+        );
+
+        let options = GenerateOptions {
+            max_memory_bytes: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            kind(generate_with_options("????????????????????", 10, &options)),
+            ErrorKind::ResourceLimit
+        );
+
+        assert_eq!(kind(GameState::from_json("{}")), ErrorKind::InvalidFormat);
+
+        // メッセージは従来通り。
+        assert_eq!(
+            decode("ざぼちずどぢぎきつたうずせれえむるのぢ")
+                .unwrap_err()
+                .to_string(),
+            "復活の呪文の形式が無効: 復活の呪文はちょうど 20 文字でなければならない(ただし空白文字は無視される)"
+        );
+    }
+
+    #[test]
+    fn test_to_message() {
+        fn en<T: core::fmt::Debug>(res: Dq1PasswordResult<T>) -> String {
+            res.unwrap_err().to_message(Lang::En)
+        }
+
+        let state = |f: fn(&mut GameState)| {
+            let mut state = GameState::default();
+            f(&mut state);
+            state.validate()
+        };
+
+        // 日本語は Display と同じ。
+        let e = decode("ざぼちずどぢぎきつたうずせれえむるのぢ").unwrap_err();
+        assert_eq!(e.to_message(Lang::Ja), e.to_string());
+        assert_eq!(Lang::default(), Lang::Ja);
+
+        // 英語
+        for (actual, expect) in [
+            (
+                en(state(|s| s.hero_name = "あいうえお".to_owned())),
+                "invalid game state: hero name must be at most 4 characters (dakuten/handakuten count as 1 character)",
+            ),
+            (
+                en(state(|s| s.hero_name = "あ漢".to_owned())),
+                "invalid game state: hero name contains invalid characters: '漢'",
+            ),
+            (
+                en(validate_hero_name_strict("あ1")),
+                "invalid game state: hero name contains a character that cannot be entered: '1'",
+            ),
+            (
+                en(validate_hero_name_strict("あ゛")),
+                "invalid game state: '゛' at character 2 of hero name cannot follow the preceding character",
+            ),
+            (
+                en(validate_hero_name_strict(" あ")),
+                "invalid game state: hero name starts with a space",
+            ),
+            (
+                en(hero_name_from_romaji("afa")),
+                "invalid game state: cannot convert \"fa\" at character 2 of romaji hero name to kana",
+            ),
+            (
+                en(state(|s| s.hero_weapon = 8)),
+                "invalid game state: hero weapon ID must be at most 7: 8",
+            ),
+            (
+                en(state(|s| s.hero_armor = 8)),
+                "invalid game state: hero armor ID must be at most 7: 8",
+            ),
+            (
+                en(state(|s| s.hero_shield = 4)),
+                "invalid game state: hero shield ID must be at most 3: 4",
+            ),
+            (
+                en(state(|s| s.herb_count = 7)),
+                "invalid game state: herb count must be at most 6: 7",
+            ),
+            (
+                en(state(|s| s.key_count = 7)),
+                "invalid game state: key count must be at most 6: 7",
+            ),
+            (
+                en(validate_inventory([0, 0, 15, 0, 0, 0, 0, 0])),
+                "invalid game state: inventory[2]: tool ID must be at most 14: 15",
+            ),
+            (
+                en(validate_tool(15)),
+                "invalid game state: tool ID must be at most 14: 15",
+            ),
+            (
+                en(state(|s| s.salt = 8)),
+                "invalid game state: salt must be at most 7: 8",
+            ),
+            (
+                en(decode("ざぼちずどぢぎきつたうずせれえむるのぢ")),
+                "invalid password: password must be exactly 20 characters, ignoring whitespace (got 19)",
+            ),
+            (
+                en(decode("ざぼちず漢ぢぎきつたうずせれえむるのぢえ")),
+                "invalid password: password contains invalid characters: '漢' at position 4",
+            ),
+            (
+                en(decode_strict("ざぼちず ザぢぎきつたうずせれえむるのぢえ")),
+                "invalid password: password is not in canonical form: ' ' at position 4 would be removed, 'ザ' at position 5 would become 'ざ'",
+            ),
+            (
+                en(decode("ざぼちずどぢぎきつたうずせれえむるのぢお")),
+                "CRC low byte mismatch: expect=0x??AD, actual=0xBA29",
+            ),
+            (
+                en(validate_pattern("ああああ")),
+                "invalid pattern: pattern must be exactly 20 characters, ignoring whitespace (got 4)",
+            ),
+            (
+                en(validate_pattern("ああああ漢ああああああああああああああ?")),
+                "invalid pattern: pattern contains invalid characters: '漢' at position 4",
+            ),
+            (
+                en(validate_pattern("あ*あ*")),
+                "invalid pattern: pattern cannot contain more than one '*'",
+            ),
+            (
+                en(validate_pattern("ああ]*")),
+                "invalid pattern: ']' at position 2 has no matching '['",
+            ),
+            (
+                en(validate_pattern("ああ[あい*")),
+                "invalid pattern: '[' at position 2 is not closed",
+            ),
+            (
+                en(validate_pattern("ああ[あ[い]]*")),
+                "invalid pattern: character class at position 2 is nested",
+            ),
+            (
+                en(validate_pattern("ああ[/]*")),
+                "invalid pattern: character class at position 2 is empty",
+            ),
+            (
+                en(validate_pattern("ああ[^あ-ぼ]*")),
+                "invalid pattern: negated character class at position 2 excludes every character",
+            ),
+            (
+                en(validate_pattern("ああ[あ-]*")),
+                "invalid pattern: range in character class at position 2 has no end: 'あ-'",
+            ),
+            (
+                en(validate_pattern("ああ[-あ]*")),
+                "invalid pattern: range in character class at position 2 has no start",
+            ),
+            (
+                en(validate_pattern("ああ[お-あ]*")),
+                "invalid pattern: range in character class at position 2 is invalid: 'お-あ'",
+            ),
+        ] {
+            assert_eq!(actual, expect);
+        }
+
+        // 入力と行番号を付加したもの。
+        let e = decode("ざぼちずどぢぎきつたうずせれえむるのぢお")
+            .unwrap_err()
+            .with_input("ざぼちずどぢぎきつたうずせれえむるのぢお");
+        assert_eq!(e.to_message(Lang::Ja), e.to_string());
+        assert_eq!(
+            e.clone().with_line(3).to_message(Lang::En),
+            "line 3: ざぼちずどぢぎきつたうずせれえむるのぢお: CRC low byte mismatch: expect=0x??AD, actual=0xBA29"
+        );
+        assert_eq!(
+            e.with_line(3).to_message(Lang::Ja),
+            "line 3: ざぼちずどぢぎきつたうずせれえむるのぢお: CRC 下位バイトが一致しない: expect=0x??AD, actual=0xBA29"
+        );
+    }
+}
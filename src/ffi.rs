@@ -0,0 +1,356 @@
+//! C 向けの FFI (`ffi` feature)。
+//!
+//! ヘッダファイルは `include/dq1_password.h`。以下で再生成できる:
+//!
+//! ```sh
+//! cbindgen --config cbindgen.toml --output include/dq1_password.h
+//! ```
+//!
+//! 文字列は全て NUL 終端の UTF-8。各関数は [`Dq1ErrorCode`] を返し、
+//! 失敗時の詳細なメッセージは [`dq1_last_error_message`] で得られる。
+//!
+//! panic が C 側へ巻き戻ることはない。各関数の内部で panic した場合は
+//! [`Dq1ErrorCode::Panic`] を返す。
+
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::error::{Dq1PasswordError, ErrorKind};
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// [`Dq1GameState`] の主人公の名前バッファのサイズ(NUL 終端を含む)。
+pub const DQ1_HERO_NAME_BUF_LEN: usize = 16;
+
+/// 復活の呪文 1 個を格納するのに十分なバッファのサイズ(NUL 終端を含む)。
+///
+/// [`dq1_generate`] は復活の呪文をこの間隔で書き込む。
+pub const DQ1_PASSWORD_BUF_LEN: usize = 64;
+
+/// FFI 関数の結果。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Dq1ErrorCode {
+    /// 成功。
+    Ok = 0,
+
+    /// NULL ポインタが渡された。
+    NullPointer,
+
+    /// 文字列が UTF-8 として無効(NUL 終端されていない場合も含む)。
+    InvalidUtf8,
+
+    /// 出力バッファが小さすぎる。
+    BufferTooSmall,
+
+    /// ゲーム状態が無効。
+    InvalidGameState,
+
+    /// 復活の呪文の形式が無効。
+    InvalidPassword,
+
+    /// CRC 下位バイトが一致しない。
+    CrcMismatch,
+
+    /// パターンが無効。
+    InvalidPattern,
+
+    /// 制約が無効。
+    InvalidConstraint,
+
+    /// リソース上限を超える。
+    ResourceLimit,
+
+    /// 内部で panic した(ライブラリのバグ)。
+    Panic,
+
+    /// データ形式が無効。
+    InvalidFormat,
+}
+
+/// [`GameState`] の C 向け表現。
+///
+/// `hero_name` は NUL 終端の UTF-8。デコード結果では正規化された名前が入る。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Dq1GameState {
+    pub hero_name: [c_char; DQ1_HERO_NAME_BUF_LEN],
+    pub hero_xp: u16,
+    pub purse: u16,
+    pub hero_weapon: u8,
+    pub hero_armor: u8,
+    pub hero_shield: u8,
+    pub herb_count: u8,
+    pub key_count: u8,
+    pub inventory: [u8; 8],
+    pub flag_equip_dragon_scale: bool,
+    pub flag_equip_warrior_ring: bool,
+    pub flag_got_death_necklace: bool,
+    pub flag_beated_golem: bool,
+    pub flag_beated_dragon: bool,
+    pub salt: u8,
+}
+
+impl TryFrom<&GameState> for Dq1GameState {
+    type Error = Dq1ErrorCode;
+
+    /// `state.hero_name` が NUL 終端してバッファに収まらなければ `Err(Dq1ErrorCode::BufferTooSmall)` を返す
+    /// (正規化された名前は常に収まる)。
+    fn try_from(state: &GameState) -> Result<Self, Self::Error> {
+        let name = state.hero_name.as_bytes();
+        if name.len() >= DQ1_HERO_NAME_BUF_LEN {
+            return Err(Dq1ErrorCode::BufferTooSmall);
+        }
+
+        let mut hero_name = [0; DQ1_HERO_NAME_BUF_LEN];
+        for (dst, &src) in hero_name.iter_mut().zip(name) {
+            *dst = src as c_char;
+        }
+
+        Ok(Self {
+            hero_name,
+            hero_xp: state.hero_xp,
+            purse: state.purse,
+            hero_weapon: state.hero_weapon,
+            hero_armor: state.hero_armor,
+            hero_shield: state.hero_shield,
+            herb_count: state.herb_count,
+            key_count: state.key_count,
+            inventory: state.inventory,
+            flag_equip_dragon_scale: state.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: state.flag_equip_warrior_ring,
+            flag_got_death_necklace: state.flag_got_death_necklace,
+            flag_beated_golem: state.flag_beated_golem,
+            flag_beated_dragon: state.flag_beated_dragon,
+            salt: state.salt,
+        })
+    }
+}
+
+impl TryFrom<&Dq1GameState> for GameState {
+    type Error = Dq1ErrorCode;
+
+    /// `hero_name` が NUL 終端された UTF-8 でなければ `Err(Dq1ErrorCode::InvalidUtf8)` を返す。
+    fn try_from(state: &Dq1GameState) -> Result<Self, Self::Error> {
+        let bytes: Vec<u8> = state.hero_name.iter().map(|&c| c as u8).collect();
+        let hero_name = CStr::from_bytes_until_nul(&bytes)
+            .ok()
+            .and_then(|s| s.to_str().ok())
+            .ok_or(Dq1ErrorCode::InvalidUtf8)?
+            .to_owned();
+
+        Ok(Self {
+            hero_name,
+            hero_xp: state.hero_xp,
+            purse: state.purse,
+            hero_weapon: state.hero_weapon,
+            hero_armor: state.hero_armor,
+            hero_shield: state.hero_shield,
+            herb_count: state.herb_count,
+            key_count: state.key_count,
+            inventory: state.inventory,
+            flag_equip_dragon_scale: state.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: state.flag_equip_warrior_ring,
+            flag_got_death_necklace: state.flag_got_death_necklace,
+            flag_beated_golem: state.flag_beated_golem,
+            flag_beated_dragon: state.flag_beated_dragon,
+            salt: state.salt,
+        })
+    }
+}
+
+std::thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// 同じスレッドで最後に呼び出した FFI 関数のエラーメッセージ(日本語)を返す。成功した場合は空文字列。
+///
+/// 戻り値は、同じスレッドで次に FFI 関数を呼び出すまで有効。解放してはならない。
+/// メッセージを取得できない場合(スレッドの終了処理中など)は NULL を返す。
+#[no_mangle]
+pub extern "C" fn dq1_last_error_message() -> *const c_char {
+    LAST_ERROR
+        .try_with(|e| e.borrow().as_ptr())
+        .unwrap_or(std::ptr::null())
+}
+
+/// 復活の呪文 `password` をデコードし、結果を `out` に書き込む。
+///
+/// # Safety
+///
+/// `password` は NULL または NUL 終端された文字列、`out` は NULL または書き込み可能な
+/// [`Dq1GameState`] へのポインタでなければならない。
+#[no_mangle]
+pub unsafe extern "C" fn dq1_decode(
+    password: *const c_char,
+    out: *mut Dq1GameState,
+) -> Dq1ErrorCode {
+    run(|| {
+        let password = read_str(password)?;
+        let out = out.as_mut().ok_or_else(null_pointer)?;
+
+        let state = crate::decode(password).map_err(error)?;
+        *out = Dq1GameState::try_from(&state)
+            .map_err(|code| (code, "主人公の名前が出力バッファに収まらない".to_owned()))?;
+
+        Ok(())
+    })
+}
+
+/// ゲーム状態 `state` をエンコードし、復活の呪文を `buf` (サイズ `len` バイト)に NUL 終端して書き込む。
+///
+/// `len` が [`DQ1_PASSWORD_BUF_LEN`] 以上ならば常に十分。
+///
+/// # Safety
+///
+/// `state` は NULL または有効な [`Dq1GameState`] へのポインタ、`buf` は NULL または
+/// `len` バイト書き込み可能な領域へのポインタでなければならない。
+#[no_mangle]
+pub unsafe extern "C" fn dq1_encode(
+    state: *const Dq1GameState,
+    buf: *mut c_char,
+    len: usize,
+) -> Dq1ErrorCode {
+    run(|| {
+        let state = state.as_ref().ok_or_else(null_pointer)?;
+        let state = GameState::try_from(state).map_err(|code| {
+            (
+                code,
+                "主人公の名前が NUL 終端された UTF-8 でない".to_owned(),
+            )
+        })?;
+
+        let password = crate::encode(&state).map_err(error)?;
+        write_str(&password, buf, len)
+    })
+}
+
+/// パターン `pattern` に合致するデコード可能な復活の呪文たちを最大 `n_max` 個生成する。
+///
+/// `buf` (サイズ `len` バイト)の先頭から [`DQ1_PASSWORD_BUF_LEN`] バイトおきに、
+/// 復活の呪文を NUL 終端して書き込む。生成した個数は `n_out` に書き込まれる。
+/// `len` は `n_max * DQ1_PASSWORD_BUF_LEN` 以上でなければならない。
+///
+/// # Safety
+///
+/// `pattern` は NULL または NUL 終端された文字列、`buf` は NULL または `len` バイト書き込み可能な領域、
+/// `n_out` は NULL または書き込み可能な `size_t` へのポインタでなければならない。
+#[no_mangle]
+pub unsafe extern "C" fn dq1_generate(
+    pattern: *const c_char,
+    n_max: usize,
+    buf: *mut c_char,
+    len: usize,
+    n_out: *mut usize,
+) -> Dq1ErrorCode {
+    run(|| {
+        let pattern = read_str(pattern)?;
+        if buf.is_null() {
+            return Err(null_pointer());
+        }
+        let n_out = n_out.as_mut().ok_or_else(null_pointer)?;
+
+        if !matches!(n_max.checked_mul(DQ1_PASSWORD_BUF_LEN), Some(need) if need <= len) {
+            return Err(buffer_too_small());
+        }
+
+        let passwords = crate::generate(pattern, n_max).map_err(error)?;
+        for (i, password) in passwords.iter().enumerate() {
+            write_str(
+                password,
+                buf.add(i * DQ1_PASSWORD_BUF_LEN),
+                DQ1_PASSWORD_BUF_LEN,
+            )?;
+        }
+        *n_out = passwords.len();
+
+        Ok(())
+    })
+}
+
+type FfiResult<T> = Result<T, (Dq1ErrorCode, String)>;
+
+unsafe fn read_str<'a>(p: *const c_char) -> FfiResult<&'a str> {
+    if p.is_null() {
+        return Err(null_pointer());
+    }
+
+    CStr::from_ptr(p).to_str().map_err(|_| {
+        (
+            Dq1ErrorCode::InvalidUtf8,
+            "文字列が UTF-8 でない".to_owned(),
+        )
+    })
+}
+
+unsafe fn write_str(s: &str, buf: *mut c_char, len: usize) -> FfiResult<()> {
+    if buf.is_null() {
+        return Err(null_pointer());
+    }
+    if s.len() + 1 > len {
+        return Err(buffer_too_small());
+    }
+
+    std::ptr::copy_nonoverlapping(s.as_ptr().cast::<c_char>(), buf, s.len());
+    *buf.add(s.len()) = 0;
+
+    Ok(())
+}
+
+fn null_pointer() -> (Dq1ErrorCode, String) {
+    (
+        Dq1ErrorCode::NullPointer,
+        "NULL ポインタが渡された".to_owned(),
+    )
+}
+
+fn buffer_too_small() -> (Dq1ErrorCode, String) {
+    (
+        Dq1ErrorCode::BufferTooSmall,
+        "出力バッファが小さすぎる".to_owned(),
+    )
+}
+
+fn error(e: Dq1PasswordError) -> (Dq1ErrorCode, String) {
+    let code = match e.kind() {
+        ErrorKind::InvalidGameState(_) => Dq1ErrorCode::InvalidGameState,
+        ErrorKind::InvalidPassword(_) => Dq1ErrorCode::InvalidPassword,
+        ErrorKind::CrcMismatch { .. } => Dq1ErrorCode::CrcMismatch,
+        ErrorKind::InvalidPattern(_) => Dq1ErrorCode::InvalidPattern,
+        ErrorKind::InvalidConstraint => Dq1ErrorCode::InvalidConstraint,
+        ErrorKind::ResourceLimit => Dq1ErrorCode::ResourceLimit,
+        ErrorKind::InvalidFormat => Dq1ErrorCode::InvalidFormat,
+    };
+
+    (code, e.to_string())
+}
+
+/// FFI 関数の本体 `f` を実行し、結果をエラーコードに変換してエラーメッセージを記録する。
+///
+/// `f` 内の panic は捕捉し、[`Dq1ErrorCode::Panic`] とする(C 側へ巻き戻してはならない)。
+fn run(f: impl FnOnce() -> FfiResult<()>) -> Dq1ErrorCode {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| (*s).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err((
+            Dq1ErrorCode::Panic,
+            format!("内部で panic した: {}", message),
+        ))
+    });
+
+    let (code, message) = match result {
+        Ok(()) => (Dq1ErrorCode::Ok, String::new()),
+        Err(e) => e,
+    };
+
+    // メッセージは NUL を含まない。スレッドの終了処理中で記録できなくても結果は返す。
+    let _ = LAST_ERROR.try_with(|e| *e.borrow_mut() = CString::new(message).unwrap_or_default());
+
+    code
+}
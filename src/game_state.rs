@@ -1,11 +1,141 @@
 use serde::{Deserialize, Serialize};
 
-use crate::error::Dq1PasswordResult;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, Lang};
 use crate::normalize::normalize_hero_name;
+use crate::prelude::*;
 use crate::validate::*;
 
+/// 武器ID に対応する名前。ID 0 (なし)は空文字列。
+pub(crate) const WEAPON_NAMES: [&str; 8] = [
+    "",
+    "たけざお",
+    "こんぼう",
+    "どうのつるぎ",
+    "てつのおの",
+    "はがねのつるぎ",
+    "ほのおのつるぎ",
+    "ロトのつるぎ",
+];
+
+/// 鎧ID に対応する名前。ID 0 (なし)は空文字列。
+pub(crate) const ARMOR_NAMES: [&str; 8] = [
+    "",
+    "ぬののふく",
+    "かわのふく",
+    "くさりかたびら",
+    "てつのよろい",
+    "はがねのよろい",
+    "まほうのよろい",
+    "ロトのよろい",
+];
+
+/// 盾ID に対応する名前。ID 0 (なし)は空文字列。
+pub(crate) const SHIELD_NAMES: [&str; 4] = ["", "かわのたて", "てつのたて", "みかがみのたて"];
+
+/// 道具ID に対応する名前。ID 0 (なし)は空文字列。
+pub(crate) const ITEM_NAMES: [&str; 15] = [
+    "",
+    "たいまつ",
+    "せいすい",
+    "キメラのつばさ",
+    "りゅうのうろこ",
+    "ようせいのふえ",
+    "せんしのゆびわ",
+    "ロトのしるし",
+    "おうじょのあい",
+    "のろいのベルト",
+    "ぎんのたてごと",
+    "しのくびかざり",
+    "たいようのいし",
+    "あまぐものつえ",
+    "にじのしずく",
+];
+
+/// 武器ID に対応する英語名(北米版のもの)。ID 0 (なし)は空文字列。
+pub(crate) const WEAPON_NAMES_EN: [&str; 8] = [
+    "",
+    "BAMBOO POLE",
+    "CLUB",
+    "COPPER SWORD",
+    "HAND AXE",
+    "BROAD SWORD",
+    "FLAME SWORD",
+    "ERDRICK'S SWORD",
+];
+
+/// 鎧ID に対応する英語名(北米版のもの)。ID 0 (なし)は空文字列。
+pub(crate) const ARMOR_NAMES_EN: [&str; 8] = [
+    "",
+    "CLOTHES",
+    "LEATHER ARMOR",
+    "CHAIN MAIL",
+    "HALF PLATE",
+    "FULL PLATE",
+    "MAGIC ARMOR",
+    "ERDRICK'S ARMOR",
+];
+
+/// 盾ID に対応する英語名(北米版のもの)。ID 0 (なし)は空文字列。
+pub(crate) const SHIELD_NAMES_EN: [&str; 4] = ["", "SMALL SHIELD", "LARGE SHIELD", "SILVER SHIELD"];
+
+/// 道具ID に対応する英語名(北米版のもの)。ID 0 (なし)は空文字列。
+pub(crate) const ITEM_NAMES_EN: [&str; 15] = [
+    "",
+    "TORCH",
+    "FAIRY WATER",
+    "WINGS",
+    "DRAGON'S SCALE",
+    "FAIRY FLUTE",
+    "FIGHTER'S RING",
+    "ERDRICK'S TOKEN",
+    "GWAELIN'S LOVE",
+    "CURSED BELT",
+    "SILVER HARP",
+    "DEATH NECKLACE",
+    "STONES OF SUNLIGHT",
+    "STAFF OF RAIN",
+    "RAINBOW DROP",
+];
+
+/// 武器ID に対応する名前を返す。英語名は北米版のもの。ID 0 (なし)や無効な ID に対しては None を返す。
+pub fn weapon_name(weapon: u8, lang: Lang) -> Option<&'static str> {
+    lookup_name(&WEAPON_NAMES, &WEAPON_NAMES_EN, weapon, lang)
+}
+
+/// 鎧ID に対応する名前を返す。英語名は北米版のもの。ID 0 (なし)や無効な ID に対しては None を返す。
+pub fn armor_name(armor: u8, lang: Lang) -> Option<&'static str> {
+    lookup_name(&ARMOR_NAMES, &ARMOR_NAMES_EN, armor, lang)
+}
+
+/// 盾ID に対応する名前を返す。英語名は北米版のもの。ID 0 (なし)や無効な ID に対しては None を返す。
+pub fn shield_name(shield: u8, lang: Lang) -> Option<&'static str> {
+    lookup_name(&SHIELD_NAMES, &SHIELD_NAMES_EN, shield, lang)
+}
+
+/// 道具ID に対応する名前を返す。英語名は北米版のもの。ID 0 (なし)や無効な ID に対しては None を返す。
+pub fn tool_name(tool: u8, lang: Lang) -> Option<&'static str> {
+    lookup_name(&ITEM_NAMES, &ITEM_NAMES_EN, tool, lang)
+}
+
+fn lookup_name(
+    names_ja: &[&'static str],
+    names_en: &[&'static str],
+    id: u8,
+    lang: Lang,
+) -> Option<&'static str> {
+    let names = match lang {
+        Lang::Ja => names_ja,
+        Lang::En => names_en,
+    };
+
+    names
+        .get(usize::from(id))
+        .copied()
+        .filter(|name| !name.is_empty())
+}
+
 /// 復活の呪文に保存されるゲーム状態。
-#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct GameState {
     /// 主人公の名前。最大 4 文字(濁点、半濁点は 1 文字と数える)。
     /// 4 文字未満の場合、ASCII space でパディングされたものとして扱われる。
@@ -19,6 +149,8 @@ pub struct GameState {
 
     /// 主人公の装備している武器ID。
     ///
+    /// JSON などからの読み込みでは、数値の代わりに名前(日本語名または英語名)も受け付ける。
+    ///
     /// | 武器ID | 内容           |
     /// | --     | --             |
     /// | 0      | (なし)         |
@@ -29,10 +161,13 @@ pub struct GameState {
     /// | 5      | はがねのつるぎ |
     /// | 6      | ほのおのつるぎ |
     /// | 7      | ロトのつるぎ   |
+    #[serde(deserialize_with = "crate::serialize::deserialize_weapon")]
     pub hero_weapon: u8,
 
     /// 主人公の装備している鎧ID。
     ///
+    /// JSON などからの読み込みでは、数値の代わりに名前(日本語名または英語名)も受け付ける。
+    ///
     /// | 鎧ID | 内容           |
     /// | --   | --             |
     /// | 0    | (なし)         |
@@ -43,16 +178,20 @@ pub struct GameState {
     /// | 5    | はがねのよろい |
     /// | 6    | まほうのよろい |
     /// | 7    | ロトのよろい   |
+    #[serde(deserialize_with = "crate::serialize::deserialize_armor")]
     pub hero_armor: u8,
 
     /// 主人公の装備している盾ID。
     ///
+    /// JSON などからの読み込みでは、数値の代わりに名前(日本語名または英語名)も受け付ける。
+    ///
     /// | 盾ID | 内容           |
     /// | --   | --             |
     /// | 0    | (なし)         |
     /// | 1    | かわのたて     |
     /// | 2    | てつのたて     |
     /// | 3    | みかがみのたて |
+    #[serde(deserialize_with = "crate::serialize::deserialize_shield")]
     pub hero_shield: u8,
 
     /// やくそう所持数 (`0..=6`)。
@@ -104,28 +243,164 @@ pub struct GameState {
 impl GameState {
     /// ゲーム状態を validate する。
     pub fn validate(&self) -> Dq1PasswordResult<()> {
+        self.validate_with_policy(&ValidationPolicy::DQ1_FC)
+    }
+
+    /// ゲーム状態を `policy` の基準で validate する。
+    pub fn validate_with_policy(&self, policy: &ValidationPolicy) -> Dq1PasswordResult<()> {
         validate_hero_name(&self.hero_name)?;
         validate_hero_weapon(self.hero_weapon)?;
         validate_hero_armor(self.hero_armor)?;
         validate_hero_shield(self.hero_shield)?;
-        validate_herb_count(self.herb_count)?;
-        validate_key_count(self.key_count)?;
-        validate_inventory(&self.inventory)?;
+        policy.validate_herb_count(self.herb_count)?;
+        policy.validate_key_count(self.key_count)?;
+        policy.validate_inventory(self.inventory)?;
         validate_salt(self.salt)?;
 
         Ok(())
     }
 
+    /// ゲーム状態を validate し、問題のあるフィールドを全て返す。
+    ///
+    /// [`validate`](Self::validate) と異なり、最初の問題で打ち切らない。
+    /// インベントリは要素ごとに検査する。問題がなければ `Ok(())` を返す。
+    pub fn validate_all(&self) -> Result<(), Vec<FieldIssue>> {
+        let mut checks = vec![
+            (
+                GameStateField::HeroName,
+                validate_hero_name(&self.hero_name),
+            ),
+            (
+                GameStateField::HeroWeapon,
+                validate_hero_weapon(self.hero_weapon),
+            ),
+            (
+                GameStateField::HeroArmor,
+                validate_hero_armor(self.hero_armor),
+            ),
+            (
+                GameStateField::HeroShield,
+                validate_hero_shield(self.hero_shield),
+            ),
+            (
+                GameStateField::HerbCount,
+                validate_herb_count(self.herb_count),
+            ),
+            (GameStateField::KeyCount, validate_key_count(self.key_count)),
+        ];
+        checks.extend(self.inventory.iter().enumerate().map(|(i, &tool)| {
+            (
+                GameStateField::Inventory(i),
+                validate_inventory_slot(i, tool),
+            )
+        }));
+        checks.push((GameStateField::Salt, validate_salt(self.salt)));
+
+        let issues: Vec<_> = checks
+            .into_iter()
+            .filter_map(|(field, res)| res.err().map(|error| FieldIssue { field, error }))
+            .collect();
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
     /// ゲーム状態を正規化したものを返す。戻り値は有効であることが保証される。
     ///
     /// 主人公の名前の正規化のみを行う。
     ///
     /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
     pub fn normalize(&self) -> Dq1PasswordResult<Self> {
+        self.normalize_with_options(&NormalizeOptions::default())
+    }
+
+    /// オプションを指定してゲーム状態を正規化したものを返す。戻り値は有効であることが保証される。
+    ///
+    /// 主人公の名前の正規化に加え、`options` で有効にした正規化を行う。
+    /// 既定のオプションでは [`GameState::normalize`] と同じ結果になる。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn normalize_with_options(&self, options: &NormalizeOptions) -> Dq1PasswordResult<Self> {
         let hero_name = normalize_hero_name(&self.hero_name)?;
 
-        Ok(Self { hero_name, ..*self })
+        let mut state = Self { hero_name, ..*self };
+        if options.compact_inventory {
+            state = state.normalize_inventory();
+        }
+
+        Ok(state)
     }
+
+    /// インベントリを詰めたゲーム状態を返す。
+    ///
+    /// 空き(道具ID 0)でない道具を順序を保ったまま前に詰める。
+    /// ゲーム内で道具を使ったり捨てたりした際の挙動に合わせたもの。
+    ///
+    /// 道具の位置が変わるため、エンコード結果の復活の呪文も変わりうることに注意。
+    pub fn normalize_inventory(&self) -> Self {
+        let mut inventory = [0; 8];
+        for (dst, &tool) in inventory
+            .iter_mut()
+            .zip(self.inventory.iter().filter(|&&tool| tool != 0))
+        {
+            *dst = tool;
+        }
+
+        Self {
+            inventory,
+            ..self.clone()
+        }
+    }
+}
+
+/// ゲーム状態の正規化時のオプション。
+///
+/// 既定では全て無効(主人公の名前の正規化のみ行う)。
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NormalizeOptions {
+    /// インベントリを詰める([`GameState::normalize_inventory`] を参照)。
+    ///
+    /// 有効にするとエンコード結果のバイト列が変わるため、
+    /// `decode` した結果を再度 `encode` しても元の復活の呪文に戻らない場合がある。
+    pub compact_inventory: bool,
+}
+
+/// 検査対象となる [`GameState`] のフィールド。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GameStateField {
+    /// `hero_name`
+    HeroName,
+    /// `hero_weapon`
+    HeroWeapon,
+    /// `hero_armor`
+    HeroArmor,
+    /// `hero_shield`
+    HeroShield,
+    /// `herb_count`
+    HerbCount,
+    /// `key_count`
+    KeyCount,
+    /// `inventory` の要素(インデックス `0..8`)。
+    Inventory(usize),
+    /// インベントリ中の位置を問わない道具ID([`validate_tool`] 単体で検査した場合)。
+    Tool,
+    /// `salt`
+    Salt,
+    /// レベル(1..=30)。[`hero_stats`](crate::hero_stats) などで指定するもの。
+    Level,
+}
+
+/// [`GameState::validate_all`] が報告する、1 フィールドの問題。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FieldIssue {
+    /// 問題のあるフィールド。
+    pub field: GameStateField,
+
+    /// 問題の内容。常に `Dq1PasswordError::InvalidGameState`。
+    pub error: Dq1PasswordError,
 }
 
 #[cfg(test)]
@@ -153,6 +428,62 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_validate_all() {
+        assert_eq!(GameState::default().validate_all(), Ok(()));
+
+        let state = GameState {
+            hero_weapon: 8,
+            inventory: [0, 15, 0, 0, 0, 0, 0, 20],
+            ..Default::default()
+        };
+        let fields: Vec<_> = state
+            .validate_all()
+            .unwrap_err()
+            .into_iter()
+            .map(|issue| {
+                match &issue.error {
+                    Dq1PasswordError::InvalidGameState(e) => assert_eq!(e.field(), issue.field),
+                    e => panic!("{:?}", e),
+                }
+                issue.field
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                GameStateField::HeroWeapon,
+                GameStateField::Inventory(1),
+                GameStateField::Inventory(7),
+            ]
+        );
+        assert_eq!(
+            state.validate(),
+            Err(state.validate_all().unwrap_err()[0].error.clone())
+        );
+
+        let state = GameState {
+            hero_name: S("あああああ"),
+            herb_count: 7,
+            salt: 8,
+            ..Default::default()
+        };
+        let fields: Vec<_> = state
+            .validate_all()
+            .unwrap_err()
+            .into_iter()
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                GameStateField::HeroName,
+                GameStateField::HerbCount,
+                GameStateField::Salt,
+            ]
+        );
+    }
+
     #[test]
     fn test_normalize() {
         assert_eq!(
@@ -182,4 +513,78 @@ mod tests {
         .normalize()
         .is_err());
     }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(weapon_name(7, Lang::Ja), Some("ロトのつるぎ"));
+        assert_eq!(weapon_name(7, Lang::En), Some("ERDRICK'S SWORD"));
+        assert_eq!(armor_name(1, Lang::En), Some("CLOTHES"));
+        assert_eq!(shield_name(3, Lang::Ja), Some("みかがみのたて"));
+        assert_eq!(tool_name(14, Lang::En), Some("RAINBOW DROP"));
+        assert_eq!(tool_name(8, Lang::Ja), Some("おうじょのあい"));
+
+        // なし、および無効な ID。
+        for lang in [Lang::Ja, Lang::En] {
+            assert_eq!(weapon_name(0, lang), None);
+            assert_eq!(weapon_name(8, lang), None);
+            assert_eq!(armor_name(8, lang), None);
+            assert_eq!(shield_name(4, lang), None);
+            assert_eq!(tool_name(0, lang), None);
+            assert_eq!(tool_name(15, lang), None);
+        }
+    }
+
+    #[test]
+    fn test_normalize_inventory() {
+        let state = |inventory: [u8; 8]| GameState {
+            inventory,
+            ..Default::default()
+        };
+
+        for (before, after) in [
+            ([0, 1, 0, 2, 0, 0, 3, 0], [1, 2, 3, 0, 0, 0, 0, 0]),
+            ([0, 0, 0, 0, 0, 0, 0, 14], [14, 0, 0, 0, 0, 0, 0, 0]),
+            ([5, 0, 5, 0, 5, 0, 5, 0], [5, 5, 5, 5, 0, 0, 0, 0]),
+            ([1, 2, 3, 0, 0, 0, 0, 0], [1, 2, 3, 0, 0, 0, 0, 0]),
+            ([1, 2, 3, 4, 5, 6, 7, 8], [1, 2, 3, 4, 5, 6, 7, 8]),
+            ([0; 8], [0; 8]),
+        ] {
+            assert_eq!(state(before).normalize_inventory(), state(after));
+        }
+
+        // 既定では詰めない。
+        let holey = GameState {
+            hero_name: S("ゆうてい"),
+            inventory: [0, 1, 0, 2, 0, 0, 3, 0],
+            ..Default::default()
+        };
+        assert_eq!(holey.normalize().unwrap().inventory, holey.inventory);
+
+        let options = NormalizeOptions {
+            compact_inventory: true,
+        };
+        let compacted = holey.normalize_with_options(&options).unwrap();
+        assert_eq!(compacted.inventory, [1, 2, 3, 0, 0, 0, 0, 0]);
+        assert_eq!(compacted.hero_name, "ゆうてい");
+
+        // 詰めると復活の呪文が変わるが、デコードすると詰めた状態に戻る。
+        let password = crate::encode(&holey).unwrap();
+        let password_compacted = crate::encode(&compacted).unwrap();
+        assert_ne!(password, password_compacted);
+        assert_eq!(crate::decode(&password_compacted).unwrap(), compacted);
+        assert_eq!(
+            crate::decode(&password)
+                .unwrap()
+                .normalize_with_options(&options)
+                .unwrap(),
+            compacted
+        );
+
+        // 詰まっている場合は何も変わらない。
+        let compact = state([1, 2, 3, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            crate::encode(&compact.normalize_with_options(&options).unwrap()),
+            crate::encode(&compact)
+        );
+    }
 }
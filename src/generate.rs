@@ -1,317 +1,4100 @@
-use std::convert::TryInto;
-use std::ops::RangeInclusive;
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use core::cell::Cell;
+use core::cmp::Reverse;
+use core::convert::{TryFrom, TryInto};
+use core::mem::size_of;
+use core::ops::{Index, IndexMut, RangeInclusive};
 
-use crate::crc::crc_update;
-use crate::decode::password_char_to_cum;
-use crate::encode::bytes_to_password;
-use crate::error::Dq1PasswordResult;
-use crate::normalize::normalize_pattern;
+use itertools::Itertools as _;
 
-/// 多次元 Vec を作る。
-macro_rules! ndvec {
-    ($elem:expr; $n:expr) => {{
-        ::std::vec![$elem; $n]
-    }};
-    ($elem:expr; $n:expr, $($ns:expr),+ $(,)?) => {{
-        ::std::vec![ndvec![$elem; $($ns),+]; $n]
-    }};
+use crate::analyze::count_by_position;
+use crate::char_set::CharSet;
+use crate::codec::Codec;
+use crate::crc::{crc16_table_tail, crc_update_poly};
+use crate::cums::{bytes_to_sixs, sixs_to_bytes, sixs_to_cums};
+use crate::decode::{
+    bytes_to_state, decode, password_char_to_cum, unpack_hero_name, unpack_hero_name_char,
+};
+use crate::encode::{bytes_to_password, pack_hero_name, state_to_bytes};
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::GameState;
+use crate::input_cost::input_cost_sort_key;
+use crate::normalize::normalize_hero_name;
+use crate::pattern::Pattern;
+use crate::plausibility::is_plausible;
+use crate::prelude::*;
+use crate::validate::{
+    validate_hero_name_strict, validate_tool, ValidationPolicy, ENTERABLE_HERO_NAME_CHARS,
+};
+
+/// 復活の呪文生成時のオプション。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenerateOptions {
+    /// 除外パターンたち。いずれかにマッチする復活の呪文は生成されない。
+    pub exclude: Vec<Pattern>,
+
+    /// 主人公の名前。指定した場合、デコード結果の主人公の名前がこれに一致するものだけを生成する。
+    /// 正規化していないものも許す。
+    pub hero_name: Option<String>,
+
+    /// 主人公の経験値の範囲。指定した場合、デコード結果の経験値がこの範囲内のものだけを生成する。
+    pub xp: Option<RangeInclusive<u16>>,
+
+    /// 所持金の範囲。指定した場合、デコード結果の所持金がこの範囲内のものだけを生成する。
+    pub purse: Option<RangeInclusive<u16>>,
+
+    /// やくそう所持数の範囲。指定した場合、デコード結果のやくそう所持数がこの範囲内のものだけを生成する。
+    pub herb_count: Option<RangeInclusive<u8>>,
+
+    /// かぎ所持数の範囲。指定した場合、デコード結果のかぎ所持数がこの範囲内のものだけを生成する。
+    pub key_count: Option<RangeInclusive<u8>>,
+
+    /// 武器IDの範囲。指定した場合、デコード結果の武器IDがこの範囲内のものだけを生成する。
+    ///
+    /// 武器IDは強さの順に並んでいるので、「はがねのつるぎ以上」は `6..=7` のように表せる。
+    pub hero_weapon: Option<RangeInclusive<u8>>,
+
+    /// 鎧IDの範囲。指定した場合、デコード結果の鎧IDがこの範囲内のものだけを生成する。
+    pub hero_armor: Option<RangeInclusive<u8>>,
+
+    /// 盾IDの範囲。指定した場合、デコード結果の盾IDがこの範囲内のものだけを生成する。
+    pub hero_shield: Option<RangeInclusive<u8>>,
+
+    /// インベントリに含まれていなければならない道具IDたち。
+    ///
+    /// 集合として扱う(同じ道具IDを複数回指定しても、1 つ以上含まれていればよい)。
+    pub require_items: Vec<u8>,
+
+    /// インベントリに含まれていてはならない道具IDたち。
+    pub forbid_items: Vec<u8>,
+
+    /// パターンの '?' の部分の埋め方。
+    pub fill: Fill,
+
+    /// 結果の並べ方。
+    pub sort_by: SortBy,
+
+    /// true の場合、デコード結果がソルト以外等しい復活の呪文は最初に見つかったものだけを生成する。
+    ///
+    /// 列挙時に適用されるので、生成上限数はソルトを無視したゲーム状態の個数となる。
+    pub dedup_by_state: bool,
+
+    /// true の場合、デコード結果の主人公の名前が [`validate_hero_name_strict`] を満たすものだけを生成する。
+    ///
+    /// 名前の文字が定まった時点で入力できない文字を弾き、並びの条件は列挙時に適用する。
+    pub strict_names: bool,
+
+    /// 生成に用いるメモリ量の上限(バイト単位)。None の場合は無制限。
+    ///
+    /// 生成前に [`estimate_memory_bytes`] で見積もり、上限を超える場合は生成を行わずにエラーを返す。
+    pub max_memory_bytes: Option<usize>,
+
+    /// true の場合、デコード結果が実際のプレイで生じうる([`is_plausible`])ものだけを生成する。
+    ///
+    /// 列挙時に適用されるので、条件を満たすものが存在する限り `n_max` 個の結果が得られる。
+    /// 読み飛ばした個数は [`generate_with_outcome`] で得られる。
+    pub plausible_only: bool,
+
+    /// 文字数の制約たち。全てを満たす復活の呪文だけを生成する。
+    ///
+    /// 復元時の探索で文字数を数えながら枝刈りするので、条件を満たすものが存在する限り `n_max` 個の結果が得られる。
+    pub count_constraints: Vec<CountConstraint>,
 }
 
-/// 指定されたパターンに合致するデコード可能な復活の呪文たちを生成する。
-///
-/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
-///
-/// `n_max` は生成上限数。
+/// 生成時の文字数の制約。復活の呪文 20 文字のうち `chars` に含まれるものの個数を `min..=max` に制限する。
 ///
-/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
-pub fn generate(pattern: impl AsRef<str>, n_max: usize) -> Dq1PasswordResult<Vec<String>> {
-    // パターンを累積値の配列に変換する。'?' の部分は None になる。
-    let cums: Vec<_> = normalize_pattern(pattern)?
-        .chars()
-        .map(password_char_to_cum)
-        .collect();
+/// `min > max` などの満たしえない制約を指定した場合、何も生成しない。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CountConstraint {
+    /// 数える文字の集合。
+    pub chars: CharSet,
 
-    let (cums_head, cums_tail) = cums.split_at(2);
-    let cums_tail: [_; 18] = cums_tail.try_into().unwrap();
+    /// 最小の個数。
+    pub min: u8,
 
-    let mut bytess = Vec::with_capacity(n_max);
-    let mut n_remain = n_max;
-    for (cum0, cum1) in itertools::iproduct!(cum_range(cums_head[0]), cum_range(cums_head[1])) {
-        if n_remain == 0 {
-            break;
-        }
+    /// 最大の個数。
+    pub max: u8,
+}
 
-        let sixs_head = [
-            cum0.wrapping_sub(4) & 0x3F,
-            cum1.wrapping_sub(cum0 + 4) & 0x3F,
-        ];
-        let partial = generate_dp(sixs_head, &cums_tail, n_remain);
-        n_remain -= partial.len();
-        bytess.extend(partial);
-    }
+/// パターンの '?' の部分の埋め方。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Fill {
+    /// 任意。
+    #[default]
+    Any,
 
-    Ok(bytess.iter().map(bytes_to_password).collect())
+    /// デコード結果のゲーム状態がなるべく `GameState::default()` に近くなるように埋める。
+    ///
+    /// 結果は `GameState::default()` と異なるフィールドの個数の少ない順に並ぶ。
+    Defaults,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-struct DpTrace(u16);
+/// デコード結果がソルト以外等しい復活の呪文たちをまとめたもの。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StateGroup {
+    /// 代表の復活の呪文(グループ内で最初に現れたもの)。
+    pub password: String,
 
-impl DpTrace {
-    fn new(j: u8, k: u8, l: u8) -> Self {
-        debug_assert!((0..=0x3F).contains(&j));
-        debug_assert!((0..=0xFF).contains(&k));
-        debug_assert!((0..=1).contains(&l));
-        Self(u16::from(k) | (u16::from(j) << 8) | (u16::from(l) << 14))
-    }
+    /// 代表の復活の呪文のデコード結果。
+    pub state: GameState,
 
-    fn j(self) -> u8 {
-        ((self.0 >> 8) & 0x3F).try_into().unwrap()
+    /// 代表以外の復活の呪文たち。現れた順に並ぶ。
+    pub siblings: Vec<String>,
+}
+
+/// 生成結果の並べ方。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SortBy {
+    /// 生成順(`options.fill` に応じた順)。
+    #[default]
+    Generation,
+
+    /// 入力コスト([`input_cost`](crate::input_cost()))の小さい順。同じなら辞書順(五十音表の順)。
+    ///
+    /// パターンに合致するもの全てを列挙して並べ替え、先頭の最大 `n_max` 個を返す。
+    /// `n_max` が小さくても全て列挙するので、パターンが緩いと時間とメモリを大量に消費する。
+    InputCost,
+}
+
+/// [`generate_with_outcome`] の結果。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GenerateOutcome {
+    /// 生成された復活の呪文たち。[`generate_with_options`] の結果と同じ。
+    pub passwords: Vec<String>,
+
+    /// true ならば、`passwords` 以外にパターンと制約を満たすものは存在しない。
+    ///
+    /// false ならば、生成上限数に達したなどの理由で生成が打ち切られた(他に存在する可能性がある)。
+    pub exhausted: bool,
+
+    /// `options.plausible_only` により読み飛ばした、他の条件は満たすが不自然なものの個数。
+    pub implausible_skipped: usize,
+}
+
+/// 生成された復活の呪文と、そのデコード結果が `GameState::default()` と異なるフィールドの個数。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FilledPassword {
+    /// 復活の呪文。
+    pub password: String,
+
+    /// デコード結果が `GameState::default()` と異なるフィールドの個数。
+    ///
+    /// インベントリは全体で 1 フィールドと数える。
+    pub diff_count: usize,
+}
+
+impl GenerateOptions {
+    /// テキスト形式の制約を追加する。同じ項目の制約が既にある場合は上書きする。
+    ///
+    /// 制約は `項目=値` の形式で、以下の項目をサポートする:
+    ///
+    /// | 項目            | 値の例                                    |
+    /// | --              | --                                        |
+    /// | `hero_name`     | `ゆうてい`                                |
+    /// | `xp`            | `1234`, `20000..=30000`, `100..`, `..=99` |
+    /// | `purse`         | `1234`, `20000..=30000`, `100..`, `..=99` |
+    /// | `herb_count`    | `6`, `1..=3`, `1..`, `..=3`               |
+    /// | `key_count`     | `6`, `1..=3`, `1..`, `..=3`               |
+    /// | `hero_weapon`   | `6`, `3..=5`, `6..`, `..=2` (武器ID)      |
+    /// | `hero_armor`    | `6`, `3..=5`, `3..`, `..=2` (鎧ID)        |
+    /// | `hero_shield`   | `3`, `1..=2`, `1..`, `..=1` (盾ID)        |
+    /// | `require_items` | `10,14` (道具IDのカンマ区切り)            |
+    /// | `forbid_items`  | `10,14` (道具IDのカンマ区切り)            |
+    ///
+    /// `constraint` が無効な場合、`Err(Dq1PasswordError::InvalidConstraint)` を返す。
+    pub fn add_constraint(&mut self, constraint: impl AsRef<str>) -> Dq1PasswordResult<()> {
+        let constraint = constraint.as_ref();
+
+        let (key, value) = constraint.split_once('=').ok_or_else(|| {
+            Dq1PasswordError::invalid_constraint(format!(
+                "制約は '項目=値' の形式でなければならない: {}",
+                constraint
+            ))
+        })?;
+
+        match key.trim() {
+            "hero_name" => self.hero_name = Some(value.to_owned()),
+            "xp" => self.xp = Some(parse_range(value, u16::MAX)?),
+            "purse" => self.purse = Some(parse_range(value, u16::MAX)?),
+            "herb_count" => self.herb_count = Some(parse_range(value, u8::MAX)?),
+            "key_count" => self.key_count = Some(parse_range(value, u8::MAX)?),
+            "hero_weapon" => self.hero_weapon = Some(parse_range(value, u8::MAX)?),
+            "hero_armor" => self.hero_armor = Some(parse_range(value, u8::MAX)?),
+            "hero_shield" => self.hero_shield = Some(parse_range(value, u8::MAX)?),
+            "require_items" => self.require_items = parse_items(value)?,
+            "forbid_items" => self.forbid_items = parse_items(value)?,
+            key => {
+                return Err(Dq1PasswordError::invalid_constraint(format!(
+                    "未知の項目: {}",
+                    key
+                )))
+            }
+        }
+
+        Ok(())
     }
+}
 
-    fn k(self) -> u8 {
-        (self.0 & 0xFF).try_into().unwrap()
+/// 装備の各項目の (項目名, 6bit 値配列の位置, シフト量, 最大値)。並び順は [`equipment_ranges`] と同じ。
+///
+/// 盾は位置 10 の上位 2bit、鎧は位置 11 の下位 3bit、武器は位置 11 の上位 3bit。
+const EQUIPMENT_SIXS: [(&str, usize, u8, u8); 3] = [
+    ("hero_weapon", 11, 3, 0x7),
+    ("hero_armor", 11, 0, 0x7),
+    ("hero_shield", 10, 4, 0x3),
+];
+
+/// 装備の範囲の制約たちを返す。
+fn equipment_ranges(options: &GenerateOptions) -> [Option<&RangeInclusive<u8>>; 3] {
+    [
+        options.hero_weapon.as_ref(),
+        options.hero_armor.as_ref(),
+        options.hero_shield.as_ref(),
+    ]
+}
+
+/// 装備の範囲の制約たちを検証する。
+///
+/// 範囲が空の場合、または下限が装備IDの最大値を超える場合、`Err(Dq1PasswordError::InvalidConstraint)` を返す。
+fn validate_equipment_ranges(options: &GenerateOptions) -> Dq1PasswordResult<()> {
+    for (range, &(name, _, _, max)) in equipment_ranges(options).iter().zip(&EQUIPMENT_SIXS) {
+        let range = match range {
+            Some(range) => range,
+            None => continue,
+        };
+        if range.is_empty() {
+            return Err(Dq1PasswordError::invalid_constraint(format!(
+                "{} の範囲が空: {}..={}",
+                name,
+                range.start(),
+                range.end()
+            )));
+        }
+        if *range.start() > max {
+            return Err(Dq1PasswordError::invalid_constraint(format!(
+                "{} の下限は {} 以下でなければならない: {}",
+                name,
+                max,
+                range.start()
+            )));
+        }
     }
 
-    fn l(self) -> u8 {
-        (self.0 >> 14).try_into().unwrap()
+    Ok(())
+}
+
+/// `10,14` 形式の道具IDリストをパースする。空文字列は空のリストとなる。
+fn parse_items(s: &str) -> Dq1PasswordResult<Vec<u8>> {
+    if s.trim().is_empty() {
+        return Ok(vec![]);
     }
+
+    s.split(',')
+        .map(|x| {
+            let x = x.trim();
+            x.parse()
+                .map_err(|_| Dq1PasswordError::invalid_constraint(format!("道具IDが無効: {}", x)))
+        })
+        .collect()
 }
 
-/// 6bit 値配列の先頭 2 要素を指定し、有効なゲーム状態に対応するバイト列たちを生成する。
+/// `1234`, `100..=200`, `100..`, `..=200` 形式の範囲をパースする。
 ///
-/// 動的計画法を用いる。
-fn generate_dp(sixs_head: [u8; 2], cums_tail: &[Option<u8>; 18], n_max: usize) -> Vec<[u8; 15]> {
-    const CRC_TABLE: [[u8; 0x40]; 18] = crc8_table_tail();
+/// `max` は型の最大値で、上限を省略した場合に用いる。
+fn parse_range<T>(s: &str, max: T) -> Dq1PasswordResult<RangeInclusive<T>>
+where
+    T: Copy + Default + core::fmt::Display + core::str::FromStr,
+{
+    let parse = |x: &str, default: T| -> Dq1PasswordResult<T> {
+        let x = x.trim();
+        if x.is_empty() {
+            return Ok(default);
+        }
+        x.parse().map_err(|_| {
+            Dq1PasswordError::invalid_constraint(format!(
+                "値は 0 以上 {} 以下の整数でなければならない: {}",
+                max, x
+            ))
+        })
+    };
 
-    debug_assert_ne!(n_max, 0);
+    match s.split_once("..") {
+        Some((start, end)) => {
+            // 上限を省略した `100..` は上限なしとして扱う。
+            let end = match end.strip_prefix('=') {
+                Some(end) => end,
+                None if end.trim().is_empty() => end,
+                None => {
+                    return Err(Dq1PasswordError::invalid_constraint(format!(
+                        "範囲は 'a..=b' の形式でなければならない: {}",
+                        s
+                    )))
+                }
+            };
+            Ok(parse(start, T::default())?..=parse(end, max)?)
+        }
+        None => {
+            if s.trim().is_empty() {
+                return Err(Dq1PasswordError::invalid_constraint("値が空"));
+            }
+            let x = parse(s, T::default())?;
+            Ok(x..=x)
+        }
+    }
+}
 
-    let cum_ini = (sixs_head[0] + sixs_head[1] + 8) & 0x3F;
-    let crc_ini = crc8_table_head()[usize::from(sixs_head[1] >> 2)];
+/// 生成時の制約。`GenerateOptions` を DP で扱いやすい形に変換したもの。
+pub(crate) struct Constraints<'a> {
+    options: &'a GenerateOptions,
 
-    // dp[i][j][k][l]:
-    //   tail 部 i 個目までで cum=j, crc=k であるときの Vec<DpTrace> (最大要素数 n_max)
-    //   l: six の上位 2bit が 0b11 であるか (道具IDの validate 用)
-    let mut dp = ndvec![Vec::with_capacity(n_max); 19, 0x40, 0x100, 2];
-    dp[0][usize::from(cum_ini)][usize::from(crc_ini)][0].push(DpTrace::new(0, 0, 0)); // 値自体に意味はない
+    /// 6bit 値配列の各位置について、許される値の集合(値 x を許すなら bit x が立つ)。
+    sixs_allowed: [u64; 20],
 
-    // 配るDP
-    for (i, j, k, l) in itertools::iproduct!(0..18, 0..=0x3F, 0..=0xFF, 0..=1) {
-        if dp[i][usize::from(j)][usize::from(k)][usize::from(l)].is_empty() {
-            continue;
+    /// インベントリに含まれていなければならない道具IDの集合(道具ID x を含むなら bit x が立つ)。
+    items_required: u16,
+
+    /// `options.plausible_only` により弾いた個数。
+    implausible_skipped: Cell<usize>,
+
+    /// ゲーム状態の検査の基準。
+    policy: ValidationPolicy,
+
+    /// 4bit 境界をまたぐ道具IDの下位 2bit (直前の six の上位 2bit)から DP の状態 l への写像。
+    ///
+    /// 上位 2bit として許される値の集合が等しいものは同じ状態にまとめる。
+    item_lo_to_state: [u8; 4],
+
+    /// 各状態 l について、4bit 境界をまたぐ道具IDの上位 2bit として許される値の集合。
+    item_hi_allowed: Vec<u8>,
+}
+
+impl<'a> Constraints<'a> {
+    /// `options` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` または
+    /// `Err(Dq1PasswordError::InvalidConstraint)` を返す。
+    pub(crate) fn new(options: &'a GenerateOptions) -> Dq1PasswordResult<Self> {
+        Self::with_policy(options, &ValidationPolicy::DQ1_FC)
+    }
+
+    /// [`Constraints::new`] と同じだが、ゲーム状態の検査の基準を `policy` とする。
+    pub(crate) fn with_policy(
+        options: &'a GenerateOptions,
+        policy: &ValidationPolicy,
+    ) -> Dq1PasswordResult<Self> {
+        let items_required = items_to_set(&options.require_items)?;
+        let items_forbidden = items_to_set(&options.forbid_items)?;
+        if items_required & items_forbidden != 0 {
+            return Err(Dq1PasswordError::invalid_constraint(format!(
+                "同じ道具IDが require_items と forbid_items の両方に含まれている: {}",
+                (0..15)
+                    .filter(|&item| items_required & items_forbidden & (1 << item) != 0)
+                    .join(", ")
+            )));
         }
+        // 無効な道具ID(本家では 15)は常に禁じる。
+        let items_forbidden = (policy.inventory_tool_max() + 1..16)
+            .fold(items_forbidden, |set, item| set | (1 << item));
 
-        for cum in cum_range(cums_tail[i]) {
-            let six = cum.wrapping_sub(j + 4) & 0x3F;
+        let mut item_lo_to_state = [0; 4];
+        let mut item_hi_allowed = Vec::with_capacity(4);
+        for (lo, state) in item_lo_to_state.iter_mut().enumerate() {
+            let hi_allowed = (0..4)
+                .filter(|hi| items_forbidden & (1 << (lo | (hi << 2))) == 0)
+                .fold(0, |acc, hi| acc | (1 << hi));
+            let pos = item_hi_allowed
+                .iter()
+                .position(|&x| x == hi_allowed)
+                .unwrap_or_else(|| {
+                    item_hi_allowed.push(hi_allowed);
+                    item_hi_allowed.len() - 1
+                });
+            *state = u8::try_from(pos).unwrap();
+        }
 
-            // やくそう所持数が無効な場合は弾く。
-            if i == 11 && (six >> 2) >= 7 {
-                continue;
-            }
+        // ゲーム状態バイト列のうち値が固定される bit を集める。
+        let mut bytes_mask = [0; 15];
+        let mut bytes_value = [0; 15];
 
-            // かぎ所持数が無効な場合は弾く。
-            if i == 12 && (six & 0xF) >= 7 {
-                continue;
+        if let Some(hero_name) = &options.hero_name {
+            let packed = pack_hero_name(normalize_hero_name(hero_name)?);
+            for (&(idx, shift), value) in core::iter::zip(&HERO_NAME_BYTES, packed) {
+                bytes_mask[idx] |= 0x3F << shift;
+                bytes_value[idx] |= value << shift;
             }
+        }
 
-            // インベントリに無効な道具IDが含まれる場合は弾く。
-            if matches!(i, 2 | 6) && (six & 0xF) == 15 {
-                continue;
+        for (range, (lo, hi)) in u16_ranges(options) {
+            let (mask, value) = range_common_bits(range);
+            for &(idx, shift) in &[(lo, 0), (hi, 8)] {
+                bytes_mask[idx] |= u8::try_from((mask >> shift) & 0xFF).unwrap();
+                bytes_value[idx] |= u8::try_from((value >> shift) & 0xFF).unwrap();
             }
-            if matches!(i, 13 | 17) && (six >> 2) == 15 {
-                continue;
+        }
+
+        let sixs_mask = bytes_to_sixs(&bytes_mask);
+        let sixs_value = bytes_to_sixs(&bytes_value);
+
+        let mut sixs_allowed = [0; 20];
+        for (allowed, &mask, &value) in itertools::izip!(&mut sixs_allowed, &sixs_mask, &sixs_value)
+        {
+            for six in 0..0x40 {
+                if (six & mask) == value {
+                    *allowed |= 1 << six;
+                }
             }
-            if matches!(i, 3 | 7 | 13 | 17) && l == 1 && (six & 3) == 3 {
-                continue;
+        }
+
+        // やくそう所持数は位置 13 の上位 4bit、かぎ所持数は位置 14 の下位 4bit。
+        for (range, idx, shift) in [(&options.herb_count, 13, 2), (&options.key_count, 14, 0)] {
+            if let Some(range) = range {
+                for six in 0..0x40 {
+                    if !range.contains(&((six >> shift) & 0xF)) {
+                        sixs_allowed[idx] &= !(1 << six);
+                    }
+                }
             }
+        }
 
-            let crc = k ^ CRC_TABLE[i][usize::from(six)];
-            let l_nxt = (six >> 4) == 3;
+        validate_equipment_ranges(options)?;
+        for (range, &(_, idx, shift, max)) in equipment_ranges(options).iter().zip(&EQUIPMENT_SIXS)
+        {
+            let range = match range {
+                Some(range) => range,
+                None => continue,
+            };
+            for six in 0..0x40 {
+                if !range.contains(&((six >> shift) & max)) {
+                    sixs_allowed[idx] &= !(1 << six);
+                }
+            }
+        }
 
-            let traces = &mut dp[i + 1][usize::from(cum)][usize::from(crc)][usize::from(l_nxt)];
-            if traces.len() < n_max {
-                traces.push(DpTrace::new(j, k, l));
+        // 4bit 境界をまたがない道具IDについて、禁じられたものを弾く。
+        for &(idx, shift) in &[(4, 0), (8, 0), (15, 2), (19, 2)] {
+            for six in 0..0x40 {
+                if items_forbidden & (1 << ((six >> shift) & 0xF)) != 0 {
+                    sixs_allowed[idx] &= !(1 << six);
+                }
             }
         }
+
+        // 空の範囲が指定された場合、必須の道具がインベントリに収まらない場合、
+        // または満たしえない文字数の制約が指定された場合、何も生成しない。
+        if u16_ranges(options).any(|(range, _)| range.is_empty())
+            || items_required.count_ones() > 8
+            || options
+                .count_constraints
+                .iter()
+                .any(|cc| cc.min > cc.max || cc.min > 20)
+        {
+            sixs_allowed = [0; 20];
+        }
+
+        Ok(Self {
+            options,
+            sixs_allowed,
+            items_required,
+            item_lo_to_state,
+            item_hi_allowed,
+            implausible_skipped: Cell::new(0),
+            policy: *policy,
+        })
     }
 
-    generate_dp_restore(sixs_head, cums_tail, n_max, &dp)
-}
+    /// 生成結果を列挙時に絞り込む必要があるかどうかを返す。
+    fn has_filter(&self) -> bool {
+        // 共通する上位 bit を固定するだけで表せる範囲は絞り込み不要。
+        !self.options.exclude.is_empty()
+            || u16_ranges(self.options).any(|(range, _)| !range_is_exact(range))
+            || self.items_required != 0
+            || self.options.dedup_by_state
+            || self.options.strict_names
+            || self.options.plausible_only
+            || !self.options.count_constraints.is_empty()
+    }
 
-fn generate_dp_restore(
-    sixs_head: [u8; 2],
-    cums_tail: &[Option<u8>; 18],
-    n_max: usize,
-    dp: &[Vec<Vec<Vec<Vec<DpTrace>>>>],
-) -> Vec<[u8; 15]> {
-    let crc_expect = sixs_head[0] | (sixs_head[1] << 6);
+    /// 6bit 値配列の位置 `idx` の値として `six` が許されるかどうかを返す。
+    pub(crate) fn allows_six(&self, idx: usize, six: u8) -> bool {
+        (self.sixs_allowed[idx] & (1 << six)) != 0
+    }
 
-    struct Dfs<'a> {
-        n_max: usize,
-        dp: &'a [Vec<Vec<Vec<Vec<DpTrace>>>>],
-        bytess: Vec<[u8; 15]>,
+    /// DP の状態 l の種類数を返す。
+    pub(crate) fn item_state_count(&self) -> u8 {
+        u8::try_from(self.item_hi_allowed.len()).unwrap()
     }
-    impl Dfs<'_> {
-        /// 発見済の解の個数が n_max に達したら true を返す。
-        fn dfs(&mut self, i: usize, j: u8, k: u8, l: u8, sixs: &mut [u8; 20]) -> bool {
-            debug_assert!(!self.dp[i][usize::from(j)][usize::from(k)][usize::from(l)].is_empty());
 
-            if i == 0 {
-                self.bytess.push(sixs_to_bytes(sixs));
-                return self.bytess.len() == self.n_max;
+    /// 4bit 境界をまたぐ道具IDの下位 2bit に対応する DP の状態 l を返す。
+    fn item_lo_state(&self, lo: u8) -> u8 {
+        self.item_lo_to_state[usize::from(lo)]
+    }
+
+    /// DP の状態 l において、4bit 境界をまたぐ道具IDの上位 2bit として `hi` が許されるかどうかを返す。
+    fn allows_item_hi(&self, l: u8, hi: u8) -> bool {
+        (self.item_hi_allowed[usize::from(l)] & (1 << hi)) != 0
+    }
+
+    /// 6bit 値配列の先頭 2 要素と位置 `idx` 以降の要素のみが定まっているとき、
+    /// 残りをどう定めても生成対象にならないことが判明したら false を返す。
+    ///
+    /// 復元時の DFS は後ろから値を定めていくので、ここで枝刈りを行う。
+    fn may_accept(&self, sixs: &[u8; 20], idx: usize) -> bool {
+        // 16bit 値の上位バイトのみ、または両方のバイトが定まったら、範囲外のものを弾く。
+        for (range, (lo, hi)) in u16_ranges(self.options) {
+            if idx != byte_determined_at(lo) && idx != byte_determined_at(hi) {
+                continue;
             }
+            let value_hi = u16::from(byte_from_sixs(sixs, hi));
+            if idx <= byte_determined_at(lo) {
+                let value = u16::from(byte_from_sixs(sixs, lo)) | (value_hi << 8);
+                if !range.contains(&value) {
+                    return false;
+                }
+            } else if idx <= byte_determined_at(hi)
+                && !(range.start() >> 8..=range.end() >> 8).contains(&value_hi)
+            {
+                return false;
+            }
+        }
 
-            for &trace in &self.dp[i][usize::from(j)][usize::from(k)][usize::from(l)] {
-                let six = j.wrapping_sub(trace.j() + 4) & 0x3F;
-                sixs[i + 1] = six;
-                if self.dfs(i - 1, trace.j(), trace.k(), trace.l(), sixs) {
-                    return true;
+        // 主人公の名前の文字が定まったら、入力できないものを弾く。
+        if self.options.strict_names {
+            for &(idx_byte, shift) in &HERO_NAME_BYTES {
+                if byte_determined_at(idx_byte) != idx {
+                    continue;
+                }
+                let c = unpack_hero_name_char((byte_from_sixs(sixs, idx_byte) >> shift) & 0x3F);
+                if !ENTERABLE_HERO_NAME_CHARS.contains(&c) {
+                    return false;
                 }
             }
+        }
 
-            false
+        // インベントリの一部が定まったら、必須の道具が残りの枠に収まらないものを弾く。
+        if INVENTORY_BYTES
+            .iter()
+            .any(|&idx_byte| byte_determined_at(idx_byte) == idx)
+            && !self.required_items_fit(sixs, idx)
+        {
+            return false;
         }
+
+        true
     }
 
-    let mut dfs = Dfs {
-        n_max,
-        dp,
-        bytess: Vec::with_capacity(n_max),
-    };
-    let mut sixs = [0; 20];
-    sixs[0] = sixs_head[0];
-    sixs[1] = sixs_head[1];
-    for (cum, l) in itertools::iproduct!(cum_range(cums_tail[17]), 0..=1) {
-        if dp[18][usize::from(cum)][usize::from(crc_expect)][usize::from(l)].is_empty() {
-            continue;
+    /// 6bit 値配列の位置 `idx` 以降で定まったインベントリの枠に含まれない必須の道具が、
+    /// 残りの枠に収まりうるかどうかを返す。
+    fn required_items_fit(&self, sixs: &[u8; 20], idx: usize) -> bool {
+        let mut missing = self.items_required;
+        let mut n_vacant = 0;
+        for &idx_byte in &INVENTORY_BYTES {
+            if byte_determined_at(idx_byte) >= idx {
+                let b = byte_from_sixs(sixs, idx_byte);
+                missing &= !(1 << (b & 0xF)) & !(1 << (b >> 4));
+            } else {
+                n_vacant += 2;
+            }
         }
-        if dfs.dfs(18, cum, crc_expect, l, &mut sixs) {
-            break;
+
+        missing.count_ones() <= n_vacant
+    }
+
+    /// 累積値 `cum` の文字を、文字数の制約ごとの個数 `counts` に数える。`add` が false なら数えたものを取り消す。
+    fn count_cum(&self, counts: &mut [u8], cum: u8, add: bool) {
+        for (count, cc) in counts.iter_mut().zip(&self.options.count_constraints) {
+            if cc.chars.contains_cum(cum) {
+                if add {
+                    *count += 1;
+                } else {
+                    *count -= 1;
+                }
+            }
         }
     }
 
-    dfs.bytess
-}
+    /// 文字数の制約ごとの個数 `counts` に、未定の `n_unknown` 文字を除く全ての文字を数えたとき、
+    /// 残りをどう定めても文字数の制約を満たさないことが判明したら false を返す。
+    fn counts_may_fit(&self, counts: &[u8], n_unknown: usize) -> bool {
+        counts
+            .iter()
+            .zip(&self.options.count_constraints)
+            .all(|(&count, cc)| {
+                count <= cc.max && usize::from(count) + n_unknown >= usize::from(cc.min)
+            })
+    }
 
-fn cum_range(opt_cum: Option<u8>) -> RangeInclusive<u8> {
-    opt_cum.map_or(0..=0x3F, |x| x..=x)
-}
+    /// 累積値の配列が文字数の制約を全て満たすかどうかを返す。制約がなければ何も数えない。
+    fn counts_fit(&self, cums: &[u8; 20]) -> bool {
+        self.options.count_constraints.iter().all(|cc| {
+            let count = cums
+                .iter()
+                .filter(|&&cum| cc.chars.contains_cum(cum))
+                .count();
+            (usize::from(cc.min)..=usize::from(cc.max)).contains(&count)
+        })
+    }
 
-/// ゲーム状態バイト列の前半から CRC 部を除いた 4bit についての CRC 下位バイトテーブルを返す。
-const fn crc8_table_head() -> [u8; 0x10] {
-    const CRC16_TABLE: [[u16; 0x40]; 18] = crc16_table_tail();
+    /// 6bit 値配列に対応する復活の呪文が生成対象かどうかを返す。
+    fn accepts(&self, sixs: &[u8; 20]) -> bool {
+        let cums = sixs_to_cums(sixs);
+        if !self.counts_fit(&cums) {
+            return false;
+        }
+
+        if self
+            .options
+            .exclude
+            .iter()
+            .any(|pat| pat.matches_cums(&cums))
+        {
+            return false;
+        }
+
+        let bytes = sixs_to_bytes(sixs);
+        for (range, (lo, hi)) in u16_ranges(self.options) {
+            if !range.contains(&u16::from_le_bytes([bytes[lo], bytes[hi]])) {
+                return false;
+            }
+        }
+
+        if self.options.strict_names {
+            let packed = HERO_NAME_BYTES.map(|(idx, shift)| (bytes[idx] >> shift) & 0x3F);
+            if validate_hero_name_strict(unpack_hero_name(packed)).is_err() {
+                return false;
+            }
+        }
 
-    let mut table = [0; 0x10];
+        if !self.required_items_fit(sixs, 2) {
+            return false;
+        }
+
+        if self.options.plausible_only && !is_plausible(&bytes_to_state(&bytes)) {
+            self.implausible_skipped
+                .set(self.implausible_skipped.get() + 1);
+            return false;
+        }
 
-    let mut j = 0;
-    while j < 0x10 {
-        table[j] = (crc_update(
-            crc_update(crc_update(CRC16_TABLE[3][j << 2], 0, 8), 0, 8),
-            0,
-            8,
-        ) & 0xFF) as u8;
-        j += 1;
+        true
     }
+}
+
+/// 指定されたパターンに合致するデコード可能な復活の呪文たちを生成する。
+///
+/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
+/// `[かが]` のような文字クラスは、括弧内のいずれかの文字にマッチする。
+/// '*' は全体が 20 文字になるように '?' の並びに展開される。
+/// `?1` のようにグループ番号(1-9)を付けた位置たちは同じ文字にマッチする。
+/// この場合、グループの文字が生成順の最も外側のループとなる。
+///
+/// `n_max` は生成上限数。0 の場合は生成を行わずに空の結果を返す。
+/// `usize::MAX` は上限なしを意味し、合致するものを全て返す
+/// (上限数の分を予め確保することはなく、結果の個数に比例するメモリのみを使う)。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+/// 原因(長さの誤り、無効な文字とその位置など)は [`PatternError::kind`](crate::PatternError::kind) で判別できる。
+pub fn generate(pattern: impl AsRef<str>, n_max: usize) -> Dq1PasswordResult<Vec<String>> {
+    generate_with_options(pattern, n_max, &GenerateOptions::default())
+}
+
+/// 文字表と CRC のパラメータを指定して、パターンに合致するデコード可能な復活の呪文たちを生成する。
+///
+/// パターンの構文は [`generate`] と同じだが、文字は `codec` の文字表のものを用いる
+/// (文字クラスの範囲も文字表の順となる)。戻り値も `codec` の文字表で表される。
+/// `Codec::dq1_fc()` を指定した場合、[`generate`] と同じ結果となる。
+///
+/// エラーは [`generate`] と同様。
+pub fn generate_with_codec(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    codec: &Codec,
+) -> Dq1PasswordResult<Vec<String>> {
+    let pattern = Pattern::parse(codec.to_dq1_pattern(pattern.as_ref())?)?;
+    let job = GenerateJob::new(&pattern, n_max, &GenerateOptions::default())?.with_codec(codec);
+
+    let mut passwords = vec![];
+    job.run(|bytes| {
+        passwords.push(codec.password_from_dq1(&bytes_to_password(&bytes)));
+        true
+    });
 
-    table
+    Ok(passwords)
 }
 
-/// ゲーム状態バイト列の後半 108bit についての 6bit 単位の CRC 下位バイトテーブルを返す。
-const fn crc8_table_tail() -> [[u8; 0x40]; 18] {
-    const CRC16_TABLE: [[u16; 0x40]; 18] = crc16_table_tail();
+/// ゲーム状態の検査の基準を指定して、パターンに合致するデコード可能な復活の呪文たちを生成する。
+///
+/// 結果は [`decode_with_policy`](crate::decode_with_policy) に同じ `policy` を渡せばデコードできる
+/// (本家の基準ではデコードできないものも含みうる)。
+/// `ValidationPolicy::DQ1_FC` を指定した場合、[`generate`] と同じ結果となる。
+///
+/// エラーは [`generate`] と同様。
+pub fn generate_with_policy(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    policy: &ValidationPolicy,
+) -> Dq1PasswordResult<Vec<String>> {
+    let job = GenerateJob::new(
+        &Pattern::parse(pattern)?,
+        n_max,
+        &GenerateOptions::default(),
+    )?
+    .with_policy(policy);
 
-    let mut table = [[0; 0x40]; 18];
+    let mut passwords = vec![];
+    job.run(|bytes| {
+        passwords.push(bytes_to_password(&bytes));
+        true
+    });
 
-    let mut i = 0;
-    while i < 18 {
-        let mut j = 0;
-        while j < 0x40 {
-            table[i][j] = (CRC16_TABLE[i][j] & 0xFF) as u8;
-            j += 1;
-        }
-        i += 1;
-    }
+    Ok(passwords)
+}
 
-    table
+/// オプションを指定して、パターンに合致するデコード可能な復活の呪文たちを生成する。
+///
+/// 除外パターンは列挙時に適用されるので、条件を満たす復活の呪文が存在する限り
+/// `n_max` 個の結果が得られる。ただし、除外される候補が多いと時間がかかることがある。
+///
+/// 主人公の名前の指定は、対応する bit を固定して DP を行うことで探索空間を絞り込む。
+/// 禁止する道具の指定も DP 中に適用される(このため、結果の順序は指定しない場合と異なりうる)。
+/// 必須の道具の指定は列挙時に適用される。
+///
+/// `options.fill` が `Fill::Defaults` の場合、[`generate_filled`] と同様の順序で結果を返す。
+/// `options.sort_by` が `SortBy::InputCost` の場合、合致するもの全てのうち入力コストの小さいものから順に返す。
+///
+/// `options` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` または
+/// `Err(Dq1PasswordError::InvalidConstraint)` を返す。
+///
+/// `options.max_memory_bytes` を指定していて、メモリ使用量の見積もりがそれを超える場合、
+/// `Err(Dq1PasswordError::ResourceLimit)` を返す。
+///
+/// その他は [`generate`] と同様。
+pub fn generate_with_options(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<String>> {
+    generate_with_pattern(&Pattern::parse(pattern)?, n_max, options)
 }
 
-/// ゲーム状態バイト列の後半 108bit についての 6bit 単位の CRC テーブルを返す。
+/// [`generate_with_options`] と同様に生成し、他に合致するものが存在するかどうかを併せて返す。
+///
+/// 生成上限数に達した場合、もう 1 個探して他に存在するかを確かめる(結果には含めない)。
+/// したがって、ちょうど `n_max` 個存在する場合も `exhausted` は true となる。
+/// `n_max` が 0 の場合は何も探さず、`exhausted` は false となる。
 ///
-/// エンコード時は 8bit 単位で `crc_update()` が行われるため、bit 順が変わることに注意。
-/// 24bit 単位で考えると以下のように並び替わる:
+/// `options.fill` が `Fill::Defaults` の場合、先頭 2 文字の組ごとの候補数が上限に達すると、
+/// 実際には全て列挙済みでも `exhausted` は false となることがある。
 ///
-/// ```text
-/// 8bit: | abcdefgh | ijklmnop | qrstuvwx |
-/// 6bit: | cdefgh | mnopab | wxijkl | qrstuv |
-/// ```
-const fn crc16_table_tail() -> [[u16; 0x40]; 18] {
-    let mut table = [[0; 0x40]; 18];
+/// エラーは [`generate_with_options`] と同様。
+pub fn generate_with_outcome(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<GenerateOutcome> {
+    let job = GenerateJob::new(&Pattern::parse(pattern)?, n_max, options)?;
 
-    let mut j = 0;
-    while j < 0x40 {
-        table[17][j as usize] = crc_update(0, j << 2, 8);
-        table[16][j as usize] =
-            crc_update(0, j >> 4, 2) ^ crc_update(crc_update(0, j << 4, 8), 0, 8);
-        table[15][j as usize] = crc_update(crc_update(0, j >> 2, 4), 0, 8)
-            ^ crc_update(crc_update(crc_update(0, j << 6, 8), 0, 8), 0, 8);
-        table[14][j as usize] = crc_update(crc_update(crc_update(0, j, 6), 0, 8), 0, 8);
-        j += 1;
-    }
+    let mut passwords = vec![];
+    let summary = job.run(|bytes| {
+        passwords.push(bytes_to_password(&bytes));
+        true
+    });
 
-    let mut i = 13;
-    loop {
-        let mut j = 0;
-        while j < 0x40 {
-            table[i][j] = crc_update(crc_update(crc_update(table[i + 4][j], 0, 8), 0, 8), 0, 8);
-            j += 1;
-        }
-        if i == 0 {
-            break;
-        }
-        i -= 1;
-    }
+    Ok(GenerateOutcome {
+        passwords,
+        exhausted: summary.exhausted,
+        implausible_skipped: summary.implausible_skipped,
+    })
+}
+
+/// 構築済みのパターンを指定して、パターンに合致するデコード可能な復活の呪文たちを生成する。
+///
+/// パターンを文字列を経由せずに組み立てたい場合は [`Pattern::from_slots`] を用いる。
+///
+/// その他は [`generate_with_options`] と同様。
+pub fn generate_with_pattern(
+    pattern: &Pattern,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<String>> {
+    let bytess = generate_bytess(pattern, n_max, options)?;
 
-    table
+    Ok(bytess.iter().map(bytes_to_password).collect())
 }
 
-fn sixs_to_bytes(sixs: &[u8; 20]) -> [u8; 15] {
-    let mut bytes = [0; 15];
+/// [`generate_with_pattern`] と同様に生成し、結果を生成順に 1 つずつ `f` に渡す。
+/// `f` が false を返したら生成を打ち切る。
+///
+/// 結果は各文字の [`PASSWORD_CHARS`](crate::PASSWORD_CHARS) 中のインデックス(累積値)の配列として渡すので、
+/// 結果ごとの `String` のアロケーションは行わない。
+/// 文字列が必要な場合は [`cums_to_password_into`](crate::cums_to_password_into) でバッファに書き込める。
+///
+/// エラーは [`generate_with_options`] と同様で、その場合 `f` は呼ばれない。
+pub fn generate_each(
+    pattern: &Pattern,
+    n_max: usize,
+    options: &GenerateOptions,
+    mut f: impl FnMut(&[u8; 20]) -> bool,
+) -> Dq1PasswordResult<()> {
+    let job = GenerateJob::new(pattern, n_max, options)?;
 
-    for (bs, ss) in itertools::izip!(bytes.chunks_mut(3), sixs.chunks(4)) {
-        bs[0] = ss[0] | (ss[1] << 6);
-        bs[1] = (ss[1] >> 2) | (ss[2] << 4);
-        bs[2] = (ss[2] >> 4) | (ss[3] << 2);
-    }
+    job.run(|bytes| f(&sixs_to_cums(&bytes_to_sixs(&bytes))));
 
-    bytes
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// オプションを指定して復活の呪文たちを生成し、それぞれのデコード結果が
+/// `GameState::default()` と異なるフィールドの個数を併せて返す。
+///
+/// `options.fill` が `Fill::Defaults` の場合、'?' の部分をなるべくデフォルト状態に近くなるよう埋め、
+/// 結果を差異の少ない順に並べる。厳密な最小化はコストが大きいので、以下の近似を用いる:
+///
+/// 1. 6bit 値配列の各位置について、その位置の bit がデフォルト状態と異なるフィールドの個数をコストとし、
+///    先頭 2 要素の組ごとにコストの総和が小さい順に最大 `n_max` 個を求める。
+///    複数の位置にまたがるフィールド(経験値など)は位置ごとに数えるので、実際の差異とは一致しない。
+/// 2. それらを実際の差異の個数(同じならコスト)の小さい順に並べ、先頭 `n_max` 個を返す。
+///
+/// 先頭 2 要素の組を全て調べるので、パターンの先頭 2 文字が '?' だと時間がかかる。
+///
+/// その他は [`generate_with_options`] と同様。
+pub fn generate_filled(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<FilledPassword>> {
+    let bytess = generate_bytess(&Pattern::parse(pattern)?, n_max, options)?;
 
-    #[test]
-    fn test_ndvec() {
-        assert_eq!(ndvec![0; 2, 3, 4], vec![vec![vec![0; 4]; 3]; 2]);
-    }
+    Ok(bytess
+        .iter()
+        .map(|bytes| FilledPassword {
+            password: bytes_to_password(bytes),
+            diff_count: diff_count_from_default(&bytes_to_state(bytes)),
+        })
+        .collect())
+}
 
-    #[test]
-    fn test_crc16_table_tail() {
-        const TABLE: [[u16; 0x40]; 18] = crc16_table_tail();
-        const BYTES: [u8; 3] = [0b01000101, 0b01100111, 0b10001001];
-
-        let sixs = [
-            BYTES[0] & 0x3F,
-            (BYTES[0] >> 6) | ((BYTES[1] & 0xF) << 2),
-            (BYTES[1] >> 4) | ((BYTES[2] & 0x3) << 4),
-            BYTES[2] >> 2,
-        ];
+/// 復活の呪文たちを、デコード結果のソルト以外のゲーム状態によってグループ分けする。
+///
+/// グループは代表の復活の呪文が現れた順に並ぶ。
+/// 生成時にまとめたい場合は `GenerateOptions::dedup_by_state` を用いる。
+///
+/// デコードできない復活の呪文が含まれる場合、[`decode`](crate::decode()) と同様のエラーを返す。
+pub fn group_by_state(passwords: Vec<String>) -> Dq1PasswordResult<Vec<StateGroup>> {
+    let mut groups: Vec<StateGroup> = vec![];
+    let mut key_to_group: BTreeMap<GameState, usize> = BTreeMap::new();
 
-        let crc_actual = sixs
-            .iter()
-            .enumerate()
-            .fold(0, |crc, (i, &s)| crc ^ TABLE[14 + i][usize::from(s)]);
-        let crc_expect = BYTES.iter().fold(0, |crc, &b| crc_update(crc, b, 8));
+    for password in passwords {
+        let state = decode(&password)?;
+        let key = GameState {
+            salt: 0,
+            ..state.clone()
+        };
+        match key_to_group.get(&key) {
+            Some(&i) => groups[i].siblings.push(password),
+            None => {
+                key_to_group.insert(key, groups.len());
+                groups.push(StateGroup {
+                    password,
+                    state,
+                    siblings: vec![],
+                });
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// 指定されたパターンに合致するデコード可能な復活の呪文のうち、辞書順(五十音表の順)で最小のものを返す。
+/// 存在しない場合は None を返す。
+///
+/// 先頭から 1 文字ずつ、残りを完成させられる最小の文字を選んでいく。
+/// 完成させられるかどうかは、末尾から逆向きに行う到達可能性 DP により判定する。
+///
+/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn first_match(pattern: impl AsRef<str>) -> Dq1PasswordResult<Option<String>> {
+    let pattern = Pattern::parse(pattern)?;
+    let options = GenerateOptions::default();
+    let constraints = Constraints::new(&options)?;
+
+    // 到達可能性の表はタイを解消したパターンや先頭 2 要素の組の間で使い回す。
+    let mut reach = FirstMatchReach::new(constraints.item_state_count());
+
+    // タイを解消したパターンごとの最小のもののうち、最小のもの。
+    let first = pattern
+        .untied_char_sets()
+        .filter_map(|cums| first_match_untied(&mut reach, &cums, &constraints, &CrcTables::DQ1_FC))
+        .min_by_key(|password| {
+            password
+                .chars()
+                .map(|c| password_char_to_cum(c).unwrap())
+                .collect::<Vec<_>>()
+        });
+
+    Ok(first)
+}
+
+/// 各位置で許される文字の集合に合致するデコード可能な復活の呪文のうち、辞書順で最小のものを返す。
+fn first_match_untied(
+    reach: &mut FirstMatchReach,
+    cums: &[CharSet; 20],
+    constraints: &Constraints,
+    crc_tables: &CrcTables,
+) -> Option<String> {
+    let (cums_head, cums_tail) = cums.split_at(2);
+    let cums_tail: [_; 18] = cums_tail.try_into().unwrap();
+
+    itertools::iproduct!(cums_head[0].cums(), cums_head[1].cums()).find_map(|(cum0, cum1)| {
+        let sixs_head = [
+            cum0.wrapping_sub(4) & 0x3F,
+            cum1.wrapping_sub(cum0 + 4) & 0x3F,
+        ];
+        first_match_dp(reach, sixs_head, &cums_tail, constraints, crc_tables)
+            .map(|bytes| bytes_to_password(&bytes))
+    })
+}
+
+/// オプションを指定して、パターンに合致する有効なゲーム状態に対応するバイト列たちを生成する。
+fn generate_bytess(
+    pattern: &Pattern,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<[u8; 15]>> {
+    let job = GenerateJob::new(pattern, n_max, options)?;
+
+    let mut bytess = vec![];
+    job.run(|bytes| {
+        bytess.push(bytes);
+        true
+    });
+
+    Ok(bytess)
+}
+
+/// [`GenerateJob::run`] の結果の要約。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct RunSummary {
+    /// 合致するものを全て渡した(他に合致するものが存在しない)場合に限り true。
+    pub(crate) exhausted: bool,
+
+    /// `options.plausible_only` により読み飛ばした個数。
+    pub(crate) implausible_skipped: usize,
+}
+
+/// 検証済みの生成要求。生成結果を 1 つずつコールバックに渡せる。
+///
+/// 同期版と非同期版の生成で結果とその順序を一致させるため、生成処理は全てここを通す。
+#[derive(Clone, Debug)]
+pub(crate) struct GenerateJob {
+    pattern: Pattern,
+    n_max: usize,
+    options: GenerateOptions,
+    crc_tables: CrcTables,
+    policy: ValidationPolicy,
+}
+
+impl GenerateJob {
+    /// パターンとオプションを検証し、生成要求を作る。
+    ///
+    /// エラーは [`generate_with_options`] と同様。
+    pub(crate) fn new(
+        pattern: &Pattern,
+        n_max: usize,
+        options: &GenerateOptions,
+    ) -> Dq1PasswordResult<Self> {
+        // パターンの各位置で許される文字の集合。
+        let cums = *pattern.char_sets();
+        let constraints = Constraints::new(options)?;
+
+        let (cums_head, cums_tail) = cums.split_at(2);
+        let cums_tail: [_; 18] = cums_tail.try_into().unwrap();
+
+        if let Some(limit) = options.max_memory_bytes {
+            let n_result = result_count_max(pattern, n_max, options);
+            let estimate = memory_estimate(cums_head, &cums_tail, n_result, &constraints);
+            if estimate > limit {
+                return Err(Dq1PasswordError::resource_limit(format!(
+                    "メモリ使用量の見積もり {} バイトが上限 {} バイトを超える(n_max を小さくするか、パターンを絞ること)",
+                    estimate, limit
+                )));
+            }
+        }
+
+        Ok(Self {
+            pattern: pattern.clone(),
+            n_max,
+            options: options.clone(),
+            crc_tables: CrcTables::DQ1_FC,
+            policy: ValidationPolicy::DQ1_FC,
+        })
+    }
+
+    /// CRC のパラメータを `codec` のものに変える。既定では本家のもの。
+    pub(crate) fn with_codec(self, codec: &Codec) -> Self {
+        Self {
+            crc_tables: CrcTables::new(codec.crc_poly(), codec.crc_init()),
+            ..self
+        }
+    }
+
+    /// ゲーム状態の検査の基準を `policy` に変える。既定では本家のもの。
+    pub(crate) fn with_policy(self, policy: &ValidationPolicy) -> Self {
+        Self {
+            policy: *policy,
+            ..self
+        }
+    }
+
+    /// 生成結果を順に `emit` に渡す。`emit` が false を返したら生成を打ち切る。
+    ///
+    /// `Fill::Any` かつ `SortBy::Generation` の場合、先頭 2 要素の組ごとの DP が終わるたびに結果を渡す。
+    /// それ以外の場合は全ての結果が揃ってから渡す。
+    ///
+    /// 生成上限数に達した場合や打ち切られた場合、戻り値の `exhausted` は false となる。
+    ///
+    /// パターンにタイがある場合、タイを解消したパターンごとに生成する
+    /// (よってタイの組の文字が最も外側のループとなる)。
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "generate", level = "debug", skip_all, fields(n_max = self.n_max))
+    )]
+    pub(crate) fn run(&self, mut emit: impl FnMut([u8; 15]) -> bool) -> RunSummary {
+        let n_max = self.n_max;
+        let options = &self.options;
+
+        // 1 個も渡さないので、DP を行わずに打ち切ったものとする。
+        if n_max == 0 {
+            return RunSummary {
+                exhausted: false,
+                implausible_skipped: 0,
+            };
+        }
+
+        let constraints = Constraints::with_policy(options, &self.policy).unwrap();
+
+        let tasks = generate_tasks(&self.pattern, &constraints);
+
+        // ソルトを無視したゲーム状態で重複を除く場合、既出のものの集合。
+        let mut states_seen = options.dedup_by_state.then(BTreeSet::new);
+
+        // 並べ替える場合は、合致するもの全てが揃うまで溜めておき、並べ替えてから先頭 n_max 個を渡す。
+        let mut sorted = (options.sort_by == SortBy::InputCost).then(Vec::new);
+        let n_search = if sorted.is_some() && n_max > 0 {
+            usize::MAX
+        } else {
+            n_max
+        };
+
+        // 他に合致するものが存在するかどうかを判定するため、1 個余分に探す(余分なものは渡さない)。
+        // 生成順は上限数によらないので、余分に探しても渡すものは変わらない。
+        let n_probe = n_search.saturating_add(1);
+        let mut n_found = 0;
+
+        let mut output = |bytes| {
+            n_found += 1;
+            if n_found > n_search {
+                return false;
+            }
+            match sorted.as_mut() {
+                Some(sorted) => {
+                    sorted.push(bytes);
+                    true
+                }
+                None => emit(bytes),
+            }
+        };
+
+        let mut exhausted = true;
+        match options.fill {
+            Fill::Any => {
+                let mut n_remain = n_probe;
+                // DP テーブルは先頭 2 要素の組の間で使い回す。
+                let mut dp = DpTraceTable::new_sparse(constraints.item_state_count());
+                for (sixs_head, cums_tail) in tasks {
+                    if n_remain == 0 {
+                        break;
+                    }
+                    let partial = generate_dp(
+                        &mut dp,
+                        sixs_head,
+                        &cums_tail,
+                        n_remain,
+                        &constraints,
+                        &self.crc_tables,
+                        states_seen.as_mut(),
+                    );
+                    n_remain -= partial.len();
+                    if !partial.into_iter().all(&mut output) {
+                        exhausted = false;
+                        break;
+                    }
+                }
+            }
+            Fill::Defaults => {
+                let costs = six_costs();
+                let mut candidates = vec![];
+                let mut dp = DpTable::new_sparse(DP_COST_INF, constraints.item_state_count());
+                for (sixs_head, cums_tail) in tasks {
+                    let partial = generate_dp_min_cost(
+                        &mut dp,
+                        sixs_head,
+                        &cums_tail,
+                        n_probe,
+                        &constraints,
+                        &costs,
+                        &self.crc_tables,
+                    );
+                    // 先頭 2 要素の組ごとの候補は最大 n_search 個とし、それを超えたら打ち切られたとみなす。
+                    // (重複除去により実際には全て列挙済みの場合もあるが、保守的に扱う)
+                    if partial.len() > n_search {
+                        exhausted = false;
+                    }
+                    candidates.extend(partial.into_iter().take(n_search));
+                }
+                candidates.sort_by_cached_key(|&(cost, bytes)| {
+                    (diff_count_from_default(&bytes_to_state(&bytes)), cost)
+                });
+                let bytess = candidates
+                    .into_iter()
+                    .map(|(_, bytes)| bytes)
+                    .filter(|bytes| {
+                        states_seen
+                            .as_mut()
+                            .is_none_or(|seen| seen.insert(state_key(bytes)))
+                    });
+                for bytes in bytess {
+                    if !output(bytes) {
+                        exhausted = false;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(mut sorted) = sorted {
+            sorted.sort_by_cached_key(|bytes| input_cost_sort_key(&bytes_to_password(bytes)));
+            if sorted.len() > n_max {
+                exhausted = false;
+            }
+            for bytes in sorted.into_iter().take(n_max) {
+                if !emit(bytes) {
+                    exhausted = false;
+                    break;
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(n_found, exhausted, "generate finished");
+
+        RunSummary {
+            exhausted,
+            implausible_skipped: constraints.implausible_skipped.get(),
+        }
+    }
+}
+
+/// 生成時に DP を行う単位 (先頭 2 要素, tail 部の各位置で許される文字の集合)。
+type GenerateTask = ([u8; 2], [CharSet; 18]);
+
+/// 生成時に DP を行う単位たちを生成順に返す。
+fn generate_tasks<'a>(
+    pattern: &'a Pattern,
+    constraints: &'a Constraints<'_>,
+) -> impl Iterator<Item = GenerateTask> + 'a {
+    pattern.untied_char_sets().flat_map(move |cums| {
+        let (cums_head, cums_tail) = cums.split_at(2);
+        let cums_tail: [_; 18] = cums_tail.try_into().unwrap();
+        itertools::iproduct!(cums_head[0].cums(), cums_head[1].cums())
+            .filter_map(move |(cum0, cum1)| head_sixs(cum0, cum1, constraints))
+            .map(move |sixs_head| (sixs_head, cums_tail))
+    })
+}
+
+/// 先頭 2 要素の累積値から 6bit 値たちを求める。制約により許されない場合は None を返す。
+fn head_sixs(cum0: u8, cum1: u8, constraints: &Constraints) -> Option<[u8; 2]> {
+    let sixs_head = [
+        cum0.wrapping_sub(4) & 0x3F,
+        cum1.wrapping_sub(cum0 + 4) & 0x3F,
+    ];
+    (constraints.allows_six(0, sixs_head[0]) && constraints.allows_six(1, sixs_head[1]))
+        .then_some(sixs_head)
+}
+
+/// 既定のオプションでの生成結果を、必要な分だけ順に求めるもの。
+/// [`PreparedSearch`](crate::PreparedSearch) と [`GenerateIter`](crate::GenerateIter) で用いる。
+///
+/// 結果とその順序は [`generate`] と同じ。
+/// DP を行う単位は [`generate_tasks`] と同じ順に、必要になったときに 1 つずつ求める
+/// (タイを解消したパターンの番号と、先頭 2 要素の累積値の組の番号のみを保持する)。
+/// 復元途中の先頭 2 要素の組については、埋めた DP テーブルを保持して続きの復元に使う。
+/// よって各組の DP は高々 1 回しか行われない。保持するテーブルは 1 組分のみ。
+#[derive(Clone, Debug)]
+pub(crate) struct ResumableGenerate {
+    pattern: Pattern,
+
+    /// 次に調べる、タイを解消したパターンの番号。
+    untie_next: usize,
+
+    /// 次に調べる、先頭 2 要素の累積値の組の番号 (`cum0 * 0x40 + cum1`)。
+    head_next: usize,
+
+    /// 復元途中の組と復元済みの個数。
+    restoring: Option<(GenerateTask, usize)>,
+
+    dp: DpTraceTable,
+
+    /// DP を行った回数。
+    #[cfg(test)]
+    fill_count: usize,
+}
+
+impl ResumableGenerate {
+    pub(crate) fn new(pattern: &Pattern) -> Self {
+        let options = GenerateOptions::default();
+        let constraints = Constraints::new(&options).unwrap();
+
+        Self {
+            pattern: pattern.clone(),
+            untie_next: 0,
+            head_next: 0,
+            restoring: None,
+            dp: DpTraceTable::new_sparse(constraints.item_state_count()),
+            #[cfg(test)]
+            fill_count: 0,
+        }
+    }
+
+    /// 次に DP を行う単位を求め、カーソルを進める。全て調べ終えたら None を返す。
+    fn next_task(&mut self, constraints: &Constraints) -> Option<GenerateTask> {
+        while self.untie_next < self.pattern.untied_count() {
+            let cums = self.pattern.untied_char_sets_at(self.untie_next);
+            while self.head_next < 0x40 * 0x40 {
+                let cum0 = (self.head_next / 0x40) as u8;
+                let cum1 = (self.head_next % 0x40) as u8;
+                self.head_next += 1;
+                if !(cums[0].contains_cum(cum0) && cums[1].contains_cum(cum1)) {
+                    continue;
+                }
+                if let Some(sixs_head) = head_sixs(cum0, cum1, constraints) {
+                    return Some((sixs_head, cums[2..].try_into().unwrap()));
+                }
+            }
+            self.untie_next += 1;
+            self.head_next = 0;
+        }
+        None
+    }
+
+    /// 求まった結果が `found` に `n` 個以上入るか、全て求まるまで、続きの結果を `found` に追加する。
+    ///
+    /// `found` には前回までに追加したもののうち、呼び出し側が消費していないものが残っていてよい
+    /// (消費したものは取り除いてよい)。
+    /// 復元の重複を減らすため、`n` 個より多く追加することがある。
+    pub(crate) fn extend(&mut self, found: &mut Vec<[u8; 15]>, n: usize) {
+        let options = GenerateOptions::default();
+        let constraints = Constraints::new(&options).unwrap();
+
+        while found.len() < n {
+            let (task, n_restored) = match self.restoring {
+                Some(x) => x,
+                None => {
+                    let task = match self.next_task(&constraints) {
+                        Some(task) => task,
+                        None => break,
+                    };
+                    let (sixs_head, cums_tail) = &task;
+                    // 後で続きを復元できるよう、DpTrace を打ち切らずに埋める。
+                    self.dp.reset(cums_tail);
+                    generate_dp_fill(
+                        &mut self.dp,
+                        *sixs_head,
+                        cums_tail,
+                        usize::MAX,
+                        &constraints,
+                        &CrcTables::DQ1_FC,
+                    );
+                    #[cfg(test)]
+                    {
+                        self.fill_count += 1;
+                    }
+                    (task, 0)
+                }
+            };
+
+            // 復元は毎回先頭からやり直すので、復元済みの個数以上を一度に求めて償却する。
+            let n_want = (n - found.len()).max(n_restored).max(1);
+            let n_max = n_restored.saturating_add(n_want);
+            let (sixs_head, cums_tail) = &task;
+            let bytess =
+                generate_dp_restore(*sixs_head, cums_tail, n_max, &constraints, &self.dp, None);
+
+            self.restoring = (bytess.len() == n_max).then_some((task, n_max));
+            found.extend_from_slice(&bytess[n_restored..]);
+        }
+    }
+
+    /// 保持している DP テーブルのおおよそのヒープ使用量(バイト単位)を返す。
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.dp.heap_bytes()
+    }
+
+    /// DP を行った回数を返す。
+    #[cfg(test)]
+    pub(crate) fn fill_count(&self) -> usize {
+        self.fill_count
+    }
+}
+
+/// パターンに合致する復活の呪文を生成する際のメモリ使用量(バイト単位)を見積もる。
+///
+/// DP は先頭 2 文字の組ごとに行われ、テーブルは組の間で使い回されるので、
+/// 1 回分の DP テーブルと結果の格納に必要な量のピークを見積もる。
+/// 概算なので、実際の使用量とは数倍程度異なりうる。
+///
+/// `options.sort_by` が `SortBy::InputCost` の場合、`n_max` によらず合致するもの全てを保持する分を見積もる
+/// (その個数を数えるので、パターンが緩いと時間がかかる)。
+///
+/// `options.max_memory_bytes` の判定にはこの値が用いられる。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+///
+/// `options` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` または
+/// `Err(Dq1PasswordError::InvalidConstraint)` を返す。
+pub fn estimate_memory_bytes(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<usize> {
+    let pattern = Pattern::parse(pattern)?;
+    let cums = *pattern.char_sets();
+    let constraints = Constraints::new(options)?;
+
+    let (cums_head, cums_tail) = cums.split_at(2);
+    let cums_tail: [_; 18] = cums_tail.try_into().unwrap();
+
+    let n_result = result_count_max(&pattern, n_max, options);
+
+    Ok(memory_estimate(
+        cums_head,
+        &cums_tail,
+        n_result,
+        &constraints,
+    ))
+}
+
+/// 生成中に保持しうる結果の個数の上限を返す。
+///
+/// `SortBy::InputCost` の場合は合致するもの全てを保持するので、パターンに合致するものの個数
+/// (オプションによる制約は考慮しない)となる。
+fn result_count_max(pattern: &Pattern, n_max: usize, options: &GenerateOptions) -> usize {
+    if options.sort_by == SortBy::InputCost && n_max > 0 {
+        let total: u128 = count_by_position(pattern)[0].iter().sum();
+        usize::try_from(total).unwrap_or(usize::MAX)
+    } else {
+        n_max
+    }
+}
+
+fn memory_estimate(
+    cums_head: &[CharSet],
+    cums_tail: &[CharSet; 18],
+    n_max: usize,
+    constraints: &Constraints,
+) -> usize {
+    let n_state = usize::from(constraints.item_state_count());
+
+    // 疎なテーブルは到達しうるセルのみを持つ。キーと木の節点の分を見込んで、1 セルあたり 3 セル分とする。
+    let n_cell_reachable = dp_reachable_cell_count(cums_tail, constraints.item_state_count());
+    let n_cell = if n_cell_reachable <= DP_SPARSE_CELL_COUNT_MAX {
+        3 * n_cell_reachable
+    } else {
+        19 * 0x40 * 0x100 * n_state
+    };
+
+    // 結果のバイト列(生成中と復元中)と、変換後の文字列。
+    let result = n_max.saturating_mul(2 * size_of::<[u8; 15]>() + size_of::<String>() + 20 * 3);
+
+    let dp = match constraints.options.fill {
+        Fill::Any => {
+            let trace_count_max = if constraints.has_filter() {
+                DP_TRACE_COUNT_MAX
+            } else {
+                n_max.min(DP_TRACE_COUNT_MAX)
+            };
+
+            // 各段の空でないセル数とセルあたりの DpTrace 数を、遷移数から見積もる。
+            // 段ごとの一時領域は最も大きい段の分とし、伸長時に最大 2 倍の領域を持ちうるとする。
+            let mut traces = 0;
+            let mut traces_layer_max = 0;
+            let mut cells = 1;
+            for set in cums_tail {
+                let width = set.len();
+                let transitions = cells * width;
+                let cells_nxt = transitions.min(width * 0x100 * n_state);
+                let per_cell = transitions.div_ceil(cells_nxt).min(trace_count_max);
+                traces += cells_nxt * per_cell;
+                traces_layer_max = traces_layer_max.max(cells_nxt * per_cell);
+                cells = cells_nxt;
+            }
+
+            (n_cell * size_of::<DpSlot>())
+                .saturating_add(traces * size_of::<DpTrace>())
+                .saturating_add(2 * traces_layer_max * size_of::<((u8, u8, u8), DpTrace)>())
+        }
+        Fill::Defaults => {
+            // 最小コストのテーブルと、全ての先頭 2 文字の組についての候補。
+            let n_head = cums_head[0].len() * cums_head[1].len();
+            n_cell.saturating_add(
+                n_max
+                    .saturating_mul(n_head)
+                    .saturating_mul(size_of::<(u16, [u8; 15])>()),
+            )
+        }
+    };
+
+    dp.saturating_add(result)
+}
+
+/// 生成時の DP テーブル。
+///
+/// 添字は (tail 部の位置 i, 累積値 j, CRC k, 状態 l) で、範囲はそれぞれ
+/// `0..19`, `0..0x40`, `0..0x100`, `0..n_state`。
+///
+/// 表全体を 1 つの連続した領域に置く(密)か、既定値でないセルのみを保持する(疎)。
+/// 疎な場合、書き込まれていないセルは既定値として読める。
+///
+/// 先頭 2 要素の組ごとの DP で使い回せるよう、[`reset`](Self::reset) で全てのセルを既定値に戻せる。
+#[derive(Clone, Debug)]
+struct DpTable<T> {
+    n_state: u8,
+    default: T,
+    cells: DpCells<T>,
+}
+
+#[derive(Clone, Debug)]
+enum DpCells<T> {
+    /// 添字から位置を計算して置く。
+    Dense {
+        cells: Vec<T>,
+
+        /// 書き込まれたかもしれないセルの位置たち(重複しうる)。
+        touched: Vec<usize>,
+    },
+
+    /// 位置をキーとして、書き込まれたセルのみを置く。
+    Sparse {
+        map: BTreeMap<usize, T>,
+
+        /// 既定値に戻したセルの値たち。セルを追加する際に再利用する。
+        spare: Vec<T>,
+    },
+}
+
+/// 疎なテーブルを用いる、tail 部の各位置から到達しうるセル数の上限の総和の最大値。
+const DP_SPARSE_CELL_COUNT_MAX: usize = 1 << 14;
+
+impl<T: Clone + PartialEq> DpTable<T> {
+    /// 全てのセルが `default` である密なテーブルを作る。
+    fn new_dense(default: T, n_state: u8) -> Self {
+        Self {
+            n_state,
+            cells: DpCells::Dense {
+                cells: vec![default.clone(); Self::cell_count(n_state)],
+                touched: vec![],
+            },
+            default,
+        }
+    }
+
+    /// 全てのセルが `default` である疎なテーブルを作る。
+    fn new_sparse(default: T, n_state: u8) -> Self {
+        Self {
+            n_state,
+            default,
+            cells: DpCells::Sparse {
+                map: BTreeMap::new(),
+                spare: vec![],
+            },
+        }
+    }
+
+    /// 全てのセルが `default` であるテーブルを作る。
+    ///
+    /// 先頭から配る DP で到達しうるセルが少ない場合(固定された文字が多い場合など)は疎なテーブルとする。
+    fn new(default: T, n_state: u8, cums_tail: &[CharSet; 18]) -> Self {
+        if Self::prefers_sparse(n_state, cums_tail) {
+            Self::new_sparse(default, n_state)
+        } else {
+            Self::new_dense(default, n_state)
+        }
+    }
+
+    fn prefers_sparse(n_state: u8, cums_tail: &[CharSet; 18]) -> bool {
+        dp_reachable_cell_count(cums_tail, n_state) <= DP_SPARSE_CELL_COUNT_MAX
+    }
+
+    fn is_sparse(&self) -> bool {
+        matches!(self.cells, DpCells::Sparse { .. })
+    }
+
+    /// 全てのセルを既定値に戻し、密/疎を `cums_tail` に応じて選び直す。
+    fn reset(&mut self, cums_tail: &[CharSet; 18]) {
+        if Self::prefers_sparse(self.n_state, cums_tail) == self.is_sparse() {
+            self.clear();
+        } else {
+            *self = Self::new(self.default.clone(), self.n_state, cums_tail);
+        }
+    }
+
+    /// 全てのセルを既定値に戻す。
+    ///
+    /// 書き込まれたセルのみを戻すので、かかる時間は前回の DP で行った書き込みの量に比例する。
+    /// セルの値の領域(`Vec` のバッファなど)は可能な限り再利用する。
+    fn clear(&mut self) {
+        let default = &self.default;
+        match &mut self.cells {
+            DpCells::Dense { cells, touched } => {
+                for pos in touched.drain(..) {
+                    cells[pos].clone_from(default);
+                }
+            }
+            DpCells::Sparse { map, spare } => {
+                spare.extend(core::mem::take(map).into_values().map(|mut value| {
+                    value.clone_from(default);
+                    value
+                }));
+            }
+        }
+    }
+}
+
+impl<T> DpTable<T> {
+    /// 状態数 `n_state` のテーブルのセル数を返す。
+    const fn cell_count(n_state: u8) -> usize {
+        19 * 0x40 * 0x100 * n_state as usize
+    }
+
+    fn position(&self, (i, j, k, l): (usize, u8, u8, u8)) -> usize {
+        debug_assert!(i < 19);
+        debug_assert!(j < 0x40);
+        debug_assert!(l < self.n_state);
+
+        ((i * 0x40 + usize::from(j)) * 0x100 + usize::from(k)) * usize::from(self.n_state)
+            + usize::from(l)
+    }
+
+    /// tail 部 i 個目のセルのうち、既定値でないものを含む添字 (j, k, l) たちを昇順に返す。
+    ///
+    /// 密なテーブルでは全ての添字を返す。
+    fn layer(&self, i: usize) -> impl Iterator<Item = (u8, u8, u8)> {
+        let n_state = self.n_state;
+        match &self.cells {
+            DpCells::Dense { .. } => {
+                itertools::Either::Left(itertools::iproduct!(0..=0x3F, 0..=0xFF, 0..n_state))
+            }
+            DpCells::Sparse { map, .. } => {
+                let n = 0x40 * 0x100 * usize::from(n_state);
+                let idxs: Vec<_> = map
+                    .range(i * n..(i + 1) * n)
+                    .map(|(&pos, _)| {
+                        let l = pos % usize::from(n_state);
+                        let jk = pos / usize::from(n_state) % (0x40 * 0x100);
+                        (
+                            u8::try_from(jk >> 8).unwrap(),
+                            u8::try_from(jk & 0xFF).unwrap(),
+                            u8::try_from(l).unwrap(),
+                        )
+                    })
+                    .collect();
+                itertools::Either::Right(idxs.into_iter())
+            }
+        }
+    }
+
+    /// おおよそのヒープ使用量(バイト単位)を返す。セルの値自体が持つヒープ領域は含めない。
+    fn heap_bytes(&self) -> usize {
+        match &self.cells {
+            DpCells::Dense { cells, touched } => {
+                cells.capacity() * size_of::<T>() + touched.capacity() * size_of::<usize>()
+            }
+            // 木の節点の分を見込んで、1 セルあたりキーと値の 2 倍とする。
+            DpCells::Sparse { map, spare } => {
+                2 * map.len() * (size_of::<usize>() + size_of::<T>())
+                    + spare.capacity() * size_of::<T>()
+            }
+        }
+    }
+
+    /// tail 部 i 個目のセルのうち、既定値でないものを含むセルたちを位置の昇順に返す。
+    ///
+    /// 密なテーブルでは全てのセルを返す。
+    fn layer_values_mut(&mut self, i: usize) -> impl Iterator<Item = &mut T> {
+        let n = 0x40 * 0x100 * usize::from(self.n_state);
+        match &mut self.cells {
+            DpCells::Dense { cells, .. } => {
+                itertools::Either::Left(cells[i * n..(i + 1) * n].iter_mut())
+            }
+            DpCells::Sparse { map, .. } => {
+                itertools::Either::Right(map.range_mut(i * n..(i + 1) * n).map(|(_, value)| value))
+            }
+        }
+    }
+}
+
+impl<T> Index<(usize, u8, u8, u8)> for DpTable<T> {
+    type Output = T;
+
+    fn index(&self, idx: (usize, u8, u8, u8)) -> &T {
+        let pos = self.position(idx);
+        match &self.cells {
+            DpCells::Dense { cells, .. } => &cells[pos],
+            DpCells::Sparse { map, .. } => map.get(&pos).unwrap_or(&self.default),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> IndexMut<(usize, u8, u8, u8)> for DpTable<T> {
+    fn index_mut(&mut self, idx: (usize, u8, u8, u8)) -> &mut T {
+        let pos = self.position(idx);
+        let default = &self.default;
+        match &mut self.cells {
+            DpCells::Dense { cells, touched } => {
+                if cells[pos] == *default {
+                    touched.push(pos);
+                }
+                &mut cells[pos]
+            }
+            DpCells::Sparse { map, spare } => map
+                .entry(pos)
+                .or_insert_with(|| spare.pop().unwrap_or_else(|| default.clone())),
+        }
+    }
+}
+
+/// 先頭から配る DP で、tail 部の各位置から到達しうるセル数の上限の総和を返す。
+///
+/// 各位置のセル数は、前の位置のセル数とその位置で許される文字数の積を超えない。
+fn dp_reachable_cell_count(cums_tail: &[CharSet; 18], n_state: u8) -> usize {
+    let mut total = 1;
+    let mut cells = 1_usize;
+    for set in cums_tail {
+        let width = set.len();
+        cells = cells
+            .saturating_mul(width)
+            .min(width * 0x100 * usize::from(n_state));
+        total += cells;
+    }
+
+    total
+}
+
+/// DP の各セルが保持しうる `DpTrace` の最大個数。
+///
+/// 遷移元のセルは six と l により一意に定まるので、この個数まで保持すれば全ての解を復元できる。
+const DP_TRACE_COUNT_MAX: usize = 0x40 * 4;
+
+/// [`generate_dp`] の DP テーブル。各セルは `DpTrace` たちを保持する。
+///
+/// tail 部の位置ごとに、その段の全セルの `DpTrace` たちを 1 つの領域(arena)に置き、各セルはその範囲のみを持つ。
+/// 同じセルの `DpTrace` たちは arena 上で連続し、追加順に並ぶ。
+///
+/// tail 部の位置の昇順に 1 段ずつ埋める。段 i に [`push`](Self::push) した後、
+/// [`commit_layer`](Self::commit_layer) で確定させるまで、その段の `DpTrace` たちは読めない。
+#[derive(Clone, Debug)]
+struct DpTraceTable {
+    slots: DpTable<DpSlot>,
+    arenas: [Vec<DpTrace>; 19],
+
+    /// 埋めている途中の段に追加された (添字 (j, k, l), `DpTrace`) たち。
+    pending: Vec<((u8, u8, u8), DpTrace)>,
+}
+
+/// [`DpTraceTable`] の 1 セルが持つ `DpTrace` たちの、その段の arena 上の範囲。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct DpSlot {
+    offset: u32,
+    len: u16,
+}
+
+impl DpTraceTable {
+    #[cfg(test)]
+    fn new_dense(n_state: u8) -> Self {
+        Self::with_slots(DpTable::new_dense(DpSlot::default(), n_state))
+    }
+
+    fn new_sparse(n_state: u8) -> Self {
+        Self::with_slots(DpTable::new_sparse(DpSlot::default(), n_state))
+    }
+
+    fn with_slots(slots: DpTable<DpSlot>) -> Self {
+        Self {
+            slots,
+            arenas: Default::default(),
+            pending: vec![],
+        }
+    }
+
+    /// 全てのセルを空にし、密/疎を `cums_tail` に応じて選び直す。arena の領域は再利用する。
+    fn reset(&mut self, cums_tail: &[CharSet; 18]) {
+        self.slots.reset(cums_tail);
+        self.clear_traces();
+    }
+
+    /// 全てのセルを空にする。
+    #[cfg(test)]
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.clear_traces();
+    }
+
+    fn clear_traces(&mut self) {
+        for arena in &mut self.arenas {
+            arena.clear();
+        }
+        self.pending.clear();
+    }
+
+    /// tail 部 i 個目のセルのうち、空でないものを含む添字 (j, k, l) たちを昇順に返す。
+    fn layer(&self, i: usize) -> impl Iterator<Item = (u8, u8, u8)> {
+        self.slots.layer(i)
+    }
+
+    /// おおよそのヒープ使用量(バイト単位)を返す。
+    fn heap_bytes(&self) -> usize {
+        self.slots.heap_bytes()
+            + self
+                .arenas
+                .iter()
+                .map(|arena| arena.capacity() * size_of::<DpTrace>())
+                .sum::<usize>()
+            + self.pending.capacity() * size_of::<((u8, u8, u8), DpTrace)>()
+    }
+
+    /// セルが持つ `DpTrace` たちを追加順に返す。
+    fn traces(&self, idx: (usize, u8, u8, u8)) -> &[DpTrace] {
+        let slot = self.slots[idx];
+        let offset = usize::try_from(slot.offset).unwrap();
+        &self.arenas[idx.0][offset..offset + usize::from(slot.len)]
+    }
+
+    /// セルに `DpTrace` を追加する。既に `count_max` 個持っている場合は何もしない。
+    fn push(&mut self, (i, j, k, l): (usize, u8, u8, u8), trace: DpTrace, count_max: usize) {
+        let slot = &mut self.slots[(i, j, k, l)];
+        if usize::from(slot.len) < count_max {
+            slot.len += 1;
+            self.pending.push(((j, k, l), trace));
+        }
+    }
+
+    /// tail 部 i 個目の段に追加された `DpTrace` たちを arena に移す。
+    fn commit_layer(&mut self, i: usize) {
+        // 各セルの範囲を位置の昇順に割り当てる。offset は一旦範囲の末尾としておき、後ろから詰める。
+        let mut end = 0;
+        for slot in self.slots.layer_values_mut(i) {
+            if slot.len == 0 {
+                continue;
+            }
+            end += usize::from(slot.len);
+            slot.offset = u32::try_from(end).unwrap();
+        }
+        let arena = &mut self.arenas[i];
+        debug_assert!(arena.is_empty());
+        arena.reserve_exact(end);
+        arena.resize(end, DpTrace(0));
+
+        for &((j, k, l), trace) in self.pending.iter().rev() {
+            let slot = &mut self.slots[(i, j, k, l)];
+            slot.offset -= 1;
+            arena[usize::try_from(slot.offset).unwrap()] = trace;
+        }
+        self.pending.clear();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct DpTrace(u16);
+
+impl DpTrace {
+    fn new(j: u8, k: u8, l: u8) -> Self {
+        debug_assert!((0..=0x3F).contains(&j));
+        debug_assert!((0..=0xFF).contains(&k));
+        debug_assert!((0..=3).contains(&l));
+        Self(u16::from(k) | (u16::from(j) << 8) | (u16::from(l) << 14))
+    }
+
+    fn j(self) -> u8 {
+        ((self.0 >> 8) & 0x3F).try_into().unwrap()
+    }
+
+    fn k(self) -> u8 {
+        (self.0 & 0xFF).try_into().unwrap()
+    }
+
+    fn l(self) -> u8 {
+        (self.0 >> 14).try_into().unwrap()
+    }
+}
+
+/// 6bit 値配列の先頭 2 要素を指定し、有効なゲーム状態に対応するバイト列たちを生成する。
+///
+/// 動的計画法を用いる。
+///
+/// 除外パターンなどで絞り込む場合、全ての解を復元できるように DP を行う。
+///
+/// `states_seen` を指定した場合、それに含まれるゲーム状態(ソルトを無視)は生成せず、
+/// 生成したもののゲーム状態を追加する。
+///
+/// `dp` は作業領域で、内容は最初にリセットされる。
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(sixs_head = ?sixs_head, n_max = n_max))
+)]
+fn generate_dp(
+    dp: &mut DpTraceTable,
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    n_max: usize,
+    constraints: &Constraints,
+    crc_tables: &CrcTables,
+    states_seen: Option<&mut BTreeSet<[u8; 15]>>,
+) -> Vec<[u8; 15]> {
+    debug_assert_ne!(n_max, 0);
+
+    dp.reset(cums_tail);
+    generate_dp_fill(dp, sixs_head, cums_tail, n_max, constraints, crc_tables);
+
+    generate_dp_restore(sixs_head, cums_tail, n_max, constraints, dp, states_seen)
+}
+
+/// [`generate_dp`] の DP テーブルを埋める。`dp` は全てのセルが空でなければならない。
+fn generate_dp_fill(
+    dp: &mut DpTraceTable,
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    n_max: usize,
+    constraints: &Constraints,
+    crc_tables: &CrcTables,
+) {
+    let trace_count_max = if constraints.has_filter() {
+        DP_TRACE_COUNT_MAX
+    } else {
+        n_max.min(DP_TRACE_COUNT_MAX)
+    };
+
+    let cum_ini = (sixs_head[0] + sixs_head[1] + 8) & 0x3F;
+    let crc_ini = crc_tables.head[usize::from(sixs_head[1] >> 2)];
+
+    // dp[i][j][k][l]:
+    //   tail 部 i 個目までで cum=j, crc=k であるときの DpTrace たち (最大要素数 n_max)
+    //   l: 4bit 境界をまたぐ道具IDの下位 2bit (直前の six の上位 2bit)に対応する状態
+    //      (道具IDの validate 用。道具IDをまたぐ位置以外では常に 0)
+    dp.push((0, cum_ini, crc_ini, 0), DpTrace::new(0, 0, 0), 1); // 値自体に意味はない
+    dp.commit_layer(0);
+
+    // 配るDP。疎なテーブルでは到達しうるセルのみを走査する。
+    for (i, cums) in cums_tail.iter().enumerate() {
+        for (j, k, l) in dp.layer(i) {
+            if dp.traces((i, j, k, l)).is_empty() {
+                continue;
+            }
+
+            for cum in cums.cums() {
+                let (six, l_nxt) = match dp_transition(i, j, l, cum, constraints) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let crc = k ^ crc_tables.tail[i][usize::from(six)];
+
+                dp.push(
+                    (i + 1, cum, crc, l_nxt),
+                    DpTrace::new(j, k, l),
+                    trace_count_max,
+                );
+            }
+        }
+        dp.commit_layer(i + 1);
+    }
+
+    #[cfg(feature = "tracing")]
+    {
+        let cells_populated = (0..19).map(|i| dp.layer(i).count()).sum::<usize>();
+        let traces_stored = dp.arenas.iter().map(Vec::len).sum::<usize>();
+        tracing::trace!(cells_populated, traces_stored, "dp filled");
+    }
+}
+
+/// DP の tail 部 i 個目で、累積値 j, 状態 l から累積値 cum へ遷移する場合の (six, 次の状態 l) を返す。
+///
+/// 無効なゲーム状態に対応する場合や、制約を満たさない場合は None を返す。
+pub(crate) fn dp_transition(
+    i: usize,
+    j: u8,
+    l: u8,
+    cum: u8,
+    constraints: &Constraints,
+) -> Option<(u8, u8)> {
+    let six = cum.wrapping_sub(j + 4) & 0x3F;
+    let l_nxt = six_transition(i, l, six, constraints)?;
+
+    Some((six, l_nxt))
+}
+
+/// DP の tail 部 i 個目で、状態 l から値 six を選ぶ場合の次の状態 l を返す。
+///
+/// 無効なゲーム状態に対応する場合や、制約を満たさない場合は None を返す。
+pub(crate) fn six_transition(i: usize, l: u8, six: u8, constraints: &Constraints) -> Option<u8> {
+    // 無効なゲーム状態に対応する場合は弾く。
+    if !six_is_valid(i + 2, six, &constraints.policy) {
+        return None;
+    }
+
+    // 制約を満たさない場合は弾く。
+    if !constraints.allows_six(i + 2, six) {
+        return None;
+    }
+    if matches!(i, 3 | 7 | 13 | 17) && !constraints.allows_item_hi(l, six & 3) {
+        return None;
+    }
+
+    let l_nxt = if matches!(i, 2 | 6 | 12 | 16) {
+        constraints.item_lo_state(six >> 4)
+    } else {
+        0
+    };
+
+    Some(l_nxt)
+}
+
+/// `Fill::Defaults` 用。6bit 値配列の先頭 2 要素を指定し、有効なゲーム状態に対応するバイト列たちを
+/// コスト(`six_costs()` の総和)の小さい順に最大 n_max 個生成する。戻り値の要素は (コスト, バイト列)。
+///
+/// 各セルの最小コストを DP で求めた後、それをヒューリスティックとする A* 探索で後ろから復元する。
+/// ヒューリスティックは正確なので、復元はコストの小さい順に行われる。
+///
+/// `dp` は作業領域で、内容は最初にリセットされる。
+fn generate_dp_min_cost(
+    dp: &mut DpTable<u8>,
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    n_max: usize,
+    constraints: &Constraints,
+    costs: &[[u8; 0x40]; 20],
+    crc_tables: &CrcTables,
+) -> Vec<(u16, [u8; 15])> {
+    const INF: u8 = DP_COST_INF;
+
+    let crc_expect = sixs_head[0] | (sixs_head[1] << 6);
+
+    let n_state = constraints.item_state_count();
+    dp.reset(cums_tail);
+    generate_dp_min_cost_fill(dp, sixs_head, cums_tail, constraints, costs, crc_tables);
+
+    // A* 探索。要素は Reverse((推定総コスト, 通し番号, i, j, k, l, 確定部のコスト, 6bit 値配列))。
+    // 通し番号により、推定総コストが等しいものは追加順に取り出される。
+    let mut heap = BinaryHeap::new();
+    let mut seq = 0_usize;
+    let mut sixs = [0; 20];
+    sixs[0] = sixs_head[0];
+    sixs[1] = sixs_head[1];
+    for (cum, l) in itertools::iproduct!(cums_tail[17].cums(), 0..n_state) {
+        let cost = dp[(18, cum, crc_expect, l)];
+        if cost == INF {
+            continue;
+        }
+        heap.push(Reverse((
+            u16::from(cost),
+            seq,
+            18,
+            cum,
+            crc_expect,
+            l,
+            0_u16,
+            sixs,
+        )));
+        seq += 1;
+    }
+
+    let mut res = vec![];
+    while let Some(Reverse((cost_total, _, i, j, k, l, cost_fixed, sixs))) = heap.pop() {
+        if i == 0 {
+            if constraints.accepts(&sixs) {
+                res.push((cost_total, sixs_to_bytes(&sixs)));
+                if res.len() == n_max {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        for (j_pre, l_pre) in itertools::iproduct!(0..=0x3F, 0..n_state) {
+            if dp_transition(i - 1, j_pre, l_pre, j, constraints).map(|(_, l_nxt)| l_nxt) != Some(l)
+            {
+                continue;
+            }
+            let six = j.wrapping_sub(j_pre + 4) & 0x3F;
+            let k_pre = k ^ crc_tables.tail[i - 1][usize::from(six)];
+            let cost_pre = dp[(i - 1, j_pre, k_pre, l_pre)];
+            if cost_pre == INF {
+                continue;
+            }
+
+            let mut sixs = sixs;
+            sixs[i + 1] = six;
+            if !constraints.may_accept(&sixs, i + 1) {
+                continue;
+            }
+
+            let cost_fixed = cost_fixed + u16::from(costs[i + 1][usize::from(six)]);
+            heap.push(Reverse((
+                cost_fixed + u16::from(cost_pre),
+                seq,
+                i - 1,
+                j_pre,
+                k_pre,
+                l_pre,
+                cost_fixed,
+                sixs,
+            )));
+            seq += 1;
+        }
+    }
+
+    res
+}
+
+/// [`generate_dp_min_cost`] の DP の、到達不能なセルのコスト。
+const DP_COST_INF: u8 = u8::MAX;
+
+/// [`generate_dp_min_cost`] の DP テーブルを埋める。`dp` は全てのセルが [`DP_COST_INF`] でなければならない。
+fn generate_dp_min_cost_fill(
+    dp: &mut DpTable<u8>,
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    constraints: &Constraints,
+    costs: &[[u8; 0x40]; 20],
+    crc_tables: &CrcTables,
+) {
+    let cum_ini = (sixs_head[0] + sixs_head[1] + 8) & 0x3F;
+    let crc_ini = crc_tables.head[usize::from(sixs_head[1] >> 2)];
+
+    // dp[i][j][k][l]: tail 部 i 個目までで cum=j, crc=k, 状態 l であるときの最小コスト
+    dp[(0, cum_ini, crc_ini, 0)] =
+        costs[0][usize::from(sixs_head[0])] + costs[1][usize::from(sixs_head[1])];
+
+    for i in 0..18 {
+        for (j, k, l) in dp.layer(i) {
+            let cost = dp[(i, j, k, l)];
+            if cost == DP_COST_INF {
+                continue;
+            }
+
+            for cum in cums_tail[i].cums() {
+                let (six, l_nxt) = match dp_transition(i, j, l, cum, constraints) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let crc = k ^ crc_tables.tail[i][usize::from(six)];
+
+                let e = &mut dp[(i + 1, cum, crc, l_nxt)];
+                *e = (*e).min(cost + costs[i + 2][usize::from(six)]);
+            }
+        }
+    }
+}
+
+/// [`first_match_dp`] で用いる到達可能性の表たち。先頭 2 要素の組の間で使い回す。
+///
+/// 添字は [`DpTable`] と同じく (tail 部の位置 i, 累積値 j, CRC k, 状態 l)。
+struct FirstMatchReach {
+    /// 先頭から到達しうるか。
+    fwd: DpTable<bool>,
+
+    /// 先頭から到達しうるセルについて、残りを完成させられるか。
+    bwd: DpTable<bool>,
+}
+
+impl FirstMatchReach {
+    /// 状態数 `n_state` の表たちを作る。密/疎は [`first_match_dp`] で選ばれる。
+    fn new(n_state: u8) -> Self {
+        Self {
+            fwd: DpTable::new_sparse(false, n_state),
+            bwd: DpTable::new_sparse(false, n_state),
+        }
+    }
+}
+
+/// 6bit 値配列の先頭 2 要素を指定し、有効なゲーム状態に対応するバイト列のうち
+/// 対応する復活の呪文が辞書順で最小のものを返す。存在しない場合は None を返す。
+///
+/// 先頭から到達しうるセルを求めてから、それらについてのみ末尾から逆向きに完成させられるかを求める。
+fn first_match_dp(
+    reach: &mut FirstMatchReach,
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    constraints: &Constraints,
+    crc_tables: &CrcTables,
+) -> Option<[u8; 15]> {
+    let FirstMatchReach { fwd, bwd } = reach;
+    fwd.reset(cums_tail);
+    bwd.reset(cums_tail);
+
+    let cum_ini = (sixs_head[0] + sixs_head[1] + 8) & 0x3F;
+    let crc_ini = crc_tables.head[usize::from(sixs_head[1] >> 2)];
+    let crc_expect = sixs_head[0] | (sixs_head[1] << 6);
+
+    // 配るDP
+    fwd[(0, cum_ini, crc_ini, 0)] = true;
+    for i in 0..18 {
+        for (j, k, l) in fwd.layer(i) {
+            if !fwd[(i, j, k, l)] {
+                continue;
+            }
+            for cum in cums_tail[i].cums() {
+                if let Some((six, l_nxt)) = dp_transition(i, j, l, cum, constraints) {
+                    let crc = k ^ crc_tables.tail[i][usize::from(six)];
+                    fwd[(i + 1, cum, crc, l_nxt)] = true;
+                }
+            }
+        }
+    }
+
+    // 集めるDP
+    for (j, k, l) in fwd.layer(18) {
+        if k == crc_expect && fwd[(18, j, k, l)] {
+            bwd[(18, j, k, l)] = true;
+        }
+    }
+    for i in (0..18).rev() {
+        for (j, k, l) in fwd.layer(i) {
+            if !fwd[(i, j, k, l)] {
+                continue;
+            }
+            let ok = cums_tail[i].cums().any(|cum| {
+                dp_transition(i, j, l, cum, constraints).is_some_and(|(six, l_nxt)| {
+                    let crc = k ^ crc_tables.tail[i][usize::from(six)];
+                    bwd[(i + 1, cum, crc, l_nxt)]
+                })
+            });
+            if ok {
+                bwd[(i, j, k, l)] = true;
+            }
+        }
+    }
+
+    if !bwd[(0, cum_ini, crc_ini, 0)] {
+        return None;
+    }
+
+    // 先頭から貪欲に最小の累積値を選ぶ。
+    let mut sixs = [0; 20];
+    sixs[0] = sixs_head[0];
+    sixs[1] = sixs_head[1];
+    let (mut j, mut k, mut l) = (cum_ini, crc_ini, 0);
+    for i in 0..18 {
+        let (cum, six, crc, l_nxt) = cums_tail[i]
+            .cums()
+            .find_map(|cum| {
+                let (six, l_nxt) = dp_transition(i, j, l, cum, constraints)?;
+                let crc = k ^ crc_tables.tail[i][usize::from(six)];
+                bwd[(i + 1, cum, crc, l_nxt)].then_some((cum, six, crc, l_nxt))
+            })
+            .unwrap();
+        sixs[i + 2] = six;
+        j = cum;
+        k = crc;
+        l = l_nxt;
+    }
+
+    Some(sixs_to_bytes(&sixs))
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "restore", level = "debug", skip_all)
+)]
+fn generate_dp_restore(
+    sixs_head: [u8; 2],
+    cums_tail: &[CharSet; 18],
+    n_max: usize,
+    constraints: &Constraints,
+    dp: &DpTraceTable,
+    states_seen: Option<&mut BTreeSet<[u8; 15]>>,
+) -> Vec<[u8; 15]> {
+    let crc_expect = sixs_head[0] | (sixs_head[1] << 6);
+
+    struct Dfs<'a> {
+        n_max: usize,
+        constraints: &'a Constraints<'a>,
+        dp: &'a DpTraceTable,
+        states_seen: Option<&'a mut BTreeSet<[u8; 15]>>,
+        bytess: Vec<[u8; 15]>,
+
+        /// 定まった文字たちの、文字数の制約ごとの個数。
+        counts: Vec<u8>,
+    }
+    impl Dfs<'_> {
+        /// 発見済の解の個数が n_max に達したら true を返す。
+        fn dfs(&mut self, i: usize, j: u8, k: u8, l: u8, sixs: &mut [u8; 20]) -> bool {
+            debug_assert!(!self.dp.traces((i, j, k, l)).is_empty());
+
+            if i == 0 {
+                if self.constraints.accepts(sixs) {
+                    let bytes = sixs_to_bytes(sixs);
+                    if self
+                        .states_seen
+                        .as_mut()
+                        .is_none_or(|seen| seen.insert(state_key(&bytes)))
+                    {
+                        self.bytess.push(bytes);
+                    }
+                }
+                return self.bytess.len() == self.n_max;
+            }
+
+            // 位置 i + 1 の文字を数え、文字数の制約を満たしえないものを弾く(未定なのは位置 2..=i)。
+            self.constraints.count_cum(&mut self.counts, j, true);
+            let mut done = false;
+            if self.constraints.counts_may_fit(&self.counts, i - 1) {
+                for &trace in self.dp.traces((i, j, k, l)) {
+                    let six = j.wrapping_sub(trace.j() + 4) & 0x3F;
+                    sixs[i + 1] = six;
+                    if !self.constraints.may_accept(sixs, i + 1) {
+                        continue;
+                    }
+                    if self.dfs(i - 1, trace.j(), trace.k(), trace.l(), sixs) {
+                        done = true;
+                        break;
+                    }
+                }
+            }
+            self.constraints.count_cum(&mut self.counts, j, false);
+
+            done
+        }
+    }
+
+    let mut sixs = [0; 20];
+    sixs[0] = sixs_head[0];
+    sixs[1] = sixs_head[1];
+
+    // 先頭 2 文字は数えておく。
+    let mut counts = vec![0; constraints.options.count_constraints.len()];
+    for &cum in &sixs_to_cums(&sixs)[..2] {
+        constraints.count_cum(&mut counts, cum, true);
+    }
+
+    let mut dfs = Dfs {
+        n_max,
+        constraints,
+        dp,
+        states_seen,
+        bytess: vec![],
+        counts,
+    };
+    for (cum, l) in itertools::iproduct!(cums_tail[17].cums(), 0..constraints.item_state_count()) {
+        if dp.traces((18, cum, crc_expect, l)).is_empty() {
+            continue;
+        }
+        if dfs.dfs(18, cum, crc_expect, l, &mut sixs) {
+            break;
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(solutions = dfs.bytess.len(), "solutions reconstructed");
+
+    dfs.bytess
+}
+
+/// 6bit 値配列の位置 `idx` の値 `six` が、`policy` の基準で単独で無効なゲーム状態を引き起こさないかどうかを返す。
+///
+/// 4bit 境界をまたぐ道具ID (位置 4-5, 8-9, 14-15, 18-19)の上位/下位の組み合わせは関知しない。
+/// 本家の基準では、これは前の値の上位 2bit と後の値の下位 2bit が共に 0b11 の場合に無効となる。
+pub(crate) fn six_is_valid(idx: usize, six: u8, policy: &ValidationPolicy) -> bool {
+    // やくそう所持数
+    if idx == 13 && (six >> 2) > policy.herb_count_max() {
+        return false;
+    }
+
+    // かぎ所持数
+    if idx == 14 && (six & 0xF) > policy.key_count_max() {
+        return false;
+    }
+
+    // インベントリ内の道具ID
+    let tool_max = policy.inventory_tool_max();
+    if matches!(idx, 4 | 8) && (six & 0xF) > tool_max {
+        return false;
+    }
+    if matches!(idx, 15 | 19) && (six >> 2) > tool_max {
+        return false;
+    }
+
+    true
+}
+
+/// 生成時の DP で用いる CRC 下位バイトテーブルたち。CRC のパラメータごとに作る。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CrcTables {
+    /// ゲーム状態バイト列の前半から CRC 部を除いた 4bit についてのテーブル。CRC の初期値の寄与を含む。
+    pub(crate) head: [u8; 0x10],
+
+    /// ゲーム状態バイト列の後半 108bit についての 6bit 単位のテーブル。
+    pub(crate) tail: [[u8; 0x40]; 18],
+}
+
+impl CrcTables {
+    /// 本家(生成多項式 0x1021、初期値 0)のテーブルたち。
+    pub(crate) const DQ1_FC: Self = Self::new(0x1021, 0);
+
+    /// 生成多項式 `poly`、初期値 `init` の CRC についてのテーブルたちを作る。
+    ///
+    /// CRC は (初期値, データ) について線形なので、初期値の寄与は
+    /// 「初期値から 112bit 分の 0 を与えた CRC」として head 側にまとめて加える。
+    pub(crate) const fn new(poly: u16, init: u16) -> Self {
+        let crc16_tail = crc16_table_tail(poly);
+
+        let mut crc_init = init;
+        let mut n = 0;
+        while n < 14 {
+            crc_init = crc_update_poly(crc_init, 0, 8, poly);
+            n += 1;
+        }
+
+        let mut head = [0; 0x10];
+        let mut j = 0;
+        while j < 0x10 {
+            let mut crc = crc16_tail[3][j << 2];
+            let mut n = 0;
+            while n < 3 {
+                crc = crc_update_poly(crc, 0, 8, poly);
+                n += 1;
+            }
+            head[j] = ((crc ^ crc_init) & 0xFF) as u8;
+            j += 1;
+        }
+
+        let mut tail = [[0; 0x40]; 18];
+        let mut i = 0;
+        while i < 18 {
+            let mut j = 0;
+            while j < 0x40 {
+                tail[i][j] = (crc16_tail[i][j] & 0xFF) as u8;
+                j += 1;
+            }
+            i += 1;
+        }
+
+        Self { head, tail }
+    }
+}
+
+/// ゲーム状態バイト列の前半から CRC 部を除いた 4bit についての CRC 下位バイトテーブルを返す。
+pub(crate) const fn crc8_table_head() -> [u8; 0x10] {
+    CrcTables::DQ1_FC.head
+}
+
+/// ゲーム状態バイト列の後半 108bit についての 6bit 単位の CRC 下位バイトテーブルを返す。
+pub(crate) const fn crc8_table_tail() -> [[u8; 0x40]; 18] {
+    CrcTables::DQ1_FC.tail
+}
+
+/// ゲーム状態の各フィールドが占めるゲーム状態バイト列の bit たち。要素は (位置, マスク)。
+const FIELD_BITS: [&[(usize, u8)]; 15] = [
+    // 主人公の名前
+    &[(5, 0xFC), (13, 0x7E), (2, 0x3F), (7, 0x3F)],
+    // 経験値
+    &[(1, 0xFF), (12, 0xFF)],
+    // 所持金
+    &[(4, 0xFF), (9, 0xFF)],
+    // 武器
+    &[(8, 0xE0)],
+    // 鎧
+    &[(8, 0x1C)],
+    // 盾
+    &[(8, 0x03)],
+    // やくそう所持数
+    &[(10, 0x0F)],
+    // かぎ所持数
+    &[(10, 0xF0)],
+    // インベントリ
+    &[(14, 0xFF), (3, 0xFF), (11, 0xFF), (6, 0xFF)],
+    // りゅうのうろこ装備フラグ
+    &[(13, 0x80)],
+    // せんしのゆびわ装備フラグ
+    &[(13, 0x01)],
+    // しのくびかざり取得済フラグ
+    &[(2, 0x40)],
+    // ゴーレム撃破済フラグ
+    &[(5, 0x02)],
+    // ドラゴン撃破済フラグ
+    &[(7, 0x40)],
+    // salt
+    &[(5, 0x01), (2, 0x80), (7, 0x80)],
+];
+
+/// `Fill::Defaults` で用いる、6bit 値配列の各位置の各値のコストを返す。
+///
+/// 各フィールドについて、その bit を含む位置の値がデフォルト状態と異なれば 1 を加える。
+fn six_costs() -> [[u8; 0x40]; 20] {
+    let sixs_default = bytes_to_sixs(&state_to_bytes(&GameState::default().normalize().unwrap()));
+
+    let mut costs = [[0; 0x40]; 20];
+    for field in &FIELD_BITS {
+        let mut bytes_mask = [0; 15];
+        for &(idx, mask) in *field {
+            bytes_mask[idx] = mask;
+        }
+        let sixs_mask = bytes_to_sixs(&bytes_mask);
+
+        for (idx, costs) in costs.iter_mut().enumerate() {
+            for (six, cost) in (0..).zip(costs.iter_mut()) {
+                if ((six ^ sixs_default[idx]) & sixs_mask[idx]) != 0 {
+                    *cost += 1;
+                }
+            }
+        }
+    }
+
+    costs
+}
+
+/// ゲーム状態のうち `GameState::default()` と異なるフィールドの個数を返す。
+///
+/// `state` は正規化済みでなければならない。
+fn diff_count_from_default(state: &GameState) -> usize {
+    let default = GameState::default().normalize().unwrap();
+
+    [
+        state.hero_name == default.hero_name,
+        state.hero_xp == default.hero_xp,
+        state.purse == default.purse,
+        state.hero_weapon == default.hero_weapon,
+        state.hero_armor == default.hero_armor,
+        state.hero_shield == default.hero_shield,
+        state.herb_count == default.herb_count,
+        state.key_count == default.key_count,
+        state.inventory == default.inventory,
+        state.flag_equip_dragon_scale == default.flag_equip_dragon_scale,
+        state.flag_equip_warrior_ring == default.flag_equip_warrior_ring,
+        state.flag_got_death_necklace == default.flag_got_death_necklace,
+        state.flag_beated_golem == default.flag_beated_golem,
+        state.flag_beated_dragon == default.flag_beated_dragon,
+        state.salt == default.salt,
+    ]
+    .iter()
+    .filter(|&&eq| !eq)
+    .count()
+}
+
+/// ゲーム状態バイト列における主人公の名前の各文字の位置 (バイト位置, bit 位置)。各文字は 6bit。
+const HERO_NAME_BYTES: [(usize, u32); 4] = [(5, 2), (13, 1), (2, 0), (7, 0)];
+
+/// ゲーム状態バイト列における経験値の位置 (下位バイト, 上位バイト)。
+const XP_BYTES: (usize, usize) = (1, 12);
+
+/// ゲーム状態バイト列における所持金の位置 (下位バイト, 上位バイト)。
+const PURSE_BYTES: (usize, usize) = (4, 9);
+
+/// 指定された 16bit 値の範囲制約たちを (範囲, (下位バイト位置, 上位バイト位置)) の形で返す。
+fn u16_ranges(
+    options: &GenerateOptions,
+) -> impl Iterator<Item = (&RangeInclusive<u16>, (usize, usize))> {
+    IntoIterator::into_iter([(&options.xp, XP_BYTES), (&options.purse, PURSE_BYTES)])
+        .filter_map(|(range, bytes)| range.as_ref().map(|range| (range, bytes)))
+}
+
+/// ゲーム状態バイト列におけるインベントリの位置。各バイトが道具ID 2 つ分を保持する。
+const INVENTORY_BYTES: [usize; 4] = [14, 3, 11, 6];
+
+/// 道具IDたちを集合(道具ID x を含むなら bit x が立つ)に変換する。
+///
+/// 無効な道具IDが含まれる場合、`Err(Dq1PasswordError::InvalidConstraint)` を返す。
+fn items_to_set(items: &[u8]) -> Dq1PasswordResult<u16> {
+    items.iter().try_fold(0, |set, &item| {
+        validate_tool(item).map_err(|e| Dq1PasswordError::invalid_constraint(e.to_string()))?;
+        Ok(set | (1 << item))
+    })
+}
+
+/// 範囲が共通する上位 bit の固定だけで正確に表せるかどうかを返す。
+fn range_is_exact(range: &RangeInclusive<u16>) -> bool {
+    let (mask, _) = range_common_bits(range);
+
+    !range.is_empty() && u32::from(range.end() - range.start()) + 1 == 1 << mask.count_zeros()
+}
+
+/// 範囲内の全ての値で共通する上位 bit を (mask, value) の形で返す。
+///
+/// 範囲内の値 x は必ず `x & mask == value` を満たす。
+fn range_common_bits(range: &RangeInclusive<u16>) -> (u16, u16) {
+    let diff = range.start() ^ range.end();
+    let mask = if diff == 0 {
+        u16::MAX
+    } else {
+        !(u16::MAX >> diff.leading_zeros())
+    };
+
+    (mask, range.start() & mask)
+}
+
+/// 復元時の DFS で、ゲーム状態バイト列の位置 `idx` のバイトが定まる 6bit 値配列の位置を返す。
+///
+/// 6bit 値配列の先頭 2 要素は最初から定まっている。
+fn byte_determined_at(idx: usize) -> usize {
+    (4 * (idx / 3) + idx % 3).max(2)
+}
+
+/// ゲーム状態バイト列から CRC 部とソルトの bit を除いたものを返す。
+///
+/// これが等しいバイト列たちは、ソルト以外のゲーム状態が等しい。
+fn state_key(bytes: &[u8; 15]) -> [u8; 15] {
+    let mut key = *bytes;
+    key[0] = 0;
+    key[2] &= 0x7F;
+    key[5] &= 0xFE;
+    key[7] &= 0x7F;
+
+    key
+}
+
+/// 6bit 値配列からゲーム状態バイト列の位置 `idx` のバイトを取り出す。
+fn byte_from_sixs(sixs: &[u8; 20], idx: usize) -> u8 {
+    let ss = &sixs[4 * (idx / 3)..][..4];
+
+    match idx % 3 {
+        0 => ss[0] | (ss[1] << 6),
+        1 => (ss[1] >> 2) | (ss[2] << 4),
+        _ => (ss[2] >> 4) | (ss[3] << 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::{decode, password_char_to_cum};
+    use crate::encode::encode;
+    use crate::error::PatternErrorKind;
+    use crate::input_cost::{input_cost, rank_by_input_cost};
+
+    #[test]
+    fn test_generate_with_options_exclude() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむるの??";
+
+        let all = generate(PATTERN, 20).unwrap();
+        assert_eq!(all.len(), 14);
+
+        let options = GenerateOptions {
+            exclude: vec![
+                Pattern::parse("ざぼちずどぢぎきつたうずせれえむるのうあ").unwrap(),
+                Pattern::parse("??????????????????ぢ?").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let expect: Vec<_> = all
+            .iter()
+            .filter(|p| !p.ends_with("うあ") && !p.ends_with("ぢえ"))
+            .cloned()
+            .collect();
+        assert_eq!(expect.len(), 12);
+
+        assert_eq!(
+            generate_with_options(PATTERN, 20, &options),
+            Ok(expect.clone())
+        );
+
+        // 除外された分を補って n_max 個生成される。
+        assert_eq!(
+            generate_with_options(PATTERN, 3, &options),
+            Ok(expect[..3].to_vec())
+        );
+    }
+
+    #[test]
+    fn test_generate_with_options_hero_name() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむるの??";
+
+        let all = generate(PATTERN, 20).unwrap();
+        let hero_name = decode(&all[0]).unwrap().hero_name;
+
+        // 全件生成してから絞り込んだ結果と一致する。
+        let expect: Vec<_> = all
+            .iter()
+            .filter(|p| decode(p).unwrap().hero_name == hero_name)
+            .cloned()
+            .collect();
+        assert!(!expect.is_empty() && expect.len() < all.len());
+
+        let options = GenerateOptions {
+            hero_name: Some(hero_name.clone()),
+            ..Default::default()
+        };
+        assert_eq!(generate_with_options(PATTERN, 20, &options), Ok(expect));
+
+        // パターンの大部分が '?' でも全ての結果が指定した名前になる。
+        let options = GenerateOptions {
+            hero_name: Some("ゆうてい".to_owned()),
+            ..Default::default()
+        };
+        let passwords = generate_with_options("ざぼ??????????????????", 5, &options).unwrap();
+        assert_eq!(passwords.len(), 5);
+        for password in passwords {
+            assert_eq!(decode(password).unwrap().hero_name, "ゆうてい");
+        }
+
+        let options = GenerateOptions {
+            hero_name: Some("ゆうていみやおう".to_owned()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_with_options(PATTERN, 20, &options),
+            Err(Dq1PasswordError::InvalidGameState(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_with_options_xp() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむ?る?の";
+
+        fn options(xp: RangeInclusive<u16>) -> GenerateOptions {
+            GenerateOptions {
+                xp: Some(xp),
+                ..Default::default()
+            }
+        }
+        fn xp_of(password: &str) -> u16 {
+            decode(password).unwrap().hero_xp
+        }
+
+        let all = generate(PATTERN, 40).unwrap();
+        let xps: Vec<_> = all.iter().map(|p| xp_of(p)).collect();
+        assert!(xps.iter().unique().count() > 1);
+
+        // 全件生成してから絞り込んだ結果と一致する。
+        let xp_mid = xps[xps.len() / 2];
+        for xp in [
+            xp_mid..=xp_mid,
+            xp_mid.saturating_sub(3000)..=xp_mid.saturating_add(3000),
+            0..=xp_mid,
+            xp_mid..=u16::MAX,
+            0..=u16::MAX,
+        ] {
+            let expect: Vec<_> = all
+                .iter()
+                .filter(|p| xp.contains(&xp_of(p)))
+                .cloned()
+                .collect();
+            assert!(!expect.is_empty());
+            assert_eq!(generate_with_options(PATTERN, 40, &options(xp)), Ok(expect));
+        }
+
+        // 空の範囲に対しては何も生成しない。
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 1..=0;
+        assert_eq!(
+            generate_with_options(PATTERN, 40, &options(empty)),
+            Ok(vec![])
+        );
+
+        // 経験値の上位バイトを決める部分が '?' でも全ての結果が範囲内になる。
+        for xp in [1234..=1234, 20000..=30000] {
+            let passwords = generate_with_options(
+                "ざぼちずどぢぎきつたうずせれえ?????",
+                5,
+                &options(xp.clone()),
+            )
+            .unwrap();
+            assert_eq!(passwords.len(), 5);
+            assert!(passwords.iter().all(|p| xp.contains(&xp_of(p))));
+        }
+    }
+
+    #[test]
+    fn test_generate_with_options_purse() {
+        const PATTERN: &str = "ざぼちずど?ぎきつたうず??えむるのぢえ";
+
+        fn options(purse: RangeInclusive<u16>) -> GenerateOptions {
+            GenerateOptions {
+                purse: Some(purse),
+                ..Default::default()
+            }
+        }
+        fn purse_of(password: &str) -> u16 {
+            decode(password).unwrap().purse
+        }
+
+        let all = generate(PATTERN, 200).unwrap();
+        assert!(all.len() < 200);
+        let purses: Vec<_> = all.iter().map(|p| purse_of(p)).sorted().collect();
+        assert!(purses.iter().unique().count() > 1);
+
+        // 全件生成してから絞り込んだ結果と一致する。
+        let purse_min = purses[0];
+        let purse_mid = purses[purses.len() / 2];
+        let purse_max = purses[purses.len() - 1];
+        for purse in [
+            purse_mid..=purse_mid,
+            purse_min..=purse_mid,
+            purse_mid..=purse_max,
+            0..=purse_min,
+            purse_max..=u16::MAX,
+        ] {
+            let expect: Vec<_> = all
+                .iter()
+                .filter(|p| purse.contains(&purse_of(p)))
+                .cloned()
+                .collect();
+            assert!(!expect.is_empty());
+            assert_eq!(
+                generate_with_options(PATTERN, 200, &options(purse)),
+                Ok(expect)
+            );
+        }
+
+        // 境界値。
+        for purse in [0..=0, u16::MAX..=u16::MAX] {
+            let passwords = generate_with_options(
+                "ざぼちずど???つたうず???むるのぢ?",
+                3,
+                &options(purse.clone()),
+            )
+            .unwrap();
+            assert!(!passwords.is_empty());
+            assert!(passwords.iter().all(|p| purse.contains(&purse_of(p))));
+        }
+
+        // 固定パターンの所持金 (5678) と共通部分がなければ何も生成しない。
+        assert_eq!(
+            generate_with_options(
+                "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+                10,
+                &options(5679..=u16::MAX)
+            ),
+            Ok(vec![])
+        );
+        assert_eq!(
+            generate_with_options(
+                "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+                10,
+                &options(5000..=6000)
+            ),
+            Ok(vec!["ざぼちずどぢぎきつたうずせれえむるのぢえ".to_owned()])
+        );
+
+        // 経験値 (1234) の範囲と組み合わせられる。
+        for (xp, n) in [(1234..=1234, 1), (1235..=u16::MAX, 0)] {
+            let options = GenerateOptions {
+                xp: Some(xp),
+                purse: Some(5678..=5678),
+                ..Default::default()
+            };
+            assert_eq!(
+                generate_with_options("ざぼちずどぢぎきつたうずせれえむるのぢえ", 10, &options)
+                    .map(|passwords| passwords.len()),
+                Ok(n)
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_with_options_counts() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうず???むるのぢえ";
+
+        let all = generate(PATTERN, 10000).unwrap();
+        assert!(all.len() < 10000);
+
+        let options = GenerateOptions {
+            herb_count: Some(1..=3),
+            key_count: Some(5..=6),
+            ..Default::default()
+        };
+        let expect: Vec<_> = all
+            .into_iter()
+            .filter(|p| {
+                let state = decode(p).unwrap();
+                (1..=3).contains(&state.herb_count) && (5..=6).contains(&state.key_count)
+            })
+            .collect();
+        assert!(!expect.is_empty());
+        assert_eq!(generate_with_options(PATTERN, 10000, &options), Ok(expect));
+    }
+
+    #[test]
+    fn test_generate_with_options_equipment() {
+        // 位置 10, 11 (装備のバイト)が変化するパターン。
+        const PATTERN: &str = "ざぼちずどぢぎきつた??せれえむるの??";
+
+        let all = generate(PATTERN, usize::MAX).unwrap();
+        assert!(all.len() > 1000);
+
+        let cases = [
+            // はがねのつるぎ以上、かわのふくより良い鎧。
+            (Some(6..=u8::MAX), Some(3..=7), None),
+            // 完全一致。
+            (Some(5..=5), Some(5..=5), Some(2..=2)),
+            (None, None, Some(0..=1)),
+            (Some(0..=2), Some(7..=7), Some(3..=3)),
+        ];
+        for (hero_weapon, hero_armor, hero_shield) in cases {
+            let options = GenerateOptions {
+                hero_weapon: hero_weapon.clone(),
+                hero_armor: hero_armor.clone(),
+                hero_shield: hero_shield.clone(),
+                ..Default::default()
+            };
+            let in_range = |range: &Option<RangeInclusive<u8>>, x| {
+                range.as_ref().is_none_or(|range| range.contains(&x))
+            };
+            let expect: Vec<_> = all
+                .iter()
+                .filter(|p| {
+                    let state = decode(p).unwrap();
+                    in_range(&hero_weapon, state.hero_weapon)
+                        && in_range(&hero_armor, state.hero_armor)
+                        && in_range(&hero_shield, state.hero_shield)
+                })
+                .cloned()
+                .collect();
+            assert!(!expect.is_empty(), "{:?}", options);
+            assert_eq!(
+                generate_with_options(PATTERN, usize::MAX, &options),
+                Ok(expect),
+                "{:?}",
+                options
+            );
+        }
+
+        // 他の制約と組み合わせられる。
+        let options = GenerateOptions {
+            hero_weapon: Some(6..=7),
+            hero_shield: Some(1..=3),
+            xp: Some(0..=30000),
+            forbid_items: vec![1],
+            ..Default::default()
+        };
+        let passwords = generate_with_options(PATTERN, usize::MAX, &options).unwrap();
+        assert!(!passwords.is_empty());
+        for password in &passwords {
+            let state = decode(password).unwrap();
+            assert!(state.hero_weapon >= 6 && state.hero_shield >= 1);
+            assert!(state.hero_xp <= 30000 && !state.inventory.contains(&1));
+        }
+
+        // 矛盾する範囲は生成前にエラーとなる。
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 6..=3;
+        for options in [
+            GenerateOptions {
+                hero_weapon: Some(reversed),
+                ..Default::default()
+            },
+            GenerateOptions {
+                hero_armor: Some(8..=u8::MAX),
+                ..Default::default()
+            },
+            GenerateOptions {
+                hero_shield: Some(4..=4),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                generate_with_options("????????????????????", 1, &options),
+                Err(Dq1PasswordError::InvalidConstraint(_))
+            ));
+            assert!(matches!(
+                generate_with_outcome(PATTERN, 0, &options),
+                Err(Dq1PasswordError::InvalidConstraint(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_generate_with_options_items() {
+        fn options(require_items: &[u8], forbid_items: &[u8]) -> GenerateOptions {
+            GenerateOptions {
+                require_items: require_items.to_vec(),
+                forbid_items: forbid_items.to_vec(),
+                ..Default::default()
+            }
+        }
+
+        // インベントリの 4bit 境界をまたぐ道具IDが変化するパターン。
+        for pattern in [
+            "ざぼちずどぢぎきつたうずせれ?むる??え",
+            "ざぼちず?ぢぎきつたうずせれ??るのぢえ",
+        ] {
+            let all = generate(pattern, 1000).unwrap();
+            assert!(all.len() < 1000);
+
+            // 全件生成してから絞り込んだ結果と(順序を除いて)一致する。
+            for (require_items, forbid_items) in [
+                (&[][..], &[14][..]),
+                (&[], &[0, 10, 13]),
+                (&[10], &[]),
+                (&[0, 14], &[]),
+                (&[10], &[5, 14]),
+            ] {
+                let expect: Vec<_> = all
+                    .iter()
+                    .filter(|p| {
+                        let inventory = decode(p).unwrap().inventory;
+                        require_items.iter().all(|item| inventory.contains(item))
+                            && !forbid_items.iter().any(|item| inventory.contains(item))
+                    })
+                    .cloned()
+                    .sorted()
+                    .collect();
+                assert!(!expect.is_empty());
+                let actual: Vec<_> =
+                    generate_with_options(pattern, 1000, &options(require_items, forbid_items))
+                        .unwrap()
+                        .into_iter()
+                        .sorted()
+                        .collect();
+                assert_eq!(actual, expect);
+            }
+        }
+
+        // 9 種類以上の道具はインベントリに収まらない。
+        assert_eq!(
+            generate_with_options(
+                "ざぼちずどぢぎきつたうずせれ?むる??え",
+                10,
+                &options(&[1, 2, 3, 4, 5, 6, 7, 8, 9], &[])
+            ),
+            Ok(vec![])
+        );
+
+        // 矛盾する指定や無効な道具IDはエラー。
+        assert!(matches!(
+            generate_with_options(
+                "ざぼちずどぢぎきつたうずせれ?むる??え",
+                10,
+                &options(&[1, 2], &[2])
+            ),
+            Err(Dq1PasswordError::InvalidConstraint(_))
+        ));
+        assert!(matches!(
+            generate_with_options(
+                "ざぼちずどぢぎきつたうずせれ?むる??え",
+                10,
+                &options(&[], &[15])
+            ),
+            Err(Dq1PasswordError::InvalidConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_filled() {
+        fn options(fill: Fill) -> GenerateOptions {
+            GenerateOptions {
+                fill,
+                ..Default::default()
+            }
+        }
+        fn diff_counts(filleds: &[FilledPassword]) -> Vec<usize> {
+            filleds.iter().map(|filled| filled.diff_count).collect()
+        }
+
+        {
+            const PATTERN: &str = "ゆうてい????????????????";
+
+            let anys = generate_filled(PATTERN, 5, &options(Fill::Any)).unwrap();
+            let defaults = generate_filled(PATTERN, 5, &options(Fill::Defaults)).unwrap();
+            assert_eq!(anys.len(), 5);
+            assert_eq!(defaults.len(), 5);
+
+            for filled in anys.iter().chain(&defaults) {
+                let state = decode(&filled.password).unwrap();
+                assert_eq!(filled.diff_count, diff_count_from_default(&state));
+            }
+
+            // デフォルト状態との差異が明らかに少ない。
+            let diff_counts_default = diff_counts(&defaults);
+            assert!(diff_counts_default.windows(2).all(|w| w[0] <= w[1]));
+            assert!(diff_counts_default.iter().max() < diff_counts(&anys).iter().min());
+
+            assert_eq!(
+                generate_with_options(PATTERN, 5, &options(Fill::Defaults)),
+                Ok(defaults.into_iter().map(|filled| filled.password).collect())
+            );
+        }
+
+        // 全件生成する場合、Fill::Any と同じものが差異の少ない順に並ぶ。
+        {
+            const PATTERN: &str = "ざぼちずどぢぎきつたうずせれ?むる??え";
+
+            let anys = generate_filled(PATTERN, 1000, &options(Fill::Any)).unwrap();
+            let defaults = generate_filled(PATTERN, 1000, &options(Fill::Defaults)).unwrap();
+            assert!(anys.len() < 1000);
+            assert!(diff_counts(&defaults).windows(2).all(|w| w[0] <= w[1]));
+
+            let sorted = |filleds: Vec<FilledPassword>| -> Vec<_> {
+                filleds
+                    .into_iter()
+                    .sorted_by(|a, b| a.password.cmp(&b.password))
+                    .collect()
+            };
+            assert_eq!(sorted(defaults), sorted(anys));
+        }
+
+        assert_eq!(
+            generate_filled("ゆうてい????????????????", 0, &options(Fill::Defaults)),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_generate_with_options_strict_names() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let all = generate(PATTERN, 10000).unwrap();
+        let n_all = all.len();
+        assert!(n_all < 10000);
+
+        let options = GenerateOptions {
+            strict_names: true,
+            ..Default::default()
+        };
+        let actual = generate_with_options(PATTERN, 10000, &options).unwrap();
+        for password in &actual {
+            let hero_name = decode(password).unwrap().hero_name;
+            assert!(normalize_hero_name(&hero_name).is_ok());
+            assert!(validate_hero_name_strict(&hero_name).is_ok());
+        }
+
+        let expect: Vec<_> = all
+            .into_iter()
+            .filter(|p| validate_hero_name_strict(decode(p).unwrap().hero_name).is_ok())
+            .collect();
+        assert!(!expect.is_empty() && expect.len() < n_all);
+        assert_eq!(actual, expect);
+    }
+
+    #[test]
+    fn test_generate_with_options_sort_by() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let options = GenerateOptions {
+            sort_by: SortBy::InputCost,
+            ..Default::default()
+        };
+        let actual = generate_with_options(PATTERN, 20, &options).unwrap();
+
+        // 生成された先頭 20 個ではなく、合致するもの全てのうち入力コストの小さい 20 個。
+        let all = generate(PATTERN, 100000).unwrap();
+        assert!(all.len() > 20 && all.len() < 100000);
+        let mut expect = all.clone();
+        let cost = |p: &String| {
+            (
+                input_cost(p).unwrap(),
+                p.chars()
+                    .map(|c| password_char_to_cum(c).unwrap())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        expect.sort_by_key(cost);
+        expect.truncate(20);
+        assert_eq!(actual, expect);
+        assert!(actual.windows(2).any(|w| cost(&w[0]).0 < cost(&w[1]).0));
+        assert_ne!(actual, {
+            let mut head = all[..20].to_vec();
+            head.sort_by_key(cost);
+            head
+        });
+
+        let ranked = rank_by_input_cost(all).unwrap();
+        for (r, p) in core::iter::zip(&ranked, &actual) {
+            assert_eq!(&r.password, p);
+            assert_eq!(r.input_cost, input_cost(p).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_generate_with_pattern() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえ?る??え";
+
+        let mut slots = [None; 20];
+        for (e, c) in core::iter::zip(&mut slots, PATTERN.chars()) {
+            *e = (c != '?').then_some(c);
+        }
+        let pattern = Pattern::from_slots(&slots).unwrap();
+
+        let options = GenerateOptions {
+            xp: Some(0..=30000),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_with_pattern(&pattern, 100, &options),
+            generate_with_options(PATTERN, 100, &options)
+        );
+        assert_eq!(
+            generate_with_pattern(&pattern, 100, &GenerateOptions::default()),
+            generate(PATTERN, 100)
+        );
+    }
+
+    #[test]
+    fn test_generate_char_class() {
+        // '?' で生成したものをクラス内の文字で絞り込んだものと一致する。
+        let all = generate("ざぼちずどぢぎきつたうずせれえむ?[のぬ]?[えおあ]", 10000).unwrap();
+        let expect: Vec<_> = generate("ざぼちずどぢぎきつたうずせれえむ????", 10000)
+            .unwrap()
+            .into_iter()
+            .filter(|password| {
+                let cs: Vec<_> = password.chars().collect();
+                "のぬ".contains(cs[17]) && "えおあ".contains(cs[19])
+            })
+            .collect();
+        assert!(!all.is_empty());
+        assert!(all.len() < 10000);
+        assert_eq!(all, expect);
+
+        // 否定文字クラスの位置には、除外した文字が現れない。
+        const PATTERN_NEG: &str = "ざぼちずどぢぎきつたうずせれえむ?[^のぬ]?[^えおあ]";
+        let all = generate(PATTERN_NEG, 100000).unwrap();
+        assert!(all.iter().all(|password| {
+            let cs: Vec<_> = password.chars().collect();
+            !"のぬ".contains(cs[17]) && !"えおあ".contains(cs[19])
+        }));
+        let total: u128 = crate::analyze::char_statistics(PATTERN_NEG).unwrap()[0]
+            .iter()
+            .sum();
+        assert!(!all.is_empty());
+        assert_eq!(u128::try_from(all.len()).unwrap(), total);
+    }
+
+    #[test]
+    fn test_generate_invalid_pattern() {
+        // 無効な文字は InvalidPassword ではなく InvalidPattern となる。
+        match generate("ざぼちずどぢぎきつたうずせれえむるの?漢", 1) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => {
+                assert_eq!(e.kind(), &PatternErrorKind::InvalidChars(vec![(19, '漢')]));
+                assert!(e.message().contains("位置 19 の '漢'"));
+            }
+            res => panic!("{:?}", res),
+        }
+        match generate("ざぼちず", 1) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => {
+                assert_eq!(e.kind(), &PatternErrorKind::WrongLength { len: 4 });
+            }
+            res => panic!("{:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_generate_katakana() {
+        assert_eq!(
+            generate("ザボチズドヂギキツタウズセレエムﾙﾉ??", 100),
+            generate("ざぼちずどぢぎきつたうずせれえむるの??", 100)
+        );
+    }
+
+    #[test]
+    fn test_generate_tied() {
+        // 位置 16 と 18 が同じ文字。
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむ[あ-こ]1?[あ-こ]1?";
+
+        let mut all = generate(PATTERN, 100000).unwrap();
+        assert!(!all.is_empty());
+        assert!(all.iter().all(|password| {
+            let cs: Vec<_> = password.chars().collect();
+            cs[16] == cs[18]
+        }));
+        assert_eq!(
+            first_match(PATTERN).unwrap().as_ref(),
+            all.iter().min_by_key(|password| {
+                password
+                    .chars()
+                    .map(|c| password_char_to_cum(c).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        );
+
+        let mut expect: Vec<_> =
+            generate("ざぼちずどぢぎきつたうずせれえむ[あ-こ]?[あ-こ]?", 100000)
+                .unwrap()
+                .into_iter()
+                .filter(|password| {
+                    let cs: Vec<_> = password.chars().collect();
+                    cs[16] == cs[18]
+                })
+                .collect();
+        all.sort();
+        expect.sort();
+        assert_eq!(all, expect);
+
+        // 固定された文字とのタイ。
+        let pattern = Pattern::parse("ざぼちずどぢぎきつたうずせれえむる???")
+            .unwrap()
+            .tie(&[15, 19])
+            .unwrap();
+        let all = generate_with_pattern(&pattern, 100, &GenerateOptions::default()).unwrap();
+        assert!(!all.is_empty());
+        assert!(all.iter().all(|password| password.ends_with('む')));
+        assert_eq!(
+            all,
+            generate("ざぼちずどぢぎきつたうずせれえむる??む", 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_with_outcome() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let all = generate(PATTERN, 10000).unwrap();
+        let n_all = all.len();
+        assert!(n_all < 10000);
+
+        let options_list = [
+            GenerateOptions::default(),
+            GenerateOptions {
+                sort_by: SortBy::InputCost,
+                ..Default::default()
+            },
+            GenerateOptions {
+                fill: Fill::Defaults,
+                ..Default::default()
+            },
+        ];
+        for options in &options_list {
+            for (n_max, exhausted) in [
+                (0, false),
+                (10, false),
+                (n_all - 1, false),
+                (n_all, true),
+                (n_all + 1, true),
+            ] {
+                let outcome = generate_with_outcome(PATTERN, n_max, options).unwrap();
+                assert_eq!(
+                    outcome.passwords,
+                    generate_with_options(PATTERN, n_max, options).unwrap()
+                );
+                assert_eq!(outcome.passwords.len(), n_max.min(n_all));
+                assert_eq!(outcome.exhausted, exhausted, "n_max={}", n_max);
+            }
+        }
+
+        // 合致するものが存在しない。
+        let outcome = generate_with_outcome(
+            "ざぼちずどぢぎきつたうずせれえむるのぢお",
+            10,
+            &GenerateOptions::default(),
+        )
+        .unwrap();
+        assert!(outcome.passwords.is_empty() && outcome.exhausted);
+
+        // 打ち切られた場合。
+        let job = GenerateJob::new(
+            &Pattern::parse(PATTERN).unwrap(),
+            n_all,
+            &GenerateOptions::default(),
+        )
+        .unwrap();
+        let mut n_emitted = 0;
+        assert!(
+            !job.run(|_| {
+                n_emitted += 1;
+                n_emitted < 5
+            })
+            .exhausted
+        );
+        assert_eq!(n_emitted, 5);
+        assert!(job.run(|_| true).exhausted);
+    }
+
+    #[test]
+    fn test_generate_with_options_plausible_only() {
+        // 最初の 7 個は不自然なゲーム状態となる。
+        const PATTERN: &str = "ざぼちずどぢぎきつたうず???むるのぢえ";
+
+        let all = generate(PATTERN, 10000).unwrap();
+        assert!(all.len() < 10000);
+        let plausible: Vec<_> = all
+            .iter()
+            .filter(|p| is_plausible(&decode(p).unwrap()))
+            .cloned()
+            .collect();
+        assert!(!is_plausible(&decode(&all[0]).unwrap()));
+        assert!(plausible.len() < all.len());
+
+        let options = GenerateOptions {
+            plausible_only: true,
+            ..Default::default()
+        };
+        for n_max in [5, plausible.len()] {
+            let outcome = generate_with_outcome(PATTERN, n_max, &options).unwrap();
+            assert_eq!(outcome.passwords, plausible[..n_max]);
+            assert_eq!(outcome.exhausted, n_max == plausible.len());
+
+            // 他の条件は満たすので、生成上限数の判定用の 1 個を見つけるまでに読み飛ばしたものを数える。
+            let n_seen = all
+                .iter()
+                .position(|p| plausible.get(n_max) == Some(p))
+                .unwrap_or(all.len());
+            assert_eq!(outcome.implausible_skipped, n_seen - n_max);
+        }
+        assert_eq!(
+            generate_with_options(PATTERN, 5, &options).unwrap(),
+            plausible[..5]
+        );
+    }
+
+    #[test]
+    fn test_generate_with_options_count_constraints() {
+        // 先頭 17 文字のうち濁音は 7 文字、あ〜と の文字も 7 文字。
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        fn char_set(chars: &str) -> CharSet {
+            let mut set = CharSet::new();
+            for c in chars.chars() {
+                assert!(set.insert(c));
+            }
+            set
+        }
+        let voiced = char_set("がぎぐげござじずぜぞだぢづでどばびぶべぼ");
+        let a_to_to = char_set("あいうえおかきくけこさしすせそたちつてと");
+        let count =
+            |password: &str, set: CharSet| password.chars().filter(|&c| set.contains(c)).count();
+
+        let count_constraints = vec![
+            CountConstraint {
+                chars: voiced,
+                min: 0,
+                max: 8,
+            },
+            CountConstraint {
+                chars: a_to_to,
+                min: 9,
+                max: 20,
+            },
+        ];
+        for fill in [Fill::Any, Fill::Defaults] {
+            let all = generate_with_options(
+                PATTERN,
+                10000,
+                &GenerateOptions {
+                    fill,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(all.len() < 10000);
+
+            // 全件生成してから絞り込んだ結果と一致し、n_max 個に満たない分は補われる。
+            let expect: Vec<_> = all
+                .iter()
+                .filter(|p| count(p, voiced) <= 8 && count(p, a_to_to) >= 9)
+                .cloned()
+                .collect();
+            assert!(!expect.is_empty() && expect.len() < all.len());
+
+            let options = GenerateOptions {
+                fill,
+                count_constraints: count_constraints.clone(),
+                ..Default::default()
+            };
+            for n_max in [5, expect.len()] {
+                let outcome = generate_with_outcome(PATTERN, n_max, &options).unwrap();
+                assert_eq!(outcome.passwords, expect[..n_max]);
+                assert_eq!(outcome.exhausted, n_max == expect.len());
+            }
+        }
+
+        // 各制約は個別にも効く。
+        let options = GenerateOptions {
+            count_constraints: vec![CountConstraint {
+                chars: voiced,
+                min: 9,
+                max: 9,
+            }],
+            ..Default::default()
+        };
+        let passwords = generate_with_options(PATTERN, 10000, &options).unwrap();
+        assert!(!passwords.is_empty());
+        assert!(passwords.iter().all(|p| count(p, voiced) == 9));
+
+        // 満たしえない制約。
+        for cc in [
+            CountConstraint {
+                chars: voiced,
+                min: 3,
+                max: 2,
+            },
+            CountConstraint {
+                chars: voiced,
+                min: 21,
+                max: 25,
+            },
+            CountConstraint {
+                chars: voiced,
+                min: 11,
+                max: 20,
+            },
+            CountConstraint {
+                chars: voiced,
+                min: 0,
+                max: 6,
+            },
+        ] {
+            for fill in [Fill::Any, Fill::Defaults] {
+                let options = GenerateOptions {
+                    fill,
+                    count_constraints: vec![cc],
+                    ..Default::default()
+                };
+                let outcome = generate_with_outcome(PATTERN, 10, &options).unwrap();
+                assert!(outcome.passwords.is_empty(), "{:?}", cc);
+                assert!(outcome.exhausted, "{:?}", cc);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_with_options_max_memory_bytes() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let estimate = estimate_memory_bytes(PATTERN, 10, &GenerateOptions::default()).unwrap();
+        let options = GenerateOptions {
+            max_memory_bytes: Some(estimate),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_with_options(PATTERN, 10, &options),
+            generate(PATTERN, 10)
+        );
+
+        let options = GenerateOptions {
+            max_memory_bytes: Some(estimate - 1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_with_options(PATTERN, 10, &options),
+            Err(Dq1PasswordError::ResourceLimit(_))
+        ));
+
+        // 巨大な n_max でも割り当てを試みずにエラーとなる。
+        let options = GenerateOptions {
+            max_memory_bytes: Some(1 << 30),
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_with_options(PATTERN, usize::MAX, &options),
+            Err(Dq1PasswordError::ResourceLimit(_))
+        ));
+        assert_eq!(
+            estimate_memory_bytes(PATTERN, usize::MAX, &options),
+            Ok(usize::MAX)
+        );
+
+        // 入力コストで並べ替える場合は、n_max によらず合致するもの全てを保持する分を見積もる。
+        let options = GenerateOptions {
+            sort_by: SortBy::InputCost,
+            ..Default::default()
+        };
+        let estimate_sorted = estimate_memory_bytes(PATTERN, 10, &options).unwrap();
+        assert!(estimate_sorted > estimate);
+        let options = GenerateOptions {
+            max_memory_bytes: Some(estimate),
+            ..options
+        };
+        assert!(matches!(
+            generate_with_options(PATTERN, 10, &options),
+            Err(Dq1PasswordError::ResourceLimit(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_large_n_max() {
+        // 結果の個数に比例するメモリのみを使う(上限数の分を予め確保しない)。
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+        const N_MAX: usize = 1_000_000_000;
+
+        let all = generate(PATTERN, N_MAX).unwrap();
+        assert!(!all.is_empty());
+        assert_eq!(all, generate(PATTERN, all.len()).unwrap());
+
+        let outcome = generate_with_outcome(PATTERN, N_MAX, &GenerateOptions::default()).unwrap();
+        assert_eq!(outcome.passwords, all);
+        assert!(outcome.exhausted);
+
+        let options = GenerateOptions {
+            fill: Fill::Defaults,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_with_options(PATTERN, N_MAX, &options)
+                .unwrap()
+                .len(),
+            all.len()
+        );
+    }
+
+    #[test]
+    fn test_generate_n_max_edge() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let options_list = [
+            GenerateOptions::default(),
+            GenerateOptions {
+                fill: Fill::Defaults,
+                ..Default::default()
+            },
+            GenerateOptions {
+                sort_by: SortBy::InputCost,
+                dedup_by_state: true,
+                ..Default::default()
+            },
+        ];
+
+        for options in &options_list {
+            // 0 個の場合は生成を行わない(先頭 2 文字が '?' でも時間がかからない)。
+            for pattern in [PATTERN, "????????????????????"] {
+                assert!(generate_with_options(pattern, 0, options)
+                    .unwrap()
+                    .is_empty());
+                let outcome = generate_with_outcome(pattern, 0, options).unwrap();
+                assert!(outcome.passwords.is_empty());
+                assert!(!outcome.exhausted);
+            }
+            assert!(generate_filled(PATTERN, 0, options).unwrap().is_empty());
+
+            // usize::MAX は上限なし。
+            let all = generate_with_options(PATTERN, usize::MAX, options).unwrap();
+            assert!(!all.is_empty());
+            assert_eq!(
+                all,
+                generate_with_options(PATTERN, all.len(), options).unwrap()
+            );
+            let outcome = generate_with_outcome(PATTERN, usize::MAX, options).unwrap();
+            assert_eq!(outcome.passwords, all);
+            assert!(outcome.exhausted);
+        }
+
+        assert!(generate("ざぼちず?", 0).is_err());
+        assert!(generate_with_codec(PATTERN, 0, &Codec::dq1_fc())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            generate_with_codec(PATTERN, usize::MAX, &Codec::dq1_fc()).unwrap(),
+            generate(PATTERN, usize::MAX).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dedup_by_state() {
+        // ソルトのみ異なり、位置 3..=5 が共通する 2 つの復活の呪文。
+        const PATTERN: &str = "???ぬひま??????????????";
+        let state = GameState {
+            hero_xp: 58,
+            salt: 1,
+            ..Default::default()
+        }
+        .normalize()
+        .unwrap();
+        let password_a = encode(&state).unwrap();
+        let password_b = encode(&GameState {
+            salt: 2,
+            ..state.clone()
+        })
+        .unwrap();
+        let pattern = Pattern::parse(PATTERN).unwrap();
+        assert_eq!(pattern.matches(&password_a), Ok(true));
+        assert_eq!(pattern.matches(&password_b), Ok(true));
+
+        let groups = group_by_state(vec![
+            password_a.clone(),
+            encode(&GameState::default()).unwrap(),
+            password_b.clone(),
+        ])
+        .unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].password, password_a);
+        assert_eq!(groups[0].state, state);
+        assert_eq!(groups[0].siblings, vec![password_b.clone()]);
+        assert!(groups[1].siblings.is_empty());
+
+        assert!(group_by_state(vec!["ゆうていみやおうきむこうほりいゆうた".to_owned()]).is_err());
+
+        // 既出のゲーム状態は生成されない。
+        let options = GenerateOptions {
+            hero_name: Some(state.hero_name.clone()),
+            xp: Some(58..=58),
+            purse: Some(0..=0),
+            forbid_items: (1..=14).collect(),
+            dedup_by_state: true,
+            ..Default::default()
+        };
+        let constraints = Constraints::new(&options).unwrap();
+        let bytes_a = state_to_bytes(&state);
+        let bytes_b = state_to_bytes(&decode(&password_b).unwrap());
+        let sixs_b = bytes_to_sixs(&bytes_b);
+        let cums_tail: [_; 18] = pattern.char_sets()[2..].try_into().unwrap();
+
+        let mut dp = DpTraceTable::new_sparse(constraints.item_state_count());
+
+        let mut seen = BTreeSet::new();
+        let bytess = generate_dp(
+            &mut dp,
+            [sixs_b[0], sixs_b[1]],
+            &cums_tail,
+            10000,
+            &constraints,
+            &CrcTables::DQ1_FC,
+            Some(&mut seen),
+        );
+        assert!(bytess.contains(&bytes_b));
+        assert_eq!(seen.len(), bytess.len());
+
+        let mut seen = BTreeSet::from([state_key(&bytes_a)]);
+        let bytess_dedup = generate_dp(
+            &mut dp,
+            [sixs_b[0], sixs_b[1]],
+            &cums_tail,
+            10000,
+            &constraints,
+            &CrcTables::DQ1_FC,
+            Some(&mut seen),
+        );
+        assert!(!bytess_dedup.contains(&bytes_b));
+        assert_eq!(bytess_dedup.len(), bytess.len() - 1);
+
+        // 生成結果のゲーム状態は互いに異なる。
+        let passwords = generate_with_options(PATTERN, 300, &options).unwrap();
+        assert_eq!(passwords.len(), 300);
+        let groups = group_by_state(passwords).unwrap();
+        assert_eq!(groups.len(), 300);
+    }
+
+    #[test]
+    fn test_add_constraint() {
+        let mut options = GenerateOptions::default();
+        options.add_constraint("hero_name=しどー").unwrap();
+        options.add_constraint("xp=1234").unwrap();
+        options.add_constraint("purse = 100..=200").unwrap();
+        assert_eq!(options.hero_name.as_deref(), Some("しどー"));
+        assert_eq!(options.xp, Some(1234..=1234));
+        assert_eq!(options.purse, Some(100..=200));
+
+        options.add_constraint("xp=100..").unwrap();
+        assert_eq!(options.xp, Some(100..=u16::MAX));
+        options.add_constraint("purse=..=99").unwrap();
+        assert_eq!(options.purse, Some(0..=99));
+
+        options.add_constraint("require_items=10, 14").unwrap();
+        options.add_constraint("forbid_items=3").unwrap();
+        assert_eq!(options.require_items, [10, 14]);
+        assert_eq!(options.forbid_items, [3]);
+        options.add_constraint("forbid_items=").unwrap();
+        assert!(options.forbid_items.is_empty());
+
+        options.add_constraint("herb_count=6").unwrap();
+        options.add_constraint("key_count=1..").unwrap();
+        assert_eq!(options.herb_count, Some(6..=6));
+        assert_eq!(options.key_count, Some(1..=u8::MAX));
+
+        options.add_constraint("hero_weapon=6..").unwrap();
+        options.add_constraint("hero_armor=..=2").unwrap();
+        options.add_constraint("hero_shield=3").unwrap();
+        assert_eq!(options.hero_weapon, Some(6..=u8::MAX));
+        assert_eq!(options.hero_armor, Some(0..=2));
+        assert_eq!(options.hero_shield, Some(3..=3));
+
+        for constraint in [
+            "xp",
+            "xp=",
+            "xp=65536",
+            "xp=1..2",
+            "purse=abc",
+            "herb_count=256",
+            "hero_weapon=はがねのつるぎ",
+            "require_items=1,x",
+            "level=1",
+        ] {
+            assert!(matches!(
+                options.add_constraint(constraint),
+                Err(Dq1PasswordError::InvalidConstraint(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_range_common_bits() {
+        assert_eq!(range_common_bits(&(0..=u16::MAX)), (0, 0));
+        assert_eq!(range_common_bits(&(1234..=1234)), (0xFFFF, 1234));
+        assert_eq!(range_common_bits(&(0x1200..=0x12FF)), (0xFF00, 0x1200));
+        assert_eq!(range_common_bits(&(0x1280..=0x1301)), (0xFE00, 0x1200));
+    }
+
+    #[test]
+    fn test_first_match() {
+        fn cums_of(password: &str) -> Vec<u8> {
+            password
+                .chars()
+                .map(|c| password_char_to_cum(c).unwrap())
+                .collect()
+        }
+
+        // 全件生成した中の最小値と一致する。
+        for pattern in [
+            "ざぼちずどぢぎきつたうずせれえむるの??",
+            "ざぼちずどぢぎきつたうずせれ?むる??え",
+            "ざぼちず?ぢぎきつたうずせれ??るのぢえ",
+        ] {
+            let all = generate(pattern, 1000).unwrap();
+            assert!(all.len() < 1000);
+            let expect = all.into_iter().min_by_key(|p| cums_of(p));
+            assert_eq!(first_match(pattern), Ok(expect));
+        }
+
+        assert_eq!(
+            first_match("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok(Some("ざぼちずどぢぎきつたうずせれえむるのぢえ".to_owned()))
+        );
+        assert_eq!(
+            first_match("ざぼちずどぢぎきつたうずせれえむるのぢお"),
+            Ok(None)
+        );
+
+        let first = first_match("????????????????????").unwrap().unwrap();
+        assert!(decode(&first).is_ok());
+        assert!(first.starts_with("ああ"));
+
+        assert!(matches!(
+            first_match("ざぼちず"),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_dp_table() {
+        for n_state in 1..=4 {
+            let mut dense = DpTable::new_dense(0_usize, n_state);
+            let mut sparse = DpTable::new_sparse(0_usize, n_state);
+            match &dense.cells {
+                DpCells::Dense { cells, .. } => {
+                    assert_eq!(cells.len(), DpTable::<usize>::cell_count(n_state))
+                }
+                DpCells::Sparse { .. } => unreachable!(),
+            }
+
+            // 添字と位置は 1 対 1 に対応する。
+            for (i, j, k, l) in itertools::iproduct!(0..19, 0..0x40, 0..=0xFF, 0..n_state) {
+                dense[(i, j, k, l)] += 1;
+            }
+            match &dense.cells {
+                DpCells::Dense { cells, .. } => assert!(cells.iter().all(|&x| x == 1)),
+                DpCells::Sparse { .. } => unreachable!(),
+            }
+
+            // 疎なテーブルは書き込まれていないセルを既定値として読み、書き込まれたセルのみを昇順に走査する。
+            sparse[(3, 0x3F, 0xFF, n_state - 1)] = 2;
+            sparse[(3, 0x01, 0x02, 0)] = 1;
+            sparse[(4, 0x00, 0x00, 0)] = 3;
+            assert_eq!(sparse[(3, 0x3F, 0xFF, n_state - 1)], 2);
+            assert_eq!(sparse[(3, 0x3F, 0xFE, 0)], 0);
+            assert_eq!(
+                sparse.layer(3).collect::<Vec<_>>(),
+                [(0x01, 0x02, 0), (0x3F, 0xFF, n_state - 1)]
+            );
+            assert_eq!(sparse.layer(4).collect::<Vec<_>>(), [(0, 0, 0)]);
+            assert_eq!(sparse.layer(5).count(), 0);
+            assert_eq!(dense.layer(3).count(), 0x40 * 0x100 * usize::from(n_state));
+
+            // クリアすると全てのセルが既定値に戻る。
+            dense.clear();
+            match &dense.cells {
+                DpCells::Dense { cells, touched } => {
+                    assert!(cells.iter().all(|&x| x == 0));
+                    assert!(touched.is_empty());
+                }
+                DpCells::Sparse { .. } => unreachable!(),
+            }
+
+            sparse.clear();
+            assert_eq!(sparse[(3, 0x3F, 0xFF, n_state - 1)], 0);
+            assert_eq!(sparse.layer(3).count(), 0);
+            assert_eq!(sparse.layer(4).count(), 0);
+            match &sparse.cells {
+                DpCells::Sparse { spare, .. } => assert_eq!(spare.len(), 3),
+                DpCells::Dense { .. } => unreachable!(),
+            }
+            sparse[(5, 0x00, 0x00, 0)] = 4;
+            assert_eq!(sparse.layer(5).collect::<Vec<_>>(), [(0, 0, 0)]);
+
+            // リセット時に密/疎を選び直す。
+            let cums_tail = Pattern::parse("ざぼちずどぢぎきつたうずせれえむるのぢえ")
+                .unwrap()
+                .char_sets()[2..]
+                .try_into()
+                .unwrap();
+            dense.reset(&cums_tail);
+            assert!(dense.is_sparse());
+            sparse.reset(&cums_tail);
+            assert!(matches!(&sparse.cells, DpCells::Sparse { spare, .. } if spare.len() == 3));
+        }
+
+        // 固定された文字が多い場合のみ疎なテーブルとなる。
+        let table = |pattern: &str| {
+            let pattern = Pattern::parse(pattern).unwrap();
+            let cums_tail: [_; 18] = pattern.char_sets()[2..].try_into().unwrap();
+            DpTable::new(0_u8, 2, &cums_tail)
+        };
+        assert!(matches!(
+            table("ざぼちずどぢぎきつたうずせれえむるのぢえ").cells,
+            DpCells::Sparse { .. }
+        ));
+        assert!(matches!(
+            table("??ちずどぢぎきつたうずせれえむるの??").cells,
+            DpCells::Sparse { .. }
+        ));
+        assert!(matches!(
+            table("ざぼちずどぢぎきつたうずせれえむ????").cells,
+            DpCells::Dense { .. }
+        ));
+    }
+
+    /// 多次元 Vec による DP テーブル(平坦化する前の実装)を埋める。[`generate_dp_fill`] の検証用。
+    fn generate_dp_fill_nested(
+        sixs_head: [u8; 2],
+        cums_tail: &[CharSet; 18],
+        n_max: usize,
+        constraints: &Constraints,
+    ) -> Vec<Vec<Vec<Vec<Vec<DpTrace>>>>> {
+        let trace_count_max = if constraints.has_filter() {
+            DP_TRACE_COUNT_MAX
+        } else {
+            n_max
+        };
+
+        let cum_ini = (sixs_head[0] + sixs_head[1] + 8) & 0x3F;
+        let crc_ini = crc8_table_head()[usize::from(sixs_head[1] >> 2)];
+
+        let n_state = constraints.item_state_count();
+        let mut dp = vec![vec![vec![vec![vec![]; usize::from(n_state)]; 0x100]; 0x40]; 19];
+        dp[0][usize::from(cum_ini)][usize::from(crc_ini)][0].push(DpTrace::new(0, 0, 0));
+
+        for (i, j, k, l) in itertools::iproduct!(0..18, 0..=0x3F, 0..=0xFF, 0..n_state) {
+            if dp[i][usize::from(j)][usize::from(k)][usize::from(l)].is_empty() {
+                continue;
+            }
+
+            for cum in cums_tail[i].cums() {
+                let (six, l_nxt) = match dp_transition(i, j, l, cum, constraints) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let crc = k ^ crc8_table_tail()[i][usize::from(six)];
+
+                let traces = &mut dp[i + 1][usize::from(cum)][usize::from(crc)][usize::from(l_nxt)];
+                if traces.len() < trace_count_max {
+                    traces.push(DpTrace::new(j, k, l));
+                }
+            }
+        }
+
+        dp
+    }
+
+    #[test]
+    fn test_dp_trace_table() {
+        for mut dp in [DpTraceTable::new_dense(2), DpTraceTable::new_sparse(2)] {
+            for _ in 0..2 {
+                dp.push((0, 0x01, 0x02, 0), DpTrace::new(0, 0, 0), 1);
+                dp.commit_layer(0);
+
+                // セルごとに追加順に並び、上限を超えた分は捨てられる。
+                let traces = [
+                    DpTrace::new(1, 2, 0),
+                    DpTrace::new(3, 4, 1),
+                    DpTrace::new(5, 6, 0),
+                ];
+                dp.push((1, 0x3F, 0xFF, 1), traces[0], 2);
+                dp.push((1, 0x00, 0x10, 0), traces[1], 2);
+                dp.push((1, 0x3F, 0xFF, 1), traces[2], 2);
+                dp.push((1, 0x3F, 0xFF, 1), traces[1], 2);
+                dp.push((1, 0x00, 0x10, 0), traces[0], 2);
+                dp.commit_layer(1);
+
+                assert_eq!(dp.traces((0, 0x01, 0x02, 0)), [DpTrace::new(0, 0, 0)]);
+                assert_eq!(dp.traces((1, 0x3F, 0xFF, 1)), [traces[0], traces[2]]);
+                assert_eq!(dp.traces((1, 0x00, 0x10, 0)), [traces[1], traces[0]]);
+                assert!(dp.traces((1, 0x00, 0x10, 1)).is_empty());
+                assert!(dp.traces((2, 0x3F, 0xFF, 1)).is_empty());
+
+                dp.clear();
+                assert!(dp.traces((1, 0x3F, 0xFF, 1)).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_dp_fill_equivalence() {
+        let options_items = GenerateOptions {
+            forbid_items: vec![3, 7],
+            ..Default::default()
+        };
+        let options_filter = GenerateOptions {
+            xp: Some(100..=2000),
+            ..Default::default()
+        };
+
+        for (pattern, options, n_max) in [
+            (
+                "ざぼちずどぢぎきつたうずせれえむる???",
+                &GenerateOptions::default(),
+                10,
+            ),
+            (
+                "ざぼ??どぢぎきつた?ずせれえ??のぢえ",
+                &GenerateOptions::default(),
+                1000,
+            ),
+            ("ゆうてい[あ-こ]???????????????", &options_items, 5),
+            ("ゆうていざぼちず????????????", &options_filter, 3),
+        ] {
+            let pattern = Pattern::parse(pattern).unwrap();
+            let constraints = Constraints::new(options).unwrap();
+            let cums_tail: [_; 18] = pattern.char_sets()[2..].try_into().unwrap();
+
+            for sixs_head in [[0x00, 0x00], [0x13, 0x2A]] {
+                let mut flat = DpTraceTable::new_dense(constraints.item_state_count());
+                generate_dp_fill(
+                    &mut flat,
+                    sixs_head,
+                    &cums_tail,
+                    n_max,
+                    &constraints,
+                    &CrcTables::DQ1_FC,
+                );
+                let nested = generate_dp_fill_nested(sixs_head, &cums_tail, n_max, &constraints);
+
+                let n_state = constraints.item_state_count();
+                for (i, j, k, l) in itertools::iproduct!(0..19, 0..0x40, 0..=0xFF, 0..n_state) {
+                    assert_eq!(
+                        flat.traces((i, j, k, l)),
+                        nested[i][usize::from(j)][usize::from(k)][usize::from(l)]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dp_sparse_equivalence() {
+        let options_filter = GenerateOptions {
+            xp: Some(100..=2000),
+            forbid_items: vec![3],
+            ..Default::default()
+        };
+        let costs = six_costs();
+
+        for (pattern, options, n_max) in [
+            (
+                "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+                &GenerateOptions::default(),
+                10,
+            ),
+            (
+                "ざぼちずどぢぎきつたうずせれえむるの??",
+                &GenerateOptions::default(),
+                10,
+            ),
+            (
+                "ざぼちずどぢぎきつたうずせれえむるの??",
+                &GenerateOptions::default(),
+                1,
+            ),
+            (
+                "ざぼちず?ぢぎきつたうずせれ?むるのぢえ",
+                &options_filter,
+                100,
+            ),
+            (
+                "ざぼちずどぢぎきつたうずせれえむるの[あ-こ]?",
+                &options_filter,
+                5,
+            ),
+        ] {
+            let pattern = Pattern::parse(pattern).unwrap();
+            let constraints = Constraints::new(options).unwrap();
+            let n_state = constraints.item_state_count();
+            let cums_tail: [_; 18] = pattern.char_sets()[2..].try_into().unwrap();
+            assert!(dp_reachable_cell_count(&cums_tail, n_state) <= DP_SPARSE_CELL_COUNT_MAX);
+
+            // 一方は使い回したテーブルを用いる。
+            let mut dense = DpTraceTable::new_dense(n_state);
+            let mut sparse_min_cost = DpTable::new_sparse(DP_COST_INF, n_state);
+
+            let heads =
+                itertools::iproduct!(pattern.char_sets()[0].cums(), pattern.char_sets()[1].cums());
+            for sixs_head in heads.map(|(cum0, cum1)| {
+                [
+                    cum0.wrapping_sub(4) & 0x3F,
+                    cum1.wrapping_sub(cum0 + 4) & 0x3F,
+                ]
+            }) {
+                let fill = |dp: &mut DpTraceTable| {
+                    dp.clear();
+                    generate_dp_fill(
+                        dp,
+                        sixs_head,
+                        &cums_tail,
+                        n_max,
+                        &constraints,
+                        &CrcTables::DQ1_FC,
+                    )
+                };
+                let mut sparse = DpTraceTable::new_sparse(n_state);
+                fill(&mut dense);
+                fill(&mut sparse);
+
+                let fill_min_cost = |dp: &mut DpTable<_>| {
+                    dp.clear();
+                    generate_dp_min_cost_fill(
+                        dp,
+                        sixs_head,
+                        &cums_tail,
+                        &constraints,
+                        &costs,
+                        &CrcTables::DQ1_FC,
+                    )
+                };
+                let mut dense_min_cost = DpTable::new_dense(DP_COST_INF, n_state);
+                fill_min_cost(&mut dense_min_cost);
+                fill_min_cost(&mut sparse_min_cost);
+
+                for (i, j, k, l) in itertools::iproduct!(0..19, 0..0x40, 0..=0xFF, 0..n_state) {
+                    assert_eq!(sparse.traces((i, j, k, l)), dense.traces((i, j, k, l)));
+                    assert_eq!(sparse_min_cost[(i, j, k, l)], dense_min_cost[(i, j, k, l)]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crc_tables_init() {
+        // 初期値の寄与を head 側に含めたテーブルで、バイト列全体の CRC 下位バイトが再現できる。
+        const BYTES: [u8; 15] = [
+            0, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF,
+        ];
+        let sixs = bytes_to_sixs(&BYTES);
+
+        for (poly, init) in [(0x1021, 0), (0x1021, 0xFFFF), (0x8005, 0x1234)] {
+            let tables = CrcTables::new(poly, init);
+            let crc_actual = sixs[2..]
+                .iter()
+                .enumerate()
+                .fold(tables.head[usize::from(sixs[1] >> 2)], |crc, (i, &s)| {
+                    crc ^ tables.tail[i][usize::from(s)]
+                });
+            let crc_expect = BYTES[1..]
+                .iter()
+                .fold(init, |crc, &b| crc_update_poly(crc, b, 8, poly));
+
+            assert_eq!(crc_actual, (crc_expect & 0xFF) as u8);
+        }
 
-        assert_eq!(crc_actual, crc_expect);
+        assert_eq!(CrcTables::new(0x1021, 0), CrcTables::DQ1_FC);
     }
 }
@@ -0,0 +1,166 @@
+use crate::cums::{bytes_to_sixs, cums_to_password_into, sixs_to_cums};
+use crate::error::Dq1PasswordResult;
+use crate::generate::ResumableGenerate;
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// パターンに合致するデコード可能な復活の呪文を、必要な分だけ生成順に求めるイテレータ。
+///
+/// 結果とその順序は、既定のオプションでの [`generate`](crate::generate) と同じ。
+/// 先頭 2 文字の組ごとに DP を行い、その組の結果を消費し終えてから次の組に進むので、
+/// 保持するのは DP テーブル 1 組分と未消費の結果のみ。
+///
+/// `Iterator` としては結果ごとに `String` を返すが、[`next_into`](Self::next_into) や
+/// [`next_into_string`](Self::next_into_string) を用いれば呼び出し側のバッファに書き込むので、
+/// 結果ごとのアロケーションは行わない。
+#[derive(Clone, Debug)]
+pub struct GenerateIter {
+    generate: ResumableGenerate,
+
+    /// 求めたが未消費の生成結果のバイト列たち(生成順)。
+    pending: Vec<[u8; 15]>,
+
+    /// `pending` 内の次に返すものの位置。
+    pos: usize,
+}
+
+impl GenerateIter {
+    /// パターンに対するイテレータを作る。この時点では DP は行わない。
+    pub fn new(pattern: &Pattern) -> Self {
+        Self {
+            generate: ResumableGenerate::new(pattern),
+            pending: vec![],
+            pos: 0,
+        }
+    }
+
+    /// 次の結果を、各文字の [`PASSWORD_CHARS`](crate::PASSWORD_CHARS) 中のインデックス(累積値)の配列として
+    /// `buf` に書き込む。結果が尽きた場合は `buf` を変更せずに false を返す。
+    ///
+    /// 文字列が必要な場合は [`cums_to_password_into`] で変換できる。
+    pub fn next_into(&mut self, buf: &mut [u8; 20]) -> bool {
+        match self.next_bytes() {
+            Some(bytes) => {
+                *buf = sixs_to_cums(&bytes_to_sixs(&bytes));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 次の結果を `buf` に書き込む(元の内容は消去する)。結果が尽きた場合は `buf` を変更せずに false を返す。
+    pub fn next_into_string(&mut self, buf: &mut String) -> bool {
+        let mut cums = [0; 20];
+        if !self.next_into(&mut cums) {
+            return false;
+        }
+        cums_to_password_into(&cums, buf).unwrap();
+
+        true
+    }
+
+    fn next_bytes(&mut self) -> Option<[u8; 15]> {
+        if self.pos == self.pending.len() {
+            // 消費済みのものを捨て、バッファの領域を使い回す。
+            self.pending.clear();
+            self.pos = 0;
+            self.generate.extend(&mut self.pending, 1);
+        }
+
+        let bytes = self.pending.get(self.pos).copied()?;
+        self.pos += 1;
+
+        Some(bytes)
+    }
+}
+
+impl Iterator for GenerateIter {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut password = String::with_capacity(3 * 20);
+
+        self.next_into_string(&mut password).then_some(password)
+    }
+}
+
+/// パターンに合致するデコード可能な復活の呪文を、必要な分だけ生成順に求めるイテレータを返す。
+///
+/// [`generate`](crate::generate) と異なり生成上限数は指定せず、消費した分だけ生成する。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn generate_iter(pattern: impl AsRef<str>) -> Dq1PasswordResult<GenerateIter> {
+    Ok(GenerateIter::new(&Pattern::parse(pattern)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cums::password_to_cums;
+    use crate::generate::{generate, generate_each, GenerateOptions};
+
+    #[test]
+    fn test_generate_iter() {
+        const PATTERN: &str = "[かが]?ちずどぢぎきつたうずせれえむる??え";
+
+        let all = generate(PATTERN, usize::MAX).unwrap();
+        assert!(all.len() > 100);
+
+        // String を返す場合と、バッファに書き込む場合で結果が等しい。
+        assert_eq!(generate_iter(PATTERN).unwrap().collect::<Vec<_>>(), all);
+
+        let mut iter = generate_iter(PATTERN).unwrap();
+        let mut buf = [0; 20];
+        let mut n = 0;
+        while iter.next_into(&mut buf) {
+            assert_eq!(buf, password_to_cums(&all[n]).unwrap());
+            n += 1;
+        }
+        assert_eq!(n, all.len());
+        // 尽きた後は buf を変更しない。
+        let last = buf;
+        assert!(!iter.next_into(&mut buf));
+        assert_eq!(buf, last);
+
+        let mut iter = generate_iter(PATTERN).unwrap();
+        let mut buf = String::new();
+        for password in &all {
+            assert!(iter.next_into_string(&mut buf));
+            assert_eq!(&buf, password);
+        }
+        assert!(!iter.next_into_string(&mut buf));
+        assert_eq!(&buf, all.last().unwrap());
+
+        // 途中で Iterator としての利用と混ぜてもよい。
+        let mut iter = generate_iter(PATTERN).unwrap();
+        assert_eq!(iter.by_ref().take(10).collect::<Vec<_>>(), all[..10]);
+        assert!(iter.next_into(&mut [0; 20]));
+        assert_eq!(iter.next().as_ref(), Some(&all[11]));
+
+        // generate_each も同じ結果を同じ順序で渡す。
+        let pattern = Pattern::parse(PATTERN).unwrap();
+        let mut each = vec![];
+        generate_each(&pattern, usize::MAX, &GenerateOptions::default(), |cums| {
+            each.push(*cums);
+            true
+        })
+        .unwrap();
+        let expected: Vec<_> = all.iter().map(|p| password_to_cums(p).unwrap()).collect();
+        assert_eq!(each, expected);
+
+        // false を返したら打ち切る。
+        let mut n_called = 0;
+        generate_each(&pattern, usize::MAX, &GenerateOptions::default(), |_| {
+            n_called += 1;
+            n_called < 5
+        })
+        .unwrap();
+        assert_eq!(n_called, 5);
+
+        assert!(generate_iter("ゆうてい").is_err());
+        assert!(generate_iter("ざぼちずどぢぎきつたうずせれえむるのぢ?")
+            .unwrap()
+            .eq(generate("ざぼちずどぢぎきつたうずせれえむるのぢ?", usize::MAX).unwrap()));
+    }
+}
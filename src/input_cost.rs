@@ -0,0 +1,162 @@
+use crate::decode::password_char_to_cum;
+use crate::error::Dq1PasswordResult;
+use crate::normalize::normalize_password;
+use crate::prelude::*;
+
+/// 入力コストのモデルで用いる、復活の呪文の入力画面の文字配置。'　' は空欄。
+///
+/// 左右 2 つのブロックに分かれており、間に 1 列の空欄がある。
+pub const INPUT_GRID: [&str; 8] = [
+    "あいうえお　はひふへほ",
+    "かきくけこ　まみむめも",
+    "さしすせそ　やゆよ　　",
+    "たちつてと　らりるれろ",
+    "なにぬねの　わ　　　　",
+    "がぎぐげご　ばびぶべぼ",
+    "ざじずぜぞ　　　　　　",
+    "だぢづでど　　　　　　",
+];
+
+/// 入力コストの付いた復活の呪文。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RankedPassword {
+    /// 復活の呪文。
+    pub password: String,
+
+    /// 入力コスト。
+    pub input_cost: u32,
+}
+
+/// 復活の呪文の入力コストを返す。
+///
+/// カーソルは [`INPUT_GRID`] の左上('あ')から始まり、1 文字ごとに
+/// 目的の文字までの移動(上下左右 1 マスごとに 1、空欄も通れるものとする)と決定(1)を行うものとする。
+/// 画面端での折り返しは考慮しない。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn input_cost(password: impl AsRef<str>) -> Dq1PasswordResult<u32> {
+    let password = normalize_password(password)?;
+
+    Ok(input_cost_normalized(&password))
+}
+
+/// 復活の呪文たちを入力コストの小さい順に並べ、コストと併せて返す。
+///
+/// コストが等しいものは辞書順(五十音表の順)に並ぶ。結果の復活の呪文は正規化済み。
+///
+/// `passwords` に形式が無効なものが含まれる場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn rank_by_input_cost(passwords: Vec<String>) -> Dq1PasswordResult<Vec<RankedPassword>> {
+    let mut ranked = passwords
+        .into_iter()
+        .map(|password| {
+            let password = normalize_password(password)?;
+            let input_cost = input_cost_normalized(&password);
+            Ok(RankedPassword {
+                password,
+                input_cost,
+            })
+        })
+        .collect::<Dq1PasswordResult<Vec<_>>>()?;
+
+    ranked.sort_by_cached_key(|r| input_cost_sort_key(&r.password));
+
+    Ok(ranked)
+}
+
+/// 入力コストの小さい順(同じなら辞書順)に並べるためのキーを返す。
+///
+/// `password` は正規化済みでなければならない。
+pub(crate) fn input_cost_sort_key(password: &str) -> (u32, Vec<u8>) {
+    (input_cost_normalized(password), canonical_key(password))
+}
+
+/// `password` は正規化済みでなければならない。
+fn input_cost_normalized(password: &str) -> u32 {
+    let mut cur: (u32, u32) = (0, 0);
+    let mut cost = 0;
+    for c in password.chars() {
+        let pos = char_position(c);
+        cost += cur.0.abs_diff(pos.0) + cur.1.abs_diff(pos.1) + 1;
+        cur = pos;
+    }
+
+    cost
+}
+
+/// 復活の呪文の文字の [`INPUT_GRID`] 上の位置 (行, 列) を返す。
+fn char_position(c: char) -> (u32, u32) {
+    (0..)
+        .zip(INPUT_GRID)
+        .find_map(|(row, line)| {
+            (0..)
+                .zip(line.chars())
+                .find(|&(_, x)| x == c)
+                .map(|(col, _)| (row, col))
+        })
+        .unwrap()
+}
+
+/// 辞書順(五十音表の順)の比較に用いるキー。
+fn canonical_key(password: &str) -> Vec<u8> {
+    password
+        .chars()
+        .map(|c| password_char_to_cum(c).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::encode::cum_to_password_char;
+    use crate::error::Dq1PasswordError;
+
+    #[test]
+    fn test_input_grid() {
+        // 全ての文字がちょうど 1 回ずつ現れる。
+        let mut cs: Vec<_> = INPUT_GRID
+            .iter()
+            .flat_map(|line| line.chars())
+            .filter(|&c| c != '　')
+            .collect();
+        cs.sort_unstable();
+        let mut expect: Vec<_> = (0..0x40).map(cum_to_password_char).collect();
+        expect.sort_unstable();
+        assert_eq!(cs, expect);
+        assert!(INPUT_GRID.iter().all(|line| line.chars().count() == 11));
+    }
+
+    #[test]
+    fn test_input_cost() {
+        assert_eq!(
+            input_cost("ああああああああああああああああああああ"),
+            Ok(20)
+        );
+        // あ→ほ: 10, ほ→だ: 7+10, だ→あ: 7, 残りは移動なし。
+        assert_eq!(
+            input_cost("あほだあ ああああ ああああ ああああ ああああ"),
+            Ok(10 + 17 + 7 + 20)
+        );
+        assert!(matches!(
+            input_cost("ゆうてい"),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+    }
+
+    #[test]
+    fn test_rank_by_input_cost() {
+        let passwords = vec![
+            "ぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼぼ".to_owned(),
+            "いいいいいいいいいいいいいいいいいいいい".to_owned(),
+            "かかかかかかかかかかかかかかかかかかかか".to_owned(),
+            "ああああああああああああああああああああ".to_owned(),
+        ];
+        let ranked = rank_by_input_cost(passwords).unwrap();
+        let actual: Vec<_> = ranked
+            .iter()
+            .map(|r| (r.password.chars().next().unwrap(), r.input_cost))
+            .collect();
+        // 'い' と 'か' はコストが等しいので辞書順。
+        assert_eq!(actual, [('あ', 20), ('い', 21), ('か', 21), ('ぼ', 35)]);
+    }
+}
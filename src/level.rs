@@ -0,0 +1,92 @@
+use core::convert::TryFrom;
+
+use crate::game_state::GameState;
+
+/// 主人公の最大レベル。
+pub const LEVEL_MAX: u8 = 30;
+
+/// 各レベルに達するのに必要な経験値。`XP_TABLE[lv - 1]` がレベル `lv` のもの。
+///
+/// 本家(FC 版)の値(攻略本などで知られている経験値表と同じ)。
+/// 経験値は 65535 で頭打ちとなるので、レベル 30 はちょうど 65535 で達する。
+const XP_TABLE: [u16; LEVEL_MAX as usize] = [
+    0, 7, 23, 47, 110, 220, 450, 800, 1300, 2000, //
+    2900, 4000, 5500, 7500, 10000, 13000, 16000, 19000, 22000, 26000, //
+    30000, 34000, 38000, 42000, 46000, 50000, 54000, 58000, 62000, 65535,
+];
+
+/// 経験値に対応するレベル(1..=30)を返す。
+pub fn level_from_xp(xp: u16) -> u8 {
+    let n = XP_TABLE.iter().take_while(|&&need| need <= xp).count();
+    u8::try_from(n).unwrap()
+}
+
+/// レベル `level` に達するのに必要な経験値を返す。`level` が 1..=30 の範囲外なら None を返す。
+pub fn xp_for_level(level: u8) -> Option<u16> {
+    let idx = usize::from(level.checked_sub(1)?);
+    XP_TABLE.get(idx).copied()
+}
+
+impl GameState {
+    /// 経験値に対応するレベル(1..=30)を返す。
+    pub fn level(&self) -> u8 {
+        level_from_xp(self.hero_xp)
+    }
+
+    /// 次のレベルまでに必要な経験値を返す。最大レベルの場合は None を返す。
+    pub fn xp_to_next_level(&self) -> Option<u16> {
+        xp_for_level(self.level() + 1).map(|need| need - self.hero_xp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_xp() {
+        assert_eq!(level_from_xp(0), 1);
+        assert_eq!(level_from_xp(u16::MAX - 1), 29);
+        assert_eq!(level_from_xp(u16::MAX), LEVEL_MAX);
+
+        // 全ての境界値。
+        for level in 2..=LEVEL_MAX {
+            let need = xp_for_level(level).unwrap();
+            assert_eq!(level_from_xp(need - 1), level - 1);
+            assert_eq!(level_from_xp(need), level);
+        }
+    }
+
+    #[test]
+    fn test_xp_for_level() {
+        assert_eq!(xp_for_level(0), None);
+        assert_eq!(xp_for_level(1), Some(0));
+        assert_eq!(xp_for_level(2), Some(7));
+        assert_eq!(xp_for_level(10), Some(2000));
+        assert_eq!(xp_for_level(17), Some(16000));
+        assert_eq!(xp_for_level(20), Some(26000));
+        assert_eq!(xp_for_level(30), Some(65535));
+        assert_eq!(xp_for_level(31), None);
+
+        assert!(XP_TABLE.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_game_state_level() {
+        let state = |hero_xp| GameState {
+            hero_xp,
+            ..Default::default()
+        };
+
+        assert_eq!(state(0).level(), 1);
+        assert_eq!(state(0).xp_to_next_level(), Some(7));
+        assert_eq!(state(6).xp_to_next_level(), Some(1));
+        assert_eq!(state(7).level(), 2);
+        assert_eq!(state(7).xp_to_next_level(), Some(16));
+        assert_eq!(state(16000).level(), 17);
+        assert_eq!(state(18999).xp_to_next_level(), Some(1));
+        assert_eq!(state(62000).xp_to_next_level(), Some(3535));
+        assert_eq!(state(u16::MAX).level(), 30);
+        assert_eq!(state(u16::MAX).xp_to_next_level(), None);
+    }
+}
@@ -1,16 +1,99 @@
-mod crc;
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+mod alphabet;
+mod analyze;
+mod char_set;
+mod codec;
+mod combat;
+pub mod compat;
+mod confusable;
+pub mod crc;
+mod crc_collision;
+mod cums;
 mod decode;
 mod encode;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod game_state;
 mod generate;
+mod generate_iter;
+mod input_cost;
+mod level;
+mod lua;
+mod name_search;
+mod neighbors;
 mod normalize;
+mod optimize;
+mod palindrome;
+mod partial_decoder;
+mod pattern;
+mod plausibility;
+mod prelude;
+mod prepared;
+mod prices;
+mod progression;
+#[cfg(feature = "python")]
+pub mod python;
+mod ram;
+mod record;
+mod romaji;
+mod search;
+mod serialize;
+mod share_code;
+mod spells;
+mod stats;
+mod status_screen;
+#[cfg(feature = "async")]
+mod stream;
+mod test_vector;
+mod tiles;
+mod typical_state;
 mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use crate::alphabet::*;
+pub use crate::analyze::*;
+pub use crate::char_set::*;
+pub use crate::codec::*;
+pub use crate::combat::*;
+pub use crate::confusable::*;
+pub use crate::crc_collision::*;
+pub use crate::cums::*;
 pub use crate::decode::*;
 pub use crate::encode::*;
 pub use crate::error::*;
 pub use crate::game_state::*;
 pub use crate::generate::*;
+pub use crate::generate_iter::*;
+pub use crate::input_cost::*;
+pub use crate::level::*;
+pub use crate::lua::*;
+pub use crate::name_search::*;
+pub use crate::neighbors::*;
 pub use crate::normalize::*;
+pub use crate::optimize::*;
+pub use crate::palindrome::*;
+pub use crate::partial_decoder::*;
+pub use crate::pattern::*;
+pub use crate::plausibility::*;
+pub use crate::prepared::*;
+pub use crate::prices::*;
+pub use crate::progression::*;
+pub use crate::ram::*;
+pub use crate::record::*;
+pub use crate::romaji::*;
+pub use crate::search::*;
+pub use crate::share_code::*;
+pub use crate::spells::*;
+pub use crate::stats::*;
+#[cfg(feature = "async")]
+pub use crate::stream::*;
+pub use crate::test_vector::*;
+pub use crate::tiles::*;
 pub use crate::validate::*;
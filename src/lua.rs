@@ -0,0 +1,202 @@
+use core::fmt::Write as _;
+
+use crate::error::Dq1PasswordResult;
+use crate::game_state::GameState;
+use crate::prelude::*;
+use crate::ram::{state_to_ram_fields, RamField, RamLayout};
+
+/// Lua スクリプトの対象とするエミュレータ。メモリの読み書きに使う関数が異なる。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum LuaEmulator {
+    /// FCEUX (`memory.readbyte`, `memory.writebyte`)。
+    #[default]
+    Fceux,
+
+    /// Mesen (`emu.read`, `emu.write` に `emu.memType.nesMemory` を指定)。
+    Mesen,
+}
+
+/// [`GameState::to_lua_script`] のオプション。
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LuaScriptOptions {
+    /// 対象とするエミュレータ。
+    pub emulator: LuaEmulator,
+
+    /// 全てのアドレスに加えるオフセット。
+    ///
+    /// エミュレータのメモリ領域の指定によっては CPU RAM が 0 番地から始まらないため、その先頭アドレスを指定する。
+    pub ram_base: u32,
+
+    /// RAM 上のゲーム状態の配置。
+    pub layout: RamLayout,
+}
+
+impl GameState {
+    /// ゲーム状態を RAM に書き込む Lua スクリプトを生成する。
+    ///
+    /// 主人公の名前、経験値、所持金、装備、インベントリ、やくそう/かぎ所持数、フラグを書き込む。
+    /// フラグは同じバイトの他のビットを変えずに書き込む。salt は RAM 上に対応するものがないので書き込まない。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn to_lua_script(&self, options: &LuaScriptOptions) -> Dq1PasswordResult<String> {
+        self.validate()?;
+        let state = self.normalize()?;
+
+        let (read, write) = match options.emulator {
+            LuaEmulator::Fceux => ("memory.readbyte(addr)", "memory.writebyte(addr, value)"),
+            LuaEmulator::Mesen => (
+                "emu.read(addr, emu.memType.nesMemory)",
+                "emu.write(addr, value, emu.memType.nesMemory)",
+            ),
+        };
+        let addr = |offset: u16| u32::from(offset) + options.ram_base;
+
+        let mut script = String::new();
+        writeln!(script, "-- hero_name = \"{}\"", state.hero_name).unwrap();
+        writeln!(script, "local function rd(addr) return {} end", read).unwrap();
+        writeln!(script, "local function wr(addr, value) {} end", write).unwrap();
+        script.push_str(concat!(
+            "local function setflag(addr, mask, on)\n",
+            "  local v = rd(addr)\n",
+            "  local has = math.floor(v / mask) % 2 == 1\n",
+            "  if on and not has then\n",
+            "    v = v + mask\n",
+            "  elseif not on and has then\n",
+            "    v = v - mask\n",
+            "  end\n",
+            "  wr(addr, v)\n",
+            "end\n",
+        ));
+
+        for (name, field) in state_to_ram_fields(&state, &options.layout) {
+            writeln!(script, "-- {}", name).unwrap();
+            match field {
+                RamField::Bytes { addr: start, bytes } => {
+                    for (i, b) in (0..).zip(bytes) {
+                        writeln!(script, "wr(0x{:04X}, 0x{:02X})", addr(start) + i, b).unwrap();
+                    }
+                }
+                RamField::Flag { bit, value } => {
+                    writeln!(
+                        script,
+                        "setflag(0x{:04X}, 0x{:02X}, {})",
+                        addr(bit.addr),
+                        bit.mask,
+                        value
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ram::RamBit;
+
+    fn state() -> GameState {
+        GameState {
+            hero_name: "しどー".to_owned(),
+            hero_xp: 1234,
+            purse: 5678,
+            hero_weapon: 5,
+            hero_armor: 5,
+            hero_shield: 2,
+            herb_count: 6,
+            key_count: 4,
+            inventory: [1, 2, 3, 4, 5, 6, 7, 8],
+            flag_equip_dragon_scale: true,
+            flag_equip_warrior_ring: false,
+            flag_got_death_necklace: true,
+            flag_beated_golem: true,
+            flag_beated_dragon: false,
+            salt: 5,
+        }
+    }
+
+    #[test]
+    fn test_to_lua_script() {
+        let script = state().to_lua_script(&LuaScriptOptions::default()).unwrap();
+        assert_eq!(
+            script,
+            r#"-- hero_name = "しと゛-"
+local function rd(addr) return memory.readbyte(addr) end
+local function wr(addr, value) memory.writebyte(addr, value) end
+local function setflag(addr, mask, on)
+  local v = rd(addr)
+  local has = math.floor(v / mask) % 2 == 1
+  if on and not has then
+    v = v + mask
+  elseif not on and has then
+    v = v - mask
+  end
+  wr(addr, v)
+end
+-- hero_name
+wr(0x00B5, 0x15)
+wr(0x00B6, 0x1D)
+wr(0x00B7, 0x3C)
+wr(0x00B8, 0x3E)
+-- hero_xp
+wr(0x00BA, 0xD2)
+wr(0x00BB, 0x04)
+-- purse
+wr(0x00BC, 0x2E)
+wr(0x00BD, 0x16)
+-- equipment
+wr(0x00BE, 0xB6)
+-- key_count
+wr(0x00BF, 0x04)
+-- herb_count
+wr(0x00C0, 0x06)
+-- inventory
+wr(0x00C1, 0x21)
+wr(0x00C2, 0x43)
+wr(0x00C3, 0x65)
+wr(0x00C4, 0x87)
+-- flag_equip_dragon_scale
+setflag(0x00CF, 0x10, true)
+-- flag_equip_warrior_ring
+setflag(0x00CF, 0x20, false)
+-- flag_got_death_necklace
+setflag(0x00CF, 0x80, true)
+-- flag_beated_golem
+setflag(0x00E4, 0x02, true)
+-- flag_beated_dragon
+setflag(0x00E4, 0x04, false)
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_lua_script_options() {
+        let options = LuaScriptOptions {
+            emulator: LuaEmulator::Mesen,
+            ram_base: 0x1000,
+            layout: RamLayout {
+                flag_beated_golem: RamBit {
+                    addr: 0x0100,
+                    mask: 0x40,
+                },
+                ..RamLayout::DQ1_FC
+            },
+        };
+        let script = state().to_lua_script(&options).unwrap();
+
+        assert!(script.contains("emu.write(addr, value, emu.memType.nesMemory)"));
+        assert!(!script.contains("memory.writebyte"));
+        assert!(script.contains("wr(0x10BA, 0xD2)\n"));
+        assert!(script.contains("setflag(0x1100, 0x40, true)\n"));
+
+        let state = GameState {
+            hero_weapon: 8,
+            ..Default::default()
+        };
+        assert!(state.to_lua_script(&options).is_err());
+    }
+}
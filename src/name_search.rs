@@ -0,0 +1,278 @@
+use crate::alphabet::HERO_NAME_CHARS;
+use crate::encode::pack_hero_name_char;
+use crate::error::Dq1PasswordResult;
+use crate::normalize::{
+    normalize_hero_name_char, normalize_hero_name_strict, HERO_NAME_SEMI_VOICEABLE,
+    HERO_NAME_VOICEABLE,
+};
+use crate::prelude::*;
+use crate::stats::GrowthType;
+use crate::validate::ENTERABLE_HERO_NAME_CHARS;
+
+/// [`find_names`] のオプション。
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NameSearchOptions {
+    /// 名前に使ってよい文字たち。None の場合、ゲーム内で入力可能な文字全て。
+    ///
+    /// 各文字を正規化して扱う(カタカナはひらがな、濁音/半濁音は清音とみなす)。
+    /// 名前に使えない文字や '゛'/'゜' は無視する('゛'/'゜' は `allow_dakuten` で指定する)。
+    /// 末尾のパディングの空白は常に許す。
+    pub allowed_chars: Option<String>,
+
+    /// 名前の先頭部分。正規化していないものも許す。末尾の空白は無視する。
+    ///
+    /// `allowed_chars`, `allow_dakuten` の制約は受けない。
+    pub prefix: String,
+
+    /// true の場合、濁音/半濁音を含む名前('゛'/'゜' が 1 文字を占める)も探す。
+    pub allow_dakuten: bool,
+}
+
+/// 成長タイプが `target` となる主人公の名前たちを最大 `n_max` 個探す。
+///
+/// 結果は正規化済みの名前(4 文字、空白でパディング)で、[`growth_type`](crate::growth_type) が `target` となる。
+/// また、ゲーム内で入力可能なもの([`normalize_hero_name_strict`] を満たすもの)に限る。
+/// 結果は名前の内部値の辞書順に並ぶ(よってパディングの多い短い名前は後になる)。
+///
+/// `options.prefix` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn find_names(
+    target: GrowthType,
+    options: &NameSearchOptions,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<String>> {
+    let prefix = normalize_hero_name_strict(&options.prefix)?;
+    let mut name: Vec<_> = prefix.trim_end_matches(' ').chars().collect();
+
+    let requested: Option<Vec<char>> = options
+        .allowed_chars
+        .as_ref()
+        .map(|chars| chars.chars().flat_map(normalize_hero_name_char).collect());
+    let is_requested = |c: char| requested.as_ref().is_none_or(|cs| cs.contains(&c));
+
+    let search = NameSearch {
+        target,
+        allowed: HERO_NAME_CHARS
+            .iter()
+            .copied()
+            .filter(|c| ENTERABLE_HERO_NAME_CHARS.contains(c))
+            .filter(|&c| match c {
+                '゛' | '゜' => options.allow_dakuten,
+                ' ' => true,
+                _ => is_requested(c),
+            })
+            .collect(),
+        allow_space: is_requested(' '),
+        n_max,
+    };
+
+    let mut found = vec![];
+    search.search(&mut name, &mut found);
+
+    Ok(found)
+}
+
+struct NameSearch {
+    target: GrowthType,
+
+    /// 名前に使える文字たち(内部値の昇順)。パディングの空白と、許す場合は '゛'/'゜' も含む。
+    allowed: Vec<char>,
+
+    /// 名前の途中に空白を置けるかどうか。
+    allow_space: bool,
+
+    n_max: usize,
+}
+
+impl NameSearch {
+    fn search(&self, name: &mut Vec<char>, found: &mut Vec<String>) {
+        if found.len() >= self.n_max {
+            return;
+        }
+
+        if name.len() == 4 {
+            let sum = name
+                .iter()
+                .map(|&c| pack_hero_name_char(c).unwrap())
+                .fold(0_u8, u8::wrapping_add);
+            if GrowthType::from_name_sum(sum) == self.target {
+                found.push(name.iter().collect());
+            }
+            return;
+        }
+
+        for &c in &self.allowed {
+            if !self.can_push(name, c) {
+                continue;
+            }
+            name.push(c);
+            self.search(name, found);
+            name.pop();
+        }
+    }
+
+    /// `name` の末尾に `c` を置いても入力可能な名前になりうるかどうかを返す。
+    fn can_push(&self, name: &[char], c: char) -> bool {
+        let pre = name.last().copied();
+        match c {
+            '゛' => pre.is_some_and(|pre| HERO_NAME_VOICEABLE.contains(pre)),
+            '゜' => pre.is_some_and(|pre| HERO_NAME_SEMI_VOICEABLE.contains(pre)),
+            ' ' => true,
+            // 空白の後に空白以外を置けるのは、途中の空白が許されていて、かつ先頭が空白でない場合のみ。
+            _ => !name.contains(&' ') || (self.allow_space && name[0] != ' '),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::normalize::normalize_hero_name;
+    use crate::stats::growth_type;
+
+    const GROWTH_TYPES: [GrowthType; 4] = [
+        GrowthType::HpMp,
+        GrowthType::StrengthHp,
+        GrowthType::AgilityMp,
+        GrowthType::StrengthAgility,
+    ];
+
+    fn assert_names(names: &[String], target: GrowthType) {
+        for name in names {
+            assert_eq!(normalize_hero_name(name).as_ref(), Ok(name));
+            assert_eq!(normalize_hero_name_strict(name).as_ref(), Ok(name));
+            assert_eq!(growth_type(name), Ok(target));
+        }
+        let mut sorted = names.to_vec();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), names.len());
+    }
+
+    #[test]
+    fn test_find_names() {
+        let options = NameSearchOptions {
+            allowed_chars: Some("アイウエオ".to_owned()),
+            ..Default::default()
+        };
+        let mut total = 0;
+        for target in GROWTH_TYPES {
+            let names = find_names(target, &options, usize::MAX).unwrap();
+            assert_names(&names, target);
+            assert!(names
+                .iter()
+                .all(|name| name.chars().all(|c| "あいうえお ".contains(c))));
+            assert!(!names.iter().any(|name| name.trim_end().contains(' ')));
+            total += names.len();
+        }
+        // 1..=4 文字の名前と空の名前。
+        assert_eq!(total, 5 + 5 * 5 + 5 * 5 * 5 + 5 * 5 * 5 * 5 + 1);
+
+        let names = find_names(GrowthType::StrengthHp, &options, 3).unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(
+            names,
+            find_names(GrowthType::StrengthHp, &options, usize::MAX).unwrap()[..3]
+        );
+        assert!(find_names(GrowthType::StrengthHp, &options, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_names_prefix() {
+        for target in GROWTH_TYPES {
+            let options = NameSearchOptions {
+                allowed_chars: Some("らりるれろ".to_owned()),
+                prefix: "ロト".to_owned(),
+                ..Default::default()
+            };
+            let names = find_names(target, &options, usize::MAX).unwrap();
+            assert!(!names.is_empty());
+            assert_names(&names, target);
+            assert!(names.iter().all(|name| name.starts_with("ろと")));
+        }
+
+        // 先頭部分だけで 4 文字。
+        let options = NameSearchOptions {
+            prefix: "ゆうてい".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            find_names(GrowthType::StrengthHp, &options, usize::MAX).unwrap(),
+            ["ゆうてい"]
+        );
+        assert!(find_names(GrowthType::HpMp, &options, usize::MAX)
+            .unwrap()
+            .is_empty());
+
+        let options = NameSearchOptions {
+            prefix: "ゆうていみ".to_owned(),
+            ..Default::default()
+        };
+        assert!(find_names(GrowthType::HpMp, &options, 1).is_err());
+    }
+
+    #[test]
+    fn test_find_names_dakuten() {
+        // 濁音は清音とみなす。
+        let options = NameSearchOptions {
+            allowed_chars: Some("がし".to_owned()),
+            allow_dakuten: true,
+            ..Default::default()
+        };
+        let mut names = vec![];
+        for target in GROWTH_TYPES {
+            let found = find_names(target, &options, usize::MAX).unwrap();
+            assert_names(&found, target);
+            names.extend(found);
+        }
+        assert!(names.contains(&"か゛し゛".to_owned()));
+        assert!(names.contains(&"かし  ".to_owned()));
+        assert!(names
+            .iter()
+            .all(|name| name.chars().all(|c| "かし゛ ".contains(c))));
+
+        let options = NameSearchOptions {
+            allow_dakuten: false,
+            ..options
+        };
+        for target in GROWTH_TYPES {
+            let found = find_names(target, &options, usize::MAX).unwrap();
+            assert_names(&found, target);
+            assert!(found
+                .iter()
+                .all(|name| name.chars().all(|c| "かし ".contains(c))));
+        }
+
+        // '゜' は半濁音化できる文字の後にのみ置く。
+        let options = NameSearchOptions {
+            allowed_chars: Some("はあ".to_owned()),
+            allow_dakuten: true,
+            ..Default::default()
+        };
+        let names = find_names(GrowthType::HpMp, &options, usize::MAX).unwrap();
+        assert_names(&names, GrowthType::HpMp);
+        assert!(names.iter().any(|name| name.contains("は゜")));
+        assert!(!names.iter().any(|name| name.contains("あ゜")));
+        assert!(!names.iter().any(|name| name.contains("あ゛")));
+    }
+
+    #[test]
+    fn test_find_names_space() {
+        let options = NameSearchOptions {
+            allowed_chars: Some("あ ".to_owned()),
+            ..Default::default()
+        };
+        let mut names = vec![];
+        for target in GROWTH_TYPES {
+            names.extend(find_names(target, &options, usize::MAX).unwrap());
+        }
+        // 途中の空白は許すが、空白で始まる名前は空の名前のみ。
+        assert!(names.contains(&"あ あ ".to_owned()));
+        assert!(names.contains(&"    ".to_owned()));
+        assert!(names
+            .iter()
+            .all(|name| !name.starts_with(' ') || name == "    "));
+    }
+}
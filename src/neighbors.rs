@@ -0,0 +1,400 @@
+use alloc::collections::BTreeSet;
+use core::convert::TryInto;
+
+use itertools::Itertools as _;
+
+use crate::decode::{decode, password_char_to_cum};
+use crate::encode::cum_to_password_char;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::GameState;
+use crate::generate::{crc8_table_head, crc8_table_tail};
+use crate::normalize::normalize_password;
+use crate::prelude::*;
+
+/// 指定された復活の呪文と高々 `d` 文字だけ異なるデコード可能な復活の呪文たちを生成する。
+///
+/// `d` は 1 または 2 でなければならない。
+/// `password` 自身はデコード可能でなくてもよい(入力ミスからの復旧に使える)が、結果には含まれない。
+///
+/// 1 文字の置換は 6bit 値配列の隣接する 2 要素を変えるだけなので、CRC 下位バイトへの寄与の差分
+/// (シンドローム)を位置ごとに求められる。2 文字の置換は CRC の線形性を使い、
+/// 差分が打ち消し合う組だけを調べる。
+///
+/// 結果は辞書順(五十音表の順)に並ぶ。
+///
+/// `n_max` は生成上限数。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+///
+/// `d` が無効な場合、`Err(Dq1PasswordError::InvalidConstraint)` を返す。
+pub fn neighbors(
+    password: impl AsRef<str>,
+    d: usize,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<String>> {
+    if !(1..=2).contains(&d) {
+        return Err(Dq1PasswordError::invalid_constraint(format!(
+            "距離は 1 または 2 でなければならない: {}",
+            d
+        )));
+    }
+
+    let password = normalize_password(password)?;
+    let cums: Vec<_> = password
+        .chars()
+        .map(|c| password_char_to_cum(c).unwrap())
+        .collect();
+    let cums: [u8; 20] = cums.try_into().unwrap();
+
+    // 全ての 6bit 値の寄与の XOR。デコード可能ならば 0。
+    let syndrome = (0..20).fold(0, |acc, i| acc ^ crc_term(i, six_at(&cums, i)));
+
+    // 位置 p の文字を累積値 cum に置換したときの寄与の差分。
+    let delta1 = |p: usize, cum: u8| -> u8 { crc_delta(&cums, &[(p, cum)]) };
+    let others = |p: usize| (0..0x40).filter(move |&cum| cum != cums[p]);
+
+    let mut candidates = vec![];
+    let mut push = |changes: &[(usize, u8)]| {
+        let mut cums_new = cums;
+        for &(p, cum) in changes {
+            cums_new[p] = cum;
+        }
+        candidates.push(cums_new);
+    };
+
+    for p in 0..20 {
+        for cum in others(p) {
+            if delta1(p, cum) == syndrome {
+                push(&[(p, cum)]);
+            }
+        }
+    }
+
+    if d == 2 {
+        // 隣接する 2 文字の置換は 6bit 値を共有するので、差分を直接計算する。
+        for p in 0..19 {
+            for (a, b) in itertools::iproduct!(others(p), others(p + 1)) {
+                if crc_delta(&cums, &[(p, a), (p + 1, b)]) == syndrome {
+                    push(&[(p, a), (p + 1, b)]);
+                }
+            }
+        }
+
+        // 隣接しない 2 文字の置換の差分は、それぞれの差分の XOR となる。
+        let mut buckets = vec![vec![vec![]; 0x100]; 20];
+        for q in 0..20 {
+            for cum in others(q) {
+                buckets[q][usize::from(delta1(q, cum))].push(cum);
+            }
+        }
+        for p in 0..18 {
+            for a in others(p) {
+                let rest = usize::from(syndrome ^ delta1(p, a));
+                for (q, bucket) in buckets.iter().enumerate().skip(p + 2) {
+                    for &b in &bucket[rest] {
+                        push(&[(p, a), (q, b)]);
+                    }
+                }
+            }
+        }
+    }
+
+    let passwords = candidates
+        .into_iter()
+        .sorted()
+        .dedup()
+        .map(|cums| {
+            cums.iter()
+                .map(|&cum| cum_to_password_char(cum))
+                .collect::<String>()
+        })
+        .filter(|password| decode(password).is_ok())
+        .take(n_max)
+        .collect();
+
+    Ok(passwords)
+}
+
+/// 復活の呪文の 1 文字を置換したものの頑健性の分析結果。[`robustness`] を参照。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RobustnessReport {
+    /// 元の復活の呪文のデコード結果。
+    pub state: GameState,
+
+    /// 1 文字の置換で得られる、元と異なるゲーム状態にデコードされる復活の呪文たち。
+    ///
+    /// 位置の昇順、同じ位置では置換後の文字の辞書順(五十音表の順)に並ぶ。
+    pub variants: Vec<SingleCharVariant>,
+}
+
+/// 復活の呪文の 1 文字を置換したもの。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SingleCharVariant {
+    /// 置換した位置 (`0..20`)。
+    pub position: usize,
+
+    /// 置換後の文字。
+    pub replacement: char,
+
+    /// 置換後の復活の呪文。
+    pub password: String,
+
+    /// 置換後の復活の呪文のデコード結果。
+    pub state: GameState,
+}
+
+impl RobustnessReport {
+    /// 1 文字の置換でデコード可能となるものの総数を返す。
+    pub fn variant_count(&self) -> usize {
+        self.variants.len()
+    }
+
+    /// 位置ごとに、その位置の 1 文字の置換でデコード可能となるものの個数を返す。
+    pub fn count_by_position(&self) -> [usize; 20] {
+        let mut counts = [0; 20];
+        for variant in &self.variants {
+            counts[variant.position] += 1;
+        }
+        counts
+    }
+}
+
+/// 復活の呪文の「壊れやすさ」を分析する。
+///
+/// 1 文字を置換したもので、元と異なるゲーム状態にデコードされるものを全て列挙する。
+/// 書き写しの誤りが気付かれずに別の状態として受理されうる箇所が分かる。
+///
+/// [`neighbors`] と同じ手法で列挙するので、高速である。
+///
+/// `password` がデコードできない場合、[`decode`](crate::decode()) と同様のエラーを返す。
+pub fn robustness(password: impl AsRef<str>) -> Dq1PasswordResult<RobustnessReport> {
+    let state = decode(&password)?;
+    let password = normalize_password(password)?;
+
+    let mut variants: Vec<_> = neighbors(&password, 1, usize::MAX)?
+        .into_iter()
+        .filter_map(|variant| {
+            let (position, replacement) = core::iter::zip(password.chars(), variant.chars())
+                .enumerate()
+                .find(|(_, (a, b))| a != b)
+                .map(|(i, (_, b))| (i, b))
+                .unwrap();
+            let variant_state = decode(&variant).unwrap();
+            (variant_state != state).then_some(SingleCharVariant {
+                position,
+                replacement,
+                password: variant,
+                state: variant_state,
+            })
+        })
+        .collect();
+    variants.sort_by_key(|v| (v.position, password_char_to_cum(v.replacement).unwrap()));
+
+    Ok(RobustnessReport { state, variants })
+}
+
+/// 累積値配列から 6bit 値配列の位置 `i` の値を求める。
+fn six_at(cums: &[u8; 20], i: usize) -> u8 {
+    let pre = if i == 0 { 0 } else { cums[i - 1] };
+    cums[i].wrapping_sub(pre + 4) & 0x3F
+}
+
+/// 6bit 値配列の位置 `i` の値 `six` の、CRC 下位バイトの検査への寄与を返す。
+///
+/// 全ての位置の寄与の XOR が 0 ならば CRC が一致する。
+fn crc_term(i: usize, six: u8) -> u8 {
+    const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+    const CRC_TABLE_TAIL: [[u8; 0x40]; 18] = crc8_table_tail();
+
+    match i {
+        // CRC 下位バイトの下位 6bit。
+        0 => six,
+        // CRC 下位バイトの上位 2bit と、CRC 計算対象の 4bit。
+        1 => (six << 6) ^ CRC_TABLE_HEAD[usize::from(six >> 2)],
+        _ => CRC_TABLE_TAIL[i - 2][usize::from(six)],
+    }
+}
+
+/// 累積値配列の一部の文字を置換したときの、寄与の XOR の差分を返す。
+fn crc_delta(cums: &[u8; 20], changes: &[(usize, u8)]) -> u8 {
+    let mut cums_new = *cums;
+    for &(p, cum) in changes {
+        cums_new[p] = cum;
+    }
+
+    // 位置 p の文字を変えると、6bit 値配列の位置 p, p+1 の値が変わる。
+    changes
+        .iter()
+        .flat_map(|&(p, _)| p..(p + 2).min(20))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .fold(0, |acc, i| {
+            acc ^ crc_term(i, six_at(cums, i)) ^ crc_term(i, six_at(&cums_new, i))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_cums(password: &str) -> Vec<u8> {
+        password
+            .chars()
+            .map(|c| password_char_to_cum(c).unwrap())
+            .collect()
+    }
+
+    /// 指定された位置たちのうち高々 `d` 箇所を置換するものを総当たりで求める。
+    fn brute_force(password: &str, d: usize, positions: &[usize]) -> Vec<String> {
+        let cs: Vec<_> = password.chars().collect();
+        let mut res = vec![];
+        for k in 1..=d {
+            for ps in positions.iter().copied().combinations(k) {
+                for chars in ps.iter().map(|_| 0..0x40).multi_cartesian_product() {
+                    let mut cs_new = cs.clone();
+                    for (&p, &cum) in core::iter::zip(&ps, &chars) {
+                        cs_new[p] = cum_to_password_char(cum);
+                    }
+                    if core::iter::zip(&cs, &cs_new)
+                        .filter(|(a, b)| a != b)
+                        .count()
+                        != k
+                    {
+                        continue;
+                    }
+                    let s: String = cs_new.into_iter().collect();
+                    if decode(&s).is_ok() {
+                        res.push(s);
+                    }
+                }
+            }
+        }
+        res.sort_by_key(|s| to_cums(s));
+        res
+    }
+
+    #[test]
+    fn test_neighbors_d1() {
+        const ALL: [usize; 20] = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        ];
+
+        for password in [
+            "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+            // 1 文字誤り(デコード不可)。
+            "ざぼちずどぢぎきつたあずせれえむるのぢえ",
+            "つにこへむゆるわげげだどべうきさそさには",
+        ] {
+            let expect = brute_force(password, 1, &ALL);
+            assert!(!expect.is_empty());
+            assert_eq!(neighbors(password, 1, usize::MAX), Ok(expect));
+        }
+
+        // 誤りを含む呪文の近傍に元の呪文が含まれる。
+        assert!(
+            neighbors("ざぼちずどぢぎきつたあずせれえむるのぢえ", 1, usize::MAX)
+                .unwrap()
+                .contains(&"ざぼちずどぢぎきつたうずせれえむるのぢえ".to_owned())
+        );
+
+        assert_eq!(
+            neighbors("ざぼちずどぢぎきつたうずせれえむるのぢえ", 1, 3)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_robustness() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        let report = robustness(PASSWORD).unwrap();
+        assert_eq!(report.state, decode(PASSWORD).unwrap());
+        // 1 文字の置換では同じゲーム状態にならないので、近傍と一致する。
+        let passwords: Vec<_> = report.variants.iter().map(|v| v.password.clone()).collect();
+        let mut expect = neighbors(PASSWORD, 1, usize::MAX).unwrap();
+        expect.sort_by_key(|p| {
+            let (i, c) = core::iter::zip(p.chars(), PASSWORD.chars())
+                .enumerate()
+                .find(|(_, (a, b))| a != b)
+                .map(|(i, (a, _))| (i, a))
+                .unwrap();
+            (i, password_char_to_cum(c).unwrap())
+        });
+        assert_eq!(passwords, expect);
+        assert!(report.variant_count() > 0);
+        assert_eq!(
+            report.count_by_position().iter().sum::<usize>(),
+            report.variant_count()
+        );
+        assert!(report
+            .variants
+            .windows(2)
+            .all(|w| w[0].position <= w[1].position));
+
+        let cs: Vec<_> = PASSWORD.chars().collect();
+        for variant in &report.variants {
+            let mut cs_variant = cs.clone();
+            cs_variant[variant.position] = variant.replacement;
+            assert_eq!(variant.password, cs_variant.iter().collect::<String>());
+            assert_eq!(decode(&variant.password), Ok(variant.state.clone()));
+            assert_ne!(variant.state, report.state);
+
+            // 置換したもの(元の呪文から見れば 1 文字の誤り)の近傍には元の呪文が含まれる。
+            assert!(neighbors(&variant.password, 1, usize::MAX)
+                .unwrap()
+                .contains(&PASSWORD.to_owned()));
+        }
+
+        // 対称性: 置換したものの分析結果にも元の呪文が現れる。
+        let variant = &report.variants[0];
+        let report_variant = robustness(&variant.password).unwrap();
+        assert!(report_variant
+            .variants
+            .iter()
+            .any(|v| v.position == variant.position
+                && v.replacement == cs[variant.position]
+                && v.password == PASSWORD));
+
+        assert!(robustness("ざぼちずどぢぎきつたうずせれえむるのぢお").is_err());
+    }
+
+    #[test]
+    fn test_neighbors_d2() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        let passwords = neighbors(PASSWORD, 2, usize::MAX).unwrap();
+        let cums = to_cums(PASSWORD);
+        assert!(passwords.iter().all(|p| p != PASSWORD));
+        assert!(passwords.iter().all(|p| decode(p).is_ok()));
+        assert!(passwords
+            .windows(2)
+            .all(|w| to_cums(&w[0]) < to_cums(&w[1])));
+        for p in &neighbors(PASSWORD, 1, usize::MAX).unwrap() {
+            assert!(passwords.contains(p));
+        }
+
+        // 置換位置が限られたものは総当たりと一致する。
+        const POSITIONS: [usize; 5] = [0, 1, 2, 9, 19];
+        let actual: Vec<_> = passwords
+            .iter()
+            .filter(|p| {
+                core::iter::zip(to_cums(p), &cums)
+                    .enumerate()
+                    .all(|(i, (a, &b))| a == b || POSITIONS.contains(&i))
+            })
+            .cloned()
+            .collect();
+        assert_eq!(actual, brute_force(PASSWORD, 2, &POSITIONS));
+
+        assert!(matches!(
+            neighbors(PASSWORD, 3, 10),
+            Err(Dq1PasswordError::InvalidConstraint(_))
+        ));
+        assert!(matches!(
+            neighbors("ゆうてい", 1, 10),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+    }
+}
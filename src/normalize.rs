@@ -1,12 +1,19 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use core::convert::TryFrom;
+use core::ops::Range;
+
 use itertools::Itertools as _;
 
 use crate::decode::password_char_to_cum;
-use crate::encode::pack_hero_name_char;
-use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::encode::{cum_to_password_char, pack_hero_name_char};
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, GameStateErrorKind, PatternErrorKind};
+use crate::prelude::*;
+use crate::validate::ENTERABLE_HERO_NAME_CHARS;
 
 /// 主人公の名前を正規化する。
 ///
-/// 濁点/半濁点の分離などを行い、4 文字に満たない場合 ASCII space でパディングする。
+/// 互換文字の畳み込み([`fold_compat`])、カタカナのひらがなへの変換、濁点/半濁点の分離などを行い、4 文字に満たない場合 ASCII space でパディングする。
 ///
 /// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
 pub fn normalize_hero_name(hero_name: impl AsRef<str>) -> Dq1PasswordResult<String> {
@@ -21,6 +28,7 @@ pub fn normalize_hero_name(hero_name: impl AsRef<str>) -> Dq1PasswordResult<Stri
 
     if cs.len() > 4 {
         return Err(Dq1PasswordError::invalid_game_state(
+            GameStateErrorKind::HeroNameTooLong,
             "主人公の名前は 4 文字以内でなければならない(濁点、半濁点は 1 文字と数える)",
         ));
     }
@@ -31,184 +39,966 @@ pub fn normalize_hero_name(hero_name: impl AsRef<str>) -> Dq1PasswordResult<Stri
         .collect();
 
     if !cs_invalid.is_empty() {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "主人公の名前に無効な文字が含まれている: {}",
-            cs_invalid
-                .into_iter()
-                .map(|c| format!("'{}'", c))
-                .join(", ")
-        )));
+        return Err(Dq1PasswordError::invalid_game_state(
+            GameStateErrorKind::HeroNameInvalidChars(cs_invalid.iter().map(|&&c| c).collect()),
+            format!(
+                "主人公の名前に無効な文字が含まれている: {}",
+                cs_invalid
+                    .into_iter()
+                    .map(|c| format!("'{}'", c))
+                    .join(", ")
+            ),
+        ));
     }
 
     Ok(cs
         .into_iter()
-        .chain(std::iter::repeat(' '))
+        .chain(core::iter::repeat(' '))
         .take(4)
         .collect())
 }
 
-fn normalize_hero_name_char(c: char) -> impl Iterator<Item = char> {
-    const MAP: phf::Map<char, &[char]> = phf::phf_map! {
-        // 数字
-        '０' => &['0'],
-        '１' => &['1'],
-        '２' => &['2'],
-        '３' => &['3'],
-        '４' => &['4'],
-        '５' => &['5'],
-        '６' => &['6'],
-        '７' => &['7'],
-        '８' => &['8'],
-        '９' => &['9'],
+/// [`normalize_hero_name`] と同じだが、入力が既に正規化されていれば借用をそのまま返す。
+pub fn normalize_hero_name_cow(hero_name: &str) -> Dq1PasswordResult<Cow<'_, str>> {
+    if is_exactly_n_chars(hero_name, 4, |c| pack_hero_name_char(c).is_some()) {
+        return Ok(Cow::Borrowed(hero_name));
+    }
+
+    normalize_hero_name(hero_name).map(Cow::Owned)
+}
+
+/// [`normalize_hero_name`] と同じだが、正規化後の各文字に対応する入力中の範囲も返す。
+///
+/// 濁音/半濁音などから分離した複数の文字は、いずれも元の 1 文字の範囲に対応する(例: "が" → "か゛")。
+/// パディングの空白は入力の末尾の空範囲に対応する。
+///
+/// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn normalize_hero_name_with_map(
+    hero_name: impl AsRef<str>,
+) -> Dq1PasswordResult<NormalizedWithMap> {
+    let hero_name = hero_name.as_ref();
+    let normalized = normalize_hero_name(hero_name)?;
+
+    let spans = hero_name
+        .char_indices()
+        .flat_map(|(offset, c)| {
+            let span = offset..offset + c.len_utf8();
+            normalize_hero_name_char(c).map(move |_| span.clone())
+        })
+        .chain(core::iter::repeat(hero_name.len()..hero_name.len()))
+        .take(4)
+        .collect();
+
+    Ok(NormalizedWithMap { normalized, spans })
+}
+
+/// `s` がちょうど `n` 文字で、全ての文字が `pred` を満たすかどうかを返す。
+/// [`normalize_hero_name`] と同じだが、主人公の名前に使えない一部のかなを近い文字に置き換えてから正規化する。
+///
+/// 置き換えるもの(カタカナ、半角カタカナも同様):
+///
+/// * 小さい母音 'ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ' → 'あ', 'い', 'う', 'え', 'お'
+/// * 'ゎ' → 'わ'
+/// * 'ゐ', 'ゑ' → 'い', 'え'
+///
+/// 戻り値は正規化された名前と、行った置き換えたち(入力中の順)。
+/// 置き換えの結果がユーザーの意図と合っているかは、呼び出し側で確認すること。
+///
+/// 置き換えた後の `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn normalize_hero_name_lenient(
+    hero_name: impl AsRef<str>,
+) -> Dq1PasswordResult<(String, Vec<HeroNameSubstitution>)> {
+    let mut substitutions = vec![];
+    let replaced: String = hero_name
+        .as_ref()
+        .chars()
+        .enumerate()
+        .map(
+            |(position, c)| match lenient_kana(katakana_to_hiragana(fold_compat(c))) {
+                Some(to) => {
+                    substitutions.push(HeroNameSubstitution {
+                        position,
+                        from: c,
+                        to,
+                    });
+                    to
+                }
+                None => c,
+            },
+        )
+        .collect();
+
+    Ok((normalize_hero_name(replaced)?, substitutions))
+}
+
+/// [`normalize_hero_name_lenient`] で行った置き換え。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct HeroNameSubstitution {
+    /// 入力中の位置(文字単位)。
+    pub position: usize,
+
+    /// 入力中の元の文字。
+    pub from: char,
+
+    /// 置き換え後の文字。
+    pub to: char,
+}
+
+/// 主人公の名前に使えないかなのうち、近い文字に置き換えられるものについて、置き換え後の文字を返す。
+fn lenient_kana(c: char) -> Option<char> {
+    match c {
+        'ぁ' => Some('あ'),
+        'ぃ' => Some('い'),
+        'ぅ' => Some('う'),
+        'ぇ' => Some('え'),
+        'ぉ' => Some('お'),
+        'ゎ' => Some('わ'),
+        'ゐ' => Some('い'),
+        'ゑ' => Some('え'),
+        _ => None,
+    }
+}
+
+fn is_exactly_n_chars(s: &str, n: usize, pred: impl Fn(char) -> bool) -> bool {
+    let mut len = 0;
+    for c in s.chars() {
+        if len == n || !pred(c) {
+            return false;
+        }
+        len += 1;
+    }
+
+    len == n
+}
+
+/// 主人公の名前の入力画面で、直後に '゛' を付けられる文字たち。
+pub(crate) const HERO_NAME_VOICEABLE: &str = "かきくけこさしすせそたちつてとはひふへほう";
+
+/// 主人公の名前の入力画面で、直後に '゜' を付けられる文字たち。
+pub(crate) const HERO_NAME_SEMI_VOICEABLE: &str = "はひふへほ";
+
+/// 主人公の名前の 1 文字を正規化する。濁音/半濁音などは複数の文字になる。有効かどうかは関知しない。
+pub(crate) fn normalize_hero_name_char(c: char) -> impl Iterator<Item = char> {
+    // カタカナ(全角/半角)はひらがなに変換してから扱う。長音は 'ー' を経て '-' になる。
+    let c = katakana_to_hiragana(fold_compat(c));
+
+    let cs: Option<&[char]> = match c {
         // 濁点/半濁点
-        'が' => &['か', '゛'],
-        'ぎ' => &['き', '゛'],
-        'ぐ' => &['く', '゛'],
-        'げ' => &['け', '゛'],
-        'ご' => &['こ', '゛'],
-        'ざ' => &['さ', '゛'],
-        'じ' => &['し', '゛'],
-        'ず' => &['す', '゛'],
-        'ぜ' => &['せ', '゛'],
-        'ぞ' => &['そ', '゛'],
-        'だ' => &['た', '゛'],
-        'ぢ' => &['ち', '゛'],
-        'づ' => &['つ', '゛'],
-        'で' => &['て', '゛'],
-        'ど' => &['と', '゛'],
-        'ば' => &['は', '゛'],
-        'び' => &['ひ', '゛'],
-        'ぶ' => &['ふ', '゛'],
-        'べ' => &['へ', '゛'],
-        'ぼ' => &['ほ', '゛'],
-        'ぱ' => &['は', '゜'],
-        'ぴ' => &['ひ', '゜'],
-        'ぷ' => &['ふ', '゜'],
-        'ぺ' => &['へ', '゜'],
-        'ぽ' => &['ほ', '゜'],
-        '\u{3094}' => &['う', '゛'], // 「う」に濁点
-        '\u{3099}' => &['゛'], // 結合文字用濁点
-        '\u{309A}' => &['゜'], // 結合文字用半濁点
+        'が' => Some(&['か', '゛']),
+        'ぎ' => Some(&['き', '゛']),
+        'ぐ' => Some(&['く', '゛']),
+        'げ' => Some(&['け', '゛']),
+        'ご' => Some(&['こ', '゛']),
+        'ざ' => Some(&['さ', '゛']),
+        'じ' => Some(&['し', '゛']),
+        'ず' => Some(&['す', '゛']),
+        'ぜ' => Some(&['せ', '゛']),
+        'ぞ' => Some(&['そ', '゛']),
+        'だ' => Some(&['た', '゛']),
+        'ぢ' => Some(&['ち', '゛']),
+        'づ' => Some(&['つ', '゛']),
+        'で' => Some(&['て', '゛']),
+        'ど' => Some(&['と', '゛']),
+        'ば' => Some(&['は', '゛']),
+        'び' => Some(&['ひ', '゛']),
+        'ぶ' => Some(&['ふ', '゛']),
+        'べ' => Some(&['へ', '゛']),
+        'ぼ' => Some(&['ほ', '゛']),
+        'ぱ' => Some(&['は', '゜']),
+        'ぴ' => Some(&['ひ', '゜']),
+        'ぷ' => Some(&['ふ', '゜']),
+        'ぺ' => Some(&['へ', '゜']),
+        'ぽ' => Some(&['ほ', '゜']),
+        '\u{3094}' => Some(&['う', '゛']), // 「う」に濁点
+        '\u{3099}' => Some(&['゛']),       // 結合文字用濁点
+        '\u{309A}' => Some(&['゜']),       // 結合文字用半濁点
+        '\u{FF9E}' => Some(&['゛']),       // 半角濁点
+        '\u{FF9F}' => Some(&['゜']),       // 半角半濁点
         // ハイフン
-        '\u{2010}' => &['-'], // hyphen
-        '\u{2011}' => &['-'], // non-breaking hyphen
-        '\u{2012}' => &['-'], // figure dash
-        '\u{2013}' => &['-'], // en dash
-        '\u{2014}' => &['-'], // em dash
-        '\u{2015}' => &['-'], // horizontal bar
-        '\u{2212}' => &['-'], // minus sign
-        '\u{30FC}' => &['-'], // 全角長音
-        '\u{FF70}' => &['-'], // 半角長音
-        // 空白
-        '\u{3000}' => &[' '], // 全角空白
+        '\u{2010}' => Some(&['-']), // hyphen
+        '\u{2011}' => Some(&['-']), // non-breaking hyphen
+        '\u{2012}' => Some(&['-']), // figure dash
+        '\u{2013}' => Some(&['-']), // en dash
+        '\u{2014}' => Some(&['-']), // em dash
+        '\u{2015}' => Some(&['-']), // horizontal bar
+        '\u{2212}' => Some(&['-']), // minus sign
+        '\u{30FC}' => Some(&['-']), // 全角長音
+        '\u{FF70}' => Some(&['-']), // 半角長音
+        _ => None,
     };
 
-    MAP.get(&c).map_or_else(
-        || itertools::Either::Left(std::iter::once(c)),
+    cs.map_or_else(
+        || itertools::Either::Left(core::iter::once(c)),
         |cs| itertools::Either::Right(cs.iter().copied()),
     )
 }
 
+/// 主人公の名前を、ゲーム内の名前入力画面で入力可能なものに限って正規化する。
+/// 戻り値は有効であることが保証される。
+///
+/// [`normalize_hero_name`] の条件に加え、正規化後の名前が以下を全て満たさなければならない:
+///
+/// * 全ての文字が [`ENTERABLE_HERO_NAME_CHARS`] に含まれる(数字は入力できない)
+/// * '゛' の直前は濁音化できる文字(か行、さ行、た行、は行、'う')。よって先頭に '゛' は置けない
+/// * '゜' の直前は半濁音化できる文字(は行)。よって先頭に '゜' は置けない
+/// * 先頭が空白ならば、全て空白(空白で始まる名前は入力できない。途中の空白は入力できる)
+///
+/// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn normalize_hero_name_strict(hero_name: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let hero_name = normalize_hero_name(hero_name)?;
+    let cs: Vec<_> = hero_name.chars().collect();
+
+    if let Some(c) = cs.iter().find(|c| !ENTERABLE_HERO_NAME_CHARS.contains(c)) {
+        return Err(Dq1PasswordError::invalid_game_state(
+            GameStateErrorKind::HeroNameNotEnterable(*c),
+            format!("主人公の名前に入力できない文字が含まれている: '{}'", c),
+        ));
+    }
+
+    for (i, &c) in cs.iter().enumerate() {
+        let pre = i.checked_sub(1).map(|i| cs[i]);
+        let ok = match c {
+            '゛' => pre.is_some_and(|pre| HERO_NAME_VOICEABLE.contains(pre)),
+            '゜' => pre.is_some_and(|pre| HERO_NAME_SEMI_VOICEABLE.contains(pre)),
+            _ => true,
+        };
+        if !ok {
+            return Err(Dq1PasswordError::invalid_game_state(
+                GameStateErrorKind::HeroNameMisplacedMark { pos: i, mark: c },
+                format!(
+                    "主人公の名前の {} 文字目の '{}' は直前の文字に付けられない",
+                    i + 1,
+                    c
+                ),
+            ));
+        }
+    }
+
+    if cs[0] == ' ' && cs.iter().any(|&c| c != ' ') {
+        return Err(Dq1PasswordError::invalid_game_state(
+            GameStateErrorKind::HeroNameLeadingSpace,
+            "主人公の名前が空白で始まっている",
+        ));
+    }
+
+    Ok(hero_name)
+}
+
+/// 主人公の名前を表示用の形にする。
+///
+/// [`normalize_hero_name`] で正規化した上で、分離した濁点/半濁点を直前の文字と合成し(例: "か゛" → "が")、
+/// 末尾のパディングの空白を除去する。合成できない濁点/半濁点はそのまま残す。
+///
+/// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn display_hero_name(hero_name: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let hero_name = normalize_hero_name(hero_name)?;
+
+    Ok(fold_kana(hero_name.trim_end_matches(' ').chars())
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect())
+}
+
 /// 復活の呪文の形式を正規化する(デコード可能かどうかは関知しない)。
 /// 戻り値は有効な形式であることが保証される。
 ///
-/// 空白文字を除去する。
+/// 互換文字を畳み込み([`fold_compat`])、空白文字を除去し、カタカナ(全角/半角)をひらがなに変換する。
+/// また、分離した濁点/半濁点は直前の文字と合成する(例: "か゛" → "が")。
 ///
 /// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
 pub fn normalize_password(password: impl AsRef<str>) -> Dq1PasswordResult<String> {
     // validation もこちらで行う。
 
-    let cs: Vec<_> = password
-        .as_ref()
-        .chars()
-        .flat_map(normalize_password_char)
-        .take(20 + 1)
-        .collect();
+    let folded = normalize_password_fragment(password.as_ref());
 
-    if cs.len() != 20 {
-        return Err(Dq1PasswordError::invalid_password(
+    if folded.len() != 20 {
+        return Err(Dq1PasswordError::invalid_password_length(
+            folded.len(),
             "復活の呪文はちょうど 20 文字でなければならない(ただし空白文字は無視される)",
         ));
     }
 
-    let cs_invalid: Vec<_> = cs
+    let cs_invalid: Vec<_> = folded
         .iter()
-        .filter(|&&c| password_char_to_cum(c).is_none())
+        .enumerate()
+        .filter(|(_, (c, _))| password_char_to_cum(*c).is_none())
         .collect();
 
     if !cs_invalid.is_empty() {
-        return Err(Dq1PasswordError::invalid_password(format!(
-            "復活の呪文に無効な文字が含まれている: {}",
-            cs_invalid
-                .into_iter()
-                .map(|c| format!("'{}'", c))
-                .join(", ")
-        )));
+        return Err(Dq1PasswordError::invalid_password_chars(
+            cs_invalid.iter().map(|&(i, &(c, _))| (i, c)).collect(),
+            format!(
+                "復活の呪文に無効な文字が含まれている: {}",
+                cs_invalid
+                    .into_iter()
+                    .map(|(_, (_, original))| format!("'{}'", original))
+                    .join(", ")
+            ),
+        ));
     }
 
-    Ok(cs.into_iter().collect())
+    Ok(folded.into_iter().map(|(c, _)| c).collect())
+}
+
+/// [`normalize_password`] と同じだが、入力が既に正規化されていれば借用をそのまま返す。
+///
+/// 大量の入力を扱う場合、正規化済みの入力(よくあるケース)に対してアロケーションを行わずに済む。
+pub fn normalize_password_cow(password: &str) -> Dq1PasswordResult<Cow<'_, str>> {
+    if is_exactly_n_chars(password, 20, |c| password_char_to_cum(c).is_some()) {
+        return Ok(Cow::Borrowed(password));
+    }
+
+    normalize_password(password).map(Cow::Owned)
+}
+
+/// [`normalize_password`] と同じだが、正規化後の各文字に対応する入力中の範囲も返す。
+///
+/// 除去された空白文字はどの範囲にも含まれない。合成された濁点/半濁点は直前の文字の範囲に含まれる
+/// (例: "か゛" → "が" の範囲は 2 文字分)。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn normalize_password_with_map(
+    password: impl AsRef<str>,
+) -> Dq1PasswordResult<NormalizedWithMap> {
+    let password = password.as_ref();
+    let normalized = normalize_password(password)?;
+
+    let spans = folded_spans(password, &normalize_password_fragment(password));
+
+    Ok(NormalizedWithMap { normalized, spans })
+}
+
+/// 復活の呪文が、ゲーム内の表示通りに書かれていることを検査する。戻り値は正規化されたもの(つまり入力と同じもの)。
+///
+/// [`normalize_password`] で何らかの変更(空白文字の除去、互換文字やカタカナの変換、濁点/半濁点の合成)が
+/// 必要となる入力は拒否する。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+/// 形式は有効だが正規形でない場合、原因は `PasswordErrorKind::NotCanonical` となり、
+/// 変更される文字とその位置が示される。
+pub fn normalize_password_strict(password: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let password = password.as_ref();
+
+    let normalized = match normalize_password_cow(password)? {
+        Cow::Borrowed(normalized) => return Ok(normalized.to_owned()),
+        Cow::Owned(normalized) if normalized == password => return Ok(normalized),
+        Cow::Owned(normalized) => normalized,
+    };
+
+    let cs_normalized: Vec<_> = normalized.chars().collect();
+    let changes: Vec<_> = analyze_password(password)
+        .chars
+        .iter()
+        .enumerate()
+        .filter_map(|(i, ca)| match ca.status {
+            PasswordCharStatus::Stripped => Some((i, ca.c, None)),
+            PasswordCharStatus::Kept { position } if cs_normalized[position] != ca.c => {
+                Some((i, ca.c, Some(cs_normalized[position])))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let msg = format!(
+        "復活の呪文が正規形でない: {}",
+        changes
+            .iter()
+            .map(|&(i, c, to)| match to {
+                Some(to) => format!("位置 {} の '{}' は '{}' になる", i, c, to),
+                None => format!("位置 {} の '{}' は除去される", i, c),
+            })
+            .join(", ")
+    );
+    Err(Dq1PasswordError::password_not_canonical(changes, msg))
+}
+
+/// 入力された復活の呪文の各文字の扱いを調べる。
+///
+/// 入力の各文字について、[`normalize_password`] で残るか、空白文字として除去されるか、
+/// 無効な文字として拒否されるかを、入力中のバイトオフセットおよび正規化後の位置とともに返す。
+/// 入力途中の文字列に対しても使える(長さが誤っていても各文字の分類は行う)。
+pub fn analyze_password(input: impl AsRef<str>) -> PasswordAnalysis {
+    let input = input.as_ref();
+
+    let folded = fold_kana(
+        input
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(fold_compat),
+    );
+
+    // 正規化後の各文字が、空白文字以外の入力文字をいくつ消費したか。
+    let mut positions = folded.iter().enumerate().flat_map(|(pos, (c, original))| {
+        let valid = password_char_to_cum(*c).is_some();
+        core::iter::repeat_n((pos, valid), original.chars().count())
+    });
+
+    let chars = input
+        .char_indices()
+        .map(|(offset, c)| {
+            let status = if c.is_whitespace() {
+                PasswordCharStatus::Stripped
+            } else {
+                match positions.next().unwrap() {
+                    (position, true) => PasswordCharStatus::Kept { position },
+                    (position, false) => PasswordCharStatus::Rejected { position },
+                }
+            };
+            PasswordCharAnalysis { offset, c, status }
+        })
+        .collect();
+
+    let normalized = normalize_password(input).ok();
+
+    PasswordAnalysis { chars, normalized }
+}
+
+/// [`analyze_password`] の結果。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PasswordAnalysis {
+    /// 入力の各文字の分類(入力順)。
+    pub chars: Vec<PasswordCharAnalysis>,
+
+    /// 正規化された復活の呪文。入力の形式が無効な場合は `None`。
+    pub normalized: Option<String>,
+}
+
+impl PasswordAnalysis {
+    /// 入力の形式が有効かどうかを返す。
+    pub fn is_valid(&self) -> bool {
+        self.normalized.is_some()
+    }
+
+    /// 無効な文字として拒否された入力文字たちを返す。
+    pub fn rejected(&self) -> impl Iterator<Item = &PasswordCharAnalysis> {
+        self.chars
+            .iter()
+            .filter(|ca| matches!(ca.status, PasswordCharStatus::Rejected { .. }))
+    }
+
+    /// 正規化後の文字数(空白文字を除き、濁点/半濁点を合成した後のもの)を返す。
+    ///
+    /// 拒否された文字も数える。
+    pub fn normalized_len(&self) -> usize {
+        self.chars
+            .iter()
+            .filter_map(|ca| ca.status.position())
+            .max()
+            .map_or(0, |pos| pos + 1)
+    }
+}
+
+/// 入力された復活の呪文の 1 文字の分類。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PasswordCharAnalysis {
+    /// 入力中のバイトオフセット。
+    pub offset: usize,
+
+    /// 入力中の文字。
+    pub c: char,
+
+    /// 分類。
+    pub status: PasswordCharStatus,
+}
+
+/// 入力された復活の呪文の 1 文字の扱い。
+///
+/// `position` は正規化後の位置。分離した濁点/半濁点は直前の文字と合成されるため、
+/// その文字と同じ位置になる。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PasswordCharStatus {
+    /// 正規化後も残る(カタカナなどは変換される)。
+    Kept { position: usize },
+
+    /// 空白文字として除去される。
+    Stripped,
+
+    /// 無効な文字として拒否される。
+    Rejected { position: usize },
+}
+
+impl PasswordCharStatus {
+    /// 正規化後の位置を返す。除去される場合は `None` を返す。
+    pub fn position(self) -> Option<usize> {
+        match self {
+            Self::Kept { position } | Self::Rejected { position } => Some(position),
+            Self::Stripped => None,
+        }
+    }
+}
+
+/// [`normalize_password_with_map`] などの結果。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NormalizedWithMap {
+    /// 正規化された文字列。
+    pub normalized: String,
+
+    /// 正規化後の各文字に対応する、入力中のバイト範囲。
+    pub spans: Vec<Range<usize>>,
+}
+
+/// [`fold_kana`] の結果の各文字に対応する、`input` 中のバイト範囲を返す。
+///
+/// `input` の空白文字以外の各文字が、`folded` の元の文字列を順に構成しているものとする。
+fn folded_spans(input: &str, folded: &[(char, String)]) -> Vec<Range<usize>> {
+    let mut kept = input
+        .char_indices()
+        .filter(|&(_, c)| !fold_compat(c).is_whitespace())
+        .map(|(offset, c)| offset..offset + c.len_utf8());
+
+    folded
+        .iter()
+        .map(|(_, original)| {
+            let first = kept.next().unwrap();
+            let last = kept.by_ref().take(original.chars().count() - 1).last();
+            first.start..last.map_or(first.end, |last| last.end)
+        })
+        .collect()
+}
+
+/// 互換文字を対応する通常の文字に畳み込む。畳み込む対象でなければそのまま返す。
+///
+/// 全ての正規化関数([`normalize_hero_name`], [`normalize_password`], [`normalize_pattern`] など)は、
+/// 入力の各文字にまずこれを適用する。よって、見た目が同等な入力はどの正規化関数でも同じように扱われる。
+///
+/// 畳み込むもの:
+///
+/// * 全角 ASCII (U+FF01..=U+FF5E) → ASCII (例: '０' → '0', '？' → '?', 'Ａ' → 'A')
+/// * 丸数字 (⓪, ①..=⑨) → 数字
+/// * 各種の空白 (全角空白 U+3000, NBSP U+00A0, U+2000..=U+200A, U+202F, U+205F) → ASCII space
+///
+/// 畳み込まないもの(必要に応じて各正規化関数が個別に扱う):
+///
+/// * カタカナ(全角/半角)、濁点/半濁点。これらは各正規化関数でひらがなへの変換や合成/分離を行う
+/// * ハイフン類 (U+2010..=U+2015, U+2212 など)。主人公の名前でのみ '-' とみなす
+///   (パターンでは '-' が範囲の意味を持つため)
+/// * 10 以上の丸数字、括弧付き文字、合字などその他の互換文字
+pub fn fold_compat(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(u32::from(c) - 0xFF01 + 0x21).unwrap(),
+        '\u{24EA}' => '0',
+        '\u{2460}'..='\u{2468}' => char::from_u32(u32::from(c) - 0x2460 + u32::from('1')).unwrap(),
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// カタカナ(全角/半角)をひらがなに変換する。それ以外の文字(半角の濁点/半濁点を含む)はそのまま返す。
+fn katakana_to_hiragana(c: char) -> char {
+    // 半角カタカナ U+FF66..=U+FF9D に対応するひらがな。
+    const HALF_WIDTH: &str = "をぁぃぅぇぉゃゅょっーあいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわん";
+
+    match c {
+        // ァ..=ヶ
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(u32::from(c) - 0x60).unwrap(),
+        '\u{FF66}'..='\u{FF9D}' => HALF_WIDTH
+            .chars()
+            .nth(usize::try_from(u32::from(c) - 0xFF66).unwrap())
+            .unwrap(),
+        _ => c,
+    }
+}
+
+/// カタカナ(全角/半角)をひらがなに変換し、分離した濁点/半濁点を直前の文字と合成する。
+///
+/// 戻り値の各要素は (変換後の文字, それに対応する元の文字列)。
+/// 変換できないものはそのまま残す(有効かどうかは呼び出し側で判定する)。
+fn fold_kana(cs: impl IntoIterator<Item = char>) -> Vec<(char, String)> {
+    const VOICEABLE: &str = "かきくけこさしすせそたちつてとはひふへほ";
+    const SEMI_VOICEABLE: &str = "はひふへほ";
+
+    let mut folded: Vec<(char, String)> = vec![];
+
+    for c in cs {
+        let hira = katakana_to_hiragana(c);
+
+        // 濁点/半濁点は、直前の文字が対応していれば合成する。
+        let delta = match hira {
+            '゛' | '\u{3099}' | '\u{FF9E}' => Some((VOICEABLE, 1)),
+            '゜' | '\u{309A}' | '\u{FF9F}' => Some((SEMI_VOICEABLE, 2)),
+            _ => None,
+        };
+        if let Some((bases, delta)) = delta {
+            if let Some((prev, original)) = folded.last_mut() {
+                if bases.contains(*prev) {
+                    *prev = char::from_u32(u32::from(*prev) + delta).unwrap();
+                    original.push(c);
+                    continue;
+                }
+            }
+        }
+
+        folded.push((hira, c.to_string()));
+    }
+
+    folded
+}
+
+/// 復活の呪文の一部を [`normalize_password`] と同様に正規化する(長さや文字の有効性は関知しない)。
+///
+/// 戻り値の各要素は (変換後の文字, それに対応する元の文字列)。
+pub(crate) fn normalize_password_fragment(fragment: &str) -> Vec<(char, String)> {
+    fold_kana(fragment.chars().flat_map(normalize_password_char))
 }
 
 fn normalize_password_char(c: char) -> impl Iterator<Item = char> {
+    let c = fold_compat(c);
     let cs = match c {
         _ if c.is_whitespace() => Some(&[]),
         _ => None,
     };
 
     cs.map_or_else(
-        || itertools::Either::Left(std::iter::once(c)),
+        || itertools::Either::Left(core::iter::once(c)),
         |cs| itertools::Either::Right(cs.iter().copied()),
     )
 }
 
 /// 復活の呪文パターンを正規化する。戻り値は有効であることが保証される。
 ///
-/// 空白文字を除去し、全角の '？' を '?' に置換する。
+/// 互換文字を畳み込み([`fold_compat`]。全角の '？', '［', '］' なども半角になる)、空白文字を除去する。
+/// また、[`normalize_password`] と同様にカタカナや分離した濁点/半濁点を変換する。
+/// これらは文字クラスの構文解析より前に行われる。
+///
+/// パターンの各位置は、文字、'?'、文字クラス(`[かが]` のように許す文字を並べたもの)、
+/// または否定文字クラス(`[^ぼ]` のように除外する文字を並べたもの)のいずれか。
+/// 文字クラス内には範囲(`[あ-お]` のように、五十音表の順で始点から終点までの文字たち)も書け、
+/// '/' は区切りとして無視される。
+/// 文字クラスは 1 文字と数える。全ての文字を除外する否定文字クラスは無効。
+///
+/// '?' と文字クラスの直後には、グループ番号として数字 1-9 (半角/全角どちらも可)を付けられる。
+/// 同じグループ番号の位置たちは同じ文字でなければならない(例: `?1` と `?1`)。
+/// グループ番号は 1 文字とは数えない。
+///
+/// '*' (半角/全角どちらも可)は、全体が 20 文字になるように 1 個以上の '?' に展開される。
+/// '*' は高々 1 個しか使えない。戻り値は展開後のものとなる。
 ///
 /// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
 pub fn normalize_pattern(pattern: impl AsRef<str>) -> Dq1PasswordResult<String> {
     // validation もこちらで行う。
 
-    let cs: Vec<_> = pattern
-        .as_ref()
-        .chars()
-        .flat_map(normalize_pattern_char)
-        .take(20 + 1)
-        .collect();
+    let folded = fold_pattern_fragment(pattern.as_ref());
+    let mut cs: Vec<_> = folded.iter().map(|&(c, _)| c).collect();
+
+    let mut slots = split_pattern_slots(&cs)?;
 
-    if cs.len() != 20 {
-        return Err(Dq1PasswordError::invalid_pattern(
+    let n_star = slots
+        .iter()
+        .filter(|(slot, _)| matches!(slot, PatternSlot::Star))
+        .count();
+    if n_star > 1 {
+        return Err(Dq1PasswordError::invalid_pattern_syntax(
+            PatternErrorKind::MultipleStars,
+            "パターンに '*' を複数含めることはできない",
+        ));
+    }
+    if n_star == 1 {
+        let n_rest = slots.len() - 1;
+        if n_rest >= 20 {
+            return Err(Dq1PasswordError::invalid_pattern_length(
+                n_rest,
+                "'*' 以外の部分が既に 20 文字以上あるため、'*' を展開できない",
+            ));
+        }
+        // 文字クラス内の '*' はエラーになるので、'*' はここで見つかる 1 個のみ。
+        let idx = cs.iter().position(|&c| c == '*').unwrap();
+        cs.splice(idx..=idx, core::iter::repeat_n('?', 20 - n_rest));
+        slots = split_pattern_slots(&cs)?;
+    }
+
+    if slots.len() != 20 {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            slots.len(),
             "パターンはちょうど 20 文字でなければならない(ただし空白文字は無視される)",
         ));
     }
 
-    let cs_invalid: Vec<_> = cs
+    let offenders: Vec<_> = slots
         .iter()
-        .filter(|&&c| password_char_to_cum(c).is_none() && c != '?')
+        .enumerate()
+        .filter_map(|(i, (slot, _))| match *slot {
+            PatternSlot::Char(c) if password_char_to_cum(c).is_none() => Some((i, c)),
+            _ => None,
+        })
         .collect();
 
-    if !cs_invalid.is_empty() {
-        return Err(Dq1PasswordError::invalid_password(format!(
+    if !offenders.is_empty() {
+        // エラーメッセージには、変換前の元の文字列を示す。
+        let original_of = |c: char| {
+            folded
+                .iter()
+                .find(|&&(folded_c, _)| folded_c == c)
+                .map_or_else(|| c.to_string(), |(_, original)| original.clone())
+        };
+        let msg = format!(
             "パターンに無効な文字が含まれている: {}",
-            cs_invalid
-                .into_iter()
-                .map(|c| format!("'{}'", c))
+            offenders
+                .iter()
+                .map(|&(i, c)| format!("位置 {} の '{}'", i, original_of(c)))
                 .join(", ")
-        )));
+        );
+        return Err(Dq1PasswordError::invalid_pattern_chars(offenders, msg));
     }
 
     Ok(cs.into_iter().collect())
 }
 
+/// [`normalize_pattern`] と同じだが、入力が既に正規化されていれば借用をそのまま返す。
+///
+/// 文字と '?' のみからなる 20 文字のパターンが正規化済みとみなされる。
+pub fn normalize_pattern_cow(pattern: &str) -> Dq1PasswordResult<Cow<'_, str>> {
+    if is_exactly_n_chars(pattern, 20, |c| {
+        c == '?' || password_char_to_cum(c).is_some()
+    }) {
+        return Ok(Cow::Borrowed(pattern));
+    }
+
+    normalize_pattern(pattern).map(Cow::Owned)
+}
+
+/// [`normalize_pattern`] と同じだが、正規化後の各文字に対応する入力中の範囲も返す。
+///
+/// 範囲の対応は [`normalize_password_with_map`] と同様。文字クラスの括弧やグループ番号も 1 文字ずつ対応する。
+/// '*' から展開された '?' たちは、いずれも '*' の範囲に対応する。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn normalize_pattern_with_map(
+    pattern: impl AsRef<str>,
+) -> Dq1PasswordResult<NormalizedWithMap> {
+    let pattern = pattern.as_ref();
+    let normalized = normalize_pattern(pattern)?;
+
+    let folded = fold_kana(pattern.chars().flat_map(normalize_pattern_char));
+    let mut spans = folded_spans(pattern, &folded);
+
+    let len = normalized.chars().count();
+    if len != folded.len() {
+        // '*' が展開されている。
+        let idx = folded.iter().position(|&(c, _)| c == '*').unwrap();
+        let span = spans[idx].clone();
+        spans.splice(
+            idx..=idx,
+            core::iter::repeat_n(span, len + 1 - folded.len()),
+        );
+    }
+
+    Ok(NormalizedWithMap { normalized, spans })
+}
+
+/// パターンの 1 位置分の要素。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum PatternSlot {
+    /// 任意の文字('?')。
+    Any,
+
+    /// 残りを埋める任意の文字列('*')。[`normalize_pattern`] で '?' たちに展開される。
+    Star,
+
+    /// 固定された文字。復活の呪文に使える文字かどうかはチェックしない。
+    Char(char),
+
+    /// 文字クラス(`[...]`)内の文字たち。
+    Class(Vec<char>),
+
+    /// 否定文字クラス(`[^...]`)内の文字たち。これら以外の文字にマッチする。
+    NegatedClass(Vec<char>),
+}
+
+/// 空白文字の除去などを済ませたパターンの文字たちを、位置ごとの要素とそのグループ番号に分割する。
+///
+/// '?' や文字クラスの直後の数字 1-9 はグループ番号で、同じ番号の位置は同じ文字でなければならない。
+///
+/// 文字クラスの構文が無効な場合や、文字クラス内に無効な文字が含まれる場合、
+/// `Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub(crate) fn split_pattern_slots(
+    cs: &[char],
+) -> Dq1PasswordResult<Vec<(PatternSlot, Option<u8>)>> {
+    let mut slots = vec![];
+
+    let mut it = cs.iter().copied().peekable();
+    while let Some(c) = it.next() {
+        let pos = slots.len();
+        let slot = match c {
+            '?' => PatternSlot::Any,
+            '*' => PatternSlot::Star,
+            ']' => {
+                return Err(Dq1PasswordError::invalid_pattern_syntax(
+                    PatternErrorKind::UnmatchedClose { pos },
+                    format!("位置 {} の ']' に対応する '[' がない", pos),
+                ))
+            }
+            '[' => {
+                let negated = it.next_if_eq(&'^').is_some();
+                let mut items = vec![];
+                loop {
+                    match it.next() {
+                        None => {
+                            return Err(Dq1PasswordError::invalid_pattern_syntax(
+                                PatternErrorKind::Unclosed { pos },
+                                format!("位置 {} の '[' が閉じられていない", pos),
+                            ))
+                        }
+                        Some('[') => {
+                            return Err(Dq1PasswordError::invalid_pattern_syntax(
+                                PatternErrorKind::NestedClass { pos },
+                                format!("位置 {} の文字クラスが入れ子になっている", pos),
+                            ))
+                        }
+                        Some(']') => break,
+                        Some(c) => items.push(c),
+                    }
+                }
+                let class = parse_class_items(pos, &items)?;
+                if class.is_empty() {
+                    return Err(Dq1PasswordError::invalid_pattern_syntax(
+                        PatternErrorKind::EmptyClass { pos },
+                        format!("位置 {} の文字クラスが空", pos),
+                    ));
+                }
+                if negated {
+                    let n_excluded = class
+                        .iter()
+                        .map(|&c| password_char_to_cum(c))
+                        .collect::<BTreeSet<_>>()
+                        .len();
+                    if n_excluded == 0x40 {
+                        return Err(Dq1PasswordError::invalid_pattern_syntax(
+                            PatternErrorKind::ClassExcludesAll { pos },
+                            format!("位置 {} の否定文字クラスが全ての文字を除外している", pos),
+                        ));
+                    }
+                    PatternSlot::NegatedClass(class)
+                } else {
+                    PatternSlot::Class(class)
+                }
+            }
+            c => PatternSlot::Char(c),
+        };
+        let group = match slot {
+            PatternSlot::Any | PatternSlot::Class(_) | PatternSlot::NegatedClass(_) => it
+                .next_if(|c| ('1'..='9').contains(c))
+                .map(|c| c.to_digit(10).unwrap() as u8),
+            _ => None,
+        };
+        slots.push((slot, group));
+    }
+
+    Ok(slots)
+}
+
+/// 位置 `pos` の文字クラスの括弧内の文字たちを解析し、クラスに含まれる文字たちを返す。
+///
+/// 括弧内には文字と範囲(`あ-お` のように、五十音表の順で始点から終点までの文字たち)を並べられる。
+/// '/' は区切りとして無視される。
+fn parse_class_items(pos: usize, items: &[char]) -> Dq1PasswordResult<Vec<char>> {
+    let mut class = vec![];
+
+    let mut i = 0;
+    while i < items.len() {
+        match items[i..] {
+            ['/', ..] => i += 1,
+            [first, '-', ..] => {
+                let last = match items.get(i + 2) {
+                    Some(&last) if last != '/' && last != '-' => last,
+                    _ => {
+                        return Err(Dq1PasswordError::invalid_pattern_syntax(
+                            PatternErrorKind::RangeWithoutEnd { pos, start: first },
+                            format!("位置 {} の文字クラスの範囲に終点がない: '{}-'", pos, first),
+                        ))
+                    }
+                };
+                let range = match (password_char_to_cum(first), password_char_to_cum(last)) {
+                    (Some(start), Some(end)) if start <= end => start..=end,
+                    _ => {
+                        return Err(Dq1PasswordError::invalid_pattern_syntax(
+                            PatternErrorKind::InvalidRange {
+                                pos,
+                                start: first,
+                                end: last,
+                            },
+                            format!(
+                                "位置 {} の文字クラスの範囲が無効: '{}-{}'",
+                                pos, first, last
+                            ),
+                        ))
+                    }
+                };
+                class.extend(range.map(cum_to_password_char));
+                i += 3;
+            }
+            ['-', ..] => {
+                return Err(Dq1PasswordError::invalid_pattern_syntax(
+                    PatternErrorKind::RangeWithoutStart { pos },
+                    format!("位置 {} の文字クラスの範囲に始点がない", pos),
+                ))
+            }
+            [c, ..] => {
+                if password_char_to_cum(c).is_none() {
+                    return Err(Dq1PasswordError::invalid_pattern_chars(
+                        vec![(pos, c)],
+                        format!(
+                            "位置 {} の文字クラスに無効な文字が含まれている: '{}'",
+                            pos, c
+                        ),
+                    ));
+                }
+                class.push(c);
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+
+    Ok(class)
+}
+
+/// 復活の呪文パターンの断片(プレフィックスなど)を正規化する。長さはチェックしない。
+///
+/// [`normalize_pattern`] と同様に、互換文字やカタカナ、分離した濁点/半濁点を変換し、空白文字を除去する。
+///
+/// `fragment` に無効な文字が含まれる場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub(crate) fn normalize_pattern_fragment(fragment: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let folded = fold_pattern_fragment(fragment.as_ref());
+
+    let offenders: Vec<_> = folded
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(c, _))| password_char_to_cum(c).is_none() && c != '?')
+        .collect();
+
+    if !offenders.is_empty() {
+        let msg = format!(
+            "パターンに無効な文字が含まれている: {}",
+            offenders
+                .iter()
+                .map(|&(i, (_, original))| format!("位置 {} の '{}'", i, original))
+                .join(", ")
+        );
+        return Err(Dq1PasswordError::invalid_pattern_chars(
+            offenders.iter().map(|&(i, &(c, _))| (i, c)).collect(),
+            msg,
+        ));
+    }
+
+    Ok(folded.into_iter().map(|(c, _)| c).collect())
+}
+
+/// 復活の呪文パターンの一部を [`normalize_pattern`] と同様に変換する(構文や文字の有効性は関知しない)。
+///
+/// 戻り値の各要素は (変換後の文字, それに対応する元の文字列)。
+pub(crate) fn fold_pattern_fragment(fragment: &str) -> Vec<(char, String)> {
+    fold_kana(fragment.chars().flat_map(normalize_pattern_char))
+}
+
 fn normalize_pattern_char(c: char) -> impl Iterator<Item = char> {
+    let c = fold_compat(c);
     let cs: Option<&[char]> = match c {
-        '？' => Some(&['?']),
         _ if c.is_whitespace() => Some(&[]),
         _ => None,
     };
 
     cs.map_or_else(
-        || itertools::Either::Left(std::iter::once(c)),
+        || itertools::Either::Left(core::iter::once(c)),
         |cs| itertools::Either::Right(cs.iter().copied()),
     )
 }
@@ -217,6 +1007,8 @@ fn normalize_pattern_char(c: char) -> impl Iterator<Item = char> {
 mod tests {
     use super::*;
 
+    use crate::error::PasswordErrorKind;
+
     #[allow(non_snake_case)]
     fn S(s: &'static str) -> String {
         s.to_owned()
@@ -230,12 +1022,142 @@ mod tests {
         assert_eq!(normalize_hero_name("がぱ"), Ok(S("か゛は゜")));
         assert_eq!(normalize_hero_name("　あーす"), Ok(S(" あ-す")));
 
+        // カタカナ
+        assert_eq!(normalize_hero_name("アレフ"), Ok(S("あれふ ")));
+        assert_eq!(normalize_hero_name("ガイア"), Ok(S("か゛いあ")));
+        assert_eq!(normalize_hero_name("ヴ"), Ok(S("う゛  ")));
+        assert_eq!(normalize_hero_name("ローラ"), Ok(S("ろ-ら ")));
+        assert_eq!(normalize_hero_name("ゆウてイ"), Ok(S("ゆうてい")));
+        assert_eq!(normalize_hero_name("ッャュョ"), Ok(S("っゃゅょ")));
+        assert!(normalize_hero_name("ガガガ").is_err());
+
+        // 半角カタカナ。濁点/半濁点は 1 文字と数える。
+        assert_eq!(normalize_hero_name("ｱﾚﾌ"), Ok(S("あれふ ")));
+        assert_eq!(normalize_hero_name("ｶﾞｲｱ"), Ok(S("か゛いあ")));
+        assert_eq!(normalize_hero_name("ﾊﾟｽﾞ"), Ok(S("は゜す゛")));
+        assert_eq!(normalize_hero_name("ﾛｰﾗ"), Ok(S("ろ-ら ")));
+        assert_eq!(normalize_hero_name("ｶﾞイあ"), normalize_hero_name("がいあ"));
+        assert_eq!(
+            normalize_hero_name("ｶﾞｲﾀﾞ").unwrap_err().kind(),
+            crate::ErrorKind::InvalidGameState(GameStateErrorKind::HeroNameTooLong)
+        );
+        assert!(normalize_hero_name("ァ").is_err());
+        for (katakana, hiragana) in [
+            ("アレフ", "あれふ"),
+            ("パズー", "ぱず-"),
+            ("ロト2", "ろと2"),
+        ] {
+            let state = |hero_name: &str| crate::GameState {
+                hero_name: hero_name.to_owned(),
+                ..Default::default()
+            };
+            assert_eq!(
+                crate::encode(&state(katakana)),
+                crate::encode(&state(hiragana))
+            );
+        }
+
         assert!(normalize_hero_name("     ").is_err());
         assert!(normalize_hero_name("あああが").is_err());
         assert!(normalize_hero_name("A").is_err());
         assert!(normalize_hero_name("漢字").is_err());
     }
 
+    #[test]
+    fn test_display_hero_name() {
+        assert_eq!(display_hero_name("ゆうてい"), Ok(S("ゆうてい")));
+        assert_eq!(display_hero_name("か゛は゜"), Ok(S("がぱ")));
+        assert_eq!(display_hero_name("シドー"), Ok(S("しど-")));
+        assert_eq!(display_hero_name("あ す "), Ok(S("あ す")));
+        assert_eq!(display_hero_name(""), Ok(S("")));
+        // 合成できないものはそのまま。
+        assert_eq!(display_hero_name("゛あ゜"), Ok(S("゛あ゜")));
+        assert!(display_hero_name("ゆうていみ").is_err());
+    }
+
+    #[test]
+    fn test_normalize_hero_name_lenient() {
+        let sub = |position, from, to| HeroNameSubstitution { position, from, to };
+
+        assert_eq!(
+            normalize_hero_name_lenient("ぁきら"),
+            Ok((S("あきら "), vec![sub(0, 'ぁ', 'あ')]))
+        );
+        assert_eq!(
+            normalize_hero_name_lenient("まりぃ"),
+            Ok((S("まりい "), vec![sub(2, 'ぃ', 'い')]))
+        );
+        assert_eq!(
+            normalize_hero_name_lenient("ぅぇぉ"),
+            Ok((
+                S("うえお "),
+                vec![sub(0, 'ぅ', 'う'), sub(1, 'ぇ', 'え'), sub(2, 'ぉ', 'お')]
+            ))
+        );
+        assert_eq!(
+            normalize_hero_name_lenient("ゎゐゑ"),
+            Ok((
+                S("わいえ "),
+                vec![sub(0, 'ゎ', 'わ'), sub(1, 'ゐ', 'い'), sub(2, 'ゑ', 'え')]
+            ))
+        );
+
+        // カタカナ、半角カタカナ。位置は入力中の文字単位。
+        assert_eq!(
+            normalize_hero_name_lenient("ガｨヰ"),
+            Ok((S("か゛いい"), vec![sub(1, 'ｨ', 'い'), sub(2, 'ヰ', 'い')]))
+        );
+        assert_eq!(
+            normalize_hero_name_lenient("ヴェヱ"),
+            Ok((S("う゛ええ"), vec![sub(1, 'ェ', 'え'), sub(2, 'ヱ', 'え')]))
+        );
+
+        // 置き換えが不要なら既定の正規化と同じ。
+        assert_eq!(
+            normalize_hero_name_lenient("ゆうてい"),
+            Ok((S("ゆうてい"), vec![]))
+        );
+        assert_eq!(
+            normalize_hero_name_lenient("ゃゅょっ"),
+            Ok((S("ゃゅょっ"), vec![]))
+        );
+
+        // 置き換えても無効なものは無効。
+        assert!(normalize_hero_name_lenient("ぁぃぅぇぉ").is_err());
+        assert!(normalize_hero_name_lenient("ぁA").is_err());
+
+        // 既定の正規化は変わらない。
+        for hero_name in ["ぁきら", "まりぃ", "ゎ", "ゐ", "ゑ", "ァ", "ｧ"] {
+            assert!(normalize_hero_name(hero_name).is_err(), "{}", hero_name);
+        }
+    }
+
+    #[test]
+    fn test_normalize_hero_name_strict() {
+        assert_eq!(normalize_hero_name_strict(""), Ok(S("    ")));
+        assert_eq!(normalize_hero_name_strict("がぱ"), Ok(S("か゛は゜")));
+        assert_eq!(normalize_hero_name_strict("あ す"), Ok(S("あ す ")));
+
+        let msg = |hero_name: &str| match normalize_hero_name_strict(hero_name) {
+            Err(Dq1PasswordError::InvalidGameState(e)) => e.message().to_owned(),
+            res => panic!("{:?}", res),
+        };
+        // 数字は入力できない。
+        assert!(msg("ゆうて1").contains("入力できない文字"));
+        // 先頭の濁点/半濁点。
+        assert!(msg("゛あ").contains("1 文字目の '゛'"));
+        assert!(msg("゜あ").contains("1 文字目の '゜'"));
+        // 濁音化/半濁音化できない文字の後の濁点/半濁点。
+        assert!(msg("あ゛").contains("2 文字目の '゛'"));
+        assert!(msg("か゜").contains("2 文字目の '゜'"));
+        // 空白で始まる。
+        assert!(msg(" あ").contains("空白で始まっている"));
+        // 寛容なモードでは通る。
+        for hero_name in ["ゆうて1", "゛あ", "゜あ", "あ゛", "か゜", " あ"] {
+            assert!(normalize_hero_name(hero_name).is_ok());
+        }
+    }
+
     #[test]
     fn test_normalize_password() {
         assert_eq!(
@@ -250,6 +1172,340 @@ mod tests {
         assert!(normalize_password("あああああああああああああああああああ").is_err());
         assert!(normalize_password("あああああああああああああああああああああ").is_err());
         assert!(normalize_password("ああああああああああああああああああ漢字").is_err());
+
+        // カタカナ(全角/半角)と分離した濁点/半濁点。
+        assert_eq!(
+            normalize_password("ザボチズドヂギキツタうずせれえむるのぢえ"),
+            Ok(S("ざぼちずどぢぎきつたうずせれえむるのぢえ"))
+        );
+        assert_eq!(
+            normalize_password("ｻﾞﾎﾞﾁｽﾞﾄﾞﾁﾞｷﾞｷﾂﾀｳｽﾞせれえむるのぢえ"),
+            Ok(S("ざぼちずどぢぎきつたうずせれえむるのぢえ"))
+        );
+        assert_eq!(
+            normalize_password("さ゛ほ\u{3099}ちす゛とﾞちﾞき゛きつたうずせれえむるのぢえ"),
+            Ok(S("ざぼちずどぢぎきつたうずせれえむるのぢえ"))
+        );
+        // 半濁点は復活の呪文に使えない。
+        assert_eq!(
+            normalize_password("ﾊﾟあああああああああああああああああああ"),
+            Err(Dq1PasswordError::invalid_password_chars(
+                vec![(0, 'ぱ')],
+                "復活の呪文に無効な文字が含まれている: 'ﾊﾟ'"
+            ))
+        );
+        // 合成できない濁点はそのまま残る。
+        assert_eq!(
+            normalize_password("゛ああああああああああああああああああア"),
+            Err(Dq1PasswordError::invalid_password_chars(
+                vec![(0, '゛')],
+                "復活の呪文に無効な文字が含まれている: '゛'"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_normalize_cow() {
+        fn check<'a>(
+            input: &'a str,
+            f: fn(&'a str) -> Dq1PasswordResult<Cow<'a, str>>,
+            g: fn(&'a str) -> Dq1PasswordResult<String>,
+        ) {
+            let actual = f(input);
+            assert_eq!(
+                actual.as_ref().map(|s| s.to_string()).map_err(Clone::clone),
+                g(input)
+            );
+            // 変更不要な場合のみ借用を返す。
+            if let Ok(cow) = actual {
+                assert_eq!(matches!(cow, Cow::Borrowed(_)), cow == input);
+            }
+        }
+
+        for password in [
+            "ああああああああああああああああああああ",
+            "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+            "あああああ あああああああ　あああああ あああ",
+            "ザボチズドヂギキツタうずせれえむるのぢえ",
+            "ｻﾞﾎﾞﾁｽﾞﾄﾞﾁﾞｷﾞｷﾂﾀｳｽﾞせれえむるのぢえ",
+            "さ゛ほ\u{3099}ちす゛とﾞちﾞき゛きつたうずせれえむるのぢえ",
+            "ﾊﾟあああああああああああああああああああ",
+            "゛ああああああああああああああああああア",
+            "あああああああああああああああああああ",
+            "あああああああああああああああああああああ",
+            "ああああああああああああああああああ漢字",
+            "",
+        ] {
+            check(password, normalize_password_cow, normalize_password);
+        }
+        assert!(matches!(
+            normalize_password_cow("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok(Cow::Borrowed(_))
+        ));
+
+        for hero_name in [
+            "",
+            "0123",
+            "ああああ",
+            "がぱ",
+            "　あーす",
+            "あ-す ",
+            "か゛は゜",
+            "ゆう て",
+            "アレフ",
+            "ｶﾞｲｱ",
+            "     ",
+            "あああが",
+            "A",
+            "漢字",
+            "あいうえ\u{3000}",
+        ] {
+            check(hero_name, normalize_hero_name_cow, |s| {
+                normalize_hero_name(s)
+            });
+        }
+        assert!(matches!(
+            normalize_hero_name_cow("か゛は゜"),
+            Ok(Cow::Borrowed(_))
+        ));
+
+        for pattern in [
+            "あああああああ?あ?ああああああああああ",
+            "あああああ ああ?あ？ああ　あああああ あああ",
+            "ざぼちずどぢぎきつたうずせれえむる?1?1",
+            "ざぼちずどぢぎきつたうずせれえむる[あ-お]?",
+            "ざぼちず*",
+            "????????????????????",
+            "???????????????????",
+            "ああああ漢ああああああ?ああああ字あああ",
+        ] {
+            check(pattern, normalize_pattern_cow, normalize_pattern);
+        }
+        assert!(matches!(
+            normalize_pattern_cow("????????????????????"),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_password_strict() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        assert_eq!(normalize_password_strict(PASSWORD), Ok(S(PASSWORD)));
+        assert_eq!(normalize_password(PASSWORD), Ok(S(PASSWORD)));
+
+        let changes = |password: &str| {
+            assert_eq!(normalize_password(password), Ok(S(PASSWORD)));
+            match normalize_password_strict(password) {
+                Err(Dq1PasswordError::InvalidPassword(e)) => match e.kind() {
+                    PasswordErrorKind::NotCanonical(changes) => {
+                        (changes.clone(), e.message().to_owned())
+                    }
+                    kind => panic!("{:?}", kind),
+                },
+                res => panic!("{:?}", res),
+            }
+        };
+
+        assert_eq!(
+            changes("ざぼちず どぢぎき　つたうずせれえむるのぢえ"),
+            (
+                vec![(4, ' ', None), (9, '　', None)],
+                S("復活の呪文が正規形でない: 位置 4 の ' ' は除去される, 位置 9 の '　' は除去される")
+            )
+        );
+        assert_eq!(
+            changes("ザぼちずどぢぎきつたうずせれえむるのぢエ").0,
+            vec![(0, 'ザ', Some('ざ')), (19, 'エ', Some('え'))]
+        );
+        assert_eq!(
+            changes("さ゛ぼちずどぢぎきつたうずせれえむるのぢえ").0,
+            vec![(0, 'さ', Some('ざ')), (1, '゛', Some('ざ'))]
+        );
+        assert_eq!(
+            changes("ざぼちずどぢぎきつたうずせれえむるのぢえ\u{00A0}").0,
+            vec![(20, '\u{00A0}', None)]
+        );
+
+        // 形式が無効なものは、寛容なモードと同じエラーになる。
+        for password in ["ざぼちず", "ざぼちず漢ぢぎきつたうずせれえむるのぢえ"]
+        {
+            assert_eq!(
+                normalize_password_strict(password),
+                normalize_password(password)
+            );
+        }
+    }
+
+    #[test]
+    fn test_analyze_password() {
+        use PasswordCharStatus::*;
+
+        let ca = |offset, c, status| PasswordCharAnalysis { offset, c, status };
+
+        assert_eq!(
+            analyze_password("か゛ 漢　ｱ"),
+            PasswordAnalysis {
+                chars: vec![
+                    ca(0, 'か', Kept { position: 0 }),
+                    ca(3, '゛', Kept { position: 0 }),
+                    ca(6, ' ', Stripped),
+                    ca(7, '漢', Rejected { position: 1 }),
+                    ca(10, '　', Stripped),
+                    ca(13, 'ｱ', Kept { position: 2 }),
+                ],
+                normalized: None,
+            }
+        );
+
+        let analysis = analyze_password("ゆうてい　みやおう きむこう\tほりいゆうじ　たろ");
+        assert!(analysis.is_valid());
+        assert_eq!(
+            analysis.normalized.as_deref(),
+            Some("ゆうていみやおうきむこうほりいゆうじたろ")
+        );
+        assert_eq!(analysis.normalized_len(), 20);
+        assert_eq!(analysis.chars.len(), 24);
+        assert_eq!(analysis.chars[4], ca(12, '　', Stripped));
+        assert_eq!(analysis.chars[5], ca(15, 'み', Kept { position: 4 }));
+        assert_eq!(analysis.chars[9], ca(27, ' ', Stripped));
+        assert_eq!(analysis.chars[10], ca(28, 'き', Kept { position: 8 }));
+        assert_eq!(analysis.chars[14], ca(40, '\t', Stripped));
+        assert_eq!(analysis.chars[15], ca(41, 'ほ', Kept { position: 12 }));
+        assert_eq!(analysis.chars[23], ca(65, 'ろ', Kept { position: 19 }));
+        assert_eq!(analysis.rejected().count(), 0);
+
+        // 各文字が有効でも、長さが誤っていれば無効。
+        let analysis = analyze_password("ゆうてい 漢字");
+        assert!(!analysis.is_valid());
+        assert_eq!(analysis.normalized_len(), 6);
+        assert_eq!(
+            analysis.rejected().copied().collect::<Vec<_>>(),
+            vec![
+                ca(13, '漢', Rejected { position: 4 }),
+                ca(16, '字', Rejected { position: 5 }),
+            ]
+        );
+
+        assert_eq!(
+            analyze_password(""),
+            PasswordAnalysis {
+                chars: vec![],
+                normalized: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize_with_map() {
+        fn slices<'a>(input: &'a str, mapped: &NormalizedWithMap) -> Vec<&'a str> {
+            assert_eq!(mapped.spans.len(), mapped.normalized.chars().count());
+            mapped
+                .spans
+                .iter()
+                .map(|span| &input[span.clone()])
+                .collect()
+        }
+
+        let input = " か゛　ｶﾞ\tきくけこさしすせそ\n たちつてとなにぬね  ";
+        let mapped = normalize_password_with_map(input).unwrap();
+        assert_eq!(
+            mapped.normalized,
+            "ががきくけこさしすせそたちつてとなにぬね"
+        );
+        assert_eq!(mapped.spans[0], 1..7);
+        assert_eq!(mapped.spans[1], 10..16);
+        assert_eq!(
+            slices(input, &mapped),
+            [
+                "か゛", "ｶﾞ", "き", "く", "け", "こ", "さ", "し", "す", "せ", "そ", "た", "ち",
+                "つ", "て", "と", "な", "に", "ぬ", "ね"
+            ]
+        );
+        assert!(normalize_password_with_map("か゛").is_err());
+
+        let input = "ガｼﾞ";
+        let mapped = normalize_hero_name_with_map(input).unwrap();
+        assert_eq!(mapped.normalized, "か゛し゛");
+        assert_eq!(slices(input, &mapped), ["ガ", "ガ", "ｼ", "ﾞ"]);
+
+        let input = "ぱ";
+        let mapped = normalize_hero_name_with_map(input).unwrap();
+        assert_eq!(mapped.normalized, "は゜  ");
+        assert_eq!(mapped.spans, [0..3, 0..3, 3..3, 3..3]);
+
+        let input = "　ゆ";
+        let mapped = normalize_hero_name_with_map(input).unwrap();
+        assert_eq!(mapped.normalized, " ゆ  ");
+        assert_eq!(slices(input, &mapped), ["　", "ゆ", "", ""]);
+        assert!(normalize_hero_name_with_map("がぎぐ").is_err());
+
+        let input = " ［か゛が］１ ＊ ﾎﾞ ? ";
+        let mapped = normalize_pattern_with_map(input).unwrap();
+        assert_eq!(mapped.normalized, format!("[がが]1{}ぼ?", "?".repeat(17)));
+        let expected: Vec<_> = ["［", "か゛", "が", "］", "１"]
+            .iter()
+            .copied()
+            .chain(core::iter::repeat_n("＊", 17))
+            .chain(["ﾎﾞ", "?"].iter().copied())
+            .collect();
+        assert_eq!(slices(input, &mapped), expected);
+
+        let input = "ゆうていみやおうきむこうほりいゆうじたろう";
+        let mapped = normalize_pattern_with_map(&input[..input.len() - 3]).unwrap();
+        assert_eq!(mapped.spans.last(), Some(&(57..60)));
+        assert!(normalize_pattern_with_map(input).is_err());
+    }
+
+    #[test]
+    fn test_fold_compat() {
+        assert_eq!(fold_compat('０'), '0');
+        assert_eq!(fold_compat('９'), '9');
+        assert_eq!(fold_compat('？'), '?');
+        assert_eq!(fold_compat('～'), '~');
+        assert_eq!(fold_compat('Ａ'), 'A');
+        assert_eq!(fold_compat('⓪'), '0');
+        assert_eq!(fold_compat('①'), '1');
+        assert_eq!(fold_compat('⑨'), '9');
+        assert_eq!(fold_compat('⑩'), '⑩');
+        for c in [
+            '\u{00A0}', '\u{2002}', '\u{200A}', '\u{202F}', '\u{205F}', '\u{3000}',
+        ] {
+            assert_eq!(fold_compat(c), ' ');
+        }
+        for c in ['あ', 'ア', 'ｱ', '゛', 'ﾞ', '-', '\u{2212}', '\u{200B}'] {
+            assert_eq!(fold_compat(c), c);
+        }
+
+        // 全ての正規化関数で同じように扱われる。
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+        for space in ['\u{00A0}', '\u{2003}', '\u{3000}'] {
+            assert_eq!(
+                normalize_hero_name(format!("ゆう{}て", space)),
+                Ok(S("ゆう て"))
+            );
+            let password = format!("ざぼちず{}どぢぎきつたうず{}せれえむるのぢえ", space, space);
+            assert_eq!(normalize_password(&password), Ok(S(PASSWORD)));
+            assert_eq!(normalize_pattern(&password), Ok(S(PASSWORD)));
+            assert_eq!(analyze_password(&password).normalized, Some(S(PASSWORD)));
+        }
+        for digits in ["1234", "１２３４", "①②③④"] {
+            assert_eq!(normalize_hero_name(digits), Ok(S("1234")));
+        }
+        assert_eq!(
+            normalize_pattern("ざぼちずどぢぎきつたうずせれえむる？①［ぢ－ど］①"),
+            normalize_pattern("ざぼちずどぢぎきつたうずせれえむる?1[ぢ-ど]1")
+        );
+        assert_eq!(
+            normalize_pattern_fragment("ざぼ？\u{00A0}ず"),
+            Ok(S("ざぼ?ず"))
+        );
+        // 全角英字は畳み込まれるが、無効な文字のまま。
+        assert_eq!(
+            normalize_hero_name("Ａ").unwrap_err().kind(),
+            crate::ErrorKind::InvalidGameState(GameStateErrorKind::HeroNameInvalidChars(vec!['A']))
+        );
+        assert!(normalize_password("ざぼちずどぢぎきつたうずせれえむるのぢＡ").is_err());
     }
 
     #[test]
@@ -263,6 +1519,49 @@ mod tests {
             Ok(S("あああああああ?あ?ああああああああああ"))
         );
 
+        // 文字クラスは 1 文字と数える。
+        assert_eq!(
+            normalize_pattern("[あい]ああああああ?あ?ああああああああ［う え］あ"),
+            Ok(S("[あい]ああああああ?あ?ああああああああ[うえ]あ"))
+        );
+        assert!(normalize_pattern("[あい]ああああああ?あ?あああああああああ[うえ]あ").is_err());
+
+        // カタカナは文字クラスの中でも変換される。
+        assert_eq!(
+            normalize_pattern("ザボチズド？ギキツタ[ウエ]ズセレエム*ﾉﾁﾞ[ｴ-ｵ]"),
+            Ok(S("ざぼちずど?ぎきつた[うえ]ずせれえむ?のぢ[え-お]"))
+        );
+        match normalize_pattern("ヴぼちずどぢぎきつたうずせれえむるのぢ?") {
+            Err(e) => assert!(e.to_string().contains("'ヴ'")),
+            res => panic!("{:?}", res),
+        }
+
+        // '*' は残りを埋める '?' たちに展開される。
+        assert_eq!(
+            normalize_pattern("ゆうて*"),
+            Ok(S("ゆうて?????????????????"))
+        );
+        assert_eq!(normalize_pattern("＊ぼ"), Ok(S("???????????????????ぼ")));
+        assert_eq!(
+            normalize_pattern("[あい]う*え[^お]"),
+            Ok(S("[あい]う????????????????え[^お]"))
+        );
+        assert_eq!(
+            normalize_pattern("あああああああああああああああああああ*"),
+            Ok(S("あああああああああああああああああああ?"))
+        );
+        for pattern in [
+            "ゆう*て*",
+            "ああああああああああああああああああああ*",
+            "あああああああああああああああああああああ*",
+        ] {
+            assert!(matches!(
+                normalize_pattern(pattern),
+                Err(Dq1PasswordError::InvalidPattern(_))
+            ));
+        }
+        assert!(normalize_pattern("[あ*]*").is_err());
+
         assert!(normalize_password("ああああああああああああああああああああ?").is_err());
         assert!(normalize_password("ああああああああああああああああああ?").is_err());
         assert!(normalize_password("あああああああああああああああああ漢字?").is_err());
@@ -0,0 +1,283 @@
+use core::convert::TryFrom;
+use core::ops::RangeInclusive;
+
+use crate::cums::{cums_to_password, cums_to_password_into};
+use crate::decode::decode;
+use crate::error::Dq1PasswordResult;
+use crate::game_state::GameState;
+use crate::generate::{generate_each, generate_with_options, GenerateOptions};
+use crate::pattern::Pattern;
+use crate::prelude::*;
+use crate::validate::ValidationPolicy;
+
+/// [`optimize`] の目的関数。
+pub enum Objective {
+    /// フィールドの値を最大化する。
+    Max(ObjectiveField),
+
+    /// フィールドの値を最小化する。
+    Min(ObjectiveField),
+
+    /// ゲーム状態に対する関数の値を最大化する(最小化したい場合は符号を反転すればよい)。
+    ///
+    /// パターンに合致するものを全て列挙するので、パターンが緩いと時間を大量に消費する
+    /// (1 つずつ評価して最良のものだけを保持するので、メモリは大量には消費しない)。
+    MaxBy(Box<dyn Fn(&GameState) -> i64>),
+}
+
+/// [`Objective`] で最大化/最小化できるフィールド。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ObjectiveField {
+    /// 主人公の経験値。
+    Xp,
+
+    /// 所持金。
+    Purse,
+
+    /// やくそう所持数。
+    HerbCount,
+
+    /// かぎ所持数。
+    KeyCount,
+}
+
+/// 指定されたパターンに合致するデコード可能な復活の呪文のうち、目的関数を最適化するものと
+/// そのデコード結果を返す。存在しない場合は None を返す。
+///
+/// 最適なものが複数ある場合、[`generate`](crate::generate()) の生成順で最初のものを返す。
+///
+/// `Objective::Max`, `Objective::Min` の場合、全列挙は行わない。経験値/所持金は上位 bit から順に
+/// 最適な値を仮定して生成を試みることで 1 bit ずつ確定させ(固定された bit は DP で扱えるので高速)、
+/// やくそう/かぎ所持数は最適な値から順に生成を試みる。
+///
+/// `Objective::MaxBy` の場合、パターンに合致するものを [`generate_each`] で全て列挙する。
+///
+/// `pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn optimize(
+    pattern: impl AsRef<str>,
+    objective: Objective,
+) -> Dq1PasswordResult<Option<(String, GameState)>> {
+    let pattern = pattern.as_ref();
+
+    let password = match objective {
+        Objective::Max(field) => optimize_field(pattern, field, true)?,
+        Objective::Min(field) => optimize_field(pattern, field, false)?,
+        Objective::MaxBy(f) => optimize_by(pattern, f)?,
+    };
+
+    Ok(password.map(|password| {
+        let state = decode(&password).unwrap();
+        (password, state)
+    }))
+}
+
+fn optimize_field(
+    pattern: &str,
+    field: ObjectiveField,
+    maximize: bool,
+) -> Dq1PasswordResult<Option<String>> {
+    // 制約を満たす最初の復活の呪文を返す。
+    let first = |options: &GenerateOptions| -> Dq1PasswordResult<Option<String>> {
+        Ok(generate_with_options(pattern, 1, options)?.pop())
+    };
+
+    let get = |password: &str| -> u16 {
+        let state = decode(password).unwrap();
+        match field {
+            ObjectiveField::Xp => state.hero_xp,
+            ObjectiveField::Purse => state.purse,
+            ObjectiveField::HerbCount => u16::from(state.herb_count),
+            ObjectiveField::KeyCount => u16::from(state.key_count),
+        }
+    };
+    let set_range = |options: &mut GenerateOptions, range: RangeInclusive<u16>| match field {
+        ObjectiveField::Xp => options.xp = Some(range),
+        ObjectiveField::Purse => options.purse = Some(range),
+        ObjectiveField::HerbCount => {
+            options.herb_count = Some(narrow(*range.start())..=narrow(*range.end()))
+        }
+        ObjectiveField::KeyCount => {
+            options.key_count = Some(narrow(*range.start())..=narrow(*range.end()))
+        }
+    };
+
+    let mut options = GenerateOptions::default();
+
+    // 現在の制約を満たす解の 1 つ(存在の証拠)の値。
+    let mut witness = match first(&options)? {
+        Some(password) => get(&password),
+        None => return Ok(None),
+    };
+
+    let value = match field {
+        ObjectiveField::Xp | ObjectiveField::Purse => {
+            // 上位 bit から順に、最適な bit を仮定して解が存在するか調べる。
+            // 仮定した範囲は下位 bit が任意なので、DP 内で扱える。
+            // 証拠の bit が既に最適ならば調べる必要はない。
+            let mut prefix = 0;
+            for b in (0..16).rev() {
+                let bit = if maximize { 1 << b } else { 0 };
+                if (witness & (1 << b)) != bit {
+                    let lower = (1 << b) - 1;
+                    set_range(&mut options, (prefix | bit)..=(prefix | bit | lower));
+                    if let Some(password) = first(&options)? {
+                        witness = get(&password);
+                    }
+                }
+                prefix |= witness & (1 << b);
+            }
+            prefix
+        }
+        ObjectiveField::HerbCount | ObjectiveField::KeyCount => {
+            // 証拠より良い値を、最適なものから順に調べる。
+            // 生成は本家の基準で検査するので、上限もそれに従う。
+            let policy = ValidationPolicy::DQ1_FC;
+            let max = u16::from(match field {
+                ObjectiveField::HerbCount => policy.herb_count_max(),
+                ObjectiveField::KeyCount => policy.key_count_max(),
+                ObjectiveField::Xp | ObjectiveField::Purse => unreachable!(),
+            });
+            let better: Vec<u16> = if maximize {
+                (witness + 1..=max).rev().collect()
+            } else {
+                (0..witness).collect()
+            };
+            let mut value = witness;
+            for x in better {
+                set_range(&mut options, x..=x);
+                if first(&options)?.is_some() {
+                    value = x;
+                    break;
+                }
+            }
+            value
+        }
+    };
+
+    // 最適な値を持つもののうち、生成順で最初のもの。
+    set_range(&mut options, value..=value);
+    first(&options)
+}
+
+/// やくそう/かぎ所持数用。値は常に [`ValidationPolicy::DQ1_FC`] の上限以下。
+fn narrow(x: u16) -> u8 {
+    u8::try_from(x).unwrap()
+}
+
+fn optimize_by(
+    pattern: &str,
+    f: Box<dyn Fn(&GameState) -> i64>,
+) -> Dq1PasswordResult<Option<String>> {
+    let pattern = Pattern::parse(pattern)?;
+
+    // 結果を 1 つずつ評価し、最良のものの値と累積値の配列のみを保持する。
+    let mut best: Option<(i64, [u8; 20])> = None;
+    let mut password = String::new();
+    generate_each(&pattern, usize::MAX, &GenerateOptions::default(), |cums| {
+        cums_to_password_into(cums, &mut password).unwrap();
+        let value = f(&decode(&password).unwrap());
+        if best
+            .as_ref()
+            .is_none_or(|(best_value, _)| value > *best_value)
+        {
+            best = Some((value, *cums));
+        }
+        true
+    })?;
+
+    Ok(best.map(|(_, cums)| cums_to_password(&cums).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::Dq1PasswordError;
+    use crate::generate::generate;
+
+    /// パターンに合致するもの全てを、生成順に (復活の呪文, デコード結果) の形で返す。
+    fn brute_force(pattern: &str) -> Vec<(String, GameState)> {
+        let all = generate(pattern, 10000).unwrap();
+        assert!(all.len() < 10000);
+        all.into_iter()
+            .map(|p| {
+                let state = decode(&p).unwrap();
+                (p, state)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_optimize() {
+        // 位置 17..=19 が任意なら経験値の上位バイト、位置 12..=14 が任意なら
+        // 所持金の上位バイトとやくそう/かぎ所持数が変わりうる。
+        type Getter = fn(&GameState) -> u16;
+        let fields: [(&str, ObjectiveField, Getter); 4] = [
+            (
+                "ざぼちずどぢぎきつたうずせれえむる???",
+                ObjectiveField::Xp,
+                |s| s.hero_xp,
+            ),
+            (
+                "ざぼちずどぢぎきつたうず???むるのぢえ",
+                ObjectiveField::Purse,
+                |s| s.purse,
+            ),
+            (
+                "ざぼちずどぢぎきつたうず???むるのぢえ",
+                ObjectiveField::HerbCount,
+                |s| u16::from(s.herb_count),
+            ),
+            (
+                "ざぼちずどぢぎきつたうず???むるのぢえ",
+                ObjectiveField::KeyCount,
+                |s| u16::from(s.key_count),
+            ),
+        ];
+        for (pattern, field, get) in fields {
+            let states = brute_force(pattern);
+            let max = states.iter().map(|(_, s)| get(s)).max().unwrap();
+            let min = states.iter().map(|(_, s)| get(s)).min().unwrap();
+            assert_ne!(max, min);
+            for (objective, expect) in [(Objective::Max(field), max), (Objective::Min(field), min)]
+            {
+                let (password, state) = optimize(pattern, objective).unwrap().unwrap();
+                assert_eq!(
+                    Pattern::parse(pattern).unwrap().matches(&password),
+                    Ok(true)
+                );
+                assert_eq!(decode(&password), Ok(state.clone()));
+                assert_eq!(get(&state), expect);
+            }
+        }
+
+        // 最適なもののうち生成順で最初のもの。
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+        let score = |s: &GameState| i64::from(s.hero_xp % 1000) - i64::from(s.key_count);
+        let states = brute_force(PATTERN);
+        let best = states.iter().map(|(_, s)| score(s)).max().unwrap();
+        let expect = states.into_iter().find(|(_, s)| score(s) == best);
+        assert_eq!(
+            optimize(PATTERN, Objective::MaxBy(Box::new(score))),
+            Ok(expect)
+        );
+
+        // 合致するものが存在しない。
+        const PATTERN_NONE: &str = "ざぼちずどぢぎきつたうずせれえむるのぢお";
+        assert_eq!(
+            optimize(PATTERN_NONE, Objective::Max(ObjectiveField::Xp)),
+            Ok(None)
+        );
+        assert_eq!(
+            optimize(PATTERN_NONE, Objective::MaxBy(Box::new(|_| 0))),
+            Ok(None)
+        );
+
+        assert!(matches!(
+            optimize("ゆうてい", Objective::Min(ObjectiveField::Purse)),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+    }
+}
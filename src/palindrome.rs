@@ -0,0 +1,290 @@
+use core::convert::TryInto;
+
+use crate::char_set::CharSet;
+use crate::decode::password_char_to_cum;
+use crate::encode::cum_to_password_char;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::generate::{crc8_table_head, crc8_table_tail, six_is_valid};
+use crate::normalize::normalize_pattern_fragment;
+use crate::prelude::*;
+use crate::validate::ValidationPolicy;
+
+/// 回文になっているデコード可能な復活の呪文たちを生成する。
+///
+/// 結果は前半 10 文字の辞書順(五十音表の順)に並ぶ。
+///
+/// `n_max` は生成上限数。
+pub fn generate_palindromes(n_max: usize) -> Vec<String> {
+    generate_palindromes_with_pattern("??????????", n_max).unwrap()
+}
+
+/// 前半 10 文字が指定されたパターンに合致し、回文になっているデコード可能な復活の呪文たちを生成する。
+///
+/// `half_pattern` はちょうど 10 文字でなければならない。
+/// `half_pattern` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。空白文字は無視される。
+///
+/// 結果は前半 10 文字の辞書順(五十音表の順)に並ぶ。
+///
+/// `n_max` は生成上限数。
+///
+/// `half_pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn generate_palindromes_with_pattern(
+    half_pattern: impl AsRef<str>,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<String>> {
+    let cums_half: Vec<_> = normalize_pattern_fragment(half_pattern)?
+        .chars()
+        .map(|c| {
+            password_char_to_cum(c).map_or(CharSet::full(), |cum| CharSet::from_bits(1 << cum))
+        })
+        .collect();
+    let len = cums_half.len();
+    let cums_half: [_; 10] = cums_half.try_into().map_err(|_| {
+        Dq1PasswordError::invalid_pattern_length(
+            len,
+            "前半パターンはちょうど 10 文字でなければならない(ただし空白文字は無視される)",
+        )
+    })?;
+
+    let feasible = palindrome_dp(&cums_half);
+
+    struct Dfs<'a> {
+        n_max: usize,
+        cums_half: &'a [CharSet; 10],
+        feasible: &'a [Feasible],
+        passwords: Vec<String>,
+    }
+    impl Dfs<'_> {
+        /// 発見済の解の個数が n_max に達したら true を返す。
+        fn dfs(&mut self, p: usize, acc: u8, flag: bool, cums: &mut [u8; 10]) -> bool {
+            if p == 9 {
+                self.passwords.push(
+                    cums.iter()
+                        .chain(cums.iter().rev())
+                        .map(|&cum| cum_to_password_char(cum))
+                        .collect(),
+                );
+                return self.passwords.len() == self.n_max;
+            }
+
+            for cum in self.cums_half[p + 1].cums() {
+                let (delta, flag_nxt) = match fold_step(p + 1, cums[p], cum, flag) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let acc_nxt = acc ^ delta;
+                if !self.feasible[p + 1].get(cum, acc_nxt, flag_nxt) {
+                    continue;
+                }
+                cums[p + 1] = cum;
+                if self.dfs(p + 1, acc_nxt, flag_nxt, cums) {
+                    return true;
+                }
+            }
+
+            false
+        }
+    }
+
+    let mut dfs = Dfs {
+        n_max,
+        cums_half: &cums_half,
+        feasible: &feasible,
+        passwords: vec![],
+    };
+    if n_max > 0 {
+        let mut cums = [0; 10];
+        for cum in cums_half[0].cums() {
+            if !feasible[0].get(cum, 0, false) {
+                continue;
+            }
+            cums[0] = cum;
+            if dfs.dfs(0, 0, false, &mut cums) {
+                break;
+            }
+        }
+    }
+
+    Ok(dfs.passwords)
+}
+
+/// 折り返し DP の各段の状態について、条件を満たす完成形に到達可能かどうか。
+///
+/// 状態は (累積値, CRC の累積 XOR, 4bit 境界をまたぐ道具IDのフラグ)。
+struct Feasible(Vec<bool>);
+
+impl Feasible {
+    fn new() -> Self {
+        Self(vec![false; 0x40 * 0x100 * 2])
+    }
+
+    fn index(cum: u8, acc: u8, flag: bool) -> usize {
+        (usize::from(cum) << 9) | (usize::from(acc) << 1) | usize::from(flag)
+    }
+
+    fn get(&self, cum: u8, acc: u8, flag: bool) -> bool {
+        self.0[Self::index(cum, acc, flag)]
+    }
+
+    fn set(&mut self, cum: u8, acc: u8, flag: bool) {
+        self.0[Self::index(cum, acc, flag)] = true;
+    }
+}
+
+/// 回文の前半の各文字を順に決めていく「折り返し DP」を後ろから行い、各段の到達可能性を求める。
+///
+/// 位置 p の文字を決めると、6bit 値配列の位置 p と 20-p の値が定まる(位置 10 の値は常に 0x3C)。
+/// 全ての値が定まったとき、CRC の累積 XOR が 0 ならばデコード可能である。
+fn palindrome_dp(cums_half: &[CharSet; 10]) -> Vec<Feasible> {
+    let mut feasible: Vec<_> = (0..10).map(|_| Feasible::new()).collect();
+
+    for cum in cums_half[9].cums() {
+        for flag in [false, true] {
+            feasible[9].set(cum, 0, flag);
+        }
+    }
+
+    for p in (0..9).rev() {
+        let (cur, nxt) = feasible.split_at_mut(p + 1);
+        let (cur, nxt) = (&mut cur[p], &nxt[0]);
+        for (cum_pre, flag, cum) in
+            itertools::iproduct!(cums_half[p].cums(), [false, true], cums_half[p + 1].cums())
+        {
+            let (delta, flag_nxt) = match fold_step(p + 1, cum_pre, cum, flag) {
+                Some(x) => x,
+                None => continue,
+            };
+            for acc in 0..=0xFF {
+                if nxt.get(cum, acc ^ delta, flag_nxt) {
+                    cur.set(cum_pre, acc, flag);
+                }
+            }
+        }
+    }
+
+    feasible
+}
+
+/// 折り返し DP の 1 段分の遷移を行う。`p` の範囲は `1..=9`。
+///
+/// 前半の位置 p-1, p の累積値から 6bit 値配列の位置 p, 20-p の値を求め、
+/// (CRC の累積 XOR への寄与, 次段のフラグ) を返す。無効なゲーム状態になる場合は None を返す。
+///
+/// フラグは 4bit 境界をまたぐ道具ID (6bit 値配列の位置 4-5, 8-9, 14-15, 18-19)の validate 用で、
+/// 片方の値を決めた段で立て、もう片方の値を決める次段で参照する。
+fn fold_step(p: usize, cum_pre: u8, cum: u8, flag: bool) -> Option<(u8, bool)> {
+    const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+    const CRC_TABLE_TAIL: [[u8; 0x40]; 18] = crc8_table_tail();
+
+    debug_assert!((1..=9).contains(&p));
+
+    let six_a = cum.wrapping_sub(cum_pre + 4) & 0x3F; // 位置 p
+    let six_b = cum_pre.wrapping_sub(cum + 4) & 0x3F; // 位置 20-p
+
+    if !six_is_valid(p, six_a, &ValidationPolicy::DQ1_FC)
+        || !six_is_valid(20 - p, six_b, &ValidationPolicy::DQ1_FC)
+    {
+        return None;
+    }
+
+    let (invalid, flag_nxt) = match p {
+        1 => (false, (six_b & 3) == 3),
+        2 | 6 => (flag && (six_b >> 4) == 3, false),
+        4 | 8 => (false, (six_a >> 4) == 3),
+        5 => (flag && (six_a & 3) == 3, (six_b & 3) == 3),
+        9 => (flag && (six_a & 3) == 3, false),
+        _ => (false, false),
+    };
+    if invalid {
+        return None;
+    }
+
+    let mut delta = CRC_TABLE_TAIL[18 - p][usize::from(six_b)];
+    if p == 1 {
+        // 先頭 2 つの値は CRC 下位バイトそのものと、CRC 計算対象の 4bit を含む。
+        let six_0 = cum_pre.wrapping_sub(4) & 0x3F;
+        delta ^= CRC_TABLE_HEAD[usize::from(six_a >> 2)] ^ six_0 ^ (six_a << 6);
+    } else {
+        delta ^= CRC_TABLE_TAIL[p - 2][usize::from(six_a)];
+    }
+    if p == 9 {
+        // 位置 10 の値は常に 0x3C。
+        delta ^= CRC_TABLE_TAIL[8][0x3C];
+    }
+
+    Some((delta, flag_nxt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::decode;
+
+    fn is_palindrome(s: &str) -> bool {
+        s.chars().eq(s.chars().rev())
+    }
+
+    #[test]
+    fn test_generate_palindromes() {
+        let passwords = generate_palindromes(30);
+        assert_eq!(passwords.len(), 30);
+        for password in &passwords {
+            assert!(is_palindrome(password));
+            assert!(decode(password).is_ok());
+        }
+        // 五十音表の順に並んでいる。
+        let keys: Vec<Vec<_>> = passwords
+            .iter()
+            .map(|p| {
+                p.chars()
+                    .map(|c| password_char_to_cum(c).unwrap())
+                    .collect()
+            })
+            .collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        assert!(generate_palindromes(0).is_empty());
+    }
+
+    #[test]
+    fn test_generate_palindromes_with_pattern() {
+        let passwords = generate_palindromes_with_pattern("ゆうてい ？？？？？？", 5).unwrap();
+        assert_eq!(passwords.len(), 5);
+        for password in &passwords {
+            assert!(password.starts_with("ゆうてい"));
+            assert!(is_palindrome(password));
+            assert!(decode(password).is_ok());
+        }
+
+        // 総当たりと一致する。
+        const HALF: &str = "あいそああいねあ";
+        let expect: Vec<_> = itertools::iproduct!(0..0x40, 0..0x40)
+            .map(|(cum0, cum1)| {
+                let half = format!(
+                    "{}{}{}",
+                    HALF,
+                    cum_to_password_char(cum0),
+                    cum_to_password_char(cum1)
+                );
+                let rev: String = half.chars().rev().collect();
+                format!("{}{}", half, rev)
+            })
+            .filter(|p| decode(p).is_ok())
+            .collect();
+        assert!(!expect.is_empty());
+        assert_eq!(
+            generate_palindromes_with_pattern(format!("{}??", HALF), 100),
+            Ok(expect)
+        );
+
+        assert!(matches!(
+            generate_palindromes_with_pattern("ゆうてい", 5),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_palindromes_with_pattern("ゆうていA?????", 5),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+    }
+}
@@ -0,0 +1,425 @@
+use itertools::Itertools as _;
+
+use crate::char_set::CharSet;
+use crate::decode::password_char_to_cum;
+use crate::encode::cum_to_password_char;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::generate::{
+    crc8_table_head, crc8_table_tail, six_transition, Constraints, GenerateOptions,
+};
+use crate::normalize::normalize_password_fragment;
+use crate::prelude::*;
+
+/// 復活の呪文を 1 文字ずつ入力する UI 向けの、部分的なデコーダ。
+///
+/// 入力済みの文字列がデコード可能な復活の呪文に補完できるかどうかや、
+/// 次に入力できる文字の集合を高速に求められる。
+/// CRC だけでなく、やくそう/かぎ所持数や道具IDの制約も考慮する。
+///
+/// 構築時に、残りの文字を任意としたときの到達可能性を後ろ向きの DP で求めておく。
+/// CRC は XOR について線形なので、tail 部の各段と DP の状態 l について
+/// 「残りの 6bit 値たちの CRC への寄与の XOR として取りうる値の集合」を持てば十分である。
+#[derive(Clone, Debug)]
+pub struct PartialDecoder {
+    /// `transitions[i][l][six]`: tail 部 i 個目で、状態 l から値 six を選ぶ場合の次の状態。
+    transitions: Vec<[[Option<u8>; 0x40]; 4]>,
+
+    /// `reachable[i][l]`: tail 部 i 個目までを決めて状態 l であるとき、
+    /// 残りの CRC への寄与の XOR として取りうる値の集合。
+    reachable: Vec<[ByteSet; 4]>,
+
+    /// 入力済みの文字の累積値たち。
+    cums: Vec<u8>,
+
+    /// 入力済みの各文字までを決めたときの状態。無効な 6bit 値を含む場合は None。
+    states: Vec<Option<State>>,
+}
+
+/// 入力済みの文字列に対応する状態。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// 1 文字入力済み。
+    Head(u8),
+
+    /// 2 文字以上入力済み。
+    Tail {
+        /// tail 部の CRC 累積 XOR の目標値(先頭 2 文字から定まる)。
+        target: u8,
+
+        /// tail 部の CRC 累積 XOR。
+        crc: u8,
+
+        /// DP の状態 l。
+        l: u8,
+    },
+}
+
+/// 0..=0xFF の値の集合。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    fn contains(&self, x: u8) -> bool {
+        (self.0[usize::from(x >> 6)] & (1 << (x & 0x3F))) != 0
+    }
+
+    fn insert(&mut self, x: u8) {
+        self.0[usize::from(x >> 6)] |= 1 << (x & 0x3F);
+    }
+}
+
+impl Default for PartialDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialDecoder {
+    /// 空の入力から始まるデコーダを返す。
+    pub fn new() -> Self {
+        const CRC_TABLE: [[u8; 0x40]; 18] = crc8_table_tail();
+
+        let options = GenerateOptions::default();
+        let constraints = Constraints::new(&options).unwrap();
+        let n_state = constraints.item_state_count();
+
+        let transitions: Vec<_> = (0..18)
+            .map(|i| {
+                let mut table = [[None; 0x40]; 4];
+                for (l, six) in itertools::iproduct!(0..n_state, 0..0x40) {
+                    table[usize::from(l)][usize::from(six)] =
+                        six_transition(i, l, six, &constraints);
+                }
+                table
+            })
+            .collect();
+
+        let mut reachable = vec![[ByteSet::default(); 4]; 19];
+        for set in &mut reachable[18][..usize::from(n_state)] {
+            set.insert(0);
+        }
+        for i in (0..18).rev() {
+            for (l, six) in itertools::iproduct!(0..usize::from(n_state), 0..0x40_u8) {
+                let l_nxt = match transitions[i][l][usize::from(six)] {
+                    Some(l_nxt) => usize::from(l_nxt),
+                    None => continue,
+                };
+                let delta = CRC_TABLE[i][usize::from(six)];
+                for x in 0..=0xFF {
+                    if reachable[i + 1][l_nxt].contains(x) {
+                        reachable[i][l].insert(x ^ delta);
+                    }
+                }
+            }
+        }
+
+        Self {
+            transitions,
+            reachable,
+            cums: Vec::with_capacity(20),
+            states: Vec::with_capacity(20),
+        }
+    }
+
+    /// 入力済みの文字数を返す。
+    pub fn len(&self) -> usize {
+        self.cums.len()
+    }
+
+    /// 入力済みの文字がなければ true を返す。
+    pub fn is_empty(&self) -> bool {
+        self.cums.is_empty()
+    }
+
+    /// 入力済みの文字列を返す。
+    pub fn prefix(&self) -> String {
+        self.cums
+            .iter()
+            .map(|&cum| cum_to_password_char(cum))
+            .collect()
+    }
+
+    /// 1 文字入力する。補完できない文字でも入力はできる([`is_feasible`](Self::is_feasible) で判定できる)。
+    ///
+    /// `c` が復活の呪文に使えない文字の場合、または既に 20 文字入力済みの場合、
+    /// `Err(Dq1PasswordError::InvalidPassword)` を返す。
+    pub fn push(&mut self, c: char) -> Dq1PasswordResult<()> {
+        let cum = password_char_to_cum(c).ok_or_else(|| {
+            Dq1PasswordError::invalid_password_chars(
+                vec![(self.len(), c)],
+                format!("復活の呪文に使えない文字: '{}'", c),
+            )
+        })?;
+        if self.len() == 20 {
+            return Err(Dq1PasswordError::invalid_password_length(
+                21,
+                "復活の呪文は 20 文字を超えられない",
+            ));
+        }
+
+        let state = self.next_state(cum);
+        self.cums.push(cum);
+        self.states.push(state);
+
+        Ok(())
+    }
+
+    /// 文字列を [`normalize_password`](crate::normalize_password) と同様に正規化し、1 文字ずつ入力する。
+    ///
+    /// 空白文字は無視し、カタカナはひらがなに変換し、分離した濁点/半濁点は直前の文字と合成する
+    /// (`s` 内で合成できるもののみ)。
+    ///
+    /// 正規化後の文字列が復活の呪文に使えない文字を含む場合、または入力済みのものと合わせて 20 文字を超える場合、
+    /// 何も入力せずに `Err(Dq1PasswordError::InvalidPassword)` を返す。
+    pub fn push_str(&mut self, s: impl AsRef<str>) -> Dq1PasswordResult<()> {
+        let folded = normalize_password_fragment(s.as_ref());
+
+        let offenders: Vec<_> = folded
+            .iter()
+            .enumerate()
+            .filter(|(_, (c, _))| password_char_to_cum(*c).is_none())
+            .map(|(i, &(c, _))| (self.len() + i, c))
+            .collect();
+        if !offenders.is_empty() {
+            return Err(Dq1PasswordError::invalid_password_chars(
+                offenders,
+                format!(
+                    "復活の呪文に使えない文字: {}",
+                    folded
+                        .iter()
+                        .filter(|(c, _)| password_char_to_cum(*c).is_none())
+                        .map(|(_, original)| format!("'{}'", original))
+                        .join(", ")
+                ),
+            ));
+        }
+        if self.len() + folded.len() > 20 {
+            return Err(Dq1PasswordError::invalid_password_length(
+                self.len() + folded.len(),
+                "復活の呪文は 20 文字を超えられない",
+            ));
+        }
+
+        for (c, _) in folded {
+            self.push(c).unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// 最後に入力した文字を取り消して返す。入力済みの文字がなければ None を返す。
+    pub fn pop(&mut self) -> Option<char> {
+        self.states.pop();
+        self.cums.pop().map(cum_to_password_char)
+    }
+
+    /// 入力済みの文字列が、デコード可能な復活の呪文に補完できるかどうかを返す。
+    pub fn is_feasible(&self) -> bool {
+        match self.states.last() {
+            None => true,
+            Some(&state) => state.is_some_and(|state| self.state_is_feasible(self.len(), state)),
+        }
+    }
+
+    /// 次に入力したとき、デコード可能な復活の呪文に補完できる文字の集合を返す。
+    ///
+    /// 入力済みの文字列が補完できない場合や、既に 20 文字入力済みの場合は空集合を返す。
+    pub fn valid_next_chars(&self) -> CharSet {
+        let mut set = CharSet::new();
+        if self.len() == 20 || !self.is_feasible() {
+            return set;
+        }
+
+        for cum in 0..0x40 {
+            if self
+                .next_state(cum)
+                .is_some_and(|state| self.state_is_feasible(self.len() + 1, state))
+            {
+                set.insert_cum(cum);
+            }
+        }
+
+        set
+    }
+
+    /// 20 文字入力済みで、それがデコード可能な復活の呪文ならば true を返す。
+    pub fn is_complete_valid(&self) -> bool {
+        self.len() == 20 && self.is_feasible()
+    }
+
+    /// 次に累積値 `cum` の文字を入力したときの状態を返す。
+    fn next_state(&self, cum: u8) -> Option<State> {
+        const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+        const CRC_TABLE: [[u8; 0x40]; 18] = crc8_table_tail();
+
+        let n = self.len();
+        let cum_pre = if n == 0 { 0 } else { self.cums[n - 1] };
+        let six = cum.wrapping_sub(cum_pre + 4) & 0x3F;
+
+        match self.states.last() {
+            None => Some(State::Head(six)),
+            Some(None) => None,
+            Some(Some(State::Head(six0))) => Some(State::Tail {
+                target: CRC_TABLE_HEAD[usize::from(six >> 2)] ^ six0 ^ (six << 6),
+                crc: 0,
+                l: 0,
+            }),
+            Some(Some(State::Tail { target, crc, l })) => {
+                let i = n - 2;
+                let l_nxt = self.transitions[i][usize::from(*l)][usize::from(six)]?;
+                Some(State::Tail {
+                    target: *target,
+                    crc: crc ^ CRC_TABLE[i][usize::from(six)],
+                    l: l_nxt,
+                })
+            }
+        }
+    }
+
+    /// `n` 文字入力済みで状態が `state` のとき、補完できるかどうかを返す。
+    fn state_is_feasible(&self, n: usize, state: State) -> bool {
+        const CRC_TABLE_HEAD: [u8; 0x10] = crc8_table_head();
+
+        match state {
+            State::Head(six0) => (0..0x40).any(|six1: u8| {
+                let target = CRC_TABLE_HEAD[usize::from(six1 >> 2)] ^ six0 ^ (six1 << 6);
+                self.reachable[0][0].contains(target)
+            }),
+            State::Tail { target, crc, l } => {
+                self.reachable[n - 2][usize::from(l)].contains(target ^ crc)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::convert::TryFrom;
+
+    use crate::analyze::feasible_chars;
+    use crate::decode::decode;
+    use crate::error::PasswordErrorKind;
+
+    #[test]
+    fn test_partial_decoder_follow_suggestions() {
+        // 提案された文字を(疑似乱数で)選び続けると、必ずデコード可能な復活の呪文になる。
+        let mut rng: u64 = 12345;
+        for _ in 0..20 {
+            let mut decoder = PartialDecoder::new();
+            while decoder.len() < 20 {
+                let cs: Vec<_> = decoder.valid_next_chars().iter().collect();
+                assert!(!cs.is_empty());
+                rng = rng
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let c = cs[usize::try_from(rng >> 33).unwrap() % cs.len()];
+                decoder.push(c).unwrap();
+                assert!(decoder.is_feasible());
+            }
+            assert!(decoder.is_complete_valid());
+            assert!(decode(decoder.prefix()).is_ok());
+            assert!(decoder.valid_next_chars().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_partial_decoder() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        let mut decoder = PartialDecoder::new();
+        assert!(decoder.is_feasible());
+        assert_eq!(decoder.valid_next_chars(), CharSet::full());
+        for c in PASSWORD.chars() {
+            assert!(decoder.valid_next_chars().contains(c));
+            decoder.push(c).unwrap();
+        }
+        assert!(decoder.is_complete_valid());
+        assert!(matches!(
+            decoder.push('あ'),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+
+        // 最後の文字を変えるとデコードできない。
+        assert_eq!(decoder.pop(), Some('え'));
+        let expect: Vec<_> = (0..0x40)
+            .map(cum_to_password_char)
+            .filter(|&c| decode(format!("{}{}", decoder.prefix(), c)).is_ok())
+            .collect();
+        assert!(!expect.is_empty());
+        assert_eq!(
+            decoder.valid_next_chars().iter().collect::<Vec<_>>(),
+            expect
+        );
+        decoder.push('お').unwrap();
+        assert!(!decoder.is_complete_valid());
+        assert!(!decoder.is_feasible());
+
+        // 途中の位置で、パターンに対する feasible_chars と一致する。
+        for n in [2, 10] {
+            let prefix: String = PASSWORD.chars().take(n).collect();
+            let mut decoder = PartialDecoder::new();
+            for c in prefix.chars() {
+                decoder.push(c).unwrap();
+            }
+            let pattern = format!("{}{}", prefix, "?".repeat(20 - n));
+            assert_eq!(
+                decoder.valid_next_chars(),
+                feasible_chars(pattern).unwrap()[n]
+            );
+        }
+
+        // やくそう所持数が 7 になる(位置 13 の 6bit 値の上位 4bit が 7)ものは補完できない。
+        let mut decoder = PartialDecoder::new();
+        for c in PASSWORD.chars().take(13) {
+            decoder.push(c).unwrap();
+        }
+        let cum12 = password_char_to_cum(PASSWORD.chars().nth(12).unwrap()).unwrap();
+        let bad = cum_to_password_char((cum12 + (7 << 2) + 4) & 0x3F);
+        assert!(!decoder.valid_next_chars().contains(bad));
+        decoder.push(bad).unwrap();
+        assert!(!decoder.is_feasible());
+        assert!(decoder.valid_next_chars().is_empty());
+        assert_eq!(decoder.pop(), Some(bad));
+        assert!(decoder.is_feasible());
+
+        assert!(matches!(
+            decoder.push('A'),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_decoder_push_str() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        // 正規化してから入力する。
+        let mut decoder = PartialDecoder::new();
+        decoder.push_str("ザボ チス゛").unwrap();
+        assert_eq!(decoder.prefix(), "ざぼちず");
+        decoder.push_str("").unwrap();
+        assert_eq!(decoder.len(), 4);
+        decoder
+            .push_str(PASSWORD.chars().skip(4).collect::<String>())
+            .unwrap();
+        assert!(decoder.is_complete_valid());
+
+        // 無効な文字を含む場合や 20 文字を超える場合は何も入力しない。
+        let mut decoder = PartialDecoder::new();
+        decoder.push_str("ざぼ").unwrap();
+        let err = decoder.push_str("ちAず").unwrap_err();
+        match err {
+            Dq1PasswordError::InvalidPassword(e) => {
+                assert_eq!(e.kind(), &PasswordErrorKind::InvalidChars(vec![(3, 'A')]));
+            }
+            _ => panic!("{:?}", err),
+        }
+        assert_eq!(decoder.prefix(), "ざぼ");
+        assert!(matches!(
+            decoder.push_str(PASSWORD),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+        assert_eq!(decoder.prefix(), "ざぼ");
+    }
+}
@@ -0,0 +1,519 @@
+use core::str::FromStr;
+
+use crate::char_set::CharSet;
+use crate::decode::password_char_to_cum;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::normalize::{normalize_password, normalize_pattern, split_pattern_slots, PatternSlot};
+use crate::prelude::*;
+
+/// 復活の呪文パターン。
+///
+/// 各位置について、その位置に許される文字の集合を保持する。
+/// また、同じ文字でなければならない位置たちの組(タイ)を保持する。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Pattern {
+    /// 各位置に許される文字の集合。'?' の部分は全ての文字からなる。
+    char_sets: [CharSet; 20],
+
+    /// 同じ文字でなければならない位置たちの組。各組は 2 要素以上で昇順に並び、互いに素。
+    ties: Vec<Vec<usize>>,
+}
+
+impl Pattern {
+    /// 文字列からパターンを作る。正規化していないものも許す。
+    ///
+    /// 各位置は文字、'?' (任意の文字)、文字クラス(`[かが]` のように許す文字を並べたもの)、
+    /// または否定文字クラス(`[^ぼ]` のように除外する文字を並べたもの)のいずれか。
+    /// 文字クラス内には `[あ-お]` のような範囲も書ける。詳細は [`normalize_pattern`] を参照。
+    /// 空白文字の除去と全角記号の置換は構文解析の前に行われるので、
+    /// 例えば `［＾ ぼ］` は `[^ぼ]` と同じ意味になる。
+    ///
+    /// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+    ///
+    /// グループ番号によるタイの組み合わせが多すぎる場合、`Err(Dq1PasswordError::ResourceLimit)` を返す
+    /// ([`tie`](Self::tie) を参照)。
+    pub fn parse(pattern: impl AsRef<str>) -> Dq1PasswordResult<Self> {
+        let cs: Vec<_> = normalize_pattern(pattern)?.chars().collect();
+
+        let mut char_sets = [CharSet::new(); 20];
+        let mut groups: [Vec<usize>; 9] = Default::default();
+        for (i, (set, (slot, group))) in
+            core::iter::zip(&mut char_sets, split_pattern_slots(&cs)?).enumerate()
+        {
+            if let Some(group) = group {
+                groups[usize::from(group - 1)].push(i);
+            }
+            *set = match slot {
+                PatternSlot::Any => CharSet::full(),
+                PatternSlot::Star => unreachable!("'*' は正規化で展開済み"),
+                PatternSlot::Char(c) => char_set_of(&[c]),
+                PatternSlot::Class(cs) => char_set_of(&cs),
+                PatternSlot::NegatedClass(cs) => CharSet::from_bits(!char_set_of(&cs).bits()),
+            };
+        }
+
+        groups
+            .iter()
+            .try_fold(Self::from_char_sets(&char_sets), |pattern, positions| {
+                pattern.tie(positions)
+            })
+    }
+
+    /// 各位置の文字からパターンを作る。None の位置は任意の文字にマッチする。
+    ///
+    /// 文字は正規化されていなければならない(空白文字や '?' は許されない)。
+    ///
+    /// 復活の呪文に使えない文字が含まれる場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+    pub fn from_slots(slots: &[Option<char>; 20]) -> Dq1PasswordResult<Self> {
+        let mut char_sets = [CharSet::full(); 20];
+
+        for (i, (set, &slot)) in core::iter::zip(&mut char_sets, slots).enumerate() {
+            if let Some(c) = slot {
+                if password_char_to_cum(c).is_none() {
+                    return Err(Dq1PasswordError::invalid_pattern_chars(
+                        vec![(i, c)],
+                        format!("位置 {} の文字が復活の呪文に使えない: '{}'", i, c),
+                    ));
+                }
+                *set = char_set_of(&[c]);
+            }
+        }
+
+        Ok(Self::from_char_sets(&char_sets))
+    }
+
+    /// 各位置に許される文字の集合からパターンを作る。空集合の位置があれば何にもマッチしない。
+    pub fn from_char_sets(char_sets: &[CharSet; 20]) -> Self {
+        Self {
+            char_sets: *char_sets,
+            ties: vec![],
+        }
+    }
+
+    /// 位置たち `positions` が全て同じ文字でなければならないという制約を加えたパターンを返す。
+    ///
+    /// 既存の組と位置を共有する場合、それらは 1 つの組に統合される。
+    /// `positions` が 1 要素以下の場合は何もしない。
+    ///
+    /// 20 以上の位置が含まれる場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+    ///
+    /// タイは各組の文字を総当たりして解消するので、各組の位置たちに共通して許される文字数の積が
+    /// [`UNTIED_COUNT_MAX`] を超える場合、`Err(Dq1PasswordError::ResourceLimit)` を返す。
+    pub fn tie(mut self, positions: &[usize]) -> Dq1PasswordResult<Self> {
+        if let Some(&pos) = positions.iter().find(|&&pos| pos >= 20) {
+            return Err(Dq1PasswordError::invalid_pattern(format!(
+                "位置 {} はパターンの範囲外",
+                pos
+            )));
+        }
+
+        let mut merged = positions.to_vec();
+        self.ties.retain(|tie| {
+            let overlaps = tie.iter().any(|pos| positions.contains(pos));
+            if overlaps {
+                merged.extend(tie);
+            }
+            !overlaps
+        });
+        merged.sort_unstable();
+        merged.dedup();
+        if merged.len() >= 2 {
+            self.ties.push(merged);
+            self.ties.sort_unstable();
+        }
+
+        let n_variant = self.tie_commons().try_fold(1_usize, |acc, common| {
+            acc.checked_mul(common.len())
+                .filter(|&n| n <= UNTIED_COUNT_MAX)
+        });
+        if n_variant.is_none() {
+            return Err(Dq1PasswordError::resource_limit(format!(
+                "タイを解消したパターンの個数が上限 {} を超える",
+                UNTIED_COUNT_MAX
+            )));
+        }
+
+        Ok(self)
+    }
+
+    /// 同じ文字でなければならない位置たちの組を返す。各組は昇順に並ぶ。
+    pub fn ties(&self) -> &[Vec<usize>] {
+        &self.ties
+    }
+
+    /// 復活の呪文がこのパターンにマッチするかどうかを返す。正規化していないものも許す。
+    ///
+    /// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+    pub fn matches(&self, password: impl AsRef<str>) -> Dq1PasswordResult<bool> {
+        let mut cums = [0; 20];
+
+        for (e, c) in core::iter::zip(&mut cums, normalize_password(password)?.chars()) {
+            *e = password_char_to_cum(c).unwrap();
+        }
+
+        Ok(self.matches_cums(&cums))
+    }
+
+    /// 各位置に許される文字の集合を返す。
+    pub fn char_sets(&self) -> &[CharSet; 20] {
+        &self.char_sets
+    }
+
+    /// 累積値の配列がこのパターンにマッチするかどうかを返す。
+    pub(crate) fn matches_cums(&self, cums: &[u8; 20]) -> bool {
+        core::iter::zip(&self.char_sets, cums).all(|(set, &cum)| set.contains_cum(cum))
+            && self
+                .ties
+                .iter()
+                .all(|tie| tie.iter().all(|&pos| cums[pos] == cums[tie[0]]))
+    }
+
+    /// タイを全て解消した、各位置に許される文字の集合たちを返す。
+    ///
+    /// 各組の文字を外側で総当たりし、組の位置たちをその文字に固定したものを順に返す。
+    /// これらにマッチするものの全体は、このパターンにマッチするもの全体と一致し、互いに重複しない。
+    /// タイがなければ [`char_sets`](Self::char_sets) のみを返す。
+    pub(crate) fn untied_char_sets(&self) -> impl Iterator<Item = [CharSet; 20]> + '_ {
+        (0..self.untied_count()).map(move |idx| self.untied_char_sets_at(idx))
+    }
+
+    /// タイを解消したものの個数、すなわち [`untied_char_sets`](Self::untied_char_sets) の要素数を返す。
+    ///
+    /// [`tie`](Self::tie) で [`UNTIED_COUNT_MAX`] 以下であることを確認済みなので、オーバーフローしない。
+    pub(crate) fn untied_count(&self) -> usize {
+        self.tie_commons().map(|set| set.len()).product()
+    }
+
+    /// [`untied_char_sets`](Self::untied_char_sets) の `idx` 番目(0 始まり)の要素を返す。
+    pub(crate) fn untied_char_sets_at(&self, mut idx: usize) -> [CharSet; 20] {
+        let commons: Vec<CharSet> = self.tie_commons().collect();
+
+        let mut char_sets = self.char_sets;
+        // 最初の組が最も外側になるよう、末尾の組から idx を分解する。
+        for (tie, common) in core::iter::zip(&self.ties, &commons).rev() {
+            let cum = common.cums().nth(idx % common.len()).unwrap();
+            idx /= common.len();
+            for &pos in tie {
+                char_sets[pos] = CharSet::from_bits(1 << cum);
+            }
+        }
+        char_sets
+    }
+
+    /// 各組の位置たちに共通して許される文字の集合たちを返す。
+    fn tie_commons(&self) -> impl Iterator<Item = CharSet> + '_ {
+        self.ties.iter().map(move |tie| {
+            CharSet::from_bits(
+                tie.iter()
+                    .fold(u64::MAX, |acc, &pos| acc & self.char_sets[pos].bits()),
+            )
+        })
+    }
+}
+
+/// タイを解消したパターンの個数の上限。全ての文字を許す組 4 つ分(64^4)。
+///
+/// これを超えると総当たりが現実的でなく、32bit 環境では個数が `usize` に収まらないこともある。
+pub const UNTIED_COUNT_MAX: usize = 1 << 24;
+
+/// 復活の呪文に使える文字たちからなる集合を返す。
+fn char_set_of(cs: &[char]) -> CharSet {
+    let mut set = CharSet::new();
+    for &c in cs {
+        set.insert(c);
+    }
+    set
+}
+
+impl FromStr for Pattern {
+    type Err = Dq1PasswordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::convert::TryInto;
+
+    use crate::encode::cum_to_password_char;
+    use crate::error::PatternErrorKind;
+
+    #[test]
+    fn test_parse() {
+        let pattern = Pattern::parse("あいう? ？あああああああああああああああ").unwrap();
+        let set = |c| char_set_of(&[c]);
+        assert_eq!(
+            pattern.char_sets()[..5],
+            [
+                set('あ'),
+                set('い'),
+                set('う'),
+                CharSet::full(),
+                CharSet::full()
+            ]
+        );
+        assert_eq!(pattern.char_sets()[5..], [set('あ'); 15]);
+
+        assert!(matches!(
+            Pattern::parse("あああ"),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+
+        assert_eq!(
+            Pattern::parse("ざぼちずどぢ*のぢえ"),
+            Pattern::parse("ざぼちずどぢ???????????のぢえ")
+        );
+    }
+
+    #[test]
+    fn test_parse_char_class() {
+        let pattern = Pattern::parse("ざぼ[ちさ]ずど?ぎきつたうずせれえむるのぢ［え お］").unwrap();
+        assert_eq!(pattern.char_sets()[2], char_set_of(&['ち', 'さ']));
+        assert_eq!(pattern.char_sets()[5], CharSet::full());
+        assert_eq!(pattern.char_sets()[19], char_set_of(&['え', 'お']));
+
+        assert_eq!(
+            pattern.matches("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("ざぼさずどあぎきつたうずせれえむるのぢお"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("ざぼちずどぢぎきつたうずせれえむるのぢあ"),
+            Ok(false)
+        );
+
+        let err = |s: &str| match Pattern::parse(s) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => e.message().to_owned(),
+            res => panic!("{:?}", res),
+        };
+        assert!(err("ざぼ[ち[さ]]ずど?ぎきつたうずせれえむるのぢえ").contains("入れ子"));
+        assert!(err("ざぼ[ちさずどぎきつたうずせれえむるのぢえ").contains("閉じられていない"));
+        assert!(err("ざぼち]ずど?ぎきつたうずせれえむるのぢえ").contains("位置 3"));
+        assert!(err("ざぼ[]ずど?ぎきつたうずせれえむるのぢえ").contains("空"));
+        assert!(err("ざぼちずど?ぎきつたうずせれえむるの[ぢ漢]え").contains("位置 18"));
+        assert!(err("ざぼちずど?ぎきつたうずせれえむるの[ぢ?]え").contains("'?'"));
+        // 文字クラスは 1 文字と数える。
+        assert!(Pattern::parse("[ざ]ぼちずどぢぎきつたうずせれえむるのぢえ").is_ok());
+        assert!(Pattern::parse("[ざ]ぼちずどぢぎきつたうずせれえむるのぢえ?").is_err());
+    }
+
+    #[test]
+    fn test_parse_negated_char_class() {
+        let pattern =
+            Pattern::parse("ざぼ[^ちさ]ずど?ぎきつたうずせれえむるのぢ［＾ え お］").unwrap();
+        assert_eq!(pattern.char_sets()[2].len(), 62);
+        assert!(!pattern.char_sets()[2].contains('ち'));
+        assert!(!pattern.char_sets()[2].contains('さ'));
+        assert_eq!(
+            pattern.char_sets()[19].bits(),
+            !char_set_of(&['え', 'お']).bits()
+        );
+
+        assert_eq!(
+            pattern.matches("ざぼあずどぢぎきつたうずせれえむるのぢか"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("ざぼちずどぢぎきつたうずせれえむるのぢか"),
+            Ok(false)
+        );
+        assert_eq!(
+            pattern.matches("ざぼあずどぢぎきつたうずせれえむるのぢお"),
+            Ok(false)
+        );
+
+        // 重複した文字は許す。
+        assert_eq!(
+            Pattern::parse("[^ざざ]ぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Pattern::parse("[^ざ]ぼちずどぢぎきつたうずせれえむるのぢえ")
+        );
+
+        let err = |s: &str| match Pattern::parse(s) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => e.message().to_owned(),
+            res => panic!("{:?}", res),
+        };
+        assert!(err("ざぼ[^]ずど?ぎきつたうずせれえむるのぢえ").contains("空"));
+        assert!(err("ざぼ[^ち^]ずど?ぎきつたうずせれえむるのぢえ").contains("'^'"));
+        assert!(Pattern::parse("ざぼ^ずど?ぎきつたうずせれえむるのぢえ").is_err());
+        let all: String = (0..0x40).map(cum_to_password_char).collect();
+        assert!(
+            err(&format!("ざぼ[^{}]ずど?ぎきつたうずせれえむるのぢえ", all)).contains("位置 2")
+        );
+        let all_but_one: String = (1..0x40).map(cum_to_password_char).collect();
+        let pattern = Pattern::parse(format!(
+            "ざぼ[^{}]ずど?ぎきつたうずせれえむるのぢえ",
+            all_but_one
+        ))
+        .unwrap();
+        assert_eq!(
+            pattern.char_sets()[2],
+            char_set_of(&[cum_to_password_char(0)])
+        );
+    }
+
+    #[test]
+    fn test_parse_char_class_range() {
+        let pattern =
+            Pattern::parse("ざぼ[あ-お]ずど?ぎきつたうずせれえむるの［わ－ぎ／ぼ］[^か-こ/ま]")
+                .unwrap();
+        assert_eq!(
+            pattern.char_sets()[2],
+            char_set_of(&['あ', 'い', 'う', 'え', 'お'])
+        );
+        // 濁音の境界をまたぐ範囲。
+        assert_eq!(
+            pattern.char_sets()[18],
+            char_set_of(&['わ', 'が', 'ぎ', 'ぼ'])
+        );
+        assert_eq!(
+            pattern.char_sets()[19].bits(),
+            !char_set_of(&['か', 'き', 'く', 'け', 'こ', 'ま']).bits()
+        );
+
+        // 始点と終点が等しい範囲は 1 文字。
+        assert_eq!(
+            Pattern::parse("[ざ-ざ]ぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Pattern::parse("ざぼちずどぢぎきつたうずせれえむるのぢえ")
+        );
+        assert_eq!(
+            Pattern::parse("[あ-ぼ]ぼちずどぢぎきつたうずせれえむるのぢえ")
+                .unwrap()
+                .char_sets()[0],
+            CharSet::full()
+        );
+
+        let err = |s: &str| match Pattern::parse(s) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => e.message().to_owned(),
+            res => panic!("{:?}", res),
+        };
+        let msg = err("ざぼ[お-あ]ずど?ぎきつたうずせれえむるのぢえ");
+        assert!(msg.contains("位置 2") && msg.contains("'お-あ'"));
+        assert!(err("ざぼ[漢-お]ずど?ぎきつたうずせれえむるのぢえ").contains("'漢-お'"));
+        assert!(err("ざぼ[あ-]ずど?ぎきつたうずせれえむるのぢえ").contains("終点"));
+        assert!(err("ざぼ[あ-/お]ずど?ぎきつたうずせれえむるのぢえ").contains("終点"));
+        assert!(err("ざぼ[-お]ずど?ぎきつたうずせれえむるのぢえ").contains("始点"));
+        assert!(err("ざぼ[あ-い-う]ずど?ぎきつたうずせれえむるのぢえ").contains("始点"));
+        assert!(err("ざぼ[／]ずど?ぎきつたうずせれえむるのぢえ").contains("空"));
+    }
+
+    #[test]
+    fn test_tie() {
+        let pattern = Pattern::parse("?1ぼちずど?2ぎきつた[うえ]1ずせれえむ?2のぢ?１").unwrap();
+        assert_eq!(pattern.ties(), [vec![0, 10, 19], vec![5, 16]]);
+        assert_eq!(pattern.char_sets()[10], char_set_of(&['う', 'え']));
+
+        assert_eq!(
+            pattern.matches("うぼちずどあぎきつたうずせれえむあのぢう"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("うぼちずどあぎきつたうずせれえむいのぢう"),
+            Ok(false)
+        );
+        assert_eq!(
+            pattern.matches("うぼちずどあぎきつたえずせれえむあのぢう"),
+            Ok(false)
+        );
+
+        // 解消したパターンは、共通して許される文字ごとに 1 個ずつ。
+        let untied: Vec<_> = pattern.untied_char_sets().collect();
+        assert_eq!(untied.len(), 2 * 64);
+        assert_eq!(untied[0][0], char_set_of(&['う']));
+        assert_eq!(untied[0][5], char_set_of(&['あ']));
+        assert_eq!(untied[1][5], char_set_of(&['い']));
+        assert_eq!(untied[64][19], char_set_of(&['え']));
+
+        // API で作っても同じ。重なる組は統合される。
+        let pattern_api = Pattern::parse("?ぼちずど?ぎきつた[うえ]ずせれえむ?のぢ?")
+            .unwrap()
+            .tie(&[0, 10])
+            .unwrap()
+            .tie(&[16, 5])
+            .unwrap()
+            .tie(&[19, 10])
+            .unwrap()
+            .tie(&[3])
+            .unwrap();
+        assert_eq!(pattern_api, pattern);
+
+        // 共通して許される文字がなければ何にもマッチしない。
+        let pattern = Pattern::parse("ざ1ぼちずどぢぎきつたうずせれえむるのぢえ").map(|_| ());
+        assert!(pattern.is_err());
+        let pattern = Pattern::parse("[ざ]1ぼちずどぢぎきつたうずせれえむるのぢ[え]1").unwrap();
+        assert_eq!(pattern.untied_char_sets().count(), 0);
+
+        assert!(matches!(
+            Pattern::from_char_sets(&[CharSet::full(); 20]).tie(&[3, 20]),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+
+        // 解消したパターンの個数が上限を超える組み合わせは作れない。
+        let pattern = Pattern::parse("?1?1?2?2?3?3?4?4????????????").unwrap();
+        assert_eq!(pattern.ties().len(), 4);
+        assert!(matches!(
+            Pattern::parse("?1?1?2?2?3?3?4?4?5?5??????????"),
+            Err(Dq1PasswordError::ResourceLimit(_))
+        ));
+        assert!(matches!(
+            Pattern::parse("?1?1?2?2?3?3?4?4?5?5?6?6????????"),
+            Err(Dq1PasswordError::ResourceLimit(_))
+        ));
+        // 共通して許される文字が少なければ、組が多くてもよい。
+        let pattern =
+            Pattern::parse("[あい]1[あい]1[あい]2[あい]2[あい]3[あい]3[あい]4[あい]4[あい]5[あい]5[あい]6[あい]6????????")
+                .unwrap();
+        assert_eq!(pattern.untied_char_sets().count(), 1 << 6);
+    }
+
+    #[test]
+    fn test_from_slots() {
+        for s in [
+            "あいう? ？あああああああああああああああ",
+            "ざぼちずどぢぎきつたうずせれえむるのぢ?",
+            "????????????????????",
+        ] {
+            let slots: Vec<_> = normalize_pattern(s)
+                .unwrap()
+                .chars()
+                .map(|c| (c != '?').then_some(c))
+                .collect();
+            let slots: [_; 20] = slots.try_into().unwrap();
+            assert_eq!(Pattern::from_slots(&slots), Pattern::parse(s));
+        }
+
+        let mut slots = [Some('あ'); 20];
+        slots[3] = None;
+        slots[7] = Some('ア');
+        let err = Pattern::from_slots(&slots).unwrap_err();
+        assert!(
+            matches!(&err, Dq1PasswordError::InvalidPattern(e) if e.kind() == &PatternErrorKind::InvalidChars(vec![(7, 'ア')]))
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let pattern = Pattern::parse("ざぼちずどぢぎきつたうずせれえむるのぢ?").unwrap();
+
+        assert_eq!(
+            pattern.matches("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("ざぼちず どぢぎき つたうず せれえむ るのぢお"),
+            Ok(true)
+        );
+        assert_eq!(
+            pattern.matches("あぼちずどぢぎきつたうずせれえむるのぢえ"),
+            Ok(false)
+        );
+
+        assert!(pattern.matches("ざぼちず").is_err());
+    }
+}
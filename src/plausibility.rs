@@ -0,0 +1,269 @@
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// ゲーム状態が実際のプレイでは生じないと考えられる理由。
+///
+/// 復活の呪文としては有効だが、不自然なゲーム状態を検出するためのもの。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Implausibility {
+    /// せんしのゆびわを装備しているが、所持していない。
+    WarriorRingNotOwned,
+
+    /// りゅうのうろこを装備しているが、所持していない。
+    DragonScaleNotOwned,
+
+    /// にじのしずくを、その材料(たいようのいし、あまぐものつえ)と同時に所持している。
+    ///
+    /// 材料はにじのしずくと引き換えに失われる。
+    RainbowDropWithMaterial,
+
+    /// しのくびかざりを所持しているが、取得済フラグが立っていない。
+    DeathNecklaceWithoutFlag,
+
+    /// ゴーレムを倒しているが、経験値が 0。
+    GolemBeatenWithoutXp,
+
+    /// ドラゴンを倒しているが、経験値が 0。
+    DragonBeatenWithoutXp,
+
+    /// おうじょのあいを所持しているが、ドラゴンを倒していない。
+    PrincessLoveWithoutDragon,
+}
+
+impl Implausibility {
+    /// この不自然さに関わる [`GameState`] のフィールド名たちを返す。
+    pub fn fields(self) -> &'static [&'static str] {
+        match self {
+            Self::WarriorRingNotOwned => &["flag_equip_warrior_ring", "inventory"],
+            Self::DragonScaleNotOwned => &["flag_equip_dragon_scale", "inventory"],
+            Self::RainbowDropWithMaterial => &["inventory"],
+            Self::DeathNecklaceWithoutFlag => &["inventory", "flag_got_death_necklace"],
+            Self::GolemBeatenWithoutXp => &["flag_beated_golem", "hero_xp"],
+            Self::DragonBeatenWithoutXp => &["flag_beated_dragon", "hero_xp"],
+            Self::PrincessLoveWithoutDragon => &["inventory", "flag_beated_dragon"],
+        }
+    }
+}
+
+impl GameState {
+    /// ゲーム状態の不自然な点を全て返す。実際のプレイで生じうると考えられる場合は空となる。
+    ///
+    /// 規則は [`Implausibility`] の各値を参照。
+    /// [`decode_with_warnings`](crate::decode_with_warnings) や `GenerateOptions::plausible_only`
+    /// はこれに基づく。
+    ///
+    /// `self` は有効でなければならない。
+    pub fn plausibility(&self) -> Vec<Implausibility> {
+        check_rules(self)
+    }
+}
+
+/// ゲーム状態の不自然な点を全て返す。[`GameState::plausibility`] と同じ。
+///
+/// `state` は有効でなければならない。
+pub fn check_plausibility(state: &GameState) -> Vec<Implausibility> {
+    state.plausibility()
+}
+
+/// ゲーム状態が実際のプレイで生じうると考えられるかどうかを返す。[`GameState::plausibility`] を参照。
+///
+/// `state` は有効でなければならない。
+pub fn is_plausible(state: &GameState) -> bool {
+    state.plausibility().is_empty()
+}
+
+fn check_rules(state: &GameState) -> Vec<Implausibility> {
+    const DRAGON_SCALE: u8 = 4;
+    const WARRIOR_RING: u8 = 6;
+    const PRINCESS_LOVE: u8 = 8;
+    const DEATH_NECKLACE: u8 = 11;
+    const SUN_STONE: u8 = 12;
+    const STAFF_OF_RAIN: u8 = 13;
+    const RAINBOW_DROP: u8 = 14;
+
+    let owns = |item: u8| state.inventory.contains(&item);
+
+    let rules = [
+        (
+            state.flag_equip_warrior_ring && !owns(WARRIOR_RING),
+            Implausibility::WarriorRingNotOwned,
+        ),
+        (
+            state.flag_equip_dragon_scale && !owns(DRAGON_SCALE),
+            Implausibility::DragonScaleNotOwned,
+        ),
+        (
+            owns(RAINBOW_DROP) && (owns(SUN_STONE) || owns(STAFF_OF_RAIN)),
+            Implausibility::RainbowDropWithMaterial,
+        ),
+        (
+            owns(DEATH_NECKLACE) && !state.flag_got_death_necklace,
+            Implausibility::DeathNecklaceWithoutFlag,
+        ),
+        (
+            state.flag_beated_golem && state.hero_xp == 0,
+            Implausibility::GolemBeatenWithoutXp,
+        ),
+        (
+            state.flag_beated_dragon && state.hero_xp == 0,
+            Implausibility::DragonBeatenWithoutXp,
+        ),
+        (
+            owns(PRINCESS_LOVE) && !state.flag_beated_dragon,
+            Implausibility::PrincessLoveWithoutDragon,
+        ),
+    ];
+
+    rules
+        .iter()
+        .filter(|(violated, _)| *violated)
+        .map(|&(_, implausibility)| implausibility)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_plausibility() {
+        assert!(is_plausible(&GameState::default()));
+
+        let state = GameState {
+            hero_xp: 1000,
+            inventory: [6, 4, 14, 8, 0, 0, 0, 0],
+            flag_equip_dragon_scale: true,
+            flag_equip_warrior_ring: true,
+            flag_beated_golem: true,
+            flag_beated_dragon: true,
+            ..Default::default()
+        };
+        assert!(is_plausible(&state));
+
+        let state = GameState {
+            inventory: [14, 12, 13, 11, 8, 0, 0, 0],
+            flag_equip_dragon_scale: true,
+            flag_equip_warrior_ring: true,
+            flag_beated_golem: true,
+            flag_beated_dragon: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            check_plausibility(&state),
+            [
+                Implausibility::WarriorRingNotOwned,
+                Implausibility::DragonScaleNotOwned,
+                Implausibility::RainbowDropWithMaterial,
+                Implausibility::DeathNecklaceWithoutFlag,
+                Implausibility::GolemBeatenWithoutXp,
+                Implausibility::DragonBeatenWithoutXp,
+            ]
+        );
+
+        let state = GameState {
+            inventory: [8, 0, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_plausibility(&state),
+            [Implausibility::PrincessLoveWithoutDragon]
+        );
+    }
+
+    #[test]
+    fn test_plausibility_each_rule() {
+        // (規則, 該当する状態, 該当しない類似の状態)
+        let cases = [
+            (
+                Implausibility::WarriorRingNotOwned,
+                GameState {
+                    flag_equip_warrior_ring: true,
+                    ..Default::default()
+                },
+                GameState {
+                    inventory: [6, 0, 0, 0, 0, 0, 0, 0],
+                    flag_equip_warrior_ring: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::DragonScaleNotOwned,
+                GameState {
+                    flag_equip_dragon_scale: true,
+                    ..Default::default()
+                },
+                GameState {
+                    inventory: [0, 0, 0, 4, 0, 0, 0, 0],
+                    flag_equip_dragon_scale: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::RainbowDropWithMaterial,
+                GameState {
+                    inventory: [12, 14, 0, 0, 0, 0, 0, 0],
+                    ..Default::default()
+                },
+                GameState {
+                    inventory: [12, 13, 0, 0, 0, 0, 0, 0],
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::DeathNecklaceWithoutFlag,
+                GameState {
+                    inventory: [11, 0, 0, 0, 0, 0, 0, 0],
+                    ..Default::default()
+                },
+                GameState {
+                    inventory: [11, 0, 0, 0, 0, 0, 0, 0],
+                    flag_got_death_necklace: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::GolemBeatenWithoutXp,
+                GameState {
+                    flag_beated_golem: true,
+                    ..Default::default()
+                },
+                GameState {
+                    hero_xp: 1,
+                    flag_beated_golem: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::DragonBeatenWithoutXp,
+                GameState {
+                    flag_beated_dragon: true,
+                    ..Default::default()
+                },
+                GameState {
+                    hero_xp: 1,
+                    flag_beated_dragon: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                Implausibility::PrincessLoveWithoutDragon,
+                GameState {
+                    inventory: [8, 0, 0, 0, 0, 0, 0, 0],
+                    ..Default::default()
+                },
+                GameState {
+                    hero_xp: 1,
+                    inventory: [8, 0, 0, 0, 0, 0, 0, 0],
+                    flag_beated_dragon: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (rule, bad, good) in cases {
+            assert_eq!(bad.plausibility(), [rule]);
+            assert!(good.plausibility().is_empty(), "{:?}", rule);
+            assert!(!rule.fields().is_empty());
+        }
+    }
+}
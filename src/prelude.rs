@@ -0,0 +1,9 @@
+//! crate 内で共通に使う alloc の型やマクロ。
+//!
+//! crate は `#![no_std]` なので、std の prelude に含まれるこれらを明示的に import する。
+
+pub(crate) use alloc::borrow::ToOwned;
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec::Vec;
+pub(crate) use alloc::{format, vec};
@@ -0,0 +1,220 @@
+use crate::analyze::{count_by_position, feasible_chars_from_counts};
+use crate::char_set::CharSet;
+use crate::encode::bytes_to_password;
+use crate::generate::ResumableGenerate;
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// 同じパターンについて繰り返し問い合わせる UI 向けの、結果をキャッシュする検索。
+///
+/// 結果のページ送りや個数の問い合わせのたびに DP をやり直さないよう、求めたものを保持する:
+///
+/// * 生成結果は、これまでに求めた分を生成順に保持する。
+///   復元途中の先頭 2 文字の組については埋めた DP テーブル(1 組分のみ)も保持し、続きの復元に使う。
+/// * 各位置の各文字の出現数([`char_statistics`](crate::char_statistics) と同じもの)は、
+///   最初に必要になったときに求めて保持する。
+///
+/// 生成結果とその順序は、既定のオプションでの [`generate`](crate::generate) と同じ。
+/// キャッシュはパターンを変えたときのみ破棄される。保持しているメモリ量は
+/// [`cache_bytes`](Self::cache_bytes) で得られる。
+#[derive(Clone, Debug)]
+pub struct PreparedSearch {
+    pattern: Pattern,
+
+    generate: ResumableGenerate,
+
+    /// これまでに求めた生成結果のバイト列たち(生成順)。
+    found: Vec<[u8; 15]>,
+
+    /// 各位置の各文字(累積値)の出現数。
+    counts: Option<Box<[[u128; 0x40]; 20]>>,
+
+    /// 出現数を求めた回数。
+    #[cfg(test)]
+    count_fill_count: usize,
+}
+
+impl PreparedSearch {
+    /// パターンに対する検索を作る。この時点では DP は行わない。
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            generate: ResumableGenerate::new(&pattern),
+            pattern,
+            found: vec![],
+            counts: None,
+            #[cfg(test)]
+            count_fill_count: 0,
+        }
+    }
+
+    /// 対象のパターンを返す。
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// 対象のパターンを変える。`pattern` が現在のものと異なる場合のみキャッシュを破棄する。
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        if pattern != self.pattern {
+            *self = Self::new(pattern);
+        }
+    }
+
+    /// 生成順で `offset` 番目(0 始まり)から最大 `len` 個の復活の呪文を返す。
+    ///
+    /// `generate(pattern, offset + len)` の `offset` 番目以降と等しい。
+    pub fn page(&mut self, offset: usize, len: usize) -> Vec<String> {
+        self.generate_until(offset.saturating_add(len));
+
+        self.found
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(bytes_to_password)
+            .collect()
+    }
+
+    /// 生成順で `n` 番目(0 始まり)の復活の呪文を返す。存在しない場合は None を返す。
+    pub fn nth(&mut self, n: usize) -> Option<String> {
+        self.generate_until(n.saturating_add(1));
+
+        self.found.get(n).map(bytes_to_password)
+    }
+
+    /// パターンに合致するデコード可能な復活の呪文の総数を返す。
+    pub fn count(&mut self) -> u128 {
+        self.counts()[0].iter().sum()
+    }
+
+    /// [`feasible_chars`](crate::feasible_chars) と同様に、各位置に現れうる文字の集合を返す。
+    pub fn feasible_chars(&mut self) -> [CharSet; 20] {
+        feasible_chars_from_counts(self.counts())
+    }
+
+    /// [`char_statistics`](crate::char_statistics) と同様に、各位置における各文字の出現数を返す。
+    pub fn char_statistics(&mut self) -> [[u128; 0x40]; 20] {
+        *self.counts()
+    }
+
+    /// キャッシュが保持しているおおよそのヒープ使用量(バイト単位)を返す。
+    ///
+    /// 生成結果の分はこれまでに求めた個数に比例する。DP テーブルの分は先頭 2 文字の組 1 つ分で頭打ちとなる。
+    pub fn cache_bytes(&self) -> usize {
+        self.found.capacity() * size_of::<[u8; 15]>()
+            + self.generate.heap_bytes()
+            + self
+                .counts
+                .as_ref()
+                .map_or(0, |_| size_of::<[[u128; 0x40]; 20]>())
+    }
+
+    /// 生成結果が `n` 個以上求まっているか、全て求まっている状態にする。
+    fn generate_until(&mut self, n: usize) {
+        if self.found.len() < n {
+            self.generate.extend(&mut self.found, n);
+        }
+    }
+
+    fn counts(&mut self) -> &[[u128; 0x40]; 20] {
+        if self.counts.is_none() {
+            self.counts = Some(Box::new(count_by_position(&self.pattern)));
+            #[cfg(test)]
+            {
+                self.count_fill_count += 1;
+            }
+        }
+
+        self.counts.as_ref().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{char_statistics, feasible_chars, generate};
+
+    #[test]
+    fn test_prepared_search_generate() {
+        const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+        let all = generate(PATTERN, usize::MAX).unwrap();
+        assert!(all.len() > 100);
+
+        let mut search = PreparedSearch::new(Pattern::parse(PATTERN).unwrap());
+        assert_eq!(search.page(0, 10), all[..10]);
+        assert_eq!(search.page(10, 10), all[10..20]);
+        assert_eq!(search.page(5, 30), all[5..35]);
+        assert_eq!(search.nth(50).as_ref(), Some(&all[50]));
+        assert_eq!(search.page(all.len() - 3, 10), all[all.len() - 3..]);
+        assert_eq!(search.nth(all.len()), None);
+        assert!(search.page(all.len() + 1, 10).is_empty());
+
+        // 先頭 2 文字が固定なので、DP は 1 回のみ。
+        assert_eq!(search.generate.fill_count(), 1);
+        assert!(search.cache_bytes() >= all.len() * 15);
+        assert_eq!(search.count(), all.len() as u128);
+
+        // 多数の組にまたがる場合も、各組の DP は高々 1 回。
+        const PATTERN_HEAD: &str = "[かが]?ちずどぢぎきつたうずせれえむる??え";
+        let all = generate(PATTERN_HEAD, usize::MAX).unwrap();
+        let mut search = PreparedSearch::new(Pattern::parse(PATTERN_HEAD).unwrap());
+        let mut paged = vec![];
+        for offset in (0..).step_by(7).take_while(|&offset| offset <= all.len()) {
+            paged.extend(search.page(offset, 7));
+        }
+        assert_eq!(paged, all);
+        assert_eq!(search.generate.fill_count(), 2 * 64);
+
+        // タイがある場合も generate と同じ。
+        let pattern = Pattern::parse("ざぼちずどぢぎきつたうずせれえむる???")
+            .unwrap()
+            .tie(&[17, 18])
+            .unwrap();
+        let mut search = PreparedSearch::new(pattern.clone());
+        assert_eq!(
+            search.page(0, 1000),
+            crate::generate_with_pattern(&pattern, 1000, &Default::default()).unwrap()
+        );
+
+        // 先頭が "??" でタイが多数あっても、作る時点では DP を行う単位を列挙しない。
+        let pattern = Pattern::parse("????????????????????")
+            .unwrap()
+            .tie(&[2, 3])
+            .unwrap()
+            .tie(&[4, 5])
+            .unwrap();
+        let mut search = PreparedSearch::new(pattern.clone());
+        assert_eq!(search.generate.fill_count(), 0);
+        assert!(search.cache_bytes() < 0x10000);
+        assert_eq!(
+            search.page(0, 3),
+            crate::generate_with_pattern(&pattern, 3, &Default::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prepared_search_count() {
+        const PATTERN: &str = "ゆうてい????????????????";
+
+        let mut search = PreparedSearch::new(Pattern::parse(PATTERN).unwrap());
+        let statistics = char_statistics(PATTERN).unwrap();
+        assert_eq!(search.char_statistics(), statistics);
+        assert_eq!(search.count(), statistics[0].iter().sum::<u128>());
+        assert_eq!(search.feasible_chars(), feasible_chars(PATTERN).unwrap());
+        assert_eq!(search.count(), search.count());
+        assert_eq!(search.count_fill_count, 1);
+
+        // パターンを変えた場合のみキャッシュが破棄される。
+        search.set_pattern(Pattern::parse(PATTERN).unwrap());
+        search.count();
+        assert_eq!(search.count_fill_count, 1);
+
+        search.set_pattern(Pattern::parse("ゆうていざぼ??????????????").unwrap());
+        assert_eq!(search.count_fill_count, 0);
+        assert_eq!(
+            search.feasible_chars(),
+            feasible_chars("ゆうていざぼ??????????????").unwrap()
+        );
+        assert_eq!(search.count_fill_count, 1);
+    }
+}
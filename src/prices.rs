@@ -0,0 +1,282 @@
+use crate::game_state::GameState;
+
+/// 店で売買される品物。各 ID の意味は [`GameState`] の対応するフィールドを参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ShopItem {
+    /// 武器ID。
+    Weapon(u8),
+
+    /// 鎧ID。
+    Armor(u8),
+
+    /// 盾ID。
+    Shield(u8),
+
+    /// 道具ID。
+    Tool(u8),
+}
+
+/// 武器ID に対応する買値。店で売っていないものは None。本家(FC 版)の値。
+const WEAPON_PRICES: [Option<u16>; 8] = [
+    None,
+    Some(10),
+    Some(60),
+    Some(180),
+    Some(560),
+    Some(1500),
+    Some(9800),
+    None,
+];
+
+/// 鎧ID に対応する買値。店で売っていないものは None。本家(FC 版)の値。
+const ARMOR_PRICES: [Option<u16>; 8] = [
+    None,
+    Some(20),
+    Some(70),
+    Some(300),
+    Some(1000),
+    Some(3000),
+    Some(7700),
+    None,
+];
+
+/// 盾ID に対応する買値。店で売っていないものは None。本家(FC 版)の値。
+const SHIELD_PRICES: [Option<u16>; 4] = [None, Some(90), Some(800), Some(14800)];
+
+/// 道具ID に対応する買値。店で売っていないものは None。本家(FC 版)の値。
+const TOOL_PRICES: [Option<u16>; 15] = [
+    None,
+    Some(8),
+    Some(38),
+    Some(70),
+    Some(20),
+    None,
+    Some(30),
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+];
+
+/// 品物の買値を返す。店で売っていないもの(ロトの装備や大事な道具など)や無効な ID に対しては None を返す。
+pub fn buy_price(item: ShopItem) -> Option<u16> {
+    let (table, id): (&[Option<u16>], u8) = match item {
+        ShopItem::Weapon(id) => (&WEAPON_PRICES, id),
+        ShopItem::Armor(id) => (&ARMOR_PRICES, id),
+        ShopItem::Shield(id) => (&SHIELD_PRICES, id),
+        ShopItem::Tool(id) => (&TOOL_PRICES, id),
+    };
+
+    table.get(usize::from(id)).copied().flatten()
+}
+
+/// 品物の売値を返す。ゲームの規則どおり買値の半分(切り捨て)。
+/// 買値がないものに対しては None を返す。
+pub fn sell_price(item: ShopItem) -> Option<u16> {
+    buy_price(item).map(|price| price / 2)
+}
+
+impl GameState {
+    /// 所持金で `item` を買えるかどうかを返す。店で売っていないものに対しては false を返す。
+    ///
+    /// 装備品は、店で買うと同じ種類の装備中のものを下取り(売値で売却)に出すので、その分も所持金に加える。
+    pub fn can_afford(&self, item: ShopItem) -> bool {
+        buy_price(item).is_some_and(|price| {
+            u32::from(self.purse) + u32::from(self.trade_in_value(item)) >= u32::from(price)
+        })
+    }
+
+    /// `item` を買う際に下取りに出す装備品の売値を返す。下取りがない場合は 0。
+    fn trade_in_value(&self, item: ShopItem) -> u16 {
+        let current = match item {
+            ShopItem::Weapon(_) => ShopItem::Weapon(self.hero_weapon),
+            ShopItem::Armor(_) => ShopItem::Armor(self.hero_armor),
+            ShopItem::Shield(_) => ShopItem::Shield(self.hero_shield),
+            ShopItem::Tool(_) => return 0,
+        };
+
+        sell_price(current).unwrap_or(0)
+    }
+}
+
+/// 現在の装備より上位の装備品のうち、所持金で買える([`GameState::can_afford`])最も安いものを返す。
+/// 該当するものがなければ None を返す。
+///
+/// 上位とは、同じ種類でより大きい ID を持つもの。買値が等しい場合は武器、鎧、盾の順に優先する。
+pub fn cheapest_upgrade(state: &GameState) -> Option<ShopItem> {
+    let weapons = (state.hero_weapon.saturating_add(1)..8).map(ShopItem::Weapon);
+    let armors = (state.hero_armor.saturating_add(1)..8).map(ShopItem::Armor);
+    let shields = (state.hero_shield.saturating_add(1)..4).map(ShopItem::Shield);
+
+    weapons
+        .chain(armors)
+        .chain(shields)
+        .filter(|&item| state.can_afford(item))
+        .min_by_key(|&item| buy_price(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game_state::{ARMOR_NAMES, ITEM_NAMES, SHIELD_NAMES, WEAPON_NAMES};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_prices() {
+        // 名前と買値の組を ID 順に並べる。
+        let table = |names: &[&'static str], item: fn(u8) -> ShopItem| -> Vec<_> {
+            (0..)
+                .zip(names)
+                .map(|(id, &name)| (name, buy_price(item(id))))
+                .collect()
+        };
+
+        assert_eq!(
+            table(&WEAPON_NAMES, ShopItem::Weapon),
+            [
+                ("", None),
+                ("たけざお", Some(10)),
+                ("こんぼう", Some(60)),
+                ("どうのつるぎ", Some(180)),
+                ("てつのおの", Some(560)),
+                ("はがねのつるぎ", Some(1500)),
+                ("ほのおのつるぎ", Some(9800)),
+                ("ロトのつるぎ", None),
+            ]
+        );
+        assert_eq!(
+            table(&ARMOR_NAMES, ShopItem::Armor),
+            [
+                ("", None),
+                ("ぬののふく", Some(20)),
+                ("かわのふく", Some(70)),
+                ("くさりかたびら", Some(300)),
+                ("てつのよろい", Some(1000)),
+                ("はがねのよろい", Some(3000)),
+                ("まほうのよろい", Some(7700)),
+                ("ロトのよろい", None),
+            ]
+        );
+        assert_eq!(
+            table(&SHIELD_NAMES, ShopItem::Shield),
+            [
+                ("", None),
+                ("かわのたて", Some(90)),
+                ("てつのたて", Some(800)),
+                ("みかがみのたて", Some(14800)),
+            ]
+        );
+        assert_eq!(
+            table(&ITEM_NAMES, ShopItem::Tool),
+            [
+                ("", None),
+                ("たいまつ", Some(8)),
+                ("せいすい", Some(38)),
+                ("キメラのつばさ", Some(70)),
+                ("りゅうのうろこ", Some(20)),
+                ("ようせいのふえ", None),
+                ("せんしのゆびわ", Some(30)),
+                ("ロトのしるし", None),
+                ("おうじょのあい", None),
+                ("のろいのベルト", None),
+                ("ぎんのたてごと", None),
+                ("しのくびかざり", None),
+                ("たいようのいし", None),
+                ("あまぐものつえ", None),
+                ("にじのしずく", None),
+            ]
+        );
+
+        assert_eq!(buy_price(ShopItem::Weapon(8)), None);
+        assert_eq!(buy_price(ShopItem::Shield(4)), None);
+        assert_eq!(buy_price(ShopItem::Tool(15)), None);
+
+        assert_eq!(sell_price(ShopItem::Weapon(1)), Some(5));
+        assert_eq!(sell_price(ShopItem::Tool(1)), Some(4));
+        assert_eq!(sell_price(ShopItem::Tool(2)), Some(19));
+        assert_eq!(sell_price(ShopItem::Armor(7)), None);
+    }
+
+    #[test]
+    fn test_can_afford() {
+        let state = GameState {
+            purse: 3000,
+            ..Default::default()
+        };
+        assert!(state.can_afford(ShopItem::Armor(5)));
+        assert!(!state.can_afford(ShopItem::Weapon(6)));
+        assert!(!state.can_afford(ShopItem::Armor(7)));
+        assert!(state.can_afford(ShopItem::Tool(3)));
+        assert!(!state.can_afford(ShopItem::Tool(5)));
+
+        // 下取りの分も加える。
+        let state = GameState {
+            purse: 2980,
+            hero_armor: 2,
+            ..Default::default()
+        };
+        assert!(state.can_afford(ShopItem::Armor(5)));
+        assert!(!GameState {
+            hero_armor: 1,
+            ..state.clone()
+        }
+        .can_afford(ShopItem::Armor(5)));
+        // 他の種類の装備は下取りに出さない。
+        assert!(!GameState {
+            hero_armor: 0,
+            hero_weapon: 6,
+            ..state
+        }
+        .can_afford(ShopItem::Armor(5)));
+
+        // 所持金が上限近くでも溢れない。
+        let state = GameState {
+            purse: u16::MAX,
+            hero_shield: 2,
+            ..Default::default()
+        };
+        assert!(state.can_afford(ShopItem::Shield(3)));
+    }
+
+    #[test]
+    fn test_cheapest_upgrade() {
+        assert_eq!(cheapest_upgrade(&GameState::default()), None);
+
+        let state = GameState {
+            purse: 10,
+            ..Default::default()
+        };
+        assert_eq!(cheapest_upgrade(&state), Some(ShopItem::Weapon(1)));
+
+        let state = GameState {
+            purse: 100,
+            hero_weapon: 2,
+            hero_armor: 2,
+            ..Default::default()
+        };
+        assert_eq!(cheapest_upgrade(&state), Some(ShopItem::Shield(1)));
+
+        let state = GameState {
+            purse: 1000,
+            hero_weapon: 6,
+            hero_armor: 6,
+            hero_shield: 3,
+            ..Default::default()
+        };
+        assert_eq!(cheapest_upgrade(&state), None);
+
+        let state = GameState {
+            purse: 1000,
+            hero_weapon: 3,
+            hero_armor: 3,
+            hero_shield: 1,
+            ..Default::default()
+        };
+        assert_eq!(cheapest_upgrade(&state), Some(ShopItem::Weapon(4)));
+    }
+}
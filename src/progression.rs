@@ -0,0 +1,380 @@
+use crate::game_state::GameState;
+use crate::level::LEVEL_MAX;
+
+/// ゲーム状態から推定される物語の進行状況。
+///
+/// フラグとインベントリのみから求める。[`GameState::progression`] を参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Progression {
+    /// メルキド入口のゴーレムを倒している。
+    pub beat_golem: bool,
+
+    /// 沼地の洞窟のドラゴンを倒している。
+    pub beat_dragon: bool,
+
+    /// しのくびかざりを取得済み。
+    pub got_death_necklace: bool,
+
+    /// おうじょのあいを所持している(ローラ姫を救出済み)。
+    pub has_princess_love: bool,
+
+    /// たいようのいしを所持している。
+    pub has_sun_stone: bool,
+
+    /// あまぐものつえを所持している。
+    pub has_staff_of_rain: bool,
+
+    /// ロトのしるしを所持している。
+    pub has_erdricks_token: bool,
+
+    /// にじのしずくの材料となる 3 つの証(たいようのいし、あまぐものつえ、ロトのしるし)を全て所持している。
+    pub has_all_proofs: bool,
+
+    /// にじのしずくを所持している(虹の橋を架けられる)。
+    pub has_rainbow_drop: bool,
+
+    /// おおよその段階。
+    pub stage: Stage,
+}
+
+/// 物語のおおよその段階。後のものほど進んでいる。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Stage {
+    /// 冒険を始めたばかり(経験値 0 で、道具もフラグもない)。
+    Beginning,
+
+    /// 序盤。以下のいずれにも該当しない。
+    Early,
+
+    /// 中盤。ゴーレムかドラゴンを倒しているか、おうじょのあいを所持している。
+    Middle,
+
+    /// 終盤。3 つの証のいずれかを所持している。
+    Late,
+
+    /// 最終盤。にじのしずくを所持しているか、3 つの証を全て所持している。
+    Final,
+}
+
+impl GameState {
+    /// 物語の進行状況を推定する。
+    ///
+    /// 各項目はフラグとインベントリから直接求まる。段階は以下の規則で、該当する最も進んだものとする:
+    ///
+    /// * [`Stage::Final`] は、にじのしずくを所持しているか、3 つの証を全て所持しているもの
+    /// * [`Stage::Late`] は、3 つの証のいずれかを所持しているもの
+    /// * [`Stage::Middle`] は、ゴーレムかドラゴンを倒しているか、おうじょのあいを所持しているもの
+    /// * [`Stage::Beginning`] は、経験値 0 で、インベントリが空で、フラグが全て立っていないもの
+    /// * [`Stage::Early`] は、上記のいずれでもないもの
+    ///
+    /// 不自然なゲーム状態([`GameState::plausibility`])に対しても、規則どおりの値を返す。
+    pub fn progression(&self) -> Progression {
+        const PRINCESS_LOVE: u8 = 8;
+        const ERDRICKS_TOKEN: u8 = 7;
+        const SUN_STONE: u8 = 12;
+        const STAFF_OF_RAIN: u8 = 13;
+        const RAINBOW_DROP: u8 = 14;
+
+        let owns = |item: u8| self.inventory.contains(&item);
+
+        let has_sun_stone = owns(SUN_STONE);
+        let has_staff_of_rain = owns(STAFF_OF_RAIN);
+        let has_erdricks_token = owns(ERDRICKS_TOKEN);
+        let has_all_proofs = has_sun_stone && has_staff_of_rain && has_erdricks_token;
+        let has_rainbow_drop = owns(RAINBOW_DROP);
+        let has_princess_love = owns(PRINCESS_LOVE);
+
+        let any_flag = self.flag_equip_dragon_scale
+            || self.flag_equip_warrior_ring
+            || self.flag_got_death_necklace
+            || self.flag_beated_golem
+            || self.flag_beated_dragon;
+
+        let stage = if has_rainbow_drop || has_all_proofs {
+            Stage::Final
+        } else if has_sun_stone || has_staff_of_rain || has_erdricks_token {
+            Stage::Late
+        } else if self.flag_beated_golem || self.flag_beated_dragon || has_princess_love {
+            Stage::Middle
+        } else if self.hero_xp == 0 && self.inventory.iter().all(|&item| item == 0) && !any_flag {
+            Stage::Beginning
+        } else {
+            Stage::Early
+        };
+
+        Progression {
+            beat_golem: self.flag_beated_golem,
+            beat_dragon: self.flag_beated_dragon,
+            got_death_necklace: self.flag_got_death_necklace,
+            has_princess_love,
+            has_sun_stone,
+            has_staff_of_rain,
+            has_erdricks_token,
+            has_all_proofs,
+            has_rainbow_drop,
+            stage,
+        }
+    }
+}
+
+/// [`GameState::progress_score`] における、レベル 1 あたりの得点。
+pub const PROGRESS_WEIGHT_LEVEL: u32 = 10;
+
+/// [`GameState::progress_score`] における、装備の ID 1 段階あたりの得点。
+pub const PROGRESS_WEIGHT_EQUIPMENT: u32 = 5;
+
+/// [`GameState::progress_score`] における、大事な道具 1 種類あたりの得点。
+pub const PROGRESS_WEIGHT_KEY_ITEM: u32 = 20;
+
+/// [`GameState::progress_score`] における、物語上のフラグ 1 つあたりの得点。
+pub const PROGRESS_WEIGHT_STORY_FLAG: u32 = 30;
+
+/// [`GameState::progress_score`] の最大値。
+pub const PROGRESS_SCORE_MAX: u32 = (LEVEL_MAX as u32 - 1) * PROGRESS_WEIGHT_LEVEL
+    + (7 + 7 + 3) * PROGRESS_WEIGHT_EQUIPMENT
+    + 4 * PROGRESS_WEIGHT_KEY_ITEM
+    + 3 * PROGRESS_WEIGHT_STORY_FLAG;
+
+/// [`GameState::progress_score`] から定まる、おおよその進み具合。後のものほど進んでいる。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ProgressStage {
+    /// 得点が最大値の 1/4 未満。
+    Early,
+
+    /// 得点が最大値の 1/4 以上 1/2 未満。
+    Middle,
+
+    /// 得点が最大値の 1/2 以上 3/4 未満。
+    Late,
+
+    /// 得点が最大値の 3/4 以上。
+    PreFinal,
+}
+
+impl ProgressStage {
+    /// 得点から段階を求める。
+    pub fn from_score(score: u32) -> Self {
+        match score.saturating_mul(4) / PROGRESS_SCORE_MAX {
+            0 => Self::Early,
+            1 => Self::Middle,
+            2 => Self::Late,
+            _ => Self::PreFinal,
+        }
+    }
+}
+
+impl GameState {
+    /// 進み具合を表す得点(0..=[`PROGRESS_SCORE_MAX`])を返す。
+    ///
+    /// 以下の和とする(重みは `PROGRESS_WEIGHT_*` 定数):
+    ///
+    /// * (レベル - 1) × [`PROGRESS_WEIGHT_LEVEL`]
+    /// * (武器ID + 鎧ID + 盾ID) × [`PROGRESS_WEIGHT_EQUIPMENT`]
+    /// * 所持している大事な道具(ロトのしるし、たいようのいし、あまぐものつえ、にじのしずく)の種類数 × [`PROGRESS_WEIGHT_KEY_ITEM`]。
+    ///   にじのしずくを所持している場合、材料の 3 つの証も所持しているものとみなす
+    /// * 立っている物語上のフラグ(ゴーレム撃破、ドラゴン撃破、しのくびかざり取得)の数 × [`PROGRESS_WEIGHT_STORY_FLAG`]
+    ///
+    /// よって初期状態は 0 となり、レベルや装備、道具、フラグのいずれを進めても得点は下がらない。
+    /// 重みを変える場合は意図的に行う(得点はバージョン間で比較できることを想定している)。
+    pub fn progress_score(&self) -> u32 {
+        const ERDRICKS_TOKEN: u8 = 7;
+        const SUN_STONE: u8 = 12;
+        const STAFF_OF_RAIN: u8 = 13;
+        const RAINBOW_DROP: u8 = 14;
+
+        let has_rainbow_drop = self.inventory.contains(&RAINBOW_DROP);
+        let owns = |item: u8| has_rainbow_drop || self.inventory.contains(&item);
+        let key_items = [ERDRICKS_TOKEN, SUN_STONE, STAFF_OF_RAIN, RAINBOW_DROP]
+            .iter()
+            .filter(|&&item| owns(item))
+            .count() as u32;
+
+        let story_flags = [
+            self.flag_beated_golem,
+            self.flag_beated_dragon,
+            self.flag_got_death_necklace,
+        ]
+        .iter()
+        .filter(|&&flag| flag)
+        .count() as u32;
+
+        let equipment =
+            u32::from(self.hero_weapon) + u32::from(self.hero_armor) + u32::from(self.hero_shield);
+
+        u32::from(self.level() - 1) * PROGRESS_WEIGHT_LEVEL
+            + equipment * PROGRESS_WEIGHT_EQUIPMENT
+            + key_items * PROGRESS_WEIGHT_KEY_ITEM
+            + story_flags * PROGRESS_WEIGHT_STORY_FLAG
+    }
+
+    /// [`progress_score`](Self::progress_score) から定まる、おおよその進み具合を返す。
+    pub fn progress_stage(&self) -> ProgressStage {
+        ProgressStage::from_score(self.progress_score())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progression() {
+        // 開始直後。
+        let progression = GameState::default().progression();
+        assert_eq!(progression.stage, Stage::Beginning);
+        assert!(!progression.beat_golem && !progression.has_rainbow_drop);
+
+        // 少し進めただけ。
+        let state = GameState {
+            hero_xp: 100,
+            inventory: [1, 3, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+        assert_eq!(state.progression().stage, Stage::Early);
+
+        // 中盤: ドラゴンを倒してローラ姫を救出済み。
+        let state = GameState {
+            hero_xp: 3000,
+            inventory: [8, 3, 0, 0, 0, 0, 0, 0],
+            flag_beated_dragon: true,
+            ..Default::default()
+        };
+        let progression = state.progression();
+        assert_eq!(progression.stage, Stage::Middle);
+        assert!(progression.beat_dragon && progression.has_princess_love);
+        assert!(!progression.has_all_proofs);
+
+        // 終盤: 証を 2 つ所持。
+        let state = GameState {
+            hero_xp: 20000,
+            inventory: [12, 7, 0, 0, 0, 0, 0, 0],
+            flag_beated_golem: true,
+            flag_beated_dragon: true,
+            ..Default::default()
+        };
+        let progression = state.progression();
+        assert_eq!(progression.stage, Stage::Late);
+        assert!(progression.has_sun_stone && progression.has_erdricks_token);
+        assert!(!progression.has_all_proofs);
+
+        // 最終盤: 3 つの証が揃っている、またはにじのしずくを所持。
+        let state = GameState {
+            hero_xp: 40000,
+            inventory: [12, 13, 7, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+        let progression = state.progression();
+        assert_eq!(progression.stage, Stage::Final);
+        assert!(progression.has_all_proofs && !progression.has_rainbow_drop);
+
+        let state = GameState {
+            hero_xp: 40000,
+            inventory: [14, 11, 0, 0, 0, 0, 0, 0],
+            flag_got_death_necklace: true,
+            flag_beated_golem: true,
+            flag_beated_dragon: true,
+            ..Default::default()
+        };
+        let progression = state.progression();
+        assert_eq!(progression.stage, Stage::Final);
+        assert!(progression.has_rainbow_drop && progression.got_death_necklace);
+
+        assert!(Stage::Beginning < Stage::Early && Stage::Late < Stage::Final);
+    }
+
+    #[test]
+    fn test_progress_score() {
+        assert_eq!(GameState::default().progress_score(), 0);
+        assert_eq!(GameState::default().progress_stage(), ProgressStage::Early);
+
+        let maxed = crate::test_vectors()
+            .iter()
+            .find(|v| v.description == "maxed state")
+            .unwrap()
+            .state();
+        assert_eq!(maxed.progress_score(), PROGRESS_SCORE_MAX);
+        assert_eq!(maxed.progress_stage(), ProgressStage::PreFinal);
+
+        // 重みの確認。
+        let state = GameState {
+            hero_xp: 7,
+            hero_weapon: 3,
+            inventory: [12, 7, 0, 0, 0, 0, 0, 0],
+            flag_beated_golem: true,
+            ..Default::default()
+        };
+        assert_eq!(state.progress_score(), 10 + 3 * 5 + 2 * 20 + 30);
+
+        // にじのしずくは 3 つの証を含む。
+        let state = GameState {
+            inventory: [14, 0, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+        assert_eq!(state.progress_score(), 4 * 20);
+
+        // 段階の境界。
+        assert_eq!(ProgressStage::from_score(0), ProgressStage::Early);
+        assert_eq!(
+            ProgressStage::from_score(PROGRESS_SCORE_MAX / 4),
+            ProgressStage::Early
+        );
+        assert_eq!(
+            ProgressStage::from_score(PROGRESS_SCORE_MAX / 4 + 1),
+            ProgressStage::Middle
+        );
+        assert_eq!(
+            ProgressStage::from_score(PROGRESS_SCORE_MAX / 2),
+            ProgressStage::Middle
+        );
+        assert_eq!(
+            ProgressStage::from_score(PROGRESS_SCORE_MAX / 2 + 1),
+            ProgressStage::Late
+        );
+        assert_eq!(
+            ProgressStage::from_score(PROGRESS_SCORE_MAX),
+            ProgressStage::PreFinal
+        );
+    }
+
+    #[test]
+    fn test_progress_score_monotonic() {
+        // 1 項目ずつ進めていき、得点が下がらないことを確かめる。
+        let mut state = GameState::default();
+        let mut score = state.progress_score();
+        let mut check = |state: &GameState| {
+            let next = state.progress_score();
+            assert!(next >= score, "{:?}", state);
+            assert!(next <= PROGRESS_SCORE_MAX);
+            score = next;
+        };
+
+        for xp in (0..=u16::MAX).step_by(997).chain([u16::MAX]) {
+            state.hero_xp = xp;
+            check(&state);
+        }
+        for weapon in 0..=7 {
+            state.hero_weapon = weapon;
+            check(&state);
+        }
+        for armor in 0..=7 {
+            state.hero_armor = armor;
+            check(&state);
+        }
+        for shield in 0..=3 {
+            state.hero_shield = shield;
+            check(&state);
+        }
+        for (i, item) in (0..).zip([1, 8, 12, 13, 7, 10, 14, 5]) {
+            state.inventory[i] = item;
+            check(&state);
+        }
+        state.flag_beated_golem = true;
+        check(&state);
+        state.flag_beated_dragon = true;
+        check(&state);
+        state.flag_got_death_necklace = true;
+        check(&state);
+
+        assert_eq!(score, PROGRESS_SCORE_MAX);
+    }
+}
@@ -0,0 +1,281 @@
+//! Python バインディング(`python` feature)。
+//!
+//! maturin でビルドすると、Python から `import dq1_password` で使えるようになる:
+//!
+//! ```sh
+//! maturin develop
+//! pytest python/tests
+//! ```
+//!
+//! 型スタブは `dq1_password.pyi`。
+//!
+//! エラーは例外 `Dq1PasswordError` のサブクラスとして送出される
+//! (`InvalidGameStateError`, `InvalidPasswordError`, `CrcMismatchError`,
+//! `InvalidPatternError`, `InvalidConstraintError`, `ResourceLimitError`, `InvalidFormatError`)。
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use crate::error::{Dq1PasswordError as Error, ErrorKind};
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+create_exception!(dq1_password, Dq1PasswordError, PyException);
+create_exception!(dq1_password, InvalidGameStateError, Dq1PasswordError);
+create_exception!(dq1_password, InvalidPasswordError, Dq1PasswordError);
+create_exception!(dq1_password, CrcMismatchError, Dq1PasswordError);
+create_exception!(dq1_password, InvalidPatternError, Dq1PasswordError);
+create_exception!(dq1_password, InvalidConstraintError, Dq1PasswordError);
+create_exception!(dq1_password, ResourceLimitError, Dq1PasswordError);
+create_exception!(dq1_password, InvalidFormatError, Dq1PasswordError);
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        let message = e.to_string();
+        match e.kind() {
+            ErrorKind::InvalidGameState(_) => InvalidGameStateError::new_err(message),
+            ErrorKind::InvalidPassword(_) => InvalidPasswordError::new_err(message),
+            ErrorKind::CrcMismatch { .. } => CrcMismatchError::new_err(message),
+            ErrorKind::InvalidPattern(_) => InvalidPatternError::new_err(message),
+            ErrorKind::InvalidConstraint => InvalidConstraintError::new_err(message),
+            ErrorKind::ResourceLimit => ResourceLimitError::new_err(message),
+            ErrorKind::InvalidFormat => InvalidFormatError::new_err(message),
+        }
+    }
+}
+
+/// [`GameState`] の Python 向け表現。各フィールドは同名の属性となる。
+#[pyclass(name = "GameState", module = "dq1_password", eq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PyGameState {
+    #[pyo3(get, set)]
+    hero_name: String,
+    #[pyo3(get, set)]
+    hero_xp: u16,
+    #[pyo3(get, set)]
+    purse: u16,
+    #[pyo3(get, set)]
+    hero_weapon: u8,
+    #[pyo3(get, set)]
+    hero_armor: u8,
+    #[pyo3(get, set)]
+    hero_shield: u8,
+    #[pyo3(get, set)]
+    herb_count: u8,
+    #[pyo3(get, set)]
+    key_count: u8,
+    inventory: [u8; 8],
+    #[pyo3(get, set)]
+    flag_equip_dragon_scale: bool,
+    #[pyo3(get, set)]
+    flag_equip_warrior_ring: bool,
+    #[pyo3(get, set)]
+    flag_got_death_necklace: bool,
+    #[pyo3(get, set)]
+    flag_beated_golem: bool,
+    #[pyo3(get, set)]
+    flag_beated_dragon: bool,
+    #[pyo3(get, set)]
+    salt: u8,
+}
+
+#[pymethods]
+impl PyGameState {
+    #[new]
+    #[pyo3(signature = (
+        hero_name = String::new(),
+        hero_xp = 0,
+        purse = 0,
+        hero_weapon = 0,
+        hero_armor = 0,
+        hero_shield = 0,
+        herb_count = 0,
+        key_count = 0,
+        inventory = [0; 8],
+        flag_equip_dragon_scale = false,
+        flag_equip_warrior_ring = false,
+        flag_got_death_necklace = false,
+        flag_beated_golem = false,
+        flag_beated_dragon = false,
+        salt = 0,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        hero_name: String,
+        hero_xp: u16,
+        purse: u16,
+        hero_weapon: u8,
+        hero_armor: u8,
+        hero_shield: u8,
+        herb_count: u8,
+        key_count: u8,
+        inventory: [u8; 8],
+        flag_equip_dragon_scale: bool,
+        flag_equip_warrior_ring: bool,
+        flag_got_death_necklace: bool,
+        flag_beated_golem: bool,
+        flag_beated_dragon: bool,
+        salt: u8,
+    ) -> Self {
+        Self {
+            hero_name,
+            hero_xp,
+            purse,
+            hero_weapon,
+            hero_armor,
+            hero_shield,
+            herb_count,
+            key_count,
+            inventory,
+            flag_equip_dragon_scale,
+            flag_equip_warrior_ring,
+            flag_got_death_necklace,
+            flag_beated_golem,
+            flag_beated_dragon,
+            salt,
+        }
+    }
+
+    // [u8; N] は bytes に変換されるため、list として扱う。
+    #[getter]
+    fn inventory(&self) -> [u32; 8] {
+        self.inventory.map(u32::from)
+    }
+
+    #[setter]
+    fn set_inventory(&mut self, inventory: [u8; 8]) {
+        self.inventory = inventory;
+    }
+
+    /// ゲーム状態を validate する。無効ならば `InvalidGameStateError` を送出する。
+    fn validate(&self) -> PyResult<()> {
+        Ok(GameState::from(self.clone()).validate()?)
+    }
+
+    fn __repr__(&self) -> String {
+        fn py_bool(b: bool) -> &'static str {
+            if b {
+                "True"
+            } else {
+                "False"
+            }
+        }
+
+        format!(
+            "GameState(hero_name={:?}, hero_xp={}, purse={}, hero_weapon={}, hero_armor={}, \
+             hero_shield={}, herb_count={}, key_count={}, inventory={:?}, \
+             flag_equip_dragon_scale={}, flag_equip_warrior_ring={}, flag_got_death_necklace={}, \
+             flag_beated_golem={}, flag_beated_dragon={}, salt={})",
+            self.hero_name,
+            self.hero_xp,
+            self.purse,
+            self.hero_weapon,
+            self.hero_armor,
+            self.hero_shield,
+            self.herb_count,
+            self.key_count,
+            self.inventory,
+            py_bool(self.flag_equip_dragon_scale),
+            py_bool(self.flag_equip_warrior_ring),
+            py_bool(self.flag_got_death_necklace),
+            py_bool(self.flag_beated_golem),
+            py_bool(self.flag_beated_dragon),
+            self.salt,
+        )
+    }
+}
+
+impl From<GameState> for PyGameState {
+    fn from(state: GameState) -> Self {
+        Self {
+            hero_name: state.hero_name,
+            hero_xp: state.hero_xp,
+            purse: state.purse,
+            hero_weapon: state.hero_weapon,
+            hero_armor: state.hero_armor,
+            hero_shield: state.hero_shield,
+            herb_count: state.herb_count,
+            key_count: state.key_count,
+            inventory: state.inventory,
+            flag_equip_dragon_scale: state.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: state.flag_equip_warrior_ring,
+            flag_got_death_necklace: state.flag_got_death_necklace,
+            flag_beated_golem: state.flag_beated_golem,
+            flag_beated_dragon: state.flag_beated_dragon,
+            salt: state.salt,
+        }
+    }
+}
+
+impl From<PyGameState> for GameState {
+    fn from(state: PyGameState) -> Self {
+        Self {
+            hero_name: state.hero_name,
+            hero_xp: state.hero_xp,
+            purse: state.purse,
+            hero_weapon: state.hero_weapon,
+            hero_armor: state.hero_armor,
+            hero_shield: state.hero_shield,
+            herb_count: state.herb_count,
+            key_count: state.key_count,
+            inventory: state.inventory,
+            flag_equip_dragon_scale: state.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: state.flag_equip_warrior_ring,
+            flag_got_death_necklace: state.flag_got_death_necklace,
+            flag_beated_golem: state.flag_beated_golem,
+            flag_beated_dragon: state.flag_beated_dragon,
+            salt: state.salt,
+        }
+    }
+}
+
+/// 復活の呪文を正規化されたゲーム状態にデコードする。
+#[pyfunction]
+fn decode(password: &str) -> PyResult<PyGameState> {
+    Ok(crate::decode(password)?.into())
+}
+
+/// ゲーム状態を復活の呪文にエンコードする。
+#[pyfunction]
+fn encode(state: PyGameState) -> PyResult<String> {
+    Ok(crate::encode(&state.into())?)
+}
+
+/// パターンに合致するデコード可能な復活の呪文たちを最大 `n_max` 個生成する。
+///
+/// 生成中は GIL を解放する。
+#[pyfunction]
+fn generate(py: Python<'_>, pattern: &str, n_max: usize) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| crate::generate(pattern, n_max))?)
+}
+
+#[pymodule]
+fn dq1_password(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+
+    m.add_class::<PyGameState>()?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+
+    m.add("Dq1PasswordError", py.get_type::<Dq1PasswordError>())?;
+    m.add(
+        "InvalidGameStateError",
+        py.get_type::<InvalidGameStateError>(),
+    )?;
+    m.add(
+        "InvalidPasswordError",
+        py.get_type::<InvalidPasswordError>(),
+    )?;
+    m.add("CrcMismatchError", py.get_type::<CrcMismatchError>())?;
+    m.add("InvalidPatternError", py.get_type::<InvalidPatternError>())?;
+    m.add(
+        "InvalidConstraintError",
+        py.get_type::<InvalidConstraintError>(),
+    )?;
+    m.add("ResourceLimitError", py.get_type::<ResourceLimitError>())?;
+    m.add("InvalidFormatError", py.get_type::<InvalidFormatError>())?;
+
+    Ok(())
+}
@@ -0,0 +1,470 @@
+use crate::decode::unpack_hero_name;
+use crate::encode::pack_hero_name;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// NES の CPU RAM のサイズ(2 KiB)。
+pub const NES_RAM_SIZE: usize = 0x800;
+
+/// NES の CPU RAM 上の 1 bit の位置。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RamBit {
+    /// アドレス。
+    pub addr: u16,
+
+    /// ビットマスク(1 bit のみ立てたもの)。
+    pub mask: u8,
+}
+
+/// NES の CPU RAM 上のゲーム状態の配置。
+///
+/// アドレスは CPU アドレス空間($0000..$0800)のもの。
+/// 既定値([`RamLayout::DQ1_FC`])は本家(FC 版)の解析結果に基づく。
+///
+/// | フィールド       | アドレス        | 内容                                                   |
+/// | --               | --              | --                                                     |
+/// | `hero_name`      | $00B5..=$00B8   | 主人公の名前(文字の内部値、4 バイト)                 |
+/// | `hero_xp`        | $00BA..=$00BB   | 経験値(リトルエンディアン)                           |
+/// | `purse`          | $00BC..=$00BD   | 所持金(リトルエンディアン)                           |
+/// | `equipment`      | $00BE           | 装備(武器ID << 5 \| 鎧ID << 2 \| 盾ID)               |
+/// | `key_count`      | $00BF           | かぎ所持数                                             |
+/// | `herb_count`     | $00C0           | やくそう所持数                                         |
+/// | `inventory`      | $00C1..=$00C4   | インベントリ(1 バイトに 2 個、下位 4bit が先)        |
+/// | `flag_equip_dragon_scale` | $00CF & 0x10 | りゅうのうろこ装備フラグ                          |
+/// | `flag_equip_warrior_ring` | $00CF & 0x20 | せんしのゆびわ装備フラグ                          |
+/// | `flag_got_death_necklace` | $00CF & 0x80 | しのくびかざり取得済フラグ                        |
+/// | `flag_beated_golem`       | $00E4 & 0x02 | ゴーレム撃破済フラグ                              |
+/// | `flag_beated_dragon`      | $00E4 & 0x04 | ドラゴン撃破済フラグ                              |
+///
+/// salt は RAM 上に対応するものがない。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RamLayout {
+    /// 主人公の名前の先頭アドレス(4 バイト)。
+    pub hero_name: u16,
+
+    /// 経験値のアドレス(2 バイト)。
+    pub hero_xp: u16,
+
+    /// 所持金のアドレス(2 バイト)。
+    pub purse: u16,
+
+    /// 装備のアドレス。
+    pub equipment: u16,
+
+    /// かぎ所持数のアドレス。
+    pub key_count: u16,
+
+    /// やくそう所持数のアドレス。
+    pub herb_count: u16,
+
+    /// インベントリの先頭アドレス(4 バイト)。
+    pub inventory: u16,
+
+    /// りゅうのうろこ装備フラグの位置。
+    pub flag_equip_dragon_scale: RamBit,
+
+    /// せんしのゆびわ装備フラグの位置。
+    pub flag_equip_warrior_ring: RamBit,
+
+    /// しのくびかざり取得済フラグの位置。
+    pub flag_got_death_necklace: RamBit,
+
+    /// ゴーレム撃破済フラグの位置。
+    pub flag_beated_golem: RamBit,
+
+    /// ドラゴン撃破済フラグの位置。
+    pub flag_beated_dragon: RamBit,
+}
+
+impl RamLayout {
+    /// 本家(FC 版)の配置。
+    pub const DQ1_FC: Self = Self {
+        hero_name: 0x00B5,
+        hero_xp: 0x00BA,
+        purse: 0x00BC,
+        equipment: 0x00BE,
+        key_count: 0x00BF,
+        herb_count: 0x00C0,
+        inventory: 0x00C1,
+        flag_equip_dragon_scale: RamBit {
+            addr: 0x00CF,
+            mask: 0x10,
+        },
+        flag_equip_warrior_ring: RamBit {
+            addr: 0x00CF,
+            mask: 0x20,
+        },
+        flag_got_death_necklace: RamBit {
+            addr: 0x00CF,
+            mask: 0x80,
+        },
+        flag_beated_golem: RamBit {
+            addr: 0x00E4,
+            mask: 0x02,
+        },
+        flag_beated_dragon: RamBit {
+            addr: 0x00E4,
+            mask: 0x04,
+        },
+    };
+}
+
+impl Default for RamLayout {
+    fn default() -> Self {
+        Self::DQ1_FC
+    }
+}
+
+/// RAM 上の 1 フィールドの値。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum RamField {
+    /// `addr` から始まるバイト列。
+    Bytes { addr: u16, bytes: Vec<u8> },
+
+    /// フラグ。他のビットは変えない。
+    Flag { bit: RamBit, value: bool },
+}
+
+/// ゲーム状態を RAM 上のフィールドたちに変換する。順序は [`RamLayout`] のフィールドの順。
+///
+/// `state` は正規化済みでなければならない。
+pub(crate) fn state_to_ram_fields(
+    state: &GameState,
+    layout: &RamLayout,
+) -> [(&'static str, RamField); 12] {
+    fn bytes(addr: u16, bytes: &[u8]) -> RamField {
+        RamField::Bytes {
+            addr,
+            bytes: bytes.to_vec(),
+        }
+    }
+    fn flag(bit: RamBit, value: bool) -> RamField {
+        RamField::Flag { bit, value }
+    }
+
+    let inventory: Vec<_> = state
+        .inventory
+        .chunks(2)
+        .map(|pair| pair[0] | (pair[1] << 4))
+        .collect();
+
+    [
+        (
+            "hero_name",
+            bytes(layout.hero_name, &pack_hero_name(&state.hero_name)),
+        ),
+        (
+            "hero_xp",
+            bytes(layout.hero_xp, &state.hero_xp.to_le_bytes()),
+        ),
+        ("purse", bytes(layout.purse, &state.purse.to_le_bytes())),
+        (
+            "equipment",
+            bytes(
+                layout.equipment,
+                &[state.hero_shield | (state.hero_armor << 2) | (state.hero_weapon << 5)],
+            ),
+        ),
+        ("key_count", bytes(layout.key_count, &[state.key_count])),
+        ("herb_count", bytes(layout.herb_count, &[state.herb_count])),
+        ("inventory", bytes(layout.inventory, &inventory)),
+        (
+            "flag_equip_dragon_scale",
+            flag(
+                layout.flag_equip_dragon_scale,
+                state.flag_equip_dragon_scale,
+            ),
+        ),
+        (
+            "flag_equip_warrior_ring",
+            flag(
+                layout.flag_equip_warrior_ring,
+                state.flag_equip_warrior_ring,
+            ),
+        ),
+        (
+            "flag_got_death_necklace",
+            flag(
+                layout.flag_got_death_necklace,
+                state.flag_got_death_necklace,
+            ),
+        ),
+        (
+            "flag_beated_golem",
+            flag(layout.flag_beated_golem, state.flag_beated_golem),
+        ),
+        (
+            "flag_beated_dragon",
+            flag(layout.flag_beated_dragon, state.flag_beated_dragon),
+        ),
+    ]
+}
+
+impl RamField {
+    /// フィールドが占める範囲の末尾(排他的)のアドレスを返す。
+    fn end(&self) -> usize {
+        match self {
+            Self::Bytes { addr, bytes } => usize::from(*addr) + bytes.len(),
+            Self::Flag { bit, .. } => usize::from(bit.addr) + 1,
+        }
+    }
+}
+
+/// `layout` の全フィールドを読み書きするのに必要な RAM のサイズを返す。
+fn required_ram_len(layout: &RamLayout) -> usize {
+    state_to_ram_fields(&GameState::default(), layout)
+        .iter()
+        .map(|(_, field)| field.end())
+        .max()
+        .unwrap()
+}
+
+/// RAM のサイズが `layout` に対して足りているか検査する。
+fn check_ram_len(len: usize, layout: &RamLayout) -> Dq1PasswordResult<()> {
+    let required = required_ram_len(layout);
+    if len < required {
+        return Err(Dq1PasswordError::invalid_format(format!(
+            "RAM のサイズが足りない: {} バイト以上必要だが {} バイトしかない",
+            required, len
+        )));
+    }
+
+    Ok(())
+}
+
+impl GameState {
+    /// NES の CPU RAM のスナップショット(先頭が $0000)からゲーム状態を読み取る。配置は [`RamLayout::DQ1_FC`]。
+    ///
+    /// [`from_nes_ram_with_layout`](Self::from_nes_ram_with_layout) を参照。
+    pub fn from_nes_ram(ram: &[u8]) -> Dq1PasswordResult<Self> {
+        Self::from_nes_ram_with_layout(ram, &RamLayout::DQ1_FC)
+    }
+
+    /// NES の CPU RAM のスナップショット(先頭が $0000)から、配置 `layout` に従ってゲーム状態を読み取る。
+    ///
+    /// `ram` は通常 [`NES_RAM_SIZE`] バイトだが、`layout` の全フィールドを含んでいれば短くてもよい。
+    /// salt は RAM 上に対応するものがないので 0 とする。結果は正規化済み。
+    ///
+    /// `ram` が短すぎる場合や名前の値が無効な場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+    /// 読み取った状態が無効な場合(やくそう所持数が範囲外など)、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn from_nes_ram_with_layout(ram: &[u8], layout: &RamLayout) -> Dq1PasswordResult<Self> {
+        check_ram_len(ram.len(), layout)?;
+
+        let bytes = |addr: u16, len: usize| &ram[usize::from(addr)..][..len];
+        let u16_le = |addr: u16| {
+            let b = bytes(addr, 2);
+            u16::from_le_bytes([b[0], b[1]])
+        };
+        let flag = |bit: RamBit| (ram[usize::from(bit.addr)] & bit.mask) != 0;
+
+        let mut hero_name = [0; 4];
+        hero_name.copy_from_slice(bytes(layout.hero_name, 4));
+        if let Some(b) = hero_name.iter().find(|&&b| b >= 0x40) {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "RAM 上の主人公の名前の値が無効: {:#04X}",
+                b
+            )));
+        }
+
+        let equipment = ram[usize::from(layout.equipment)];
+        let mut inventory = [0; 8];
+        for (pair, &b) in inventory.chunks_mut(2).zip(bytes(layout.inventory, 4)) {
+            pair[0] = b & 0xF;
+            pair[1] = b >> 4;
+        }
+
+        let state = Self {
+            hero_name: unpack_hero_name(hero_name),
+            hero_xp: u16_le(layout.hero_xp),
+            purse: u16_le(layout.purse),
+            hero_weapon: equipment >> 5,
+            hero_armor: (equipment >> 2) & 7,
+            hero_shield: equipment & 3,
+            herb_count: ram[usize::from(layout.herb_count)],
+            key_count: ram[usize::from(layout.key_count)],
+            inventory,
+            flag_equip_dragon_scale: flag(layout.flag_equip_dragon_scale),
+            flag_equip_warrior_ring: flag(layout.flag_equip_warrior_ring),
+            flag_got_death_necklace: flag(layout.flag_got_death_necklace),
+            flag_beated_golem: flag(layout.flag_beated_golem),
+            flag_beated_dragon: flag(layout.flag_beated_dragon),
+            salt: 0,
+        };
+        state.validate()?;
+
+        Ok(state)
+    }
+
+    /// ゲーム状態を NES の CPU RAM のスナップショット(先頭が $0000)に書き込む。配置は [`RamLayout::DQ1_FC`]。
+    ///
+    /// [`apply_to_nes_ram_with_layout`](Self::apply_to_nes_ram_with_layout) を参照。
+    pub fn apply_to_nes_ram(&self, ram: &mut [u8]) -> Dq1PasswordResult<()> {
+        self.apply_to_nes_ram_with_layout(ram, &RamLayout::DQ1_FC)
+    }
+
+    /// ゲーム状態を NES の CPU RAM のスナップショット(先頭が $0000)に、配置 `layout` に従って書き込む。
+    ///
+    /// [`to_lua_script`](Self::to_lua_script) と同じものを書き込む。
+    /// フラグは同じバイトの他のビットを変えずに書き込む。salt は RAM 上に対応するものがないので書き込まない。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    /// `ram` が短すぎる場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。いずれの場合も `ram` は変更しない。
+    pub fn apply_to_nes_ram_with_layout(
+        &self,
+        ram: &mut [u8],
+        layout: &RamLayout,
+    ) -> Dq1PasswordResult<()> {
+        self.validate()?;
+        let state = self.normalize()?;
+        check_ram_len(ram.len(), layout)?;
+
+        for (_, field) in state_to_ram_fields(&state, layout) {
+            match field {
+                RamField::Bytes { addr, bytes } => {
+                    ram[usize::from(addr)..][..bytes.len()].copy_from_slice(&bytes);
+                }
+                RamField::Flag { bit, value } => {
+                    let b = &mut ram[usize::from(bit.addr)];
+                    if value {
+                        *b |= bit.mask;
+                    } else {
+                        *b &= !bit.mask;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::ErrorKind;
+
+    fn sample_state() -> GameState {
+        GameState {
+            hero_name: "か゛し-".to_owned(),
+            hero_xp: 0x1234,
+            purse: 0xABCD,
+            hero_weapon: 6,
+            hero_armor: 5,
+            hero_shield: 2,
+            herb_count: 4,
+            key_count: 3,
+            inventory: [1, 2, 3, 4, 5, 6, 7, 14],
+            flag_equip_dragon_scale: true,
+            flag_equip_warrior_ring: false,
+            flag_got_death_necklace: true,
+            flag_beated_golem: true,
+            flag_beated_dragon: false,
+            salt: 0,
+        }
+    }
+
+    #[test]
+    fn test_nes_ram_round_trip() {
+        let state = sample_state();
+
+        let mut ram = vec![0xFF; NES_RAM_SIZE];
+        state.apply_to_nes_ram(&mut ram).unwrap();
+
+        assert_eq!(ram[0xB5..=0xB8], [0x0F, 0x3C, 0x15, 0x3E]);
+        assert_eq!(ram[0xBA..=0xBD], [0x34, 0x12, 0xCD, 0xAB]);
+        assert_eq!(ram[0xBE], (6 << 5) | (5 << 2) | 2);
+        assert_eq!(ram[0xBF], 3);
+        assert_eq!(ram[0xC0], 4);
+        assert_eq!(ram[0xC1..=0xC4], [0x21, 0x43, 0x65, 0xE7]);
+        // フラグ以外のビットは変えない。
+        assert_eq!(ram[0xCF], 0xDF);
+        assert_eq!(ram[0xE4], 0xFB);
+        assert_eq!(ram[0xB9], 0xFF);
+
+        assert_eq!(GameState::from_nes_ram(&ram), Ok(state.clone()));
+
+        // 名前は正規化され、salt は 0 となる。
+        let state_unnormalized = GameState {
+            hero_name: "ガシー".to_owned(),
+            salt: 5,
+            ..state.clone()
+        };
+        let mut ram2 = vec![0xFF; NES_RAM_SIZE];
+        state_unnormalized.apply_to_nes_ram(&mut ram2).unwrap();
+        assert_eq!(ram2, ram);
+
+        // 必要な範囲を含んでいれば短くてもよい。
+        let short = &ram[..=0xE4];
+        assert_eq!(GameState::from_nes_ram(short), Ok(state));
+    }
+
+    #[test]
+    fn test_nes_ram_errors() {
+        let state = sample_state();
+
+        let mut ram = vec![0; 0xE4];
+        assert_eq!(
+            state.apply_to_nes_ram(&mut ram).unwrap_err().kind(),
+            ErrorKind::InvalidFormat
+        );
+        assert!(ram.iter().all(|&b| b == 0));
+        assert_eq!(
+            GameState::from_nes_ram(&ram).unwrap_err().kind(),
+            ErrorKind::InvalidFormat
+        );
+
+        let mut ram = vec![0; NES_RAM_SIZE];
+        let invalid = GameState {
+            herb_count: 7,
+            ..state.clone()
+        };
+        assert!(matches!(
+            invalid.apply_to_nes_ram(&mut ram).unwrap_err().kind(),
+            ErrorKind::InvalidGameState(_)
+        ));
+        assert!(ram.iter().all(|&b| b == 0));
+
+        state.apply_to_nes_ram(&mut ram).unwrap();
+        ram[0xB6] = 0x40;
+        assert_eq!(
+            GameState::from_nes_ram(&ram).unwrap_err().kind(),
+            ErrorKind::InvalidFormat
+        );
+
+        state.apply_to_nes_ram(&mut ram).unwrap();
+        ram[0xC4] = 0xF0;
+        assert!(matches!(
+            GameState::from_nes_ram(&ram).unwrap_err().kind(),
+            ErrorKind::InvalidGameState(_)
+        ));
+    }
+
+    #[test]
+    fn test_nes_ram_layout() {
+        let layout = RamLayout {
+            hero_name: 0x0300,
+            ..RamLayout::DQ1_FC
+        };
+        let state = sample_state();
+
+        let mut ram = vec![0; NES_RAM_SIZE];
+        state
+            .apply_to_nes_ram_with_layout(&mut ram, &layout)
+            .unwrap();
+        assert_eq!(ram[0x300..0x304], pack_hero_name(&state.hero_name));
+        assert!(ram[0xB5..=0xB8].iter().all(|&b| b == 0));
+        assert_eq!(
+            GameState::from_nes_ram_with_layout(&ram, &layout),
+            Ok(state)
+        );
+
+        assert_eq!(
+            GameState::from_nes_ram_with_layout(&ram[..0x303], &layout)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidFormat
+        );
+    }
+}
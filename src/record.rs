@@ -0,0 +1,256 @@
+use serde::Serialize;
+
+use crate::decode::decode;
+use crate::game_state::{GameState, ARMOR_NAMES, ITEM_NAMES, SHIELD_NAMES, WEAPON_NAMES};
+use crate::prelude::*;
+
+/// 復活の呪文 1 個のデコード結果を平坦化したレコード。[`decode_records`] で作る。
+///
+/// フィールドの宣言順がそのまま CSV の列順(ヘッダはフィールド名)および JSON のキー順となる:
+///
+/// | 列                                   | 内容                                             |
+/// | --                                   | --                                               |
+/// | `input`                              | 元の入力                                         |
+/// | `error`                              | デコードに失敗した場合のエラーメッセージ         |
+/// | `hero_name`                          | 主人公の名前(正規化済み)                       |
+/// | `hero_xp`, `purse`                   | 経験値、所持金                                   |
+/// | `hero_weapon`, `hero_weapon_name`    | 武器ID とその名前                                |
+/// | `hero_armor`, `hero_armor_name`      | 鎧ID とその名前                                  |
+/// | `hero_shield`, `hero_shield_name`    | 盾ID とその名前                                  |
+/// | `herb_count`, `key_count`            | やくそう所持数、かぎ所持数                       |
+/// | `item1` ... `item8`                  | インベントリの各スロットの道具名(空きは空文字列) |
+/// | `flag_equip_dragon_scale` ... `flag_beated_dragon` | 各フラグ                           |
+/// | `salt`                               | salt                                             |
+///
+/// デコードに失敗した場合、`input` と `error` 以外は全て `None` (CSV では空欄、JSON では `null`)となる。
+/// 成功した場合、`error` が `None` となる。
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub struct Record {
+    pub input: String,
+    pub error: Option<String>,
+    pub hero_name: Option<String>,
+    pub hero_xp: Option<u16>,
+    pub purse: Option<u16>,
+    pub hero_weapon: Option<u8>,
+    pub hero_weapon_name: Option<String>,
+    pub hero_armor: Option<u8>,
+    pub hero_armor_name: Option<String>,
+    pub hero_shield: Option<u8>,
+    pub hero_shield_name: Option<String>,
+    pub herb_count: Option<u8>,
+    pub key_count: Option<u8>,
+    pub item1: Option<String>,
+    pub item2: Option<String>,
+    pub item3: Option<String>,
+    pub item4: Option<String>,
+    pub item5: Option<String>,
+    pub item6: Option<String>,
+    pub item7: Option<String>,
+    pub item8: Option<String>,
+    pub flag_equip_dragon_scale: Option<bool>,
+    pub flag_equip_warrior_ring: Option<bool>,
+    pub flag_got_death_necklace: Option<bool>,
+    pub flag_beated_golem: Option<bool>,
+    pub flag_beated_dragon: Option<bool>,
+    pub salt: Option<u8>,
+}
+
+impl Record {
+    /// CSV のヘッダ(列名)たち。[`Record`] のフィールドの宣言順と一致する。
+    pub const HEADERS: [&'static str; 27] = [
+        "input",
+        "error",
+        "hero_name",
+        "hero_xp",
+        "purse",
+        "hero_weapon",
+        "hero_weapon_name",
+        "hero_armor",
+        "hero_armor_name",
+        "hero_shield",
+        "hero_shield_name",
+        "herb_count",
+        "key_count",
+        "item1",
+        "item2",
+        "item3",
+        "item4",
+        "item5",
+        "item6",
+        "item7",
+        "item8",
+        "flag_equip_dragon_scale",
+        "flag_equip_warrior_ring",
+        "flag_got_death_necklace",
+        "flag_beated_golem",
+        "flag_beated_dragon",
+        "salt",
+    ];
+
+    fn from_state(input: String, state: &GameState) -> Self {
+        // デコード結果は validate 済みなので、各IDは範囲内。
+        let item = |i: usize| Some(ITEM_NAMES[usize::from(state.inventory[i])].to_owned());
+
+        Self {
+            input,
+            error: None,
+            hero_name: Some(state.hero_name.clone()),
+            hero_xp: Some(state.hero_xp),
+            purse: Some(state.purse),
+            hero_weapon: Some(state.hero_weapon),
+            hero_weapon_name: Some(WEAPON_NAMES[usize::from(state.hero_weapon)].to_owned()),
+            hero_armor: Some(state.hero_armor),
+            hero_armor_name: Some(ARMOR_NAMES[usize::from(state.hero_armor)].to_owned()),
+            hero_shield: Some(state.hero_shield),
+            hero_shield_name: Some(SHIELD_NAMES[usize::from(state.hero_shield)].to_owned()),
+            herb_count: Some(state.herb_count),
+            key_count: Some(state.key_count),
+            item1: item(0),
+            item2: item(1),
+            item3: item(2),
+            item4: item(3),
+            item5: item(4),
+            item6: item(5),
+            item7: item(6),
+            item8: item(7),
+            flag_equip_dragon_scale: Some(state.flag_equip_dragon_scale),
+            flag_equip_warrior_ring: Some(state.flag_equip_warrior_ring),
+            flag_got_death_necklace: Some(state.flag_got_death_necklace),
+            flag_beated_golem: Some(state.flag_beated_golem),
+            flag_beated_dragon: Some(state.flag_beated_dragon),
+            salt: Some(state.salt),
+        }
+    }
+
+    fn from_error(input: String, error: String) -> Self {
+        Self {
+            input,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// 復活の呪文たちをデコードし、入力ごとに 1 つの [`Record`] を返す。
+///
+/// デコードに失敗した入力も、エラーメッセージを持つレコードとして含まれる(入力の順序は保たれる)。
+pub fn decode_records<I>(inputs: I) -> Vec<Record>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    inputs
+        .into_iter()
+        .map(|input| {
+            let input = input.as_ref();
+            match decode(input) {
+                Ok(state) => Record::from_state(input.to_owned(), &state),
+                Err(e) => Record::from_error(input.to_owned(), e.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// レコードたちをヘッダ付きの CSV として書き込む(`csv` feature)。
+///
+/// 列の順序は [`Record::HEADERS`] の通り。レコードが空でもヘッダは書き込まれる。
+#[cfg(feature = "csv")]
+pub fn to_csv(records: &[Record], writer: impl std::io::Write) -> std::io::Result<()> {
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+
+    wtr.write_record(Record::HEADERS)?;
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// レコードたちを JSON Lines として書き込む(`csv` feature)。
+///
+/// 各行のキーの順序は [`Record::HEADERS`] の通り。
+#[cfg(feature = "csv")]
+pub fn to_jsonl(records: &[Record], mut writer: impl std::io::Write) -> std::io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+    fn records() -> Vec<Record> {
+        decode_records([A, "ざぼちず"])
+    }
+
+    #[test]
+    fn test_decode_records() {
+        let records = records();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].input, A);
+        assert_eq!(records[0].error, None);
+        assert_eq!(records[0].hero_name.as_deref(), Some("しと゛-"));
+        assert_eq!(
+            records[0].hero_weapon_name.as_deref(),
+            Some("はがねのつるぎ")
+        );
+        assert_eq!(records[0].item8.as_deref(), Some("おうじょのあい"));
+
+        assert_eq!(records[1].input, "ざぼちず");
+        assert!(records[1].error.is_some());
+        assert_eq!(records[1].hero_name, None);
+    }
+
+    #[test]
+    fn test_headers() {
+        // HEADERS がフィールドの宣言順と一致すること。
+        let json = serde_json::to_string(&Record::default()).unwrap();
+        let positions: Vec<_> = Record::HEADERS
+            .iter()
+            .map(|header| json.find(&format!("\"{}\":", header)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(json.matches("\":").count(), Record::HEADERS.len());
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_to_csv() {
+        let mut buf = Vec::new();
+        to_csv(&records(), &mut buf).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\
+input,error,hero_name,hero_xp,purse,hero_weapon,hero_weapon_name,hero_armor,hero_armor_name,hero_shield,hero_shield_name,herb_count,key_count,item1,item2,item3,item4,item5,item6,item7,item8,flag_equip_dragon_scale,flag_equip_warrior_ring,flag_got_death_necklace,flag_beated_golem,flag_beated_dragon,salt
+ざぼちずどぢぎきつたうずせれえむるのぢえ,,しと゛-,1234,5678,5,はがねのつるぎ,5,はがねのよろい,2,てつのたて,6,6,たいまつ,せいすい,キメラのつばさ,りゅうのうろこ,ようせいのふえ,せんしのゆびわ,ロトのしるし,おうじょのあい,true,true,true,true,true,5
+ざぼちず,復活の呪文の形式が無効: 復活の呪文はちょうど 20 文字でなければならない(ただし空白文字は無視される),,,,,,,,,,,,,,,,,,,,,,,,,
+"
+        );
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_to_jsonl() {
+        let mut buf = Vec::new();
+        to_jsonl(&records(), &mut buf).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"{"input":"ざぼちずどぢぎきつたうずせれえむるのぢえ","error":null,"hero_name":"しと゛-","hero_xp":1234,"purse":5678,"hero_weapon":5,"hero_weapon_name":"はがねのつるぎ","hero_armor":5,"hero_armor_name":"はがねのよろい","hero_shield":2,"hero_shield_name":"てつのたて","herb_count":6,"key_count":6,"item1":"たいまつ","item2":"せいすい","item3":"キメラのつばさ","item4":"りゅうのうろこ","item5":"ようせいのふえ","item6":"せんしのゆびわ","item7":"ロトのしるし","item8":"おうじょのあい","flag_equip_dragon_scale":true,"flag_equip_warrior_ring":true,"flag_got_death_necklace":true,"flag_beated_golem":true,"flag_beated_dragon":true,"salt":5}
+{"input":"ざぼちず","error":"復活の呪文の形式が無効: 復活の呪文はちょうど 20 文字でなければならない(ただし空白文字は無視される)","hero_name":null,"hero_xp":null,"purse":null,"hero_weapon":null,"hero_weapon_name":null,"hero_armor":null,"hero_armor_name":null,"hero_shield":null,"hero_shield_name":null,"herb_count":null,"key_count":null,"item1":null,"item2":null,"item3":null,"item4":null,"item5":null,"item6":null,"item7":null,"item8":null,"flag_equip_dragon_scale":null,"flag_equip_warrior_ring":null,"flag_got_death_necklace":null,"flag_beated_golem":null,"flag_beated_dragon":null,"salt":null}
+"#
+        );
+    }
+}
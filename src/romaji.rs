@@ -0,0 +1,309 @@
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, GameStateErrorKind};
+use crate::normalize::{fold_compat, normalize_hero_name};
+use crate::prelude::*;
+
+/// ローマ字で書かれた主人公の名前をかなに変換し、正規化する(例: "yuute" → "ゆうて ")。
+///
+/// 戻り値は [`normalize_hero_name`] の結果と同じ形式(濁点/半濁点は分離され、4 文字にパディングされる)。
+/// よって濁音/半濁音は 2 文字と数えることに注意("dabu" は "た゛ふ゛" で 4 文字となる)。
+///
+/// 変換規則:
+///
+/// * 大文字/小文字、全角/半角は区別しない。
+/// * ヘボン式と訓令式(日本式)の綴りをどちらも受け付ける(例: "shi"/"si" → 'し', "tsu"/"tu" → 'つ', "fu"/"hu" → 'ふ')。
+///   "ji" は 'じ'、"di" は 'ぢ'、"du" は 'づ'、"wo" は 'を'、"vu" は 'ゔ' となる。
+/// * 長音は "ou", "oo" などと母音を書けば、そのまま 'う', 'お' などになる。
+///   長音符付きの母音(ā, ī, ū, ē, ō およびサーカムフレックス付きのもの)は、'ō' のみ 'う' を、他は同じ母音を後に付ける
+///   (例: "yūte" → "ゆうて", "ryō" → "りょう")。'-' はそのまま名前の '-' となる。
+/// * 'n' は、母音または 'y' が続く場合は な行の音節の始まり、それ以外(子音の前や末尾)では 'ん' となる。
+///   "n'" は常に 'ん' となる(例: "ken'ya" → "けんや"、"kenya" → "けにゃ")。
+/// * "nn" は、母音または 'y' が続く場合は 'ん' + な行とみなし(ヘボン式。例: "anna" → "あんな")、
+///   それ以外では 1 個の 'ん' とみなす(IME 式。例: "kenn" → "けん")。
+/// * 'b', 'm', 'p' の前の 'm' は 'ん' となる(ヘボン式。例: "gemba" → "けんば")。
+/// * 同じ子音の重複(例: "kitte")および "tch" は 'っ' となる。
+/// * 'x' または 'l' を前置すると小書き文字になる("xtsu", "xya" など。名前に使えるのは 'っ', 'ゃ', 'ゅ', 'ょ' のみ)。
+/// * 空白、数字、'-' はそのまま残し、'\'' は区切りとして除去する。
+///
+/// かなに変換できない綴りがある場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+/// 原因は `GameStateErrorKind::HeroNameInvalidRomaji` となり、その音節と位置が示される。
+/// 変換後の名前が無効な場合(長すぎるなど)は、[`normalize_hero_name`] と同じエラーを返す。
+pub fn hero_name_from_romaji(input: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let orig: Vec<char> = input.as_ref().chars().collect();
+    let cs: Vec<char> = orig
+        .iter()
+        .map(|&c| {
+            let c = fold_compat(c);
+            c.to_lowercase().next().unwrap_or(c)
+        })
+        .collect();
+
+    let mut kana = String::new();
+    let mut i = 0;
+    while i < cs.len() {
+        let c = cs[i];
+        let next = cs.get(i + 1).copied();
+
+        match c {
+            ' ' | '0'..='9' | '-' => {
+                kana.push(c);
+                i += 1;
+                continue;
+            }
+            '\'' => {
+                i += 1;
+                continue;
+            }
+            'n' => match next {
+                Some('\'') => {
+                    kana.push('ん');
+                    i += 2;
+                    continue;
+                }
+                Some('n') => {
+                    kana.push('ん');
+                    i += if matches!(cs.get(i + 2), Some(&c) if starts_syllable_after_n(c)) {
+                        1
+                    } else {
+                        2
+                    };
+                    continue;
+                }
+                Some(next) if starts_syllable_after_n(next) => {}
+                _ => {
+                    kana.push('ん');
+                    i += 1;
+                    continue;
+                }
+            },
+            'm' if matches!(next, Some('b' | 'm' | 'p')) => {
+                kana.push('ん');
+                i += 1;
+                continue;
+            }
+            'b'..='z' if base_vowel(c).is_none() && next == Some(c) => {
+                kana.push('っ');
+                i += 1;
+                continue;
+            }
+            't' if next == Some('c') && cs.get(i + 2) == Some(&'h') => {
+                kana.push('っ');
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (len, syllable, vowel, long) = match_syllable(&cs[i..]).ok_or_else(|| {
+            let end = cs[i..]
+                .iter()
+                .position(|&c| base_vowel(c).is_some())
+                .map_or(cs.len(), |j| i + j + 1);
+            let end = if c.is_ascii_alphabetic() { end } else { i + 1 };
+            let syllable: String = orig[i..end].iter().collect();
+            Dq1PasswordError::invalid_game_state(
+                GameStateErrorKind::HeroNameInvalidRomaji {
+                    pos: i,
+                    syllable: syllable.clone(),
+                },
+                format!(
+                    "ローマ字の主人公の名前の位置 {} の \"{}\" をかなに変換できない",
+                    i, syllable
+                ),
+            )
+        })?;
+
+        kana.push_str(syllable);
+        if long {
+            kana.push(match vowel {
+                'a' => 'あ',
+                'i' => 'い',
+                'u' | 'o' => 'う',
+                'e' => 'え',
+                _ => unreachable!(),
+            });
+        }
+        i += len;
+    }
+
+    normalize_hero_name(kana)
+}
+
+/// 'n' の直後に `c` が続くとき、'n' が な行の音節の始まりとなるかどうかを返す。
+fn starts_syllable_after_n(c: char) -> bool {
+    c == 'y' || base_vowel(c).is_some()
+}
+
+/// 母音ならば、長音符を除いた母音と長音符の有無を返す。
+fn base_vowel(c: char) -> Option<(char, bool)> {
+    match c {
+        'a' | 'i' | 'u' | 'e' | 'o' => Some((c, false)),
+        'ā' | 'â' => Some(('a', true)),
+        'ī' | 'î' => Some(('i', true)),
+        'ū' | 'û' => Some(('u', true)),
+        'ē' | 'ê' => Some(('e', true)),
+        'ō' | 'ô' => Some(('o', true)),
+        _ => None,
+    }
+}
+
+/// `cs` の先頭の音節を最長一致で変換する。
+///
+/// 成功したら (消費した文字数, かな, 末尾の母音, 長音符の有無) を返す。
+fn match_syllable(cs: &[char]) -> Option<(usize, &'static str, char, bool)> {
+    (1..=cs.len().min(4)).rev().find_map(|len| {
+        // 長音符付きの母音は音節の末尾にのみ置ける。
+        let (vowel, long) = base_vowel(cs[len - 1])?;
+        if cs[..len - 1].iter().any(|&c| base_vowel(c).is_some()) {
+            return None;
+        }
+        let key: String = cs[..len - 1]
+            .iter()
+            .copied()
+            .chain(core::iter::once(vowel))
+            .collect();
+        syllable_kana(&key).map(|kana| (len, kana, vowel, long))
+    })
+}
+
+/// ローマ字の音節(長音符を除いたもの)に対応するかなを返す。
+#[rustfmt::skip]
+fn syllable_kana(syllable: &str) -> Option<&'static str> {
+    let kana = match syllable {
+        "a" => "あ", "i" => "い", "u" => "う", "e" => "え", "o" => "お",
+        "ka" => "か", "ki" => "き", "ku" => "く", "ke" => "け", "ko" => "こ",
+        "sa" => "さ", "shi" => "し", "si" => "し", "su" => "す", "se" => "せ", "so" => "そ",
+        "ta" => "た", "chi" => "ち", "ti" => "ち", "tsu" => "つ", "tu" => "つ", "te" => "て", "to" => "と",
+        "na" => "な", "ni" => "に", "nu" => "ぬ", "ne" => "ね", "no" => "の",
+        "ha" => "は", "hi" => "ひ", "fu" => "ふ", "hu" => "ふ", "he" => "へ", "ho" => "ほ",
+        "ma" => "ま", "mi" => "み", "mu" => "む", "me" => "め", "mo" => "も",
+        "ya" => "や", "yu" => "ゆ", "yo" => "よ",
+        "ra" => "ら", "ri" => "り", "ru" => "る", "re" => "れ", "ro" => "ろ",
+        "wa" => "わ", "wo" => "を",
+        "ga" => "が", "gi" => "ぎ", "gu" => "ぐ", "ge" => "げ", "go" => "ご",
+        "za" => "ざ", "ji" => "じ", "zi" => "じ", "zu" => "ず", "ze" => "ぜ", "zo" => "ぞ",
+        "da" => "だ", "di" => "ぢ", "du" => "づ", "de" => "で", "do" => "ど",
+        "ba" => "ば", "bi" => "び", "bu" => "ぶ", "be" => "べ", "bo" => "ぼ",
+        "pa" => "ぱ", "pi" => "ぴ", "pu" => "ぷ", "pe" => "ぺ", "po" => "ぽ",
+        "vu" => "ゔ",
+        // 拗音
+        "kya" => "きゃ", "kyu" => "きゅ", "kyo" => "きょ",
+        "sha" => "しゃ", "shu" => "しゅ", "sho" => "しょ",
+        "sya" => "しゃ", "syu" => "しゅ", "syo" => "しょ",
+        "cha" => "ちゃ", "chu" => "ちゅ", "cho" => "ちょ",
+        "tya" => "ちゃ", "tyu" => "ちゅ", "tyo" => "ちょ",
+        "cya" => "ちゃ", "cyu" => "ちゅ", "cyo" => "ちょ",
+        "nya" => "にゃ", "nyu" => "にゅ", "nyo" => "にょ",
+        "hya" => "ひゃ", "hyu" => "ひゅ", "hyo" => "ひょ",
+        "mya" => "みゃ", "myu" => "みゅ", "myo" => "みょ",
+        "rya" => "りゃ", "ryu" => "りゅ", "ryo" => "りょ",
+        "gya" => "ぎゃ", "gyu" => "ぎゅ", "gyo" => "ぎょ",
+        "ja" => "じゃ", "ju" => "じゅ", "jo" => "じょ",
+        "jya" => "じゃ", "jyu" => "じゅ", "jyo" => "じょ",
+        "zya" => "じゃ", "zyu" => "じゅ", "zyo" => "じょ",
+        "dya" => "ぢゃ", "dyu" => "ぢゅ", "dyo" => "ぢょ",
+        "bya" => "びゃ", "byu" => "びゅ", "byo" => "びょ",
+        "pya" => "ぴゃ", "pyu" => "ぴゅ", "pyo" => "ぴょ",
+        // 小書き文字
+        "xtu" => "っ", "xtsu" => "っ", "ltu" => "っ", "ltsu" => "っ",
+        "xya" => "ゃ", "xyu" => "ゅ", "xyo" => "ょ",
+        "lya" => "ゃ", "lyu" => "ゅ", "lyo" => "ょ",
+        _ => return None,
+    };
+
+    Some(kana)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::GameStateError;
+
+    #[test]
+    fn test_hero_name_from_romaji() {
+        fn romaji_kind(input: &str) -> GameStateErrorKind {
+            match hero_name_from_romaji(input).unwrap_err() {
+                Dq1PasswordError::InvalidGameState(e) => GameStateError::kind(&e).clone(),
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+
+        for (input, expect) in [
+            ("", "    "),
+            ("yuute", "ゆうて "),
+            ("YUUTE", "ゆうて "),
+            ("ｙｕｕｔｅ", "ゆうて "),
+            ("arefu", "あれふ "),
+            ("arehu", "あれふ "),
+            // 長音
+            ("yūte", "ゆうて "),
+            ("yûte", "ゆうて "),
+            ("ryō", "りょう "),
+            ("rou", "ろう  "),
+            ("roo", "ろお  "),
+            ("ā", "ああ  "),
+            ("ro-ra", "ろ-ら "),
+            // ヘボン式/訓令式
+            ("shika", "しか  "),
+            ("sika", "しか  "),
+            ("chitsu", "ちつ  "),
+            ("titu", "ちつ  "),
+            ("fuji", "ふし゛ "),
+            ("huzi", "ふし゛ "),
+            ("sho", "しょ  "),
+            ("syo", "しょ  "),
+            // 撥音
+            ("ken", "けん  "),
+            ("kenn", "けん  "),
+            ("anna", "あんな "),
+            ("kenya", "けにゃ "),
+            ("ken'ya", "けんや "),
+            ("kan'i", "かんい "),
+            ("nnnn", "んん  "),
+            ("semba", "せんは゛"),
+            // 促音
+            ("kitte", "きって "),
+            ("matcha", "まっちゃ"),
+            ("xtsuxyo", "っょ  "),
+            // その他
+            ("a 1-", "あ 1-"),
+        ] {
+            assert_eq!(hero_name_from_romaji(input).unwrap(), expect, "{}", input);
+        }
+
+        // 濁音/半濁音は 2 文字となる。
+        assert_eq!(hero_name_from_romaji("dabu").unwrap(), "た゛ふ゛");
+        assert_eq!(hero_name_from_romaji("pipo").unwrap(), "ひ゜ほ゜");
+        assert_eq!(hero_name_from_romaji("vuo").unwrap(), "う゛お ");
+        assert_eq!(romaji_kind("dabude"), GameStateErrorKind::HeroNameTooLong);
+        assert_eq!(romaji_kind("pipapo"), GameStateErrorKind::HeroNameTooLong);
+        assert_eq!(hero_name_from_romaji("yuusha").unwrap(), "ゆうしゃ");
+        assert_eq!(romaji_kind("yuushai"), GameStateErrorKind::HeroNameTooLong);
+
+        // 変換できない音節
+        for (input, pos, syllable) in [
+            ("afa", 1, "fa"),
+            ("aqi", 1, "qi"),
+            ("ki!", 2, "!"),
+            ("wi", 0, "wi"),
+            ("kaxa", 2, "xa"),
+            ("tsy", 0, "tsy"),
+            ("aKYE", 1, "KYE"),
+        ] {
+            assert_eq!(
+                romaji_kind(input),
+                GameStateErrorKind::HeroNameInvalidRomaji {
+                    pos,
+                    syllable: syllable.to_owned()
+                },
+                "{}",
+                input
+            );
+        }
+        let e = hero_name_from_romaji("afa").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "ゲーム状態が無効: ローマ字の主人公の名前の位置 1 の \"fa\" をかなに変換できない"
+        );
+    }
+}
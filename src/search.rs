@@ -0,0 +1,517 @@
+use alloc::collections::BTreeSet;
+use core::cmp::Reverse;
+
+use itertools::Itertools as _;
+
+use crate::decode::password_char_to_cum;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::generate::{generate, generate_with_options, GenerateOptions};
+use crate::normalize::{fold_pattern_fragment, normalize_password, normalize_pattern_fragment};
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// 単語を含む復活の呪文。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ContainingMatch {
+    /// 単語の出現位置(文字単位)。
+    pub offset: usize,
+
+    /// 復活の呪文。
+    pub password: String,
+}
+
+/// 辞書中の単語を含む復活の呪文。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WordlikeMatch {
+    /// 復活の呪文。
+    pub password: String,
+
+    /// 含まれる単語たち。出現位置の昇順(同じなら辞書中の順)に並ぶ。
+    pub words: Vec<WordOccurrence>,
+
+    /// いずれかの単語に覆われる文字数。
+    pub covered: usize,
+}
+
+/// 復活の呪文中の単語の出現。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WordOccurrence {
+    /// 単語(正規化済み)。
+    pub word: String,
+
+    /// 出現位置(文字単位)。
+    pub offset: usize,
+}
+
+/// 指定された単語を含むデコード可能な復活の呪文たちを生成する。
+///
+/// 単語の出現位置 `0..=20-word.len()` それぞれについてパターンを作り、
+/// 出現位置の昇順に結果を連結する。複数の位置に単語を含む復活の呪文は、
+/// 最初の出現位置でのみ報告される。
+///
+/// `word` はパターンと同様に正規化される(カタカナは全角/半角どちらも可。空白文字は無視される)。
+///
+/// `n_max` は生成上限数。
+///
+/// `word` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn generate_containing(
+    word: impl AsRef<str>,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<ContainingMatch>> {
+    generate_containing_with_options(word, n_max, &GenerateOptions::default())
+}
+
+/// 辞書中の単語を 1 つ以上含むデコード可能な復活の呪文たちを生成する。
+///
+/// 復活の呪文に使えない文字を含む単語や、空または 20 文字を超える単語は無視する。
+/// 単語はパターンと同様に正規化される(カタカナは全角/半角どちらも可。空白文字は無視される)。
+///
+/// 各単語について [`generate_containing`] と同様に最大 `n_max` 個を生成し、
+/// それぞれに含まれる全ての単語の出現を調べる。結果は単語に覆われる文字数の多い順
+/// (同じなら生成順)に並び、最大 `n_max` 個を返す。
+///
+/// `options` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` または
+/// `Err(Dq1PasswordError::InvalidConstraint)` を返す。
+pub fn generate_wordlike(
+    dict: &[&str],
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<WordlikeMatch>> {
+    let mut words_seen = BTreeSet::new();
+    let words: Vec<_> = dict
+        .iter()
+        .filter_map(|word| normalize_word(word).ok())
+        .filter(|word| words_seen.insert(word.clone()))
+        .collect();
+
+    let mut passwords = vec![];
+    for word in &words {
+        for m in generate_containing_with_options(word, n_max, options)? {
+            passwords.push(m.password);
+        }
+    }
+
+    let mut passwords_seen = BTreeSet::new();
+    let mut matches: Vec<_> = passwords
+        .into_iter()
+        .filter(|password| passwords_seen.insert(password.clone()))
+        .map(|password| {
+            let cs: Vec<_> = password.chars().collect();
+            let mut covered = [false; 20];
+            let mut occurrences = vec![];
+            for word in &words {
+                let ws: Vec<_> = word.chars().collect();
+                for (offset, window) in cs.windows(ws.len()).enumerate() {
+                    if window == ws.as_slice() {
+                        covered[offset..][..ws.len()].fill(true);
+                        occurrences.push(WordOccurrence {
+                            word: word.clone(),
+                            offset,
+                        });
+                    }
+                }
+            }
+            // 安定ソートなので、出現位置が同じものは辞書中の順に並ぶ。
+            occurrences.sort_by_key(|occurrence| occurrence.offset);
+
+            WordlikeMatch {
+                password,
+                words: occurrences,
+                covered: covered.iter().filter(|&&b| b).count(),
+            }
+        })
+        .collect();
+    matches.sort_by_key(|m| Reverse(m.covered));
+    matches.truncate(n_max);
+
+    Ok(matches)
+}
+
+/// オプションを指定して、単語を含むデコード可能な復活の呪文たちを生成する。
+///
+/// その他は [`generate_containing`] と同様。
+fn generate_containing_with_options(
+    word: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<Vec<ContainingMatch>> {
+    let word = normalize_word(word)?;
+    let len = word.chars().count();
+
+    let mut matches = vec![];
+    let mut options = options.clone();
+    for offset in 0..=20 - len {
+        if matches.len() == n_max {
+            break;
+        }
+
+        let pattern = word_pattern(&word, offset);
+        let passwords = generate_with_options(&pattern, n_max - matches.len(), &options)?;
+        matches.extend(
+            passwords
+                .into_iter()
+                .map(|password| ContainingMatch { offset, password }),
+        );
+
+        // 以降の出現位置では、既に報告したものを除外する。
+        options.exclude.push(Pattern::parse(&pattern)?);
+    }
+
+    Ok(matches)
+}
+
+/// 復活の呪文が指定された単語を含むかどうかを返す。正規化していないものも許す
+/// (`word` はパターンと同様に正規化される)。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+///
+/// `word` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn contains_word(password: impl AsRef<str>, word: impl AsRef<str>) -> Dq1PasswordResult<bool> {
+    let password = normalize_password(password)?;
+    let word = normalize_word(word)?;
+
+    Ok(password.contains(&word))
+}
+
+/// 指定されたプレフィックスで始まるデコード可能な復活の呪文たちを生成する。
+///
+/// `prefix` は 1 文字以上 20 文字以下でなければならない。
+/// `prefix` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。空白文字は無視される。
+///
+/// `n_max` は生成上限数。
+///
+/// `prefix` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn generate_with_prefix(
+    prefix: impl AsRef<str>,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<String>> {
+    generate(prefix_pattern(prefix)?, n_max)
+}
+
+/// プレフィックスの後ろを '?' で埋めた 20 文字のパターンを返す。
+///
+/// `prefix` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn prefix_pattern(prefix: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let prefix = normalize_pattern_fragment(prefix)?;
+    let len = prefix.chars().count();
+
+    if len == 0 {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            len,
+            "プレフィックスが空",
+        ));
+    }
+    if len > 20 {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            len,
+            "プレフィックスは 20 文字以下でなければならない(ただし空白文字は無視される)",
+        ));
+    }
+
+    Ok(format!("{}{}", prefix, "?".repeat(20 - len)))
+}
+
+/// 指定されたサフィックスで終わるデコード可能な復活の呪文たちを生成する。
+///
+/// `suffix` は 1 文字以上 20 文字以下でなければならない。
+/// `suffix` 内の '?' (半角/全角どちらも可)は任意の文字にマッチする。空白文字は無視される。
+///
+/// `n_max` は生成上限数。
+///
+/// `suffix` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn generate_with_suffix(
+    suffix: impl AsRef<str>,
+    n_max: usize,
+) -> Dq1PasswordResult<Vec<String>> {
+    generate(suffix_pattern(suffix)?, n_max)
+}
+
+/// サフィックスの前を '?' で埋めた 20 文字のパターンを返す。
+///
+/// `suffix` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+pub fn suffix_pattern(suffix: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let suffix = normalize_pattern_fragment(suffix)?;
+    let len = suffix.chars().count();
+
+    if len == 0 {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            len,
+            "サフィックスが空",
+        ));
+    }
+    if len > 20 {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            len,
+            "サフィックスは 20 文字以下でなければならない(ただし空白文字は無視される)",
+        ));
+    }
+
+    Ok(format!("{}{}", "?".repeat(20 - len), suffix))
+}
+
+/// 単語を正規化する。
+///
+/// パターンの断片と同様に互換文字やカタカナ、分離した濁点/半濁点を変換し、空白文字を除去する。
+/// その上で、1 文字以上 20 文字以下で復活の呪文に使える文字のみからなることを確認する。
+fn normalize_word(word: impl AsRef<str>) -> Dq1PasswordResult<String> {
+    let folded = fold_pattern_fragment(word.as_ref());
+
+    if !(1..=20).contains(&folded.len()) {
+        return Err(Dq1PasswordError::invalid_pattern_length(
+            folded.len(),
+            "単語は 1 文字以上 20 文字以下でなければならない(ただし空白文字は無視される)",
+        ));
+    }
+
+    let offenders: Vec<_> = folded
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(c, _))| password_char_to_cum(c).is_none())
+        .collect();
+
+    if !offenders.is_empty() {
+        let msg = format!(
+            "単語に無効な文字が含まれている: {}",
+            offenders
+                .iter()
+                .map(|&(_, (_, original))| format!("'{}'", original))
+                .join(", ")
+        );
+        return Err(Dq1PasswordError::invalid_pattern_chars(
+            offenders.iter().map(|&(i, &(c, _))| (i, c)).collect(),
+            msg,
+        ));
+    }
+
+    Ok(folded.into_iter().map(|(c, _)| c).collect())
+}
+
+/// 位置 `offset` に単語を置き、残りを '?' で埋めたパターンを返す。
+///
+/// `word` は正規化済みでなければならない。
+fn word_pattern(word: &str, offset: usize) -> String {
+    let len = word.chars().count();
+
+    format!(
+        "{}{}{}",
+        "?".repeat(offset),
+        word,
+        "?".repeat(20 - offset - len)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::decode;
+
+    #[test]
+    fn test_generate_containing() {
+        // 'ん' は復活の呪文に使えない。
+        assert!(matches!(
+            generate_containing("どらごん", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(generate_containing("", 10).is_err());
+
+        let matches = generate_containing("はがね", 5).unwrap();
+        assert_eq!(matches.len(), 5);
+        for m in &matches {
+            assert_eq!(m.offset, 0);
+            assert!(m.password.starts_with("はがね"));
+            assert!(decode(&m.password).is_ok());
+        }
+
+        // カタカナ(全角/半角)や分離した濁点も受け付ける。
+        assert_eq!(generate_containing("ハガネ", 5).unwrap(), matches);
+        assert_eq!(generate_containing("ﾊｶﾞﾈ", 5).unwrap(), matches);
+        assert_eq!(generate_containing("は か゛ ね", 5).unwrap(), matches);
+
+        // 全ての出現位置を調べ、複数の位置に単語を含むものは最初の出現位置でのみ報告する。
+        const WORD: &str = "あきあきあきあきあきあきあきあきあ";
+        let first_offset = |password: &str| {
+            let cs: Vec<_> = password.chars().collect();
+            let ws: Vec<_> = WORD.chars().collect();
+            cs.windows(ws.len())
+                .position(|window| window == ws.as_slice())
+        };
+        let mut n_total = 0;
+        let mut expected = vec![];
+        for offset in 0..=3 {
+            let passwords = generate(word_pattern(WORD, offset), usize::MAX).unwrap();
+            n_total += passwords.len();
+            expected.extend(
+                passwords
+                    .into_iter()
+                    .filter(|password| first_offset(password) == Some(offset))
+                    .map(|password| ContainingMatch { offset, password }),
+            );
+        }
+        let matches = generate_containing(WORD, usize::MAX).unwrap();
+        assert_eq!(matches, expected);
+        assert!(matches.len() < n_total);
+        assert!(matches.iter().any(|m| m.offset > 0));
+        assert_eq!(generate_containing(WORD, 10).unwrap(), matches[..10]);
+    }
+
+    #[test]
+    fn test_generate_wordlike() {
+        // 'ん' を含む単語や空の単語は無視される。
+        // 正規化後に同じになる単語は 1 つとみなす。
+        const DICT: &[&str] = &[
+            "はがね",
+            "どらごん",
+            "",
+            "つるぎ",
+            "は が ね",
+            "ハガネ",
+            "ﾂﾙｷﾞ",
+        ];
+
+        let matches = generate_wordlike(DICT, 8, &GenerateOptions::default()).unwrap();
+        assert_eq!(matches.len(), 8);
+        assert!(matches.windows(2).all(|w| w[0].covered >= w[1].covered));
+        for m in &matches {
+            assert!(decode(&m.password).is_ok());
+            assert!(!m.words.is_empty());
+
+            let cs: Vec<_> = m.password.chars().collect();
+            let mut covered = [false; 20];
+            for occurrence in &m.words {
+                assert!(["はがね", "つるぎ"].contains(&occurrence.word.as_str()));
+                let ws: Vec<_> = occurrence.word.chars().collect();
+                assert_eq!(cs[occurrence.offset..][..ws.len()], ws[..]);
+                covered[occurrence.offset..][..ws.len()].fill(true);
+            }
+            assert_eq!(m.covered, covered.iter().filter(|&&b| b).count());
+        }
+        // 覆われる文字数が同じなら生成順(辞書中の順)に並ぶ。
+        assert!(matches[7].password.starts_with("はがね"));
+
+        // オプションが適用される。
+        let options = GenerateOptions {
+            xp: Some(0..=0),
+            ..Default::default()
+        };
+        let matches = generate_wordlike(&["はがね"], 3, &options).unwrap();
+        assert_eq!(matches.len(), 3);
+        for m in &matches {
+            assert_eq!(decode(&m.password).unwrap().hero_xp, 0);
+        }
+
+        assert_eq!(
+            generate_wordlike(&["どらごん"], 3, &GenerateOptions::default()),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_word_pattern() {
+        assert_eq!(word_pattern("はがね", 0), "はがね?????????????????");
+        assert_eq!(word_pattern("はがね", 5), "?????はがね????????????");
+        assert_eq!(word_pattern("はがね", 17), "?????????????????はがね");
+    }
+
+    #[test]
+    fn test_generate_with_prefix() {
+        assert_eq!(
+            generate_with_prefix("ざぼちずどぢぎきつたうずせれえむるの", 20),
+            generate("ざぼちずどぢぎきつたうずせれえむるの??", 20)
+        );
+        assert_eq!(
+            generate_with_prefix("ざぼちず どぢぎき つたうず せれえむ るの？", 20),
+            generate("ざぼちずどぢぎきつたうずせれえむるの??", 20)
+        );
+        assert_eq!(
+            generate_with_prefix("ザボチズ ﾄﾞﾁﾞｷﾞｷ つたうず せれえむ るの？", 20),
+            generate("ざぼちずどぢぎきつたうずせれえむるの??", 20)
+        );
+        assert_eq!(
+            generate_with_prefix("ざぼちずどぢぎきつたうずせれえむるのぢえ", 20),
+            Ok(vec!["ざぼちずどぢぎきつたうずせれえむるのぢえ".to_owned()])
+        );
+
+        assert!(matches!(
+            generate_with_prefix("", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_with_prefix(" 　", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_with_prefix("ざぼちずどぢぎきつたうずせれえむるのぢええ", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_with_prefix("どらごん", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_with_suffix() {
+        assert_eq!(
+            generate_with_suffix("ざぼちずどぢぎきつたうずせれえむるのぢえ", 20),
+            Ok(vec!["ざぼちずどぢぎきつたうずせれえむるのぢえ".to_owned()])
+        );
+
+        let passwords = generate_with_suffix("きつたうずせれえむるのぢえ", 3).unwrap();
+        assert_eq!(passwords.len(), 3);
+        for password in passwords {
+            assert!(password.ends_with("きつたうずせれえむるのぢえ"));
+            assert!(decode(&password).is_ok());
+        }
+
+        assert!(matches!(
+            generate_with_suffix("", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_with_suffix("ざぼちずどぢぎきつたうずせれえむるのぢええ", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            generate_with_suffix("A", 10),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_contains_word() {
+        assert_eq!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "つたう"),
+            Ok(true)
+        );
+        assert_eq!(
+            contains_word("ざぼちず どぢぎき つたうず せれえむ るのぢえ", "ず どぢ"),
+            Ok(true)
+        );
+        assert_eq!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "はがね"),
+            Ok(false)
+        );
+        assert_eq!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "ツタウ"),
+            Ok(true)
+        );
+        assert_eq!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "ﾂﾀﾞｳ"),
+            Ok(false)
+        );
+        assert_eq!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "ﾄﾞﾁﾞｷﾞ"),
+            Ok(true)
+        );
+
+        assert!(matches!(
+            contains_word("ざぼちずどぢぎきつたうずせれえむるのぢえ", "どらごん"),
+            Err(Dq1PasswordError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            contains_word("ざぼちず", "はがね"),
+            Err(Dq1PasswordError::InvalidPassword(_))
+        ));
+    }
+}
@@ -0,0 +1,473 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+use itertools::Itertools as _;
+use serde::de::{self, Unexpected, Visitor};
+use serde::ser::SerializeStruct as _;
+use serde::{Deserializer, Serialize, Serializer};
+
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, Lang};
+use crate::game_state::{
+    armor_name, shield_name, weapon_name, GameState, ARMOR_NAMES, ARMOR_NAMES_EN, SHIELD_NAMES,
+    SHIELD_NAMES_EN, WEAPON_NAMES, WEAPON_NAMES_EN,
+};
+use crate::prelude::*;
+
+impl GameState {
+    /// JSON 文字列からゲーム状態を読み込む。戻り値は有効であることが保証される。
+    ///
+    /// JSON として解釈できない場合や型が合わない場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+    /// 原因となったフィールドが特定できる場合、メッセージにそのフィールド名(`inventory[3]` など)を含む。
+    ///
+    /// 読み込んだゲーム状態が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn from_json(json: impl AsRef<str>) -> Dq1PasswordResult<Self> {
+        let json = json.as_ref();
+
+        let state: Self = serde_json::from_str(json).map_err(|e| {
+            let msg = match json_error_field(json) {
+                Some(field) => format!("JSON のフィールド {} が無効: {}", field, e),
+                None => format!("JSON として解釈できない: {}", e),
+            };
+            Dq1PasswordError::invalid_format(msg)
+        })?;
+        state.validate()?;
+
+        Ok(state)
+    }
+
+    /// ゲーム状態を整形された JSON 文字列に変換する。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn to_json_pretty(&self) -> Dq1PasswordResult<String> {
+        self.validate()?;
+
+        Ok(serde_json::to_string_pretty(self).expect("GameState should be serializable"))
+    }
+
+    /// ゲーム状態を整形された JSON 文字列に変換する。装備(武器、鎧、盾)は ID の代わりに `lang` の名前で書き出す。
+    ///
+    /// 装備なしは `"なし"` (英語では `"NONE"`)となる。結果は [`GameState::from_json`] で読み込める。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn to_json_pretty_named(&self, lang: Lang) -> Dq1PasswordResult<String> {
+        self.validate()?;
+
+        Ok(
+            serde_json::to_string_pretty(&NamedEquipment { state: self, lang })
+                .expect("GameState should be serializable"),
+        )
+    }
+
+    /// TOML 文字列からゲーム状態を読み込む(`toml` feature)。戻り値は有効であることが保証される。
+    ///
+    /// TOML として解釈できない場合や型が合わない場合、`Err(Dq1PasswordError::InvalidFormat)` を返す
+    /// (メッセージは原因となった行を含む)。
+    ///
+    /// 読み込んだゲーム状態が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: impl AsRef<str>) -> Dq1PasswordResult<Self> {
+        let state: Self = toml::from_str(s.as_ref()).map_err(|e| {
+            Dq1PasswordError::invalid_format(format!(
+                "TOML として解釈できない: {}",
+                e.to_string().trim_end()
+            ))
+        })?;
+        state.validate()?;
+
+        Ok(state)
+    }
+
+    /// ゲーム状態を TOML 文字列に変換する(`toml` feature)。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Dq1PasswordResult<String> {
+        self.validate()?;
+
+        Ok(toml::to_string(self).expect("GameState should be serializable"))
+    }
+}
+
+/// 装備を名前で書き出すためのラッパー。フィールドの並びは [`GameState`] と同じ。
+struct NamedEquipment<'a> {
+    state: &'a GameState,
+    lang: Lang,
+}
+
+impl Serialize for NamedEquipment<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self { state, lang } = *self;
+        let none = match lang {
+            Lang::Ja => EQUIPMENT_NONE,
+            Lang::En => EQUIPMENT_NONE_EN,
+        };
+
+        let mut s = serializer.serialize_struct("GameState", 14)?;
+        s.serialize_field("hero_name", &state.hero_name)?;
+        s.serialize_field("hero_xp", &state.hero_xp)?;
+        s.serialize_field("purse", &state.purse)?;
+        s.serialize_field(
+            "hero_weapon",
+            weapon_name(state.hero_weapon, lang).unwrap_or(none),
+        )?;
+        s.serialize_field(
+            "hero_armor",
+            armor_name(state.hero_armor, lang).unwrap_or(none),
+        )?;
+        s.serialize_field(
+            "hero_shield",
+            shield_name(state.hero_shield, lang).unwrap_or(none),
+        )?;
+        s.serialize_field("herb_count", &state.herb_count)?;
+        s.serialize_field("key_count", &state.key_count)?;
+        s.serialize_field("inventory", &state.inventory)?;
+        s.serialize_field("flag_equip_dragon_scale", &state.flag_equip_dragon_scale)?;
+        s.serialize_field("flag_equip_warrior_ring", &state.flag_equip_warrior_ring)?;
+        s.serialize_field("flag_got_death_necklace", &state.flag_got_death_necklace)?;
+        s.serialize_field("flag_beated_golem", &state.flag_beated_golem)?;
+        s.serialize_field("flag_beated_dragon", &state.flag_beated_dragon)?;
+        s.serialize_field("salt", &state.salt)?;
+        s.end()
+    }
+}
+
+/// 装備なしを表す名前。
+const EQUIPMENT_NONE: &str = "なし";
+
+/// 装備なしを表す英語名。
+const EQUIPMENT_NONE_EN: &str = "NONE";
+
+/// 装備の ID または名前を読み込む Visitor。
+///
+/// 名前は日本語名と英語名(大文字小文字を区別しない)を受け付ける。
+/// ID の範囲はここでは検査しない(ゲーム状態の検証で扱う)。
+struct EquipmentVisitor {
+    kind: &'static str,
+    names_ja: &'static [&'static str],
+    names_en: &'static [&'static str],
+}
+
+impl EquipmentVisitor {
+    fn id(&self, name: &str) -> Option<u8> {
+        if name == EQUIPMENT_NONE || name.eq_ignore_ascii_case(EQUIPMENT_NONE_EN) {
+            return Some(0);
+        }
+
+        self.names_ja
+            .iter()
+            .skip(1)
+            .position(|&s| s == name)
+            .or_else(|| {
+                self.names_en
+                    .iter()
+                    .skip(1)
+                    .position(|s| s.eq_ignore_ascii_case(name))
+            })
+            .map(|i| u8::try_from(i + 1).unwrap())
+    }
+}
+
+impl<'de> Visitor<'de> for EquipmentVisitor {
+    type Value = u8;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ID or name", self.kind)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<u8, E> {
+        u8::try_from(v).map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<u8, E> {
+        u8::try_from(v).map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<u8, E> {
+        self.id(v).ok_or_else(|| {
+            let valid = core::iter::once(EQUIPMENT_NONE)
+                .chain(self.names_ja.iter().skip(1).copied())
+                .chain(core::iter::once(EQUIPMENT_NONE_EN))
+                .chain(self.names_en.iter().skip(1).copied())
+                .join(", ");
+            E::custom(format!(
+                "unknown {} name {:?}, expected one of: {}",
+                self.kind, v, valid
+            ))
+        })
+    }
+}
+
+/// 武器ID または武器の名前を読み込む(`GameState::hero_weapon` 用)。
+pub(crate) fn deserialize_weapon<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u8, D::Error> {
+    deserializer.deserialize_any(EquipmentVisitor {
+        kind: "weapon",
+        names_ja: &WEAPON_NAMES,
+        names_en: &WEAPON_NAMES_EN,
+    })
+}
+
+/// 鎧ID または鎧の名前を読み込む(`GameState::hero_armor` 用)。
+pub(crate) fn deserialize_armor<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u8, D::Error> {
+    deserializer.deserialize_any(EquipmentVisitor {
+        kind: "armor",
+        names_ja: &ARMOR_NAMES,
+        names_en: &ARMOR_NAMES_EN,
+    })
+}
+
+/// 盾ID または盾の名前を読み込む(`GameState::hero_shield` 用)。
+pub(crate) fn deserialize_shield<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u8, D::Error> {
+    deserializer.deserialize_any(EquipmentVisitor {
+        kind: "shield",
+        names_ja: &SHIELD_NAMES,
+        names_en: &SHIELD_NAMES_EN,
+    })
+}
+
+/// JSON からのゲーム状態の読み込みに失敗した場合に、原因となったフィールドを探す。
+///
+/// デフォルトのゲーム状態のフィールドを 1 つずつ入力の値で置き換えて読み込みを試み、最初に失敗したものを返す。
+/// JSON オブジェクトとして解釈できない場合や、特定できない場合は None を返す。
+fn json_error_field(json: &str) -> Option<String> {
+    let map = match serde_json::from_str(json).ok()? {
+        serde_json::Value::Object(map) => map,
+        _ => return None,
+    };
+    let default = serde_json::to_value(GameState::default()).ok()?;
+
+    map.into_iter().find_map(|(key, value)| {
+        // インベントリは要素まで特定する。
+        if let (true, Some(elems)) = (key == "inventory", value.as_array()) {
+            if let Some(i) = elems
+                .iter()
+                .position(|elem| serde_json::from_value::<u8>(elem.clone()).is_err())
+            {
+                return Some(format!("inventory[{}]", i));
+            }
+        }
+
+        let mut probe = default.clone();
+        *probe.get_mut(&key)? = value;
+        serde_json::from_value::<GameState>(probe)
+            .is_err()
+            .then_some(key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::{ErrorKind, GameStateErrorKind};
+    use crate::game_state::GameStateField;
+
+    const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+    fn json_with(key: &str, value: serde_json::Value) -> String {
+        let mut json = serde_json::to_value(crate::decode(A).unwrap()).unwrap();
+        json[key] = value;
+        json.to_string()
+    }
+
+    #[test]
+    fn test_json() {
+        let state = crate::decode(A).unwrap();
+
+        let json = state.to_json_pretty().unwrap();
+        assert!(json.contains("\n  \"hero_xp\": 1234,\n"));
+        assert_eq!(GameState::from_json(&json).unwrap(), state);
+
+        let invalid = GameState {
+            herb_count: 7,
+            ..state
+        };
+        assert!(matches!(
+            invalid.to_json_pretty(),
+            Err(Dq1PasswordError::InvalidGameState(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_json_malformed() {
+        let e = GameState::from_json("{\"hero_name\": ").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e
+            .to_string()
+            .starts_with("データ形式が無効: JSON として解釈できない: "));
+
+        let e = GameState::from_json("{}").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e.to_string().contains("missing field `hero_name`"));
+
+        let e = GameState::from_json(json_with("flag_beated_dragon", "yes".into())).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e
+            .to_string()
+            .starts_with("データ形式が無効: JSON のフィールド flag_beated_dragon が無効: "));
+    }
+
+    #[test]
+    fn test_from_json_out_of_range() {
+        // 型の範囲外。
+        let e = GameState::from_json(json_with("hero_xp", 70000.into())).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e
+            .to_string()
+            .starts_with("データ形式が無効: JSON のフィールド hero_xp が無効: "));
+
+        let e = GameState::from_json(json_with(
+            "inventory",
+            serde_json::json!([1, 2, 3, -4, 5, 6, 7, 8]),
+        ))
+        .unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("データ形式が無効: JSON のフィールド inventory[3] が無効: "));
+
+        // 型の範囲内だが、ゲーム状態として無効。
+        let e = GameState::from_json(json_with("herb_count", 7.into())).unwrap_err();
+        assert_eq!(
+            e.kind(),
+            ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange {
+                field: GameStateField::HerbCount,
+                value: 7,
+                max: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_equipment_names() {
+        let state = crate::decode(A).unwrap();
+        let expected = GameState {
+            hero_weapon: 5,
+            hero_armor: 7,
+            hero_shield: 0,
+            ..state.clone()
+        };
+
+        // 数値と名前のどちらも受け付ける。
+        let mut json = serde_json::to_value(&state).unwrap();
+        json["hero_weapon"] = 5.into();
+        json["hero_armor"] = 7.into();
+        json["hero_shield"] = 0.into();
+        assert_eq!(GameState::from_json(json.to_string()).unwrap(), expected);
+
+        json["hero_weapon"] = "はがねのつるぎ".into();
+        json["hero_armor"] = "ERDRICK'S ARMOR".into();
+        json["hero_shield"] = "なし".into();
+        assert_eq!(GameState::from_json(json.to_string()).unwrap(), expected);
+
+        // 英語名は大文字小文字を区別しない。
+        json["hero_weapon"] = "Broad Sword".into();
+        json["hero_armor"] = "ロトのよろい".into();
+        json["hero_shield"] = "none".into();
+        assert_eq!(GameState::from_json(json.to_string()).unwrap(), expected);
+
+        // 既定の書き出しは数値。
+        let numeric = expected.to_json_pretty().unwrap();
+        assert!(numeric.contains("\n  \"hero_weapon\": 5,\n"));
+        assert_eq!(GameState::from_json(&numeric).unwrap(), expected);
+
+        // 名前での書き出し。フィールドの並びは変わらない。
+        let named = expected.to_json_pretty_named(Lang::Ja).unwrap();
+        assert_eq!(
+            named,
+            numeric
+                .replace("\"hero_weapon\": 5", "\"hero_weapon\": \"はがねのつるぎ\"")
+                .replace("\"hero_armor\": 7", "\"hero_armor\": \"ロトのよろい\"")
+                .replace("\"hero_shield\": 0", "\"hero_shield\": \"なし\"")
+        );
+        assert_eq!(GameState::from_json(&named).unwrap(), expected);
+
+        let named_en = expected.to_json_pretty_named(Lang::En).unwrap();
+        assert!(named_en.contains("\n  \"hero_weapon\": \"BROAD SWORD\",\n"));
+        assert!(named_en.contains("\n  \"hero_shield\": \"NONE\",\n"));
+        assert_eq!(GameState::from_json(&named_en).unwrap(), expected);
+
+        let invalid = GameState {
+            hero_shield: 4,
+            ..expected
+        };
+        assert!(matches!(
+            invalid.to_json_pretty_named(Lang::Ja),
+            Err(Dq1PasswordError::InvalidGameState(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_equipment_names_error() {
+        let e = GameState::from_json(json_with("hero_shield", "ロトのたて".into())).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e.to_string().starts_with(
+            "データ形式が無効: JSON のフィールド hero_shield が無効: \
+             unknown shield name \"ロトのたて\", expected one of: \
+             なし, かわのたて, てつのたて, みかがみのたて, \
+             NONE, SMALL SHIELD, LARGE SHIELD, SILVER SHIELD"
+        ));
+
+        let e = GameState::from_json(json_with("hero_weapon", 256.into())).unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("データ形式が無効: JSON のフィールド hero_weapon が無効: "));
+
+        let e = GameState::from_json(json_with("hero_armor", true.into())).unwrap_err();
+        assert!(e.to_string().contains("expected armor ID or name"));
+
+        // 範囲外の ID はゲーム状態として無効。
+        let e = GameState::from_json(json_with("hero_weapon", 8.into())).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange {
+                field: GameStateField::HeroWeapon,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml() {
+        let state = crate::decode(A).unwrap();
+
+        let toml = state.to_toml().unwrap();
+        assert!(toml.contains("hero_name = \"しと゛-\"\n"));
+        assert_eq!(GameState::from_toml(&toml).unwrap(), state);
+
+        let e =
+            GameState::from_toml(toml.replace("hero_xp = 1234", "hero_xp = 70000")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+        assert!(e.to_string().contains("hero_xp = 70000"));
+
+        let e = GameState::from_toml(toml.replace("salt = 5", "salt = 8")).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange {
+                field: GameStateField::Salt,
+                ..
+            })
+        ));
+
+        // 装備は名前でも読み込める。
+        let named = toml
+            .replace("hero_weapon = 5", "hero_weapon = \"どうのつるぎ\"")
+            .replace("hero_shield = 2", "hero_shield = \"SMALL SHIELD\"");
+        assert_eq!(
+            GameState::from_toml(named).unwrap(),
+            GameState {
+                hero_weapon: 3,
+                hero_shield: 1,
+                ..state
+            }
+        );
+
+        let e = GameState::from_toml("hero_name = ").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidFormat);
+    }
+}
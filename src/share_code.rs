@@ -0,0 +1,231 @@
+use core::convert::TryFrom;
+
+use crate::codec::Codec;
+use crate::decode::decode_bytes;
+use crate::encode::state_to_bytes;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::GameState;
+use crate::prelude::*;
+use crate::validate::ValidationPolicy;
+
+/// 共有コードの長さ(形式のバージョンを表す 1 文字を含む)。
+pub const SHARE_CODE_LEN: usize = 21;
+
+/// 共有コードの現在の形式のバージョンを表す先頭の文字。
+const SHARE_CODE_VERSION: char = '1';
+
+/// base64url (RFC 4648 §5)の文字表。
+const BASE64URL_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl GameState {
+    /// ゲーム状態を URL に埋め込みやすい共有コードに変換する。
+    ///
+    /// 共有コードは常に [`SHARE_CODE_LEN`] 文字の ASCII 文字列で、以下をこの順に並べたもの:
+    ///
+    /// 1. 形式のバージョンを表す 1 文字。現在は `1` のみ。
+    /// 2. 復活の呪文と同じ 15 バイトのバイト列(先頭は CRC)を
+    ///    base64url (RFC 4648 §5, パディングなし)で表した 20 文字。
+    ///
+    /// 形式を変える場合はバージョンの文字を変え、既存のバージョンのコードも読めるようにする。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn to_share_code(&self) -> Dq1PasswordResult<String> {
+        self.validate()?;
+
+        Ok(bytes_to_share_code(&state_to_bytes(&self.normalize()?)))
+    }
+
+    /// 共有コード([`GameState::to_share_code`] を参照)を正規化されたゲーム状態に変換する。
+    ///
+    /// 長さ、バージョン、文字のいずれかが無効な場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+    ///
+    /// 変換結果が無効なゲーム状態となる場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    ///
+    /// 変換結果の CRC が一致しない場合、`Err(Dq1PasswordError::CrcMismatch)` を返す。
+    pub fn from_share_code(code: impl AsRef<str>) -> Dq1PasswordResult<Self> {
+        let code = code.as_ref();
+
+        let body = match code.strip_prefix(SHARE_CODE_VERSION) {
+            Some(body) => body,
+            None => {
+                return Err(Dq1PasswordError::invalid_format(format!(
+                    "共有コードのバージョンが未知: {}",
+                    code
+                )))
+            }
+        };
+        if code.chars().count() != SHARE_CODE_LEN {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "共有コードは {} 文字でなければならない: {}",
+                SHARE_CODE_LEN, code
+            )));
+        }
+
+        let sixs = body
+            .chars()
+            .map(|c| {
+                BASE64URL_CHARS
+                    .iter()
+                    .position(|&b| char::from(b) == c)
+                    .ok_or_else(|| {
+                        Dq1PasswordError::invalid_format(format!(
+                            "共有コードに無効な文字が含まれる: '{}'",
+                            c
+                        ))
+                    })
+            })
+            .collect::<Dq1PasswordResult<Vec<_>>>()?;
+
+        let mut bytes = [0; 15];
+        for (chunk, sixs) in bytes.chunks_mut(3).zip(sixs.chunks(4)) {
+            let x = sixs
+                .iter()
+                .fold(0_u32, |acc, &six| (acc << 6) | u32::try_from(six).unwrap());
+            for (b, shift) in chunk.iter_mut().zip([16, 8, 0]) {
+                *b = u8::try_from((x >> shift) & 0xFF).unwrap();
+            }
+        }
+
+        decode_bytes(&bytes, &Codec::dq1_fc(), &ValidationPolicy::DQ1_FC)
+    }
+}
+
+/// ゲーム状態を表すバイト列を共有コードに変換する。
+fn bytes_to_share_code(bytes: &[u8; 15]) -> String {
+    let mut code = String::with_capacity(SHARE_CODE_LEN);
+    code.push(SHARE_CODE_VERSION);
+    for chunk in bytes.chunks(3) {
+        let x = (u32::from(chunk[0]) << 16) | (u32::from(chunk[1]) << 8) | u32::from(chunk[2]);
+        for shift in [18, 12, 6, 0] {
+            let idx = usize::try_from((x >> shift) & 0x3F).unwrap();
+            code.push(char::from(BASE64URL_CHARS[idx]));
+        }
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::decode;
+    use crate::error::{ErrorKind, GameStateErrorKind};
+    use crate::test_vector::test_vectors;
+
+    #[test]
+    fn test_share_code_roundtrip() {
+        let states = [
+            GameState::default(),
+            GameState {
+                hero_name: "ゆうてい".to_owned(),
+                hero_xp: 65535,
+                purse: 65535,
+                hero_weapon: 7,
+                hero_armor: 7,
+                hero_shield: 3,
+                herb_count: 6,
+                key_count: 6,
+                inventory: [14, 13, 12, 11, 10, 9, 8, 7],
+                flag_equip_dragon_scale: true,
+                flag_equip_warrior_ring: true,
+                flag_got_death_necklace: true,
+                flag_beated_golem: true,
+                flag_beated_dragon: true,
+                salt: 7,
+            },
+            GameState {
+                hero_name: "ガー".to_owned(),
+                hero_xp: 1234,
+                inventory: [1, 0, 2, 0, 0, 0, 0, 3],
+                salt: 2,
+                ..Default::default()
+            },
+        ];
+        for state in &states {
+            let code = state.to_share_code().unwrap();
+            assert_eq!(code.len(), SHARE_CODE_LEN);
+            assert!(code.starts_with('1'));
+            assert!(code
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+            assert_eq!(GameState::from_share_code(&code), state.normalize());
+        }
+
+        for vector in test_vectors() {
+            let code = vector.state().to_share_code().unwrap();
+            assert_eq!(code.len(), SHARE_CODE_LEN);
+            assert_eq!(GameState::from_share_code(&code), decode(vector.password));
+        }
+
+        assert!(GameState {
+            hero_weapon: 8,
+            ..Default::default()
+        }
+        .to_share_code()
+        .is_err());
+    }
+
+    #[test]
+    fn test_share_code_invalid() {
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            hero_xp: 1234,
+            purse: 567,
+            ..Default::default()
+        };
+        let code = state.to_share_code().unwrap();
+
+        for invalid in [
+            String::new(),
+            code[1..].to_owned(),
+            format!("2{}", &code[1..]),
+            format!("{}A", code),
+            code[..SHARE_CODE_LEN - 1].to_owned(),
+            format!("{}+", &code[..SHARE_CODE_LEN - 1]),
+            format!("{}=", &code[..SHARE_CODE_LEN - 1]),
+            format!("{}あ", &code[..SHARE_CODE_LEN - 1]),
+        ] {
+            assert!(
+                matches!(
+                    GameState::from_share_code(&invalid),
+                    Err(Dq1PasswordError::InvalidFormat(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+
+        // 1 文字でも書き換えると、元のゲーム状態には戻らない。
+        // バージョンの次の文字は CRC の bit のみを含むので、書き換えると必ず CRC が一致しなくなる。
+        for pos in 1..SHARE_CODE_LEN {
+            let mut tampered: Vec<char> = code.chars().collect();
+            tampered[pos] = if tampered[pos] == 'A' { 'B' } else { 'A' };
+            let tampered: String = tampered.into_iter().collect();
+            let res = GameState::from_share_code(&tampered);
+            assert_ne!(res, state.normalize(), "{}", tampered);
+            if pos == 1 {
+                assert!(
+                    matches!(res, Err(Dq1PasswordError::CrcMismatch { .. })),
+                    "{}",
+                    tampered
+                );
+            }
+        }
+
+        // CRC は一致するが、やくそう所持数が範囲外。
+        let mut bytes = state_to_bytes(&state.normalize().unwrap());
+        bytes[10] = 7;
+        bytes[0] = u8::try_from(crate::crc::crc16(&bytes[1..]) & 0xFF).unwrap();
+        let err = GameState::from_share_code(bytes_to_share_code(&bytes)).unwrap_err();
+        assert!(
+            matches!(
+                err.kind(),
+                ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange { .. })
+            ),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::error::Lang;
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// 主人公が覚える呪文。覚える順(レベルの昇順)に並ぶ。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Spell {
+    /// ホイミ。
+    Hoimi,
+
+    /// ギラ。
+    Gira,
+
+    /// ラリホー。
+    Rariho,
+
+    /// レミーラ。
+    Remira,
+
+    /// マホトーン。
+    Mahoton,
+
+    /// リレミト。
+    Riremito,
+
+    /// ルーラ。
+    Rura,
+
+    /// トヘロス。
+    Toherosu,
+
+    /// ベホイミ。
+    Behoimi,
+
+    /// ベギラマ。
+    Begirama,
+}
+
+/// 各呪文とそれを覚えるレベル。本家(FC 版)の値。
+const SPELL_LEARN_LEVELS: [(Spell, u8); 10] = [
+    (Spell::Hoimi, 3),
+    (Spell::Gira, 4),
+    (Spell::Rariho, 7),
+    (Spell::Remira, 9),
+    (Spell::Mahoton, 10),
+    (Spell::Riremito, 12),
+    (Spell::Rura, 13),
+    (Spell::Toherosu, 15),
+    (Spell::Behoimi, 17),
+    (Spell::Begirama, 19),
+];
+
+impl Spell {
+    /// 全ての呪文(覚える順)。
+    pub const ALL: [Self; 10] = [
+        Self::Hoimi,
+        Self::Gira,
+        Self::Rariho,
+        Self::Remira,
+        Self::Mahoton,
+        Self::Riremito,
+        Self::Rura,
+        Self::Toherosu,
+        Self::Behoimi,
+        Self::Begirama,
+    ];
+
+    /// 呪文を覚えるレベルを返す。
+    pub fn learn_level(self) -> u8 {
+        SPELL_LEARN_LEVELS[self as usize].1
+    }
+
+    /// 指定した言語での呪文の名前を返す。英語名は北米版(Dragon Warrior)のもの。
+    pub fn name(self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::Ja => match self {
+                Self::Hoimi => "ホイミ",
+                Self::Gira => "ギラ",
+                Self::Rariho => "ラリホー",
+                Self::Remira => "レミーラ",
+                Self::Mahoton => "マホトーン",
+                Self::Riremito => "リレミト",
+                Self::Rura => "ルーラ",
+                Self::Toherosu => "トヘロス",
+                Self::Behoimi => "ベホイミ",
+                Self::Begirama => "ベギラマ",
+            },
+            Lang::En => match self {
+                Self::Hoimi => "HEAL",
+                Self::Gira => "HURT",
+                Self::Rariho => "SLEEP",
+                Self::Remira => "RADIANT",
+                Self::Mahoton => "STOPSPELL",
+                Self::Riremito => "OUTSIDE",
+                Self::Rura => "RETURN",
+                Self::Toherosu => "REPEL",
+                Self::Behoimi => "HEALMORE",
+                Self::Begirama => "HURTMORE",
+            },
+        }
+    }
+}
+
+/// レベル `level` で覚えている呪文たちを覚える順に返す。
+pub fn spells_at_level(level: u8) -> Vec<Spell> {
+    SPELL_LEARN_LEVELS
+        .iter()
+        .filter(|&&(_, learn)| learn <= level)
+        .map(|&(spell, _)| spell)
+        .collect()
+}
+
+impl GameState {
+    /// 経験値から定まるレベルで覚えている呪文たちを覚える順に返す。[`spells_at_level`] を参照。
+    pub fn spells(&self) -> Vec<Spell> {
+        spells_at_level(self.level())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::level::{xp_for_level, LEVEL_MAX};
+
+    #[test]
+    fn test_spells_at_level() {
+        assert!(spells_at_level(1).is_empty());
+        assert!(spells_at_level(2).is_empty());
+        assert_eq!(spells_at_level(3), [Spell::Hoimi]);
+        assert_eq!(spells_at_level(4), [Spell::Hoimi, Spell::Gira]);
+        assert_eq!(spells_at_level(6), [Spell::Hoimi, Spell::Gira]);
+        assert_eq!(
+            spells_at_level(7),
+            [Spell::Hoimi, Spell::Gira, Spell::Rariho]
+        );
+        assert_eq!(spells_at_level(18).len(), 9);
+        assert_eq!(spells_at_level(19), Spell::ALL);
+        assert_eq!(spells_at_level(LEVEL_MAX), Spell::ALL);
+
+        for (i, &(spell, level)) in SPELL_LEARN_LEVELS.iter().enumerate() {
+            assert_eq!(Spell::ALL[i], spell);
+            assert_eq!(spell.learn_level(), level);
+            assert_eq!(spells_at_level(level).last(), Some(&spell));
+            assert!(!spells_at_level(level - 1).contains(&spell));
+        }
+    }
+
+    #[test]
+    fn test_spell_name() {
+        assert_eq!(Spell::Hoimi.name(Lang::Ja), "ホイミ");
+        assert_eq!(Spell::Begirama.name(Lang::Ja), "ベギラマ");
+        assert_eq!(Spell::Hoimi.name(Lang::En), "HEAL");
+        assert_eq!(Spell::Riremito.name(Lang::En), "OUTSIDE");
+    }
+
+    #[test]
+    fn test_game_state_spells() {
+        let state = |level| GameState {
+            hero_xp: xp_for_level(level).unwrap(),
+            ..Default::default()
+        };
+
+        assert!(GameState::default().spells().is_empty());
+        assert_eq!(state(3).spells(), [Spell::Hoimi]);
+        assert_eq!(
+            GameState {
+                hero_xp: xp_for_level(3).unwrap() - 1,
+                ..Default::default()
+            }
+            .spells(),
+            []
+        );
+        assert_eq!(state(17).spells().last(), Some(&Spell::Behoimi));
+        assert_eq!(state(LEVEL_MAX).spells(), Spell::ALL);
+    }
+}
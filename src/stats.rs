@@ -0,0 +1,296 @@
+use core::convert::TryFrom;
+
+use crate::encode::pack_hero_name;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::{GameState, GameStateField};
+use crate::level::LEVEL_MAX;
+use crate::normalize::normalize_hero_name;
+use crate::prelude::*;
+
+/// 主人公の能力値。[`hero_stats`] を参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct HeroStats {
+    /// 最大 HP。
+    pub max_hp: u8,
+
+    /// 最大 MP。
+    pub max_mp: u8,
+
+    /// ちから。
+    pub strength: u8,
+
+    /// すばやさ。
+    pub agility: u8,
+
+    /// 名前から定まる成長タイプ。
+    pub growth_type: GrowthType,
+}
+
+/// 名前から定まる能力値の成長タイプ。よく伸びる 2 つの能力値で区別する。
+///
+/// 残り 2 つの能力値は基本値より抑えられる。[`hero_stats`] を参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GrowthType {
+    /// 最大 HP と最大 MP がよく伸びる。
+    HpMp,
+
+    /// ちからと最大 HP がよく伸びる。
+    StrengthHp,
+
+    /// すばやさと最大 MP がよく伸びる。
+    AgilityMp,
+
+    /// ちからとすばやさがよく伸びる。
+    StrengthAgility,
+}
+
+/// 各レベルの能力値の基本値 (ちから, すばやさ, 最大 HP, 最大 MP)。`BASE_STATS[lv - 1]` がレベル `lv` のもの。
+///
+/// 本家(FC 版)の値。
+#[rustfmt::skip]
+const BASE_STATS: [(u8, u8, u8, u8); LEVEL_MAX as usize] = [
+    (4, 4, 15, 0),        (5, 4, 22, 0),        (7, 6, 24, 5),        (7, 8, 31, 16),
+    (12, 10, 35, 20),     (16, 10, 38, 24),     (18, 17, 40, 26),     (22, 20, 46, 29),
+    (30, 22, 50, 36),     (35, 31, 54, 40),     (40, 35, 62, 50),     (48, 40, 63, 58),
+    (52, 48, 70, 64),     (60, 55, 78, 70),     (68, 64, 86, 72),     (72, 70, 92, 95),
+    (72, 78, 100, 100),   (85, 84, 115, 108),   (87, 86, 130, 115),   (92, 88, 138, 128),
+    (95, 90, 149, 135),   (97, 90, 158, 146),   (99, 94, 165, 153),   (103, 98, 170, 161),
+    (113, 100, 174, 161), (117, 105, 180, 168), (125, 107, 189, 175), (130, 115, 195, 180),
+    (135, 120, 200, 190), (140, 130, 210, 200),
+];
+
+/// 主人公の名前とレベルから能力値を求める。`hero_name` は正規化していないものも許す。
+///
+/// 各能力値はレベルごとの基本値から求める。名前から [`growth_type`] と同様に N を求めると、
+/// よく伸びる 2 つの能力値は基本値そのものとなり、
+/// 残り 2 つは `基本値 * 9 / 10 (切り捨て) + (N >> 2)` となる。
+///
+/// `hero_name` が無効な場合や、`level` が 1..=30 の範囲外の場合、
+/// `Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn hero_stats(hero_name: impl AsRef<str>, level: u8) -> Dq1PasswordResult<HeroStats> {
+    if !(1..=LEVEL_MAX).contains(&level) {
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::Level,
+            u32::from(level),
+            u32::from(LEVEL_MAX),
+            format!(
+                "レベルは 1 以上 {} 以下でなければならない: {}",
+                LEVEL_MAX, level
+            ),
+        ));
+    }
+
+    let n = name_sum(&hero_name)?;
+    let growth_type = GrowthType::from_name_sum(n);
+    let bonus = n >> 2;
+
+    let (strength, agility, max_hp, max_mp) = BASE_STATS[usize::from(level - 1)];
+    let lower = |base: u8| u8::try_from(u16::from(base) * 9 / 10).unwrap() + bonus;
+    let (strength, agility, max_hp, max_mp) = match growth_type {
+        GrowthType::HpMp => (lower(strength), lower(agility), max_hp, max_mp),
+        GrowthType::StrengthHp => (strength, lower(agility), max_hp, lower(max_mp)),
+        GrowthType::AgilityMp => (lower(strength), agility, lower(max_hp), max_mp),
+        GrowthType::StrengthAgility => (strength, agility, lower(max_hp), lower(max_mp)),
+    };
+
+    Ok(HeroStats {
+        max_hp,
+        max_mp,
+        strength,
+        agility,
+        growth_type,
+    })
+}
+
+/// 主人公の名前から定まる能力値の成長タイプを返す。`hero_name` は正規化していないものも許す。
+///
+/// ゲーム内では以下のように求める:
+///
+/// 1. 名前を 4 文字の内部値(6bit)の配列とする。これは復活の呪文に格納される値
+///    (`pack_hero_name` の結果)と同じで、4 文字に満たない名前は空白(0x3F)で埋められ、
+///    濁音/半濁音は清音と '゛'/'゜' の 2 文字に分かれる(例えば "が" は "か゛" と同じ)。
+/// 2. 4 つの値の和の下位 4bit を N とする。
+/// 3. N の下位 2bit が 0, 1, 2, 3 のとき、成長タイプはそれぞれ
+///    [`HpMp`](GrowthType::HpMp), [`StrengthHp`](GrowthType::StrengthHp),
+///    [`AgilityMp`](GrowthType::AgilityMp), [`StrengthAgility`](GrowthType::StrengthAgility) となる。
+///
+/// N の上位 2bit は、抑えられる能力値への加算値となる([`hero_stats`] を参照)。
+///
+/// `hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+pub fn growth_type(hero_name: impl AsRef<str>) -> Dq1PasswordResult<GrowthType> {
+    name_sum(&hero_name).map(GrowthType::from_name_sum)
+}
+
+impl GrowthType {
+    /// 名前の内部値の和の下位 4bit から成長タイプを求める。
+    pub(crate) fn from_name_sum(n: u8) -> Self {
+        match n & 3 {
+            0 => Self::HpMp,
+            1 => Self::StrengthHp,
+            2 => Self::AgilityMp,
+            3 => Self::StrengthAgility,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// 主人公の名前の 4 文字の内部値の和の下位 4bit を返す。
+fn name_sum(hero_name: impl AsRef<str>) -> Dq1PasswordResult<u8> {
+    let packed = pack_hero_name(normalize_hero_name(hero_name)?);
+
+    Ok(packed.iter().fold(0_u8, |acc, &x| acc.wrapping_add(x)) & 0xF)
+}
+
+impl GameState {
+    /// 主人公の名前と経験値から定まるレベルでの能力値を返す。[`hero_stats`] を参照。
+    ///
+    /// `self.hero_name` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn stats(&self) -> Dq1PasswordResult<HeroStats> {
+        hero_stats(&self.hero_name, self.level())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::collections::BTreeSet;
+
+    use crate::error::GameStateErrorKind;
+
+    #[test]
+    fn test_hero_stats() {
+        // "ゆうてい": 0x2E + 0x0C + 0x1C + 0x0B = 0x61 → N = 1
+        assert_eq!(
+            hero_stats("ゆうてい", 1),
+            Ok(HeroStats {
+                max_hp: 15,
+                max_mp: 0,
+                strength: 4,
+                agility: 3,
+                growth_type: GrowthType::StrengthHp,
+            })
+        );
+        assert_eq!(
+            hero_stats("ゆうてい", 30),
+            Ok(HeroStats {
+                max_hp: 210,
+                max_mp: 180,
+                strength: 140,
+                agility: 117,
+                growth_type: GrowthType::StrengthHp,
+            })
+        );
+
+        // "しと゛-": 0x15 + 0x1D + 0x3C + 0x3E = 0xAC → N = 0xC
+        assert_eq!(
+            hero_stats("しどー", 17),
+            Ok(HeroStats {
+                max_hp: 100,
+                max_mp: 100,
+                strength: 64 + 3,
+                agility: 70 + 3,
+                growth_type: GrowthType::HpMp,
+            })
+        );
+
+        // "    ": 0x3F * 4 = 0xFC → N = 0xC
+        assert_eq!(
+            hero_stats("", 10),
+            Ok(HeroStats {
+                max_hp: 54,
+                max_mp: 40,
+                strength: 31 + 3,
+                agility: 27 + 3,
+                growth_type: GrowthType::HpMp,
+            })
+        );
+
+        // "あ   ": 0x0A + 0x3F * 3 = 0xC7 → N = 7
+        assert_eq!(
+            hero_stats("あ", 5).map(|stats| stats.growth_type),
+            Ok(GrowthType::StrengthAgility)
+        );
+
+        // 正規化してから求める。
+        assert_eq!(hero_stats("ユウテイ", 12), hero_stats("ゆうてい", 12));
+
+        assert!(hero_stats("ゆうていみやおう", 1).is_err());
+        for level in [0, 31] {
+            let err = hero_stats("ゆうてい", level).unwrap_err();
+            match err {
+                Dq1PasswordError::InvalidGameState(e) => {
+                    assert!(matches!(e.kind(), GameStateErrorKind::OutOfRange { .. }));
+                    assert_eq!(e.field(), GameStateField::Level);
+                }
+                _ => panic!("{:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_growth_type() {
+        assert_eq!(growth_type("ゆうてい"), Ok(GrowthType::StrengthHp));
+        assert_eq!(growth_type("しどー"), Ok(GrowthType::HpMp));
+        assert_eq!(growth_type("あ"), Ok(GrowthType::StrengthAgility));
+
+        // "ああ  ": 0x0A * 2 + 0x3F * 2 = 0x92 → N = 2
+        assert_eq!(growth_type("ああ"), Ok(GrowthType::AgilityMp));
+
+        // 空白でのパディングを含めて計算する。和なので文字の順序にはよらない。
+        assert_eq!(growth_type("あ"), growth_type("あ   "));
+        assert_eq!(growth_type("あ"), growth_type("  あ "));
+        assert_eq!(growth_type(""), Ok(GrowthType::HpMp));
+
+        // 濁音は 2 文字に分かれる。
+        assert_eq!(growth_type("がか"), growth_type("か゛か"));
+        assert_eq!(growth_type("ガカ"), growth_type("がか"));
+        // "か゛か ": 0x0F + 0x3C + 0x0F + 0x3F = 0x99 → N = 9
+        assert_eq!(growth_type("がか"), Ok(GrowthType::StrengthHp));
+
+        // 成長タイプは hero_stats のものと一致し、4 種類全てが現れる。
+        let mut seen = BTreeSet::new();
+        for name in [
+            "ゆうてい",
+            "しどー",
+            "あ",
+            "ああ",
+            "",
+            "がか",
+            "ろと",
+            "ろーら",
+        ] {
+            let growth = growth_type(name).unwrap();
+            assert_eq!(hero_stats(name, 1).unwrap().growth_type, growth);
+            seen.insert(growth);
+        }
+        assert_eq!(seen.len(), 4);
+
+        assert!(growth_type("ゆうていみやおう").is_err());
+    }
+
+    #[test]
+    fn test_hero_stats_monotonic() {
+        for name in ["ゆうてい", "しどー", "", "あ"] {
+            let stats: Vec<_> = (1..=LEVEL_MAX)
+                .map(|level| hero_stats(name, level).unwrap())
+                .collect();
+            for w in stats.windows(2) {
+                assert!(w[0].max_hp <= w[1].max_hp);
+                assert!(w[0].max_mp <= w[1].max_mp);
+                assert!(w[0].strength <= w[1].strength);
+                assert!(w[0].agility <= w[1].agility);
+            }
+        }
+    }
+
+    #[test]
+    fn test_game_state_stats() {
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            hero_xp: 26000,
+            ..Default::default()
+        };
+        assert_eq!(state.stats(), hero_stats("ゆうてい", 20));
+    }
+}
@@ -0,0 +1,171 @@
+use core::fmt::Write as _;
+
+use crate::combat::combat_stats;
+use crate::error::{Dq1PasswordResult, Lang};
+use crate::game_state::{
+    GameState, ARMOR_NAMES, ARMOR_NAMES_EN, SHIELD_NAMES, SHIELD_NAMES_EN, WEAPON_NAMES,
+    WEAPON_NAMES_EN,
+};
+use crate::normalize::display_hero_name;
+use crate::prelude::*;
+
+impl GameState {
+    /// ゲーム内のつよさ画面に相当する内容を、固定幅のテキストとして返す。
+    ///
+    /// 名前([`display_hero_name`] で濁点を合成したもの)に続き、レベル、能力値([`GameState::stats`])、
+    /// 攻撃力/守備力([`combat_stats`])、所持金、経験値、装備の名前を 1 行ずつ並べる。
+    /// 数値は右詰めで、日本語の場合はラベルを全角空白で揃える。HP/MP は最大値を表示する。
+    ///
+    /// `self` が無効な場合、`Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn status_screen(&self, lang: Lang) -> Dq1PasswordResult<String> {
+        let stats = self.stats()?;
+        let combat = combat_stats(self)?;
+
+        let numbers = [
+            (("レベル", "LEVEL"), u16::from(self.level())),
+            (("ちから", "STRENGTH"), u16::from(stats.strength)),
+            (("すばやさ", "AGILITY"), u16::from(stats.agility)),
+            (("さいだいＨＰ", "MAXIMUM HP"), u16::from(stats.max_hp)),
+            (("さいだいＭＰ", "MAXIMUM MP"), u16::from(stats.max_mp)),
+            (("こうげきりょく", "ATTACK POWER"), u16::from(combat.attack)),
+            (("しゅびりょく", "DEFENSE POWER"), u16::from(combat.defense)),
+            (("Ｇ", "GOLD"), self.purse),
+            (("Ｅ", "EXPERIENCE"), self.hero_xp),
+        ];
+
+        let weapon = usize::from(self.hero_weapon);
+        let armor = usize::from(self.hero_armor);
+        let shield = usize::from(self.hero_shield);
+
+        let mut screen = String::new();
+        writeln!(screen, "{}", display_hero_name(&self.hero_name)?).unwrap();
+
+        match lang {
+            Lang::Ja => {
+                const LABEL_WIDTH: usize = 7;
+                for ((label, _), value) in numbers {
+                    let pad = "　".repeat(LABEL_WIDTH - label.chars().count());
+                    writeln!(screen, "{}{}{:>6}", label, pad, value).unwrap();
+                }
+                let name = |name: &'static str| if name.is_empty() { "なし" } else { name };
+                writeln!(screen, "ぶき：{}", name(WEAPON_NAMES[weapon])).unwrap();
+                writeln!(screen, "よろい：{}", name(ARMOR_NAMES[armor])).unwrap();
+                writeln!(screen, "たて：{}", name(SHIELD_NAMES[shield])).unwrap();
+            }
+            Lang::En => {
+                for ((_, label), value) in numbers {
+                    writeln!(screen, "{:<14}{:>6}", label, value).unwrap();
+                }
+                let name = |name: &'static str| if name.is_empty() { "NONE" } else { name };
+                writeln!(screen, "WEAPON: {}", name(WEAPON_NAMES_EN[weapon])).unwrap();
+                writeln!(screen, "ARMOR:  {}", name(ARMOR_NAMES_EN[armor])).unwrap();
+                writeln!(screen, "SHIELD: {}", name(SHIELD_NAMES_EN[shield])).unwrap();
+            }
+        }
+
+        Ok(screen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_screen() {
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            state.status_screen(Lang::Ja).unwrap(),
+            concat!(
+                "ゆうてい\n",
+                "レベル　　　　     1\n",
+                "ちから　　　　     4\n",
+                "すばやさ　　　     3\n",
+                "さいだいＨＰ　    15\n",
+                "さいだいＭＰ　     0\n",
+                "こうげきりょく     4\n",
+                "しゅびりょく　     1\n",
+                "Ｇ　　　　　　     0\n",
+                "Ｅ　　　　　　     0\n",
+                "ぶき：なし\n",
+                "よろい：なし\n",
+                "たて：なし\n",
+            )
+        );
+        assert_eq!(
+            state.status_screen(Lang::En).unwrap(),
+            concat!(
+                "ゆうてい\n",
+                "LEVEL              1\n",
+                "STRENGTH           4\n",
+                "AGILITY            3\n",
+                "MAXIMUM HP        15\n",
+                "MAXIMUM MP         0\n",
+                "ATTACK POWER       4\n",
+                "DEFENSE POWER      1\n",
+                "GOLD               0\n",
+                "EXPERIENCE         0\n",
+                "WEAPON: NONE\n",
+                "ARMOR:  NONE\n",
+                "SHIELD: NONE\n",
+            )
+        );
+
+        // "か゛し-": 0x0F + 0x3C + 0x15 + 0x3E = 0x9E → N = 0xE (すばやさと最大 MP がよく伸びる)
+        let state = GameState {
+            hero_name: "ガシー".to_owned(),
+            hero_xp: u16::MAX,
+            purse: 12345,
+            hero_weapon: 7,
+            hero_armor: 6,
+            hero_shield: 3,
+            flag_equip_dragon_scale: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            state.status_screen(Lang::Ja).unwrap(),
+            concat!(
+                "がし-\n",
+                "レベル　　　　    30\n",
+                "ちから　　　　   129\n",
+                "すばやさ　　　   130\n",
+                "さいだいＨＰ　   192\n",
+                "さいだいＭＰ　   200\n",
+                "こうげきりょく   169\n",
+                "しゅびりょく　   111\n",
+                "Ｇ　　　　　　 12345\n",
+                "Ｅ　　　　　　 65535\n",
+                "ぶき：ロトのつるぎ\n",
+                "よろい：まほうのよろい\n",
+                "たて：みかがみのたて\n",
+            )
+        );
+        assert_eq!(
+            state.status_screen(Lang::En).unwrap(),
+            concat!(
+                "がし-\n",
+                "LEVEL             30\n",
+                "STRENGTH         129\n",
+                "AGILITY          130\n",
+                "MAXIMUM HP       192\n",
+                "MAXIMUM MP       200\n",
+                "ATTACK POWER     169\n",
+                "DEFENSE POWER    111\n",
+                "GOLD           12345\n",
+                "EXPERIENCE     65535\n",
+                "WEAPON: ERDRICK'S SWORD\n",
+                "ARMOR:  MAGIC ARMOR\n",
+                "SHIELD: SILVER SHIELD\n",
+            )
+        );
+
+        let invalid = GameState {
+            hero_shield: 4,
+            ..state
+        };
+        assert!(invalid.status_screen(Lang::Ja).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+use crate::encode::bytes_to_password;
+use crate::error::Dq1PasswordResult;
+use crate::generate::{GenerateJob, GenerateOptions};
+use crate::pattern::Pattern;
+use crate::prelude::*;
+
+/// 生成結果を受け渡すチャネルの容量。
+const CHANNEL_CAPACITY: usize = 64;
+
+/// [`generate_with_options`](crate::generate_with_options) の非同期版。
+/// パターンに合致するデコード可能な復活の呪文を、生成された順に流す Stream を返す。
+///
+/// 生成は `tokio::task::spawn_blocking` で行うので、executor のワーカースレッドをブロックしない。
+/// 結果とその順序は [`generate_with_options`](crate::generate_with_options) と同じ。
+/// ただし `Fill::Defaults` や `SortBy::InputCost` の場合、全ての結果が揃ってから流れ始める。
+///
+/// Stream を drop すると生成を打ち切る(実行中の DP 1 回分が終わるまでは計算が続く)。
+///
+/// tokio ランタイム内で呼び出さなければならない。
+///
+/// パターンやオプションが無効な場合、Stream を返す前に
+/// [`generate_with_options`](crate::generate_with_options) と同様のエラーを返す。
+pub fn generate_stream(
+    pattern: impl AsRef<str>,
+    n_max: usize,
+    options: &GenerateOptions,
+) -> Dq1PasswordResult<impl Stream<Item = String>> {
+    let job = GenerateJob::new(&Pattern::parse(pattern)?, n_max, options)?;
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        // 受信側が drop されたら送信に失敗するので、そこで打ち切る。
+        job.run(|bytes| tx.blocking_send(bytes_to_password(&bytes)).is_ok());
+    });
+
+    Ok(ReceiverStream::new(rx))
+}
@@ -0,0 +1,206 @@
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// 既知の復活の呪文とゲーム状態の組。[`test_vectors`] を参照。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TestVector {
+    /// 内容の短い説明。
+    pub description: &'static str,
+
+    /// 正規化済みの復活の呪文。
+    pub password: &'static str,
+
+    state: VectorState,
+}
+
+impl TestVector {
+    /// 復活の呪文に対応する正規化済みのゲーム状態。
+    ///
+    /// `password` をデコードした結果と等しく、また `password` はこれをエンコードした結果と等しい。
+    pub fn state(&self) -> GameState {
+        let s = &self.state;
+
+        GameState {
+            hero_name: s.hero_name.to_owned(),
+            hero_xp: s.hero_xp,
+            purse: s.purse,
+            hero_weapon: s.hero_weapon,
+            hero_armor: s.hero_armor,
+            hero_shield: s.hero_shield,
+            herb_count: s.herb_count,
+            key_count: s.key_count,
+            inventory: s.inventory,
+            flag_equip_dragon_scale: s.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: s.flag_equip_warrior_ring,
+            flag_got_death_necklace: s.flag_got_death_necklace,
+            flag_beated_golem: s.flag_beated_golem,
+            flag_beated_dragon: s.flag_beated_dragon,
+            salt: s.salt,
+        }
+    }
+}
+
+/// [`GameState`] を static に置くためのもの。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct VectorState {
+    hero_name: &'static str,
+    hero_xp: u16,
+    purse: u16,
+    hero_weapon: u8,
+    hero_armor: u8,
+    hero_shield: u8,
+    herb_count: u8,
+    key_count: u8,
+    inventory: [u8; 8],
+    flag_equip_dragon_scale: bool,
+    flag_equip_warrior_ring: bool,
+    flag_got_death_necklace: bool,
+    flag_beated_golem: bool,
+    flag_beated_dragon: bool,
+    salt: u8,
+}
+
+/// `GameState::default()` を正規化したもの。
+const DEFAULT: VectorState = VectorState {
+    hero_name: "    ",
+    hero_xp: 0,
+    purse: 0,
+    hero_weapon: 0,
+    hero_armor: 0,
+    hero_shield: 0,
+    herb_count: 0,
+    key_count: 0,
+    inventory: [0; 8],
+    flag_equip_dragon_scale: false,
+    flag_equip_warrior_ring: false,
+    flag_got_death_necklace: false,
+    flag_beated_golem: false,
+    flag_beated_dragon: false,
+    salt: 0,
+};
+
+/// 全フィールドを最大にしたもの。
+const MAXED: VectorState = VectorState {
+    hero_name: "ゆうてい",
+    hero_xp: u16::MAX,
+    purse: u16::MAX,
+    hero_weapon: 7,
+    hero_armor: 7,
+    hero_shield: 3,
+    herb_count: 6,
+    key_count: 6,
+    inventory: [14; 8],
+    flag_equip_dragon_scale: true,
+    flag_equip_warrior_ring: true,
+    flag_got_death_necklace: true,
+    flag_beated_golem: true,
+    flag_beated_dragon: true,
+    salt: 7,
+};
+
+/// salt を変えて用いる状態。
+const SIDO: VectorState = VectorState {
+    hero_name: "しと゛-",
+    hero_xp: 1234,
+    purse: 5678,
+    hero_weapon: 5,
+    hero_armor: 5,
+    hero_shield: 2,
+    herb_count: 6,
+    key_count: 6,
+    inventory: [1, 2, 3, 4, 5, 6, 7, 8],
+    salt: 0,
+    ..MAXED
+};
+
+const fn vector(
+    description: &'static str,
+    password: &'static str,
+    state: VectorState,
+) -> TestVector {
+    TestVector {
+        description,
+        password,
+        state,
+    }
+}
+
+#[rustfmt::skip]
+const TEST_VECTORS: &[TestVector] = &[
+    vector("default state", "つにこへむゆるわげげだどべうきさそさには", DEFAULT),
+    vector("maxed state", "ほみじぎみもよねこびびぼうめしさせづよゆ", MAXED),
+
+    vector("flag_equip_dragon_scale only", "はみとらろぐじぞでであおけすちなのなりわ", VectorState { flag_equip_dragon_scale: true, ..DEFAULT }),
+    vector("flag_equip_warrior_ring only", "あおづしたとねひままよれぎざぞでぶぶけす", VectorState { flag_equip_warrior_ring: true, ..DEFAULT }),
+    vector("flag_got_death_necklace only", "めるへぼえくしそててはほめよれぎざぎづび", VectorState { flag_got_death_necklace: true, ..DEFAULT }),
+    vector("flag_beated_golem only", "しつかのへむおくししてぬひまもらろらざぞ", VectorState { flag_beated_golem: true, ..DEFAULT }),
+    vector("flag_beated_dragon only", "めるへげずぢばべううせつにはほめよめがご", VectorState { flag_beated_dragon: true, ..DEFAULT }),
+
+    vector("hero_xp 1", "ごでぎあおけすたととひまもらろぐじぐでぶ", VectorState { hero_xp: 1, ..DEFAULT }),
+    vector("hero_xp 255", "ほみもぞでぶいおけけたとねふみやりやぐじ", VectorState { hero_xp: 255, ..DEFAULT }),
+    vector("hero_xp 256", "いかですちなのふみみらろぐじだどぼばきさ", VectorState { hero_xp: 256, ..DEFAULT }),
+    vector("hero_xp 65535", "べいおねふみやらろろざぞでぶいかけくてぬ", VectorState { hero_xp: u16::MAX, ..DEFAULT }),
+    vector("purse 1", "けせいなのむゆりわわじだどべうきさきつに", VectorState { purse: 1, ..DEFAULT }),
+    vector("purse 255", "さそうにははがげずずどべうきさそてそはほ", VectorState { purse: 255, ..DEFAULT }),
+    vector("purse 256", "ざぢわべうきさせつつのへめよれぎざぎづび", VectorState { purse: 256, ..DEFAULT }),
+    vector("purse 65535", "ぶえぢさそそめゆるるげずだぶいかこかちな", VectorState { purse: u16::MAX, ..DEFAULT }),
+
+    vector("salt 0", "ぎどしぐぞじのざびどよぬごすらえしばひら", VectorState { salt: 0, ..SIDO }),
+    vector("salt 1", "ごびせごぢぜろえそすぢるうまづにほせがづ", VectorState { salt: 1, ..SIDO }),
+    vector("salt 2", "せへぎごぢぜひずべびりのじそるかせぶへる", VectorState { salt: 2, ..SIDO }),
+    vector("salt 3", "ちまげじでだがかちそでろおむどねみたぐど", VectorState { salt: 3, ..SIDO }),
+    vector("salt 4", "がばすげだずはじぶばぐみでにげすなおやげ", VectorState { salt: 4, ..SIDO }),
+    vector("salt 5", "ざぼちずどぢぎきつたうずせれえむるのぢえ", VectorState { salt: 5, ..SIDO }),
+    vector("salt 6", "すはろぎぜざねごばでがほぢとぎさてうめぎ", VectorState { salt: 6, ..SIDO }),
+    vector("salt 7", "つほぐざづぞわおたせあざしりいまらぬぞい", VectorState { salt: 7, ..SIDO }),
+];
+
+/// 既知の復活の呪文とゲーム状態の組たち(テストベクタ)を返す。
+///
+/// 既定の状態、全フィールドを最大にした状態、各フラグを 1 つずつ立てた状態、
+/// 経験値/所持金の境界値、ある状態の全ての salt を含む。
+/// この crate のテストはこれらを用いているので、常にエンコード/デコード結果と一致する。
+///
+/// 要素は追加されることはあっても、変更や削除はされない(既存の要素の順序も保たれる)。
+pub fn test_vectors() -> &'static [TestVector] {
+    TEST_VECTORS
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    use crate::{decode, decode_strict, encode};
+
+    #[test]
+    fn test_test_vectors() {
+        for v in test_vectors() {
+            let state = v.state();
+            assert_eq!(decode(v.password).as_ref(), Ok(&state), "{}", v.description);
+            assert_eq!(
+                decode_strict(v.password),
+                Ok(state.clone()),
+                "{}",
+                v.description
+            );
+            assert_eq!(
+                encode(&state).as_deref(),
+                Ok(v.password),
+                "{}",
+                v.description
+            );
+            assert_eq!(state.normalize(), Ok(state.clone()), "{}", v.description);
+        }
+    }
+
+    #[test]
+    fn test_test_vectors_unique() {
+        let passwords: HashSet<_> = test_vectors().iter().map(|v| v.password).collect();
+        assert_eq!(passwords.len(), test_vectors().len());
+
+        let descriptions: HashSet<_> = test_vectors().iter().map(|v| v.description).collect();
+        assert_eq!(descriptions.len(), test_vectors().len());
+    }
+}
@@ -0,0 +1,504 @@
+use itertools::Itertools as _;
+
+use crate::alphabet::PASSWORD_CHARS;
+use crate::decode::password_char_to_cum;
+use crate::error::{Dq1PasswordError, Dq1PasswordResult, ErrorKind, PasswordErrorKind};
+use crate::normalize::normalize_password;
+use crate::prelude::*;
+
+/// 空白のタイル番号。主人公の名前の ' ' の内部値と同じ。
+pub const TILE_BLANK: u8 = 0x3F;
+
+/// 濁点のタイル番号。主人公の名前の '゛' の内部値と同じ。
+pub const TILE_DAKUTEN: u8 = 0x3C;
+
+/// 復活の呪文の各文字(並び順は [`PASSWORD_CHARS`] と同じ)を表示するタイルたち。要素は [濁点の行, 文字の行]。
+///
+/// タイル番号は本家(FC 版)の BG の文字タイルのもの。
+///
+/// 出典: この表は ROM の CHR から直接抜き出したものではなく、主人公の名前の内部値
+/// ([`HERO_NAME_CHARS`](crate::HERO_NAME_CHARS) の並び順)から導いている。
+/// 本家は名前などの文字を内部値のまま名前テーブルに書き込んで表示するので、内部値がそのままタイル番号となる。
+/// 内部値の表は、このクレートのデコード/エンコードや RAM 上の名前([`GameState::from_nes_ram`](crate::GameState::from_nes_ram) を参照)で
+/// 用いているものと同じ。空白と濁点のタイルも同様に ' ' と '゛' の内部値である。
+/// CHR ROM のダンプとは照合していない。ROM ハック等でタイル配置が異なる場合は当てはまらない。
+///
+/// 濁音は清音のタイルの 1 行上に濁点のタイルを重ねて表示する(濁点のない文字の上は空白)。
+/// 半濁音は復活の呪文には現れない。
+#[rustfmt::skip]
+pub const PASSWORD_CHAR_TILES: [[u8; 2]; 64] = [
+    // あ..お
+    [0x3F, 0x0A], [0x3F, 0x0B], [0x3F, 0x0C], [0x3F, 0x0D], [0x3F, 0x0E],
+    // か..こ
+    [0x3F, 0x0F], [0x3F, 0x10], [0x3F, 0x11], [0x3F, 0x12], [0x3F, 0x13],
+    // さ..そ
+    [0x3F, 0x14], [0x3F, 0x15], [0x3F, 0x16], [0x3F, 0x17], [0x3F, 0x18],
+    // た..と
+    [0x3F, 0x19], [0x3F, 0x1A], [0x3F, 0x1B], [0x3F, 0x1C], [0x3F, 0x1D],
+    // な..の
+    [0x3F, 0x1E], [0x3F, 0x1F], [0x3F, 0x20], [0x3F, 0x21], [0x3F, 0x22],
+    // は..ほ
+    [0x3F, 0x23], [0x3F, 0x24], [0x3F, 0x25], [0x3F, 0x26], [0x3F, 0x27],
+    // ま..も
+    [0x3F, 0x28], [0x3F, 0x29], [0x3F, 0x2A], [0x3F, 0x2B], [0x3F, 0x2C],
+    // や, ゆ, よ
+    [0x3F, 0x2D], [0x3F, 0x2E], [0x3F, 0x2F],
+    // ら..ろ
+    [0x3F, 0x30], [0x3F, 0x31], [0x3F, 0x32], [0x3F, 0x33], [0x3F, 0x34],
+    // わ
+    [0x3F, 0x35],
+    // が..ご
+    [0x3C, 0x0F], [0x3C, 0x10], [0x3C, 0x11], [0x3C, 0x12], [0x3C, 0x13],
+    // ざ..ぞ
+    [0x3C, 0x14], [0x3C, 0x15], [0x3C, 0x16], [0x3C, 0x17], [0x3C, 0x18],
+    // だ..ど
+    [0x3C, 0x19], [0x3C, 0x1A], [0x3C, 0x1B], [0x3C, 0x1C], [0x3C, 0x1D],
+    // ば..ぼ
+    [0x3C, 0x23], [0x3C, 0x24], [0x3C, 0x25], [0x3C, 0x26], [0x3C, 0x27],
+];
+
+/// 復活の呪文をゲーム内で表示する際のタイル番号の列に変換する。
+///
+/// 戻り値は 40 要素で、前半 20 要素が濁点の行、後半 20 要素が文字の行
+/// ([`PASSWORD_CHAR_TILES`] を参照)。画面上の配置は関知しない。
+///
+/// `password` の形式が無効な場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+pub fn password_to_tiles(password: impl AsRef<str>) -> Dq1PasswordResult<Vec<u8>> {
+    let tiles: Vec<_> = normalize_password(password)?
+        .chars()
+        .map(|c| PASSWORD_CHAR_TILES[usize::from(password_char_to_cum(c).unwrap())])
+        .collect();
+
+    Ok(tiles
+        .iter()
+        .map(|&[mark, _]| mark)
+        .chain(tiles.iter().map(|&[_, base]| base))
+        .collect())
+}
+
+/// [`password_to_tiles`] の逆変換。タイル番号の列を復活の呪文に変換する。
+///
+/// `tiles` が 40 要素でない場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+///
+/// 復活の呪文の文字を表さないタイル(未知のタイル、清音でない文字の上の濁点など)が含まれる場合、
+/// `Err(Dq1PasswordError::InvalidPassword)` を返す。原因は `PasswordErrorKind::InvalidTiles` となる。
+pub fn tiles_to_password(tiles: &[u8]) -> Dq1PasswordResult<String> {
+    if tiles.len() != 40 {
+        return Err(Dq1PasswordError::invalid_format(format!(
+            "タイル列は 40 要素(濁点の行と文字の行 20 要素ずつ)でなければならない: {} 要素",
+            tiles.len()
+        )));
+    }
+    let (marks, bases) = tiles.split_at(20);
+
+    let mut password = String::with_capacity(3 * 20);
+    let mut offenders = vec![];
+    for (i, (&mark, &base)) in core::iter::zip(marks, bases).enumerate() {
+        match PASSWORD_CHAR_TILES.iter().position(|&x| x == [mark, base]) {
+            Some(cum) => password.push(PASSWORD_CHARS[cum]),
+            None => {
+                // 文字の行が有効なら、濁点の行のタイルが原因。
+                if PASSWORD_CHAR_TILES.iter().any(|&[_, x]| x == base) {
+                    offenders.push((i, mark));
+                } else {
+                    offenders.push((20 + i, base));
+                }
+            }
+        }
+    }
+
+    if !offenders.is_empty() {
+        let msg = format!(
+            "復活の呪文の文字を表さないタイルが含まれている: {}",
+            offenders
+                .iter()
+                .map(|&(i, tile)| format!("位置 {} の {:#04X}", i, tile))
+                .join(", ")
+        );
+        return Err(Dq1PasswordError::invalid_password_tiles(offenders, msg));
+    }
+
+    Ok(password)
+}
+
+/// 名前テーブル 1 枚のサイズ(1 KiB。タイル 32×30 と属性テーブル)。
+pub const NAMETABLE_SIZE: usize = 0x400;
+
+/// 名前テーブルの横のタイル数。
+const NAMETABLE_WIDTH: usize = 32;
+
+/// 名前テーブルの縦のタイル数。
+const NAMETABLE_HEIGHT: usize = 30;
+
+/// ふっかつのじゅもんの表示画面における、名前テーブル上の復活の呪文の配置。
+///
+/// 復活の呪文は `line_len` 文字ずつ複数行に分けて表示され、各文字の 1 行上に濁点の行がある
+/// ([`PASSWORD_CHAR_TILES`] を参照)。
+/// 既定値([`ScreenLayout::DQ1_FC`])と実際の画面が異なる場合は、各フィールドを指定する。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ScreenLayout {
+    /// 2 KiB の VRAM スナップショットを与えた場合に用いる名前テーブルの番号(0 または 1)。
+    /// 1 KiB のダンプを与えた場合は無視する。
+    pub nametable: u8,
+
+    /// 1 行目の先頭の文字の列(0..32)。
+    pub col: u8,
+
+    /// 1 行目の先頭の文字の行(1..30)。濁点はこの 1 行上にある。
+    pub row: u8,
+
+    /// 1 行あたりの文字数。20 の約数でなければならない。
+    pub line_len: u8,
+
+    /// 復活の呪文の行の間隔(タイル単位)。濁点の行を含むので 2 以上でなければならない。
+    pub line_stride: u8,
+}
+
+impl ScreenLayout {
+    /// 本家(FC 版)を想定した配置。10 文字ずつ 2 行。
+    ///
+    /// この値は実機やエミュレータの名前テーブルのダンプと照合していない(テストのダンプもこの値から作っている)。
+    /// 実際の画面と合わない場合は、ダンプ上の位置を確認して各フィールドを指定すること。
+    pub const DQ1_FC: Self = Self {
+        nametable: 0,
+        col: 11,
+        row: 13,
+        line_len: 10,
+        line_stride: 2,
+    };
+
+    /// 復活の呪文の各文字について、(濁点のタイルの位置, 文字のタイルの位置) を名前テーブル内のオフセットで返す。
+    ///
+    /// 配置が名前テーブルに収まらないなど無効な場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+    fn tile_offsets(&self) -> Dq1PasswordResult<Vec<(usize, usize)>> {
+        let col = usize::from(self.col);
+        let row = usize::from(self.row);
+        let line_len = usize::from(self.line_len);
+        let line_stride = usize::from(self.line_stride);
+
+        let valid = self.nametable <= 1
+            && line_len != 0
+            && 20 % line_len == 0
+            && line_stride >= 2
+            && row >= 1
+            && col + line_len <= NAMETABLE_WIDTH
+            && row + line_stride * (20 / line_len - 1) < NAMETABLE_HEIGHT;
+        if !valid {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "復活の呪文の配置が無効: {:?}",
+                self
+            )));
+        }
+
+        Ok((0..20)
+            .map(|i| {
+                let y = row + line_stride * (i / line_len);
+                let x = col + i % line_len;
+                (NAMETABLE_WIDTH * (y - 1) + x, NAMETABLE_WIDTH * y + x)
+            })
+            .collect())
+    }
+}
+
+impl Default for ScreenLayout {
+    fn default() -> Self {
+        Self::DQ1_FC
+    }
+}
+
+/// ふっかつのじゅもんの表示画面の名前テーブルのダンプから、復活の呪文を読み取る。
+///
+/// `dump` は名前テーブル 1 枚([`NAMETABLE_SIZE`] バイト)または VRAM 全体(その 2 倍)。
+/// 後者の場合、`layout.nametable` の名前テーブルを用いる。
+/// 戻り値は正規化された復活の呪文で、そのまま [`decode`](crate::decode()) に渡せる(デコードできるかどうかは関知しない)。
+///
+/// `dump` のサイズまたは `layout` が無効な場合、`Err(Dq1PasswordError::InvalidFormat)` を返す。
+///
+/// 文字の位置に復活の呪文の文字のタイルが 1 つもない場合、画面に復活の呪文が表示されていないとみなし、
+/// `Err(Dq1PasswordError::InvalidFormat)` を返す。
+///
+/// 復活の呪文の文字を表さないタイルが含まれる場合、`Err(Dq1PasswordError::InvalidPassword)` を返す。
+/// 原因は `PasswordErrorKind::InvalidTiles` で、位置は `dump` 中のオフセットとなる。
+pub fn password_from_nametable(dump: &[u8], layout: &ScreenLayout) -> Dq1PasswordResult<String> {
+    let base = match dump.len() {
+        NAMETABLE_SIZE => 0,
+        len if len == 2 * NAMETABLE_SIZE => NAMETABLE_SIZE * usize::from(layout.nametable),
+        len => {
+            return Err(Dq1PasswordError::invalid_format(format!(
+                "名前テーブルのダンプは {} バイトまたは {} バイトでなければならない: {} バイト",
+                NAMETABLE_SIZE,
+                2 * NAMETABLE_SIZE,
+                len
+            )))
+        }
+    };
+
+    let offsets: Vec<_> = layout
+        .tile_offsets()?
+        .into_iter()
+        .map(|(mark, char)| (base + mark, base + char))
+        .collect();
+
+    if !offsets
+        .iter()
+        .any(|&(_, char)| PASSWORD_CHAR_TILES.iter().any(|&[_, x]| x == dump[char]))
+    {
+        return Err(Dq1PasswordError::invalid_format(
+            "画面に復活の呪文が表示されていない",
+        ));
+    }
+
+    let tiles: Vec<_> = offsets
+        .iter()
+        .map(|&(mark, _)| dump[mark])
+        .chain(offsets.iter().map(|&(_, char)| dump[char]))
+        .collect();
+
+    tiles_to_password(&tiles).map_err(|e| match e.kind() {
+        ErrorKind::InvalidPassword(PasswordErrorKind::InvalidTiles(offenders)) => {
+            // タイル列中の位置を dump 中のオフセットに置き換える。
+            let offenders: Vec<_> = offenders
+                .into_iter()
+                .map(|(i, tile)| {
+                    let offset = if i < 20 {
+                        offsets[i].0
+                    } else {
+                        offsets[i - 20].1
+                    };
+                    (offset, tile)
+                })
+                .collect();
+            let msg = format!(
+                "名前テーブルに復活の呪文の文字を表さないタイルが含まれている: {}",
+                offenders
+                    .iter()
+                    .map(|&(offset, tile)| format!("オフセット {:#05X} の {:#04X}", offset, tile))
+                    .join(", ")
+            );
+            Dq1PasswordError::invalid_password_tiles(offenders, msg)
+        }
+        _ => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::alphabet::{hero_name_char_index, HERO_NAME_CHARS};
+    use crate::decode::decode;
+    use crate::normalize::normalize_hero_name;
+    use crate::test_vector::test_vectors;
+
+    #[test]
+    fn test_password_char_tiles() {
+        // 表の出典である主人公の名前の内部値と一致する。
+        assert_eq!(HERO_NAME_CHARS[usize::from(TILE_BLANK)], ' ');
+        assert_eq!(HERO_NAME_CHARS[usize::from(TILE_DAKUTEN)], '゛');
+        for (&c, &[mark, base]) in PASSWORD_CHARS.iter().zip(&PASSWORD_CHAR_TILES) {
+            let name = normalize_hero_name(c.to_string()).unwrap();
+            let codes: Vec<_> = name
+                .chars()
+                .map(|c| hero_name_char_index(c).unwrap())
+                .collect();
+            if mark == TILE_BLANK {
+                assert_eq!(codes, [base, TILE_BLANK, TILE_BLANK, TILE_BLANK]);
+            } else {
+                assert_eq!(codes, [base, TILE_DAKUTEN, TILE_BLANK, TILE_BLANK]);
+            }
+        }
+        assert!(PASSWORD_CHAR_TILES.iter().all_unique());
+    }
+
+    #[test]
+    fn test_password_to_tiles() {
+        let tiles = password_to_tiles("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+        assert_eq!(tiles.len(), 40);
+        assert_eq!(
+            &tiles[..4],
+            [TILE_DAKUTEN, TILE_DAKUTEN, TILE_BLANK, TILE_DAKUTEN]
+        );
+        assert_eq!(&tiles[20..24], [0x14, 0x27, 0x1A, 0x16]);
+
+        // 正規化される。
+        assert_eq!(
+            password_to_tiles("ザボチズ ドヂギキツタ ウズセレエ ムルノヂエ").unwrap(),
+            tiles
+        );
+        assert!(password_to_tiles("ざぼちず").is_err());
+
+        for vector in test_vectors() {
+            let tiles = password_to_tiles(vector.password).unwrap();
+            assert_eq!(
+                tiles_to_password(&tiles).unwrap(),
+                normalize_password(vector.password).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tiles_to_password() {
+        let tiles = password_to_tiles("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+
+        let kind = |tiles: &[u8]| tiles_to_password(tiles).unwrap_err().kind();
+
+        assert_eq!(kind(&tiles[..39]), ErrorKind::InvalidFormat);
+        assert_eq!(
+            kind(&[tiles.as_slice(), &[TILE_BLANK]].concat()),
+            ErrorKind::InvalidFormat
+        );
+
+        // 未知のタイル、数字、'を'
+        let mut invalid = tiles.clone();
+        invalid[20] = 0x40;
+        invalid[21] = 0x00;
+        invalid[39] = 0x36;
+        assert_eq!(
+            kind(&invalid),
+            ErrorKind::InvalidPassword(PasswordErrorKind::InvalidTiles(vec![
+                (20, 0x40),
+                (21, 0x00),
+                (39, 0x36),
+            ]))
+        );
+
+        // 'ち' の上の半濁点、'う' の上の濁点
+        let mut invalid = tiles.clone();
+        invalid[2] = 0x3D;
+        invalid[10] = TILE_DAKUTEN;
+        assert_eq!(
+            kind(&invalid),
+            ErrorKind::InvalidPassword(PasswordErrorKind::InvalidTiles(vec![
+                (2, 0x3D),
+                (10, TILE_DAKUTEN),
+            ]))
+        );
+
+        // 濁点を外すと清音になる。
+        let mut voiceless = tiles;
+        voiceless[0] = TILE_BLANK;
+        assert_eq!(
+            tiles_to_password(&voiceless).unwrap(),
+            "さぼちずどぢぎきつたうずせれえむるのぢえ"
+        );
+    }
+
+    /// `password` を `layout` の配置で表示した名前テーブル(`size` バイト)を作る。
+    ///
+    /// 実機のダンプではないので、これを用いるテストでは [`ScreenLayout::DQ1_FC`] の値自体の正しさは確かめられない。
+    fn nametable_fixture(password: &str, layout: &ScreenLayout, size: usize) -> Vec<u8> {
+        // 復活の呪文以外の部分は、文字でないタイルで埋める。
+        let mut dump = vec![0xFF; size];
+        let base = if size == NAMETABLE_SIZE {
+            0
+        } else {
+            NAMETABLE_SIZE * usize::from(layout.nametable)
+        };
+        let tiles = password_to_tiles(password).unwrap();
+        for (i, (mark, char)) in layout.tile_offsets().unwrap().into_iter().enumerate() {
+            dump[base + mark] = tiles[i];
+            dump[base + char] = tiles[20 + i];
+        }
+
+        dump
+    }
+
+    #[test]
+    fn test_password_from_nametable() {
+        const PASSWORD: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+        for layout in [
+            ScreenLayout::DQ1_FC,
+            ScreenLayout {
+                nametable: 1,
+                col: 0,
+                row: 1,
+                line_len: 5,
+                line_stride: 3,
+            },
+            ScreenLayout {
+                nametable: 0,
+                col: 12,
+                row: 29,
+                line_len: 20,
+                line_stride: 2,
+            },
+        ] {
+            for size in [NAMETABLE_SIZE, 2 * NAMETABLE_SIZE] {
+                let dump = nametable_fixture(PASSWORD, &layout, size);
+                let password = password_from_nametable(&dump, &layout).unwrap();
+                assert_eq!(password, PASSWORD);
+                assert_eq!(decode(password), decode(PASSWORD));
+            }
+        }
+
+        let layout = ScreenLayout::default();
+        let dump = nametable_fixture(PASSWORD, &layout, 2 * NAMETABLE_SIZE);
+        let kind = |dump: &[u8], layout: &ScreenLayout| {
+            password_from_nametable(dump, layout).unwrap_err().kind()
+        };
+
+        // サイズが無効。
+        assert_eq!(kind(&dump[..1000], &layout), ErrorKind::InvalidFormat);
+
+        // 配置が無効。
+        for invalid in [
+            ScreenLayout {
+                nametable: 2,
+                ..layout
+            },
+            ScreenLayout { col: 23, ..layout },
+            ScreenLayout { row: 0, ..layout },
+            ScreenLayout { row: 28, ..layout },
+            ScreenLayout {
+                line_len: 3,
+                ..layout
+            },
+            ScreenLayout {
+                line_stride: 1,
+                ..layout
+            },
+        ] {
+            assert_eq!(
+                kind(&dump, &invalid),
+                ErrorKind::InvalidFormat,
+                "{:?}",
+                invalid
+            );
+        }
+
+        // 復活の呪文が表示されていない。
+        assert_eq!(
+            kind(&[0xFF; NAMETABLE_SIZE], &layout),
+            ErrorKind::InvalidFormat
+        );
+        assert_eq!(
+            kind(&[TILE_BLANK; NAMETABLE_SIZE], &layout),
+            ErrorKind::InvalidFormat
+        );
+        // もう一方の名前テーブルを見ている。
+        assert_eq!(
+            kind(
+                &dump,
+                &ScreenLayout {
+                    nametable: 1,
+                    ..layout
+                }
+            ),
+            ErrorKind::InvalidFormat
+        );
+
+        // 無効なタイルが含まれる。位置は dump 中のオフセットで、文字の順に並ぶ。
+        let mut invalid = dump;
+        let char_offset = 32 * 13 + 11;
+        invalid[char_offset + 1] = 0x40;
+        invalid[char_offset - 32 + 2] = 0x3D;
+        assert_eq!(
+            kind(&invalid, &layout),
+            ErrorKind::InvalidPassword(PasswordErrorKind::InvalidTiles(vec![
+                (char_offset + 1, 0x40),
+                (char_offset - 32 + 2, 0x3D),
+            ]))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::{Dq1PasswordError, Dq1PasswordResult};
+use crate::game_state::{GameState, GameStateField};
+use crate::level::{xp_for_level, LEVEL_MAX};
+use crate::normalize::normalize_hero_name;
+use crate::prelude::*;
+
+/// あるレベル以上の典型的なプレイヤーの状態。[`GameState::at_level`] を参照。
+struct TypicalStage {
+    /// この段階となる最小のレベル。
+    level: u8,
+    hero_weapon: u8,
+    hero_armor: u8,
+    hero_shield: u8,
+    purse: u16,
+    herb_count: u8,
+    key_count: u8,
+    inventory: [u8; 8],
+    flag_equip_dragon_scale: bool,
+    flag_beated_golem: bool,
+    flag_beated_dragon: bool,
+}
+
+/// 典型的なプレイヤーの状態の段階たち(レベルの昇順)。[`GameState::at_level`] の表と同じ内容。
+#[rustfmt::skip]
+const TYPICAL_STAGES: [TypicalStage; 7] = [
+    TypicalStage { level: 1,  hero_weapon: 1, hero_armor: 1, hero_shield: 0, purse: 50,    herb_count: 1, key_count: 0, inventory: [1, 0, 0, 0, 0, 0, 0, 0],   flag_equip_dragon_scale: false, flag_beated_golem: false, flag_beated_dragon: false },
+    TypicalStage { level: 3,  hero_weapon: 2, hero_armor: 2, hero_shield: 1, purse: 200,   herb_count: 2, key_count: 0, inventory: [1, 3, 0, 0, 0, 0, 0, 0],   flag_equip_dragon_scale: false, flag_beated_golem: false, flag_beated_dragon: false },
+    TypicalStage { level: 7,  hero_weapon: 3, hero_armor: 3, hero_shield: 1, purse: 500,   herb_count: 4, key_count: 1, inventory: [3, 4, 0, 0, 0, 0, 0, 0],   flag_equip_dragon_scale: true,  flag_beated_golem: false, flag_beated_dragon: false },
+    TypicalStage { level: 12, hero_weapon: 4, hero_armor: 4, hero_shield: 2, purse: 1000,  herb_count: 5, key_count: 2, inventory: [3, 4, 8, 0, 0, 0, 0, 0],   flag_equip_dragon_scale: true,  flag_beated_golem: false, flag_beated_dragon: true },
+    TypicalStage { level: 16, hero_weapon: 5, hero_armor: 5, hero_shield: 2, purse: 2000,  herb_count: 6, key_count: 3, inventory: [3, 4, 8, 10, 0, 0, 0, 0],  flag_equip_dragon_scale: true,  flag_beated_golem: true,  flag_beated_dragon: true },
+    TypicalStage { level: 20, hero_weapon: 6, hero_armor: 6, hero_shield: 2, purse: 5000,  herb_count: 6, key_count: 3, inventory: [3, 4, 8, 12, 13, 7, 0, 0], flag_equip_dragon_scale: true,  flag_beated_golem: true,  flag_beated_dragon: true },
+    TypicalStage { level: 25, hero_weapon: 7, hero_armor: 7, hero_shield: 3, purse: 10000, herb_count: 6, key_count: 3, inventory: [3, 4, 8, 14, 7, 0, 0, 0],  flag_equip_dragon_scale: true,  flag_beated_golem: true,  flag_beated_dragon: true },
+];
+
+impl GameState {
+    /// レベル `level` の典型的なプレイヤーのゲーム状態を作る。`hero_name` は正規化していないものも許す。
+    ///
+    /// 経験値はそのレベルに達するのに必要な最小値とする。その他のフィールドは以下の表に従う
+    /// (道具IDなどは [`GameState`] の各フィールドを参照)。
+    /// salt, せんしのゆびわ装備フラグ、しのくびかざり取得済フラグは常に 0/false とする。
+    ///
+    /// | レベル | 武器 | 鎧 | 盾 | 所持金 | やくそう | かぎ | インベントリ | うろこ装備 | ゴーレム | ドラゴン |
+    /// | --     | --   | -- | -- | --     | --       | --   | --           | --         | --       | --       |
+    /// | 1..=2   | 1 | 1 | 0 | 50    | 1 | 0 | 1             | false | false | false |
+    /// | 3..=6   | 2 | 2 | 1 | 200   | 2 | 0 | 1, 3          | false | false | false |
+    /// | 7..=11  | 3 | 3 | 1 | 500   | 4 | 1 | 3, 4          | true  | false | false |
+    /// | 12..=15 | 4 | 4 | 2 | 1000  | 5 | 2 | 3, 4, 8       | true  | false | true  |
+    /// | 16..=19 | 5 | 5 | 2 | 2000  | 6 | 3 | 3, 4, 8, 10   | true  | true  | true  |
+    /// | 20..=24 | 6 | 6 | 2 | 5000  | 6 | 3 | 3, 4, 8, 12, 13, 7 | true | true | true |
+    /// | 25..=30 | 7 | 7 | 3 | 10000 | 6 | 3 | 3, 4, 8, 14, 7 | true | true | true |
+    ///
+    /// 結果は有効かつ正規化済みで、[`plausibility`](Self::plausibility) は空となる。
+    ///
+    /// `hero_name` が無効な場合や、`level` が 1..=30 の範囲外の場合、
+    /// `Err(Dq1PasswordError::InvalidGameState)` を返す。
+    pub fn at_level(level: u8, hero_name: impl AsRef<str>) -> Dq1PasswordResult<Self> {
+        let hero_xp = xp_for_level(level).ok_or_else(|| {
+            Dq1PasswordError::out_of_range(
+                GameStateField::Level,
+                u32::from(level),
+                u32::from(LEVEL_MAX),
+                format!(
+                    "レベルは 1 以上 {} 以下でなければならない: {}",
+                    LEVEL_MAX, level
+                ),
+            )
+        })?;
+        let hero_name = normalize_hero_name(hero_name)?;
+
+        let stage = TYPICAL_STAGES
+            .iter()
+            .rev()
+            .find(|stage| stage.level <= level)
+            .unwrap();
+
+        Ok(Self {
+            hero_name,
+            hero_xp,
+            purse: stage.purse,
+            hero_weapon: stage.hero_weapon,
+            hero_armor: stage.hero_armor,
+            hero_shield: stage.hero_shield,
+            herb_count: stage.herb_count,
+            key_count: stage.key_count,
+            inventory: stage.inventory,
+            flag_equip_dragon_scale: stage.flag_equip_dragon_scale,
+            flag_equip_warrior_ring: false,
+            flag_got_death_necklace: false,
+            flag_beated_golem: stage.flag_beated_golem,
+            flag_beated_dragon: stage.flag_beated_dragon,
+            salt: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{decode, encode};
+
+    #[test]
+    fn test_at_level() {
+        for level in 1..=LEVEL_MAX {
+            let state = GameState::at_level(level, "ロト").unwrap();
+            assert_eq!(state.level(), level);
+            assert_eq!(state.validate(), Ok(()));
+            assert_eq!(state.normalize().as_ref(), Ok(&state));
+            assert!(state.plausibility().is_empty(), "{:?}", state);
+
+            let password = encode(&state).unwrap();
+            assert_eq!(decode(&password), Ok(state));
+        }
+
+        let state = GameState::at_level(1, "ゆうてい").unwrap();
+        assert_eq!(state.hero_xp, 0);
+        assert_eq!((state.hero_weapon, state.hero_armor), (1, 1));
+
+        let state = GameState::at_level(12, "ゆうてい").unwrap();
+        assert_eq!(state.hero_xp, 4000);
+        assert!(state.flag_beated_dragon && !state.flag_beated_golem);
+
+        let state = GameState::at_level(30, "").unwrap();
+        assert_eq!(state.hero_name, "    ");
+        assert_eq!(state.hero_xp, u16::MAX);
+        assert_eq!(
+            (state.hero_weapon, state.hero_armor, state.hero_shield),
+            (7, 7, 3)
+        );
+
+        // 進めるほど進み具合が下がらない。
+        let scores: Vec<_> = (1..=LEVEL_MAX)
+            .map(|level| GameState::at_level(level, "ロト").unwrap().progress_score())
+            .collect();
+        assert!(scores.windows(2).all(|w| w[0] <= w[1]));
+
+        assert!(GameState::at_level(0, "ロト").is_err());
+        assert!(GameState::at_level(31, "ロト").is_err());
+        assert!(GameState::at_level(5, "ゆうていみやおう").is_err());
+    }
+
+    #[test]
+    fn test_typical_stages() {
+        assert_eq!(TYPICAL_STAGES[0].level, 1);
+        assert!(TYPICAL_STAGES.windows(2).all(|w| w[0].level < w[1].level));
+    }
+}
@@ -1,9 +1,41 @@
 use crate::error::{Dq1PasswordError, Dq1PasswordResult};
-use crate::normalize::{normalize_hero_name, normalize_password, normalize_pattern};
+use crate::game_state::GameStateField;
+use crate::normalize::{
+    normalize_hero_name_cow, normalize_hero_name_strict, normalize_password_cow,
+    normalize_pattern_cow,
+};
+use crate::prelude::*;
 
 /// 主人公の名前を validate する。正規化していないものも許す。
 pub fn validate_hero_name(hero_name: impl AsRef<str>) -> Dq1PasswordResult<()> {
-    normalize_hero_name(hero_name).map(|_| ())
+    normalize_hero_name_cow(hero_name.as_ref()).map(|_| ())
+}
+
+/// 名前入力画面で入力可能とみなす主人公の名前の文字たち(正規化後の形)。
+///
+/// 内部の文字表(64 文字)から数字を除いたもの。
+/// [`validate_hero_name_strict`] や `GenerateOptions::strict_names` はこれに基づく。
+#[rustfmt::skip]
+pub const ENTERABLE_HERO_NAME_CHARS: [char; 54] = [
+    'あ', 'い', 'う', 'え', 'お',
+    'か', 'き', 'く', 'け', 'こ',
+    'さ', 'し', 'す', 'せ', 'そ',
+    'た', 'ち', 'つ', 'て', 'と',
+    'な', 'に', 'ぬ', 'ね', 'の',
+    'は', 'ひ', 'ふ', 'へ', 'ほ',
+    'ま', 'み', 'む', 'め', 'も',
+    'や', 'ゆ', 'よ',
+    'ら', 'り', 'る', 'れ', 'ろ',
+    'わ', 'を', 'ん',
+    'っ', 'ゃ', 'ゅ', 'ょ',
+    '゛', '゜', '-', ' ',
+];
+
+/// 主人公の名前を、ゲーム内で入力可能かどうかまで含めて validate する。正規化していないものも許す。
+///
+/// 規則は [`normalize_hero_name_strict`] を参照。
+pub fn validate_hero_name_strict(hero_name: impl AsRef<str>) -> Dq1PasswordResult<()> {
+    normalize_hero_name_strict(hero_name).map(|_| ())
 }
 
 /// 主人公の装備している武器IDを validate する。
@@ -11,10 +43,15 @@ pub fn validate_hero_weapon(weapon: u8) -> Dq1PasswordResult<()> {
     const WEAPON_MAX: u8 = 7;
 
     if weapon > WEAPON_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "主人公の装備している武器IDは {} 以下でなければならない: {}",
-            WEAPON_MAX, weapon
-        )));
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::HeroWeapon,
+            u32::from(weapon),
+            u32::from(WEAPON_MAX),
+            format!(
+                "主人公の装備している武器IDは {} 以下でなければならない: {}",
+                WEAPON_MAX, weapon
+            ),
+        ));
     }
 
     Ok(())
@@ -25,10 +62,15 @@ pub fn validate_hero_armor(armor: u8) -> Dq1PasswordResult<()> {
     const ARMOR_MAX: u8 = 7;
 
     if armor > ARMOR_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "主人公の装備している鎧IDは {} 以下でなければならない: {}",
-            ARMOR_MAX, armor
-        )));
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::HeroArmor,
+            u32::from(armor),
+            u32::from(ARMOR_MAX),
+            format!(
+                "主人公の装備している鎧IDは {} 以下でなければならない: {}",
+                ARMOR_MAX, armor
+            ),
+        ));
     }
 
     Ok(())
@@ -39,72 +81,180 @@ pub fn validate_hero_shield(shield: u8) -> Dq1PasswordResult<()> {
     const SHIELD_MAX: u8 = 3;
 
     if shield > SHIELD_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "主人公の装備している盾IDは {} 以下でなければならない: {}",
-            SHIELD_MAX, shield
-        )));
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::HeroShield,
+            u32::from(shield),
+            u32::from(SHIELD_MAX),
+            format!(
+                "主人公の装備している盾IDは {} 以下でなければならない: {}",
+                SHIELD_MAX, shield
+            ),
+        ));
     }
 
     Ok(())
 }
 
-/// やくそう所持数を validate する。
-pub fn validate_herb_count(herb: u8) -> Dq1PasswordResult<()> {
-    const HERB_MAX: u8 = 6;
+/// ゲーム状態の検査の基準。
+///
+/// 改造版などで、やくそう/かぎ所持数の上限や道具IDの範囲が本家と異なる場合に
+/// [`decode_with_policy`](crate::decode_with_policy), [`encode_with_policy`](crate::encode_with_policy),
+/// [`generate_with_policy`](crate::generate_with_policy) などに渡す。既定値は本家の基準 [`ValidationPolicy::DQ1_FC`]。
+///
+/// これらの値は復活の呪文上いずれも 4bit なので、15 を超える上限は 15 とみなす。
+/// 主人公の名前、装備、salt の検査は本家と同じ。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ValidationPolicy {
+    /// やくそう所持数の上限。
+    pub herb_max: u8,
 
-    if herb > HERB_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "やくそう所持数は {} 以下でなければならない: {}",
-            HERB_MAX, herb
-        )));
-    }
+    /// かぎ所持数の上限。
+    pub key_max: u8,
 
-    Ok(())
+    /// インベントリ内の道具IDの上限。
+    pub tool_max: u8,
+
+    /// false の場合、インベントリ内の道具IDを検査しない(`tool_max` に関わらず 15 まで許す)。
+    pub check_inventory: bool,
 }
 
-/// かぎ所持数を validate する。
-pub fn validate_key_count(key: u8) -> Dq1PasswordResult<()> {
-    const KEY_MAX: u8 = 6;
+impl ValidationPolicy {
+    /// 本家(FC 版)の基準。既定の API はこれを用いる。
+    pub const DQ1_FC: Self = Self {
+        herb_max: 6,
+        key_max: 6,
+        tool_max: 14,
+        check_inventory: true,
+    };
+
+    /// やくそう所持数を validate する。
+    pub fn validate_herb_count(&self, herb: u8) -> Dq1PasswordResult<()> {
+        let herb_max = self.herb_count_max();
+
+        if herb > herb_max {
+            return Err(Dq1PasswordError::out_of_range(
+                GameStateField::HerbCount,
+                u32::from(herb),
+                u32::from(herb_max),
+                format!(
+                    "やくそう所持数は {} 以下でなければならない: {}",
+                    herb_max, herb
+                ),
+            ));
+        }
 
-    if key > KEY_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "かぎ所持数は {} 以下でなければならない: {}",
-            KEY_MAX, key
-        )));
+        Ok(())
     }
 
-    Ok(())
+    /// かぎ所持数を validate する。
+    pub fn validate_key_count(&self, key: u8) -> Dq1PasswordResult<()> {
+        let key_max = self.key_count_max();
+
+        if key > key_max {
+            return Err(Dq1PasswordError::out_of_range(
+                GameStateField::KeyCount,
+                u32::from(key),
+                u32::from(key_max),
+                format!("かぎ所持数は {} 以下でなければならない: {}", key_max, key),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// インベントリを validate する。
+    ///
+    /// `inventory.len() == 8` でない場合、panic する。
+    pub fn validate_inventory(&self, inventory: impl AsRef<[u8]>) -> Dq1PasswordResult<()> {
+        let inventory = inventory.as_ref();
+
+        assert_eq!(inventory.len(), 8);
+
+        for (i, &tool) in inventory.iter().enumerate() {
+            self.validate_inventory_slot(i, tool)?;
+        }
+
+        Ok(())
+    }
+
+    /// インベントリの 1 要素を validate する。エラーのフィールドは `GameStateField::Inventory(i)` となる。
+    pub(crate) fn validate_inventory_slot(&self, i: usize, tool: u8) -> Dq1PasswordResult<()> {
+        let tool_max = self.inventory_tool_max();
+
+        if tool > tool_max {
+            return Err(Dq1PasswordError::out_of_range(
+                GameStateField::Inventory(i),
+                u32::from(tool),
+                u32::from(tool_max),
+                format!(
+                    "インベントリ[{}]: 道具IDは {} 以下でなければならない: {}",
+                    i, tool_max, tool
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// やくそう所持数として許される最大値を返す。
+    pub(crate) fn herb_count_max(&self) -> u8 {
+        self.herb_max.min(15)
+    }
+
+    /// かぎ所持数として許される最大値を返す。
+    pub(crate) fn key_count_max(&self) -> u8 {
+        self.key_max.min(15)
+    }
+
+    /// インベントリ内の道具IDとして許される最大値を返す。
+    pub(crate) fn inventory_tool_max(&self) -> u8 {
+        if self.check_inventory {
+            self.tool_max.min(15)
+        } else {
+            15
+        }
+    }
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self::DQ1_FC
+    }
+}
+
+/// やくそう所持数を validate する。
+pub fn validate_herb_count(herb: u8) -> Dq1PasswordResult<()> {
+    ValidationPolicy::DQ1_FC.validate_herb_count(herb)
+}
+
+/// かぎ所持数を validate する。
+pub fn validate_key_count(key: u8) -> Dq1PasswordResult<()> {
+    ValidationPolicy::DQ1_FC.validate_key_count(key)
 }
 
 /// インベントリを validate する。
 ///
 /// `inventory.len() == 8` でない場合、panic する。
 pub fn validate_inventory(inventory: impl AsRef<[u8]>) -> Dq1PasswordResult<()> {
-    let inventory = inventory.as_ref();
-
-    assert_eq!(inventory.len(), 8);
-
-    for (i, &tool) in inventory.iter().enumerate() {
-        if let Err(e) = validate_tool(tool) {
-            return Err(Dq1PasswordError::invalid_game_state(format!(
-                "インベントリ[{}]: {}",
-                i, e
-            )));
-        }
-    }
+    ValidationPolicy::DQ1_FC.validate_inventory(inventory)
+}
 
-    Ok(())
+/// インベントリの 1 要素を validate する。エラーのフィールドは `GameStateField::Inventory(i)` となる。
+pub(crate) fn validate_inventory_slot(i: usize, tool: u8) -> Dq1PasswordResult<()> {
+    ValidationPolicy::DQ1_FC.validate_inventory_slot(i, tool)
 }
 
+const TOOL_MAX: u8 = 14;
+
 /// 道具IDを validate する。
 pub fn validate_tool(tool: u8) -> Dq1PasswordResult<()> {
-    const TOOL_MAX: u8 = 14;
-
     if tool > TOOL_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "道具IDは {} 以下でなければならない: {}",
-            TOOL_MAX, tool
-        )));
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::Tool,
+            u32::from(tool),
+            u32::from(TOOL_MAX),
+            format!("道具IDは {} 以下でなければならない: {}", TOOL_MAX, tool),
+        ));
     }
 
     Ok(())
@@ -115,10 +265,12 @@ pub fn validate_salt(salt: u8) -> Dq1PasswordResult<()> {
     const SALT_MAX: u8 = 7;
 
     if salt > SALT_MAX {
-        return Err(Dq1PasswordError::invalid_game_state(format!(
-            "salt は {} 以下でなければならない: {}",
-            SALT_MAX, salt
-        )));
+        return Err(Dq1PasswordError::out_of_range(
+            GameStateField::Salt,
+            u32::from(salt),
+            u32::from(SALT_MAX),
+            format!("salt は {} 以下でなければならない: {}", SALT_MAX, salt),
+        ));
     }
 
     Ok(())
@@ -126,18 +278,29 @@ pub fn validate_salt(salt: u8) -> Dq1PasswordResult<()> {
 
 /// 復活の呪文の形式を validate する(デコード可能かどうかは関知しない)。正規化していないものも許す。
 pub fn validate_password(password: impl AsRef<str>) -> Dq1PasswordResult<()> {
-    normalize_password(password).map(|_| ())
+    normalize_password_cow(password.as_ref()).map(|_| ())
 }
 
 /// 復活の呪文パターンを validate する。正規化していないものも許す。
+///
+/// `pattern` が無効な場合、`Err(Dq1PasswordError::InvalidPattern)` を返す。
+/// 長さの誤りや無効な文字(とその位置)は [`PatternError::kind`](crate::PatternError::kind) で判別できる。
 pub fn validate_pattern(pattern: impl AsRef<str>) -> Dq1PasswordResult<()> {
-    normalize_pattern(pattern).map(|_| ())
+    normalize_pattern_cow(pattern.as_ref()).map(|_| ())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use alloc::collections::BTreeSet;
+
+    use crate::decode::{decode, decode_with_policy};
+    use crate::encode::{encode, encode_with_policy};
+    use crate::error::{ErrorKind, GameStateErrorKind, PatternErrorKind};
+    use crate::game_state::GameState;
+    use crate::generate::{generate, generate_with_policy};
+
     #[test]
     fn test_validate_hero_name() {
         assert!(validate_hero_name("").is_ok());
@@ -152,6 +315,24 @@ mod tests {
         assert!(validate_hero_name("漢字").is_err());
     }
 
+    #[test]
+    fn test_validate_hero_name_strict() {
+        assert!(validate_hero_name_strict("").is_ok());
+        assert!(validate_hero_name_strict("ああああ").is_ok());
+        assert!(validate_hero_name_strict("がぱ").is_ok());
+        assert!(validate_hero_name_strict("ゔ").is_ok());
+        assert!(validate_hero_name_strict("あーす").is_ok());
+        assert!(validate_hero_name_strict("あ す").is_ok());
+
+        assert!(validate_hero_name_strict("0123").is_err());
+        assert!(validate_hero_name_strict("゛").is_err());
+        assert!(validate_hero_name_strict("あ゛").is_err());
+        assert!(validate_hero_name_strict("か゜").is_err());
+        assert!(validate_hero_name_strict("ぱ゜").is_err());
+        assert!(validate_hero_name_strict("　あーす").is_err());
+        assert!(validate_hero_name_strict("あああが").is_err());
+    }
+
     #[test]
     fn test_validate_hero_weapon() {
         assert!(validate_hero_weapon(0).is_ok());
@@ -200,6 +381,112 @@ mod tests {
         assert!(validate_inventory([0, 0, 0, 0, 0, 0, 0, 15]).is_err());
     }
 
+    #[test]
+    fn test_validation_policy() {
+        let kind = |res: Dq1PasswordResult<()>| match res {
+            Err(Dq1PasswordError::InvalidGameState(e)) => e.kind().clone(),
+            res => panic!("{:?}", res),
+        };
+        let out_of_range = |field, value, max| GameStateErrorKind::OutOfRange { field, value, max };
+
+        assert_eq!(ValidationPolicy::default(), ValidationPolicy::DQ1_FC);
+
+        let policy = ValidationPolicy {
+            herb_max: 9,
+            key_max: 20,
+            tool_max: 10,
+            check_inventory: true,
+        };
+        assert!(policy.validate_herb_count(9).is_ok());
+        assert_eq!(
+            kind(policy.validate_herb_count(10)),
+            out_of_range(GameStateField::HerbCount, 10, 9)
+        );
+
+        // 4bit を超える上限は 15 とみなす。
+        assert!(policy.validate_key_count(15).is_ok());
+        assert_eq!(
+            kind(policy.validate_key_count(16)),
+            out_of_range(GameStateField::KeyCount, 16, 15)
+        );
+
+        assert!(policy.validate_inventory([10; 8]).is_ok());
+        assert_eq!(
+            kind(policy.validate_inventory([0, 0, 11, 0, 0, 0, 0, 0])),
+            out_of_range(GameStateField::Inventory(2), 11, 10)
+        );
+        let unchecked = ValidationPolicy {
+            check_inventory: false,
+            ..policy
+        };
+        assert!(unchecked.validate_inventory([15; 8]).is_ok());
+    }
+
+    #[test]
+    fn test_validation_policy_roundtrip() {
+        let relaxed = ValidationPolicy {
+            herb_max: 9,
+            ..ValidationPolicy::DQ1_FC
+        };
+        let state = GameState {
+            hero_name: "ゆうてい".to_owned(),
+            herb_count: 8,
+            ..Default::default()
+        };
+
+        // 既定の基準では無効。
+        assert!(state.validate().is_err());
+        assert!(encode_with_policy(&state, &ValidationPolicy::DQ1_FC).is_err());
+        state.validate_with_policy(&relaxed).unwrap();
+
+        let password = encode_with_policy(&state, &relaxed).unwrap();
+        assert_eq!(
+            decode(&password).unwrap_err().kind(),
+            ErrorKind::InvalidGameState(GameStateErrorKind::OutOfRange {
+                field: GameStateField::HerbCount,
+                value: 8,
+                max: 6,
+            })
+        );
+        let decoded = decode_with_policy(&password, &relaxed).unwrap();
+        assert_eq!(decoded, state.normalize().unwrap());
+        assert_eq!(encode_with_policy(&decoded, &relaxed).unwrap(), password);
+
+        // 既定の基準を指定した場合は既定の API と同じ。
+        let a = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+        assert_eq!(decode_with_policy(a, &ValidationPolicy::DQ1_FC), decode(a));
+        assert_eq!(
+            encode_with_policy(&decode(a).unwrap(), &ValidationPolicy::DQ1_FC),
+            encode(&decode(a).unwrap())
+        );
+
+        // やくそう所持数の位置(14 文字目)と末尾 2 文字を空けたパターンで生成する。
+        let cs: Vec<_> = password.chars().collect();
+        let pattern: String = cs[..13]
+            .iter()
+            .chain(&['?'])
+            .chain(&cs[14..18])
+            .chain(&['?', '?'])
+            .collect();
+
+        let vanilla = generate(&pattern, usize::MAX).unwrap();
+        assert_eq!(
+            generate_with_policy(&pattern, usize::MAX, &ValidationPolicy::DQ1_FC).unwrap(),
+            vanilla
+        );
+        assert!(!vanilla.contains(&password));
+
+        let passwords = generate_with_policy(&pattern, usize::MAX, &relaxed).unwrap();
+        assert!(passwords.contains(&password));
+        assert!(vanilla.iter().all(|p| passwords.contains(p)));
+        let herb_counts: BTreeSet<_> = passwords
+            .iter()
+            .map(|p| decode_with_policy(p, &relaxed).unwrap().herb_count)
+            .collect();
+        assert!(herb_counts.contains(&8));
+        assert!(herb_counts.iter().all(|&herb| herb <= 9));
+    }
+
     #[test]
     fn test_validate_tool() {
         assert!(validate_tool(0).is_ok());
@@ -216,6 +503,83 @@ mod tests {
         assert!(validate_salt(8).is_err());
     }
 
+    #[test]
+    fn test_game_state_error_kind() {
+        let kind = |res: Dq1PasswordResult<()>| match res {
+            Err(Dq1PasswordError::InvalidGameState(e)) => e.kind().clone(),
+            res => panic!("{:?}", res),
+        };
+        let out_of_range = |field, value, max| GameStateErrorKind::OutOfRange { field, value, max };
+
+        assert_eq!(
+            kind(validate_hero_weapon(8)),
+            out_of_range(GameStateField::HeroWeapon, 8, 7)
+        );
+        assert_eq!(
+            kind(validate_hero_armor(9)),
+            out_of_range(GameStateField::HeroArmor, 9, 7)
+        );
+        assert_eq!(
+            kind(validate_hero_shield(4)),
+            out_of_range(GameStateField::HeroShield, 4, 3)
+        );
+        assert_eq!(
+            kind(validate_herb_count(7)),
+            out_of_range(GameStateField::HerbCount, 7, 6)
+        );
+        assert_eq!(
+            kind(validate_key_count(255)),
+            out_of_range(GameStateField::KeyCount, 255, 6)
+        );
+        assert_eq!(
+            kind(validate_inventory([0, 0, 0, 0, 0, 15, 0, 20])),
+            out_of_range(GameStateField::Inventory(5), 15, 14)
+        );
+        assert_eq!(
+            kind(validate_tool(15)),
+            out_of_range(GameStateField::Tool, 15, 14)
+        );
+        assert_eq!(
+            kind(validate_salt(8)),
+            out_of_range(GameStateField::Salt, 8, 7)
+        );
+
+        assert_eq!(
+            kind(validate_hero_name("あああああ")),
+            GameStateErrorKind::HeroNameTooLong
+        );
+        assert_eq!(
+            kind(validate_hero_name("あ漢A")),
+            GameStateErrorKind::HeroNameInvalidChars(vec!['漢', 'A'])
+        );
+        assert_eq!(
+            kind(validate_hero_name_strict("0123")),
+            GameStateErrorKind::HeroNameNotEnterable('0')
+        );
+        assert_eq!(
+            kind(validate_hero_name_strict("あ゛")),
+            GameStateErrorKind::HeroNameMisplacedMark {
+                pos: 1, mark: '゛'
+            }
+        );
+        assert_eq!(
+            kind(validate_hero_name_strict(" あ")),
+            GameStateErrorKind::HeroNameLeadingSpace
+        );
+
+        // メッセージは従来通り。
+        assert_eq!(
+            validate_herb_count(7).unwrap_err().to_string(),
+            "ゲーム状態が無効: やくそう所持数は 6 以下でなければならない: 7"
+        );
+        assert_eq!(
+            validate_inventory([0, 0, 0, 0, 0, 15, 0, 0])
+                .unwrap_err()
+                .to_string(),
+            "ゲーム状態が無効: インベントリ[5]: 道具IDは 14 以下でなければならない: 15"
+        );
+    }
+
     #[test]
     fn test_validate_password() {
         assert!(validate_password("ああああああああああああああああああああ").is_ok());
@@ -234,5 +598,30 @@ mod tests {
         assert!(validate_password("ああああああああああああああああああああ?").is_err());
         assert!(validate_password("ああああああああああああああああああ?").is_err());
         assert!(validate_password("あああああああああああああああああ漢字?").is_err());
+
+        let kind = |pattern: &str| match validate_pattern(pattern) {
+            Err(Dq1PasswordError::InvalidPattern(e)) => e.kind().clone(),
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(
+            kind("ああああああああああああああああああ?"),
+            PatternErrorKind::WrongLength { len: 19 }
+        );
+        assert_eq!(
+            kind("ああああああああああああああああああああ?"),
+            PatternErrorKind::WrongLength { len: 21 }
+        );
+        assert_eq!(
+            kind("ああああ漢ああああああ?ああああ字あああ"),
+            PatternErrorKind::InvalidChars(vec![(4, '漢'), (16, '字')])
+        );
+        assert_eq!(
+            kind("あああ[あ漢]あああああああああああああああ"),
+            PatternErrorKind::InvalidChars(vec![(3, '漢')])
+        );
+        assert_eq!(
+            kind("あああ[あああああああああああああああああ"),
+            PatternErrorKind::Unclosed { pos: 3 }
+        );
     }
 }
@@ -0,0 +1,85 @@
+//! JavaScript 向けのラッパー(`wasm` feature)。
+//!
+//! wasm-pack でビルドすると、`decodeJson`, `encodeJson`, `generate` が JS から呼べるようになる。
+//!
+//! エラーは JS の `Error` オブジェクトとして投げられる。`name` は `"Dq1PasswordError"`、
+//! `message` は日本語のメッセージで、以下のプロパティが追加される:
+//!
+//! * `kind`: 原因の種類(`"InvalidGameState"`, `"InvalidPassword"`, `"CrcMismatch"`,
+//!   `"InvalidPattern"`, `"InvalidConstraint"`, `"ResourceLimit"`, `"InvalidFormat"`, `"InvalidJson"` のいずれか)
+//! * `messageEn`: 英語のメッセージ
+//! * `expect`, `actual`: CRC の値(`kind` が `"CrcMismatch"` の場合のみ)
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Dq1PasswordError, ErrorKind, Lang};
+use crate::game_state::GameState;
+use crate::prelude::*;
+
+/// 復活の呪文をデコードし、ゲーム状態を JSON 文字列として返す。
+#[wasm_bindgen(js_name = decodeJson)]
+pub fn decode_json(password: &str) -> Result<String, JsValue> {
+    let state = crate::decode(password).map_err(error_to_js)?;
+
+    Ok(serde_json::to_string(&state).expect("GameState should be serializable"))
+}
+
+/// JSON 文字列で与えられたゲーム状態を復活の呪文にエンコードする。
+#[wasm_bindgen(js_name = encodeJson)]
+pub fn encode_json(json: &str) -> Result<String, JsValue> {
+    let state: GameState = serde_json::from_str(json).map_err(|e| {
+        let message = e.to_string();
+        new_error(
+            "InvalidJson",
+            &format!("JSON が無効: {}", message),
+            &format!("invalid JSON: {}", message),
+        )
+    })?;
+
+    crate::encode(&state).map_err(error_to_js)
+}
+
+/// パターンに合致するデコード可能な復活の呪文たちを最大 `n_max` 個生成し、文字列の配列として返す。
+#[wasm_bindgen]
+pub fn generate(pattern: &str, n_max: usize) -> Result<js_sys::Array, JsValue> {
+    let passwords = crate::generate(pattern, n_max).map_err(error_to_js)?;
+
+    Ok(passwords.into_iter().map(JsValue::from).collect())
+}
+
+fn error_to_js(e: Dq1PasswordError) -> JsValue {
+    let kind = e.kind();
+    let name = match kind {
+        ErrorKind::InvalidGameState(_) => "InvalidGameState",
+        ErrorKind::InvalidPassword(_) => "InvalidPassword",
+        ErrorKind::CrcMismatch { .. } => "CrcMismatch",
+        ErrorKind::InvalidPattern(_) => "InvalidPattern",
+        ErrorKind::InvalidConstraint => "InvalidConstraint",
+        ErrorKind::ResourceLimit => "ResourceLimit",
+        ErrorKind::InvalidFormat => "InvalidFormat",
+    };
+
+    let obj = new_error(name, &e.to_string(), &e.to_message(Lang::En));
+    if let ErrorKind::CrcMismatch { expect, actual } = kind {
+        set_property(&obj, "expect", &JsValue::from(expect));
+        set_property(&obj, "actual", &JsValue::from(actual));
+    }
+
+    obj
+}
+
+fn new_error(kind: &str, message: &str, message_en: &str) -> JsValue {
+    let e = js_sys::Error::new(message);
+    e.set_name("Dq1PasswordError");
+
+    let obj = JsValue::from(e);
+    set_property(&obj, "kind", &JsValue::from_str(kind));
+    set_property(&obj, "messageEn", &JsValue::from_str(message_en));
+
+    obj
+}
+
+fn set_property(obj: &JsValue, key: &str, value: &JsValue) {
+    // Error オブジェクトへの代入は失敗しない。
+    js_sys::Reflect::set(obj, &JsValue::from_str(key), value).unwrap();
+}
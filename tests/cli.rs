@@ -0,0 +1,290 @@
+//! CLI (`dq1-password` バイナリ)のテスト。
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+
+const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+fn cli() -> Command {
+    Command::cargo_bin("dq1-password").unwrap()
+}
+
+fn stdout(output: &std::process::Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_decode() {
+    let assert = cli().args(["decode", A]).assert().success();
+    let out = stdout(assert.get_output());
+    assert!(out.contains("hero_name: しと゛-\n"));
+    assert!(out.contains("inventory: [1, 2, 3, 4, 5, 6, 7, 8]\n"));
+
+    // 標準入力から。空行は無視される。
+    let assert = cli()
+        .args(["decode", "--json"])
+        .write_stdin(format!("{}\n\n{}\n", A, A))
+        .assert()
+        .success();
+    let out = stdout(assert.get_output());
+    let states: Vec<dq1_password::GameState> = out
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(states, vec![dq1_password::decode(A).unwrap(); 2]);
+
+    let assert = cli().args(["decode", "--pretty", A]).assert().success();
+    let state: dq1_password::GameState =
+        serde_json::from_str(&stdout(assert.get_output())).unwrap();
+    assert_eq!(state, dq1_password::decode(A).unwrap());
+}
+
+#[test]
+fn test_decode_error() {
+    // 失敗した入力があっても残りは処理する。
+    let assert = cli()
+        .arg("decode")
+        .write_stdin(format!("{}\nざぼちず\n", A))
+        .assert()
+        .code(4);
+    let output = assert.get_output();
+    assert!(stdout(output).contains("hero_name: しと゛-\n"));
+    assert!(stderr(output).starts_with("error: line 2: ざぼちず: 復活の呪文の形式が無効"));
+
+    // 最後の文字を変えたもの
+    let assert = cli()
+        .args(["decode", "ざぼちずどぢぎきつたうずせれえむるのぢお"])
+        .assert()
+        .code(5);
+    assert!(stderr(assert.get_output()).contains("CRC 下位バイトが一致しない"));
+}
+
+#[test]
+fn test_encode() {
+    let json = serde_json::to_string(&dq1_password::decode(A).unwrap()).unwrap();
+
+    cli()
+        .args(["encode", &json])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", A));
+
+    // 標準入力からは複数の JSON 値を読める。
+    let json_pretty = serde_json::to_string_pretty(&dq1_password::decode(A).unwrap()).unwrap();
+    cli()
+        .args(["encode", "--json"])
+        .write_stdin(format!("{}\n{}\n", json_pretty, json))
+        .assert()
+        .success()
+        .stdout(format!("\"{}\"\n\"{}\"\n", A, A));
+}
+
+#[test]
+fn test_encode_error() {
+    let assert = cli().args(["encode", "{}"]).assert().code(2);
+    assert!(stderr(assert.get_output()).starts_with("error: JSON が無効"));
+
+    let mut state = dq1_password::decode(A).unwrap();
+    state.hero_name = "漢".to_owned();
+    let json = serde_json::to_string(&state).unwrap();
+    let assert = cli().args(["encode", &json]).assert().code(3);
+    assert!(stderr(assert.get_output()).contains("ゲーム状態が無効"));
+}
+
+#[test]
+fn test_generate() {
+    let expected = dq1_password::generate(dq1_password::prefix_pattern("ゆうてい").unwrap(), 3)
+        .unwrap()
+        .iter()
+        .map(|password| format!("{}\n", password))
+        .collect::<String>();
+    cli()
+        .args(["generate", "--prefix", "--n-max", "3", "ゆうてい"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let assert = cli()
+        .args(["generate", "--suffix", "--n-max", "2", "--json"])
+        .write_stdin("のぢえ\n")
+        .assert()
+        .success();
+    let passwords: Vec<String> = stdout(assert.get_output())
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(passwords.len(), 2);
+    assert!(passwords.iter().all(|p| p.ends_with("のぢえ")));
+}
+
+#[test]
+fn test_generate_error() {
+    let assert = cli().args(["generate", "ああ"]).assert().code(6);
+    assert!(stderr(assert.get_output()).contains("パターンが無効"));
+
+    let assert = cli()
+        .args(["generate", "-c", "bogus=1", A])
+        .assert()
+        .code(7);
+    assert!(stderr(assert.get_output()).contains("制約が無効"));
+
+    cli().arg("bogus").assert().code(1);
+}
+
+#[test]
+fn test_interactive() {
+    // 複数行に分けて入力でき、'<' で取り消せる。カタカナも受け付ける。
+    let assert = cli()
+        .arg("interactive")
+        .write_stdin("ザボチズあ<\nどぢぎきつたうずせれえむるのぢえ\n")
+        .assert()
+        .success();
+    let out = stdout(assert.get_output());
+    assert!(out.contains("ざぼちず (4/20)\n次の文字: "));
+    assert!(out.contains(&format!("{} (20/20)\n", A)));
+    assert!(out.contains("hero_name: しと゛-\n"));
+
+    // 無効な文字を含む行は無視される。
+    let assert = cli()
+        .arg("interactive")
+        .write_stdin("ざぼXちず\n")
+        .assert()
+        .code(4);
+    let out = stdout(assert.get_output());
+    assert!(out.contains("入力を無視した: "));
+    assert!(out.contains(" (0/20)\n次の文字: 任意\n"));
+
+    // 次の文字として提案されるものだけを選べば補完できる。
+    let prefix: String = A.chars().take(19).collect();
+    let assert = cli()
+        .arg("interactive")
+        .write_stdin(format!("{}\n", prefix))
+        .assert()
+        .code(4);
+    let out = stdout(assert.get_output());
+    let hint = out
+        .lines()
+        .find_map(|line| line.strip_prefix("次の文字: "))
+        .unwrap();
+    assert!(hint.contains('え'));
+    assert!(!hint.contains('お'));
+    assert!(hint
+        .chars()
+        .all(|c| dq1_password::decode(format!("{}{}", prefix, c)).is_ok()));
+}
+
+#[test]
+fn test_interactive_error() {
+    // 最後の文字を変えたもの。補完できないことが分かり、修正候補が出力される。
+    let assert = cli()
+        .arg("interactive")
+        .write_stdin("ざぼちずどぢぎきつたうずせれえむるのぢ\nお\n")
+        .assert()
+        .code(5);
+    let output = assert.get_output();
+    let out = stdout(output);
+    assert!(out.contains("修正候補:\n"));
+    assert!(out.contains(&format!("{}\n", A)));
+    assert!(stderr(output).contains("CRC 下位バイトが一致しない"));
+
+    // JSON 出力の場合、修正候補は JSON 文字列として出力される。
+    let assert = cli()
+        .args(["interactive", "--json"])
+        .write_stdin("ざぼちずどぢぎきつたうずせれえむるのぢお\n")
+        .assert()
+        .code(5);
+    let suggestions: Vec<String> = stdout(assert.get_output())
+        .lines()
+        .filter(|line| line.starts_with('"'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(!suggestions.is_empty() && suggestions.len() <= 5);
+    assert!(suggestions.iter().all(|p| dq1_password::decode(p).is_ok()));
+}
+
+#[test]
+fn test_lang_en() {
+    // JSON のキーは言語によらない。
+    let expected = r#"{
+  "hero_name": "しと゛-",
+  "hero_xp": 1234,
+  "purse": 5678,
+  "hero_weapon": 5,
+  "hero_armor": 5,
+  "hero_shield": 2,
+  "herb_count": 6,
+  "key_count": 6,
+  "inventory": [
+    1,
+    2,
+    3,
+    4,
+    5,
+    6,
+    7,
+    8
+  ],
+  "flag_equip_dragon_scale": true,
+  "flag_equip_warrior_ring": true,
+  "flag_got_death_necklace": true,
+  "flag_beated_golem": true,
+  "flag_beated_dragon": true,
+  "salt": 5
+}
+"#;
+    for lang in ["ja", "en"] {
+        cli()
+            .args(["decode", "--pretty", "--lang", lang, A])
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    // 名前は指定した言語で表示する。
+    let assert = cli().args(["decode", "--lang", "en", A]).assert().success();
+    let out = stdout(assert.get_output());
+    assert!(out.contains("hero_weapon: 5 (BROAD SWORD)\n"));
+    assert!(out.contains("hero_shield: 2 (LARGE SHIELD)\n"));
+    assert!(out.contains("inventory: [1, 2, 3, 4, 5, 6, 7, 8]\n"));
+    assert!(out.contains(
+        "inventory_names: [TORCH, FAIRY WATER, WINGS, DRAGON'S SCALE, FAIRY FLUTE, FIGHTER'S RING, ERDRICK'S TOKEN, GWAELIN'S LOVE]\n"
+    ));
+    let assert = cli().args(["decode", A]).assert().success();
+    assert!(stdout(assert.get_output()).contains("hero_weapon: 5 (はがねのつるぎ)\n"));
+
+    // どのサブコマンドでも、エラーメッセージは指定した言語で表示する。
+    cli()
+        .args([
+            "--lang",
+            "en",
+            "decode",
+            "ざぼちずどぢぎきつたうずせれえむるのぢお",
+        ])
+        .assert()
+        .code(5)
+        .stderr("error: ざぼちずどぢぎきつたうずせれえむるのぢお: CRC low byte mismatch: expect=0x??AD, actual=0xBA29\n");
+    let assert = cli()
+        .args(["generate", "--lang", "en", "ああ"])
+        .assert()
+        .code(6);
+    assert!(stderr(assert.get_output()).starts_with("error: ああ: invalid pattern: "));
+    let assert = cli()
+        .args(["encode", "--lang", "en", "{}"])
+        .assert()
+        .code(2);
+    assert!(stderr(assert.get_output()).starts_with("error: invalid JSON: "));
+    let assert = cli()
+        .args(["interactive", "--lang", "en"])
+        .write_stdin("ざぼ\n")
+        .assert()
+        .code(4);
+    let output = assert.get_output();
+    assert!(stdout(output).contains("ざぼ (2/20)\nnext: "));
+    assert!(stderr(output).starts_with("error: ざぼ: invalid password: "));
+
+    cli().args(["decode", "--lang", "fr", A]).assert().code(1);
+}
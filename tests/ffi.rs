@@ -0,0 +1,194 @@
+//! C FFI のテスト。C から呼び出す場合と同様に、生ポインタと呼び出し側のバッファを使う。
+#![cfg(feature = "ffi")]
+
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use dq1_password::ffi::*;
+
+const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+fn last_error_message() -> String {
+    unsafe { CStr::from_ptr(dq1_last_error_message()) }
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn buf_to_string(buf: &[c_char]) -> String {
+    let bytes: Vec<u8> = buf.iter().map(|&c| c as u8).collect();
+    CStr::from_bytes_until_nul(&bytes)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn test_dq1_decode() {
+    let password = CString::new(A).unwrap();
+    let mut state = Dq1GameState::default();
+    assert_eq!(
+        unsafe { dq1_decode(password.as_ptr(), &mut state) },
+        Dq1ErrorCode::Ok
+    );
+    assert_eq!(last_error_message(), "");
+    assert_eq!(buf_to_string(&state.hero_name), "しと゛-");
+    assert_eq!(state.hero_xp, 1234);
+    assert_eq!(state.purse, 5678);
+    assert_eq!(state.inventory, [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert!(state.flag_beated_dragon);
+    assert_eq!(state.salt, 5);
+
+    // 最後の文字を変えたもの
+    let password = CString::new("ざぼちずどぢぎきつたうずせれえむるのぢお").unwrap();
+    assert_eq!(
+        unsafe { dq1_decode(password.as_ptr(), &mut state) },
+        Dq1ErrorCode::CrcMismatch
+    );
+    assert!(last_error_message().starts_with("CRC 下位バイトが一致しない"));
+
+    let password = CString::new("ざぼちず").unwrap();
+    assert_eq!(
+        unsafe { dq1_decode(password.as_ptr(), &mut state) },
+        Dq1ErrorCode::InvalidPassword
+    );
+
+    let invalid_utf8 = [0xFF_u8 as c_char, 0];
+    assert_eq!(
+        unsafe { dq1_decode(invalid_utf8.as_ptr(), &mut state) },
+        Dq1ErrorCode::InvalidUtf8
+    );
+
+    assert_eq!(
+        unsafe { dq1_decode(std::ptr::null(), &mut state) },
+        Dq1ErrorCode::NullPointer
+    );
+    assert_eq!(
+        unsafe { dq1_decode(password.as_ptr(), std::ptr::null_mut()) },
+        Dq1ErrorCode::NullPointer
+    );
+}
+
+#[test]
+fn test_dq1_encode() {
+    let password = CString::new(A).unwrap();
+    let mut state = Dq1GameState::default();
+    assert_eq!(
+        unsafe { dq1_decode(password.as_ptr(), &mut state) },
+        Dq1ErrorCode::Ok
+    );
+
+    let mut buf = [0 as c_char; DQ1_PASSWORD_BUF_LEN];
+    assert_eq!(
+        unsafe { dq1_encode(&state, buf.as_mut_ptr(), buf.len()) },
+        Dq1ErrorCode::Ok
+    );
+    assert_eq!(buf_to_string(&buf), A);
+
+    // 20 文字 * 3 バイト + NUL 終端が必要。
+    let mut small = [0 as c_char; 60];
+    assert_eq!(
+        unsafe { dq1_encode(&state, small.as_mut_ptr(), small.len()) },
+        Dq1ErrorCode::BufferTooSmall
+    );
+
+    let mut invalid = state;
+    for (dst, &src) in invalid.hero_name.iter_mut().zip("漢\0".as_bytes()) {
+        *dst = src as c_char;
+    }
+    assert_eq!(
+        unsafe { dq1_encode(&invalid, buf.as_mut_ptr(), buf.len()) },
+        Dq1ErrorCode::InvalidGameState
+    );
+    assert!(!last_error_message().is_empty());
+
+    // NUL 終端されていない名前
+    let mut invalid = state;
+    invalid.hero_name = [b'a' as c_char; DQ1_HERO_NAME_BUF_LEN];
+    assert_eq!(
+        unsafe { dq1_encode(&invalid, buf.as_mut_ptr(), buf.len()) },
+        Dq1ErrorCode::InvalidUtf8
+    );
+}
+
+#[test]
+fn test_dq1_generate() {
+    const N_MAX: usize = 3;
+
+    let pattern = CString::new("ざぼちずどぢぎきつたうずせれえむる???").unwrap();
+    let mut buf = vec![0 as c_char; N_MAX * DQ1_PASSWORD_BUF_LEN];
+    let mut n_out = 0;
+    assert_eq!(
+        unsafe {
+            dq1_generate(
+                pattern.as_ptr(),
+                N_MAX,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut n_out,
+            )
+        },
+        Dq1ErrorCode::Ok
+    );
+    assert_eq!(n_out, N_MAX);
+
+    let passwords: Vec<_> = buf
+        .chunks(DQ1_PASSWORD_BUF_LEN)
+        .take(n_out)
+        .map(buf_to_string)
+        .collect();
+    assert_eq!(
+        passwords,
+        dq1_password::generate("ざぼちずどぢぎきつたうずせれえむる???", N_MAX).unwrap()
+    );
+
+    assert_eq!(
+        unsafe {
+            dq1_generate(
+                pattern.as_ptr(),
+                N_MAX + 1,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut n_out,
+            )
+        },
+        Dq1ErrorCode::BufferTooSmall
+    );
+
+    let pattern = CString::new("ああああ").unwrap();
+    assert_eq!(
+        unsafe {
+            dq1_generate(
+                pattern.as_ptr(),
+                N_MAX,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut n_out,
+            )
+        },
+        Dq1ErrorCode::InvalidPattern
+    );
+}
+
+#[test]
+fn test_dq1_game_state_conversion() {
+    let state = dq1_password::decode(A).unwrap();
+    let c_state = Dq1GameState::try_from(&state).unwrap();
+    assert_eq!(buf_to_string(&c_state.hero_name), state.hero_name);
+    assert_eq!(
+        dq1_password::GameState::try_from(&c_state),
+        Ok(state.clone())
+    );
+
+    // 正規化されていない長い名前はバッファに収まらない(panic しない)。
+    let long = dq1_password::GameState {
+        hero_name: "あいうえおかきくけこ".to_owned(),
+        ..state
+    };
+    assert_eq!(
+        Dq1GameState::try_from(&long),
+        Err(Dq1ErrorCode::BufferTooSmall)
+    );
+}
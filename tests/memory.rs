@@ -0,0 +1,85 @@
+//! メモリ使用量の見積もりを、実際の割り当て量と比較する。
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dq1_password::*;
+
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let cur = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(cur, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// 生成中に増えたメモリ使用量のピークを返す。
+fn measure(pattern: &str, n_max: usize, options: &GenerateOptions) -> usize {
+    let base = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+    let passwords = generate_with_options(pattern, n_max, options).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - base;
+    drop(passwords);
+    peak
+}
+
+#[test]
+fn test_estimate_memory_bytes() {
+    let exclude = GenerateOptions {
+        exclude: vec![Pattern::parse("あ???????????????????").unwrap()],
+        ..Default::default()
+    };
+    let filled = GenerateOptions {
+        fill: Fill::Defaults,
+        ..Default::default()
+    };
+    let cases = [
+        (
+            "ざぼちずどぢぎきつたうずせれえむるのぢえ",
+            10,
+            GenerateOptions::default(),
+        ),
+        (
+            "ざぼちずどぢぎきつたうずせれえむる???",
+            1000,
+            GenerateOptions::default(),
+        ),
+        (
+            "ざぼちずどぢぎきつたうず???むるのぢえ",
+            1000,
+            exclude.clone(),
+        ),
+        ("ざぼ??????????????????", 100000, GenerateOptions::default()),
+        ("ざぼ??????????????????", 100, exclude),
+        ("ざぼちずどぢぎきつたうず???むるのぢえ", 1000, filled),
+    ];
+    for (pattern, n_max, options) in cases {
+        let estimate = estimate_memory_bytes(pattern, n_max, &options).unwrap();
+        let actual = measure(pattern, n_max, &options);
+        // 概算なので、桁が合っていればよい。
+        assert!(
+            actual / 10 <= estimate && estimate <= actual * 10,
+            "pattern={}, n_max={}, estimate={}, actual={}",
+            pattern,
+            n_max,
+            estimate,
+            actual
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! `std` feature なし(`cargo test --no-default-features`)でのスモークテスト。
+//!
+//! テストハーネスは std を使うので、ここでは `std` feature なしで使える API を確かめるのみ。
+//! crate が std に依存していないことは、std のない target でのビルド
+//! (`cargo build --no-default-features --target thumbv7em-none-eabihf --lib`)で確かめる。
+#![cfg(not(feature = "std"))]
+
+use dq1_password::*;
+
+const A: &str = "ざぼちずどぢぎきつたうずせれえむるのぢえ";
+
+#[test]
+fn test_decode_encode() {
+    let state = decode(A).unwrap();
+    assert_eq!(state.hero_name, "しと゛-");
+    assert_eq!(state.hero_xp, 1234);
+    assert_eq!(state.purse, 5678);
+    assert_eq!(encode(&state).unwrap(), A);
+
+    assert!(matches!(
+        decode("ざぼちずどぢぎきつたうずせれえむるのぢお")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::CrcMismatch { .. }
+    ));
+}
+
+#[test]
+fn test_generate() {
+    let passwords = generate("ざぼちずどぢぎきつたうずせれえむる???", 3).unwrap();
+    assert_eq!(passwords.len(), 3);
+    assert!(passwords.iter().all(|password| decode(password).is_ok()));
+}
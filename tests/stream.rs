@@ -0,0 +1,86 @@
+//! 非同期版の生成 API のテスト。
+#![cfg(feature = "async")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio_stream::StreamExt as _;
+
+use dq1_password::*;
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_generate_stream() {
+    const PATTERN: &str = "ざぼちずどぢぎきつたうずせれえむる???";
+
+    // 同期版と結果・順序が一致する。
+    for options in [
+        GenerateOptions::default(),
+        GenerateOptions {
+            sort_by: SortBy::InputCost,
+            ..Default::default()
+        },
+    ] {
+        let expect = generate_with_options(PATTERN, 100, &options).unwrap();
+        let actual: Vec<_> = generate_stream(PATTERN, 100, &options)
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(actual, expect);
+    }
+
+    assert!(matches!(
+        generate_stream("ゆうてい", 10, &GenerateOptions::default()),
+        Err(Dq1PasswordError::InvalidPattern(_))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_generate_stream_does_not_starve() {
+    // 先頭 2 文字が任意なので、DP を何度も行う。
+    const PATTERN: &str = "?ぼちずどぢぎきつたうずせれえむる???";
+
+    // 生成と並行して動くタスク。
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = {
+        let ticks = Arc::clone(&ticks);
+        tokio::spawn(async move {
+            loop {
+                ticks.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+    };
+
+    let expect = generate(PATTERN, 300).unwrap();
+    let actual: Vec<_> = generate_stream(PATTERN, 300, &GenerateOptions::default())
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(actual, expect);
+    assert!(ticks.load(Ordering::SeqCst) > 1);
+
+    ticker.abort();
+}
+
+#[test]
+fn test_generate_stream_early_drop() {
+    // 全て生成すると非常に時間がかかるパターン。
+    const PATTERN: &str = "????????????????????";
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let first = rt.block_on(async {
+        let stream = generate_stream(PATTERN, 1_000_000, &GenerateOptions::default()).unwrap();
+        let first: Vec<_> = stream.take(3).collect().await;
+        first
+    });
+    assert_eq!(first, generate(PATTERN, 3).unwrap());
+
+    // 受信側が drop されたので、生成はすぐに打ち切られる。
+    // (打ち切られなければ、ランタイムの drop は生成の完了を待つ)
+    let start = Instant::now();
+    drop(rt);
+    assert!(start.elapsed() < Duration::from_secs(30));
+}
@@ -0,0 +1,66 @@
+//! `tracing` feature による計装のテスト。
+#![cfg(feature = "tracing")]
+
+use std::sync::{Arc, Mutex};
+
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt as _};
+use tracing_subscriber::registry::LookupSpan;
+
+use dq1_password::*;
+
+/// span 名とイベントのメッセージを記録する。
+#[derive(Clone, Default)]
+struct Capture {
+    spans: Arc<Mutex<Vec<String>>>,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        self.spans
+            .lock()
+            .unwrap()
+            .push(attrs.metadata().name().to_owned());
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        struct Message<'a>(&'a mut String);
+        impl tracing::field::Visit for Message<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{:?}", value);
+                }
+            }
+        }
+
+        let mut message = String::new();
+        event.record(&mut Message(&mut message));
+        self.events.lock().unwrap().push(message);
+    }
+}
+
+#[test]
+fn test_generate_spans() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+    let passwords = tracing::subscriber::with_default(subscriber, || {
+        generate("ざぼちずどぢぎきつたうずせれえむる???", 3).unwrap()
+    });
+    assert_eq!(passwords.len(), 3);
+
+    let spans = capture.spans.lock().unwrap();
+    assert_eq!(spans[0], "generate");
+    assert!(spans.iter().any(|name| name == "generate_dp"));
+    assert!(spans.iter().any(|name| name == "restore"));
+
+    let events = capture.events.lock().unwrap();
+    for message in ["dp filled", "solutions reconstructed", "generate finished"] {
+        assert!(events.iter().any(|e| e == message), "{}", message);
+    }
+}
@@ -0,0 +1,66 @@
+//! JS 向けラッパーのテスト。`wasm-pack test --node -- --features wasm` で実行する。
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+use dq1_password::wasm::{decode_json, encode_json, generate};
+
+fn property(obj: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(obj, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_decode_json() {
+    let json = decode_json("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+    let state: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(state["hero_name"], "しと゛-");
+    assert_eq!(state["hero_xp"], 1234);
+    assert_eq!(state["purse"], 5678);
+
+    let e = decode_json("ざぼちずどぢぎきつたうずせれえむるのぢお").unwrap_err();
+    assert_eq!(property(&e, "name"), "Dq1PasswordError");
+    assert_eq!(property(&e, "kind"), "CrcMismatch");
+    assert_eq!(property(&e, "expect").as_f64(), Some(f64::from(0xAD)));
+    assert_eq!(property(&e, "actual").as_f64(), Some(f64::from(0xBA29)));
+
+    let e = decode_json("ざぼちず").unwrap_err();
+    assert_eq!(property(&e, "kind"), "InvalidPassword");
+    assert_eq!(
+        property(&e, "messageEn"),
+        "invalid password: password must be exactly 20 characters, ignoring whitespace (got 4)"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_encode_json() {
+    let json = decode_json("ざぼちずどぢぎきつたうずせれえむるのぢえ").unwrap();
+    assert_eq!(
+        encode_json(&json).unwrap(),
+        "ざぼちずどぢぎきつたうずせれえむるのぢえ"
+    );
+
+    let mut state: serde_json::Value = serde_json::from_str(&json).unwrap();
+    state["hero_name"] = "漢".into();
+    let e = encode_json(&state.to_string()).unwrap_err();
+    assert_eq!(property(&e, "kind"), "InvalidGameState");
+
+    let e = encode_json("{").unwrap_err();
+    assert_eq!(property(&e, "kind"), "InvalidJson");
+}
+
+#[wasm_bindgen_test]
+fn test_generate() {
+    let passwords = generate("ざぼちずどぢぎきつたうずせれえむるのぢ?", 10).unwrap();
+    assert!(passwords.length() <= 10);
+    assert!(passwords.includes(
+        &JsValue::from_str("ざぼちずどぢぎきつたうずせれえむるのぢえ"),
+        0
+    ));
+    assert!(passwords
+        .iter()
+        .all(|pw| decode_json(&pw.as_string().unwrap()).is_ok()));
+
+    let e = generate("ああああ", 10).unwrap_err();
+    assert_eq!(property(&e, "kind"), "InvalidPattern");
+}